@@ -0,0 +1,32 @@
+/* C API for scoped edits of Rust-owned byte blobs; see src/ffi.rs (feature "ffi").
+ * Functions returning int return 0 on success and -1 on a null handle. */
+#ifndef GHOST_SCOPE_H
+#define GHOST_SCOPE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct GhostBlob GhostBlob;
+typedef struct GhostScope GhostScope;
+
+GhostBlob *ghost_blob_new(const uint8_t *data, size_t len);
+void ghost_blob_free(GhostBlob *blob);
+size_t ghost_blob_read(const GhostBlob *blob, uint8_t *out, size_t capacity);
+
+GhostScope *ghost_scope_begin(const GhostBlob *blob);
+int ghost_scope_set_bytes(GhostScope *scope, const uint8_t *data, size_t len);
+size_t ghost_scope_read(const GhostScope *scope, uint8_t *out, size_t capacity);
+bool ghost_scope_is_modified(const GhostScope *scope);
+int ghost_scope_commit(GhostScope *scope);
+int ghost_scope_rollback(GhostScope *scope);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GHOST_SCOPE_H */
@@ -0,0 +1,320 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Field, Fields, GenericParam, Generics, ItemFn,
+    Lifetime, MetaNameValue,
+};
+
+// Derive `Scoped` for a struct with named fields.  Generates `<Name>Scope<'scope>` holding one
+// `ScopeCellMut` per field, a `scope()` constructor on the struct, and `commit_all`,
+// `revert_all` and `diff` on the shadow struct.
+#[proc_macro_derive(Scoped)]
+pub fn derive_scoped(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_scoped(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// Add per-field dirty bits to a struct with named fields.  Generates `<Name>Fields<'scope>`,
+// a scoped edit of the whole struct whose field accessors record which fields were written,
+// and a `scope_fields()` constructor on the struct.
+#[proc_macro_attribute]
+pub fn scoped_fields(args: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    if !args.is_empty() {
+        return Error::new(Span::call_site(), "scoped_fields takes no arguments")
+            .into_compile_error()
+            .into();
+    }
+    expand_scoped_fields(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// Wrap a function body with scoped overrides of `ScopedStatic`s or `thread_local!`
+// `ScopedThreadLocal`s: `#[with_scoped(LEVEL = 3, NAME = String::from("test"))]`.  The
+// overrides are removed when the function returns or unwinds.
+#[proc_macro_attribute]
+pub fn with_scoped(args: TokenStream, item: TokenStream) -> TokenStream {
+    let overrides =
+        parse_macro_input!(args with Punctuated::<MetaNameValue, Comma>::parse_terminated);
+    let function = parse_macro_input!(item as ItemFn);
+    expand_with_scoped(overrides, function)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_with_scoped(
+    overrides: Punctuated<MetaNameValue, Comma>,
+    function: ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+    if overrides.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            "with_scoped needs at least one `NAME = value` override",
+        ));
+    }
+    let keys: Vec<_> = overrides.iter().map(|o| &o.path).collect();
+    let values: Vec<_> = overrides.iter().map(|o| &o.value).collect();
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            // `set_scoped` is inherent on ScopedStatic and a trait method on thread-locals
+            #[allow(unused_imports)]
+            use ::scope_cell::ScopedLocalKey as _;
+            let _scoped_overrides = ( #( #keys.set_scoped(#values), )* );
+            #block
+        }
+    })
+}
+
+// The named fields of a struct, or an error naming the macro that needs them
+fn named_fields<'i>(
+    input: &'i DeriveInput,
+    macro_name: &str,
+) -> syn::Result<&'i Punctuated<Field, Comma>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(Error::new_spanned(
+                &input.ident,
+                format!("{} only supports structs with named fields", macro_name),
+            )),
+        },
+        _ => Err(Error::new_spanned(
+            &input.ident,
+            format!("{} only supports structs", macro_name),
+        )),
+    }
+}
+
+// The struct's generic parameters as they appear in a type path, without bounds
+fn generic_args(generics: &Generics) -> Vec<proc_macro2::TokenStream> {
+    generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+        })
+        .collect()
+}
+
+fn expand_scoped(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = named_fields(&input, "Scoped")?;
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let scope_name = format_ident!("{}Scope", name);
+    let scope_lifetime = Lifetime::new("'scope", Span::call_site());
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let field_vis: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let field_labels: Vec<_> = field_names.iter().map(|f| f.to_string()).collect();
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ty_args = generic_args(&input.generics);
+
+    let mut scope_generics = input.generics.clone();
+    scope_generics
+        .params
+        .insert(0, syn::parse_quote!(#scope_lifetime));
+    {
+        let where_clause = scope_generics.make_where_clause();
+        for ty in &field_types {
+            where_clause
+                .predicates
+                .push(syn::parse_quote!(#ty: ::core::clone::Clone + #scope_lifetime));
+        }
+    }
+    let (scope_impl_generics, scope_ty_generics, scope_where_clause) =
+        scope_generics.split_for_impl();
+
+    let doc = format!(
+        "Scoped shadow of `{}` with one `ScopeCellMut` per field, generated by `#[derive(Scoped)]`.",
+        name
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #scope_name #scope_generics #scope_where_clause {
+            #( #field_vis #field_names: ::scope_cell::ScopeCellMut<#scope_lifetime, #field_types>, )*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            // Open a scoped edit session over every field
+            #vis fn scope(&mut self) -> #scope_name<'_, #(#ty_args),*>
+            where
+                #( #field_types: ::core::clone::Clone, )*
+            {
+                #scope_name {
+                    #( #field_names: ::scope_cell::ScopeCellMut::new(&mut self.#field_names), )*
+                }
+            }
+        }
+
+        impl #scope_impl_generics #scope_name #scope_ty_generics #scope_where_clause {
+            // Write every modified field back into the original struct
+            #vis fn commit_all(self) {
+                #( self.#field_names.commit(); )*
+            }
+
+            // Discard the modifications of every field
+            #vis fn revert_all(&mut self) {
+                #( self.#field_names.revert(); )*
+            }
+
+            // Names of the fields that have been mutably accessed
+            #vis fn diff(&self) -> ::std::vec::Vec<&'static str> {
+                let mut changed = ::std::vec::Vec::new();
+                #(
+                    if self.#field_names.is_modified() {
+                        changed.push(#field_labels);
+                    }
+                )*
+                changed
+            }
+        }
+    })
+}
+
+fn expand_scoped_fields(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = named_fields(&input, "scoped_fields")?;
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let fields_name = format_ident!("{}Fields", name);
+    let scope_lifetime = Lifetime::new("'scope", Span::call_site());
+
+    let field_names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let field_vis: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let field_labels: Vec<_> = field_names.iter().map(|f| f.to_string()).collect();
+    let field_muts: Vec<_> = field_names
+        .iter()
+        .map(|f| format_ident!("{}_mut", f))
+        .collect();
+    let field_setters: Vec<_> = field_names
+        .iter()
+        .map(|f| format_ident!("set_{}", f))
+        .collect();
+    let field_bits: Vec<_> = (0..fields.len()).collect();
+    let field_count = fields.len();
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ty_args = generic_args(&input.generics);
+
+    let mut scope_generics = input.generics.clone();
+    scope_generics
+        .params
+        .insert(0, syn::parse_quote!(#scope_lifetime));
+    scope_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#name #ty_generics: ::core::clone::Clone + #scope_lifetime));
+    let (scope_impl_generics, scope_ty_generics, scope_where_clause) =
+        scope_generics.split_for_impl();
+
+    let doc = format!(
+        "Scoped edit of `{}` tracking which fields were written, generated by `#[scoped_fields]`.",
+        name
+    );
+
+    Ok(quote! {
+        #input
+
+        #[doc = #doc]
+        #vis struct #fields_name #scope_generics #scope_where_clause {
+            cell: ::scope_cell::ScopeCellMut<#scope_lifetime, #name #ty_generics>,
+            dirty: [bool; #field_count],
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            // Open a scoped edit of the whole struct with per-field dirty tracking
+            #vis fn scope_fields(&mut self) -> #fields_name<'_, #(#ty_args),*>
+            where
+                Self: ::core::clone::Clone,
+            {
+                #fields_name {
+                    cell: ::scope_cell::ScopeCellMut::new(self),
+                    dirty: [false; #field_count],
+                }
+            }
+        }
+
+        impl #scope_impl_generics #fields_name #scope_ty_generics #scope_where_clause {
+            #(
+                // Borrow the field of the effective value
+                #field_vis fn #field_names(&self) -> &#field_types {
+                    &self.cell.get().#field_names
+                }
+
+                // Mutably borrow the field, marking it as written
+                #field_vis fn #field_muts(&mut self) -> &mut #field_types {
+                    self.dirty[#field_bits] = true;
+                    &mut self.cell.get_mut().#field_names
+                }
+
+                // Overwrite the field, marking it as written
+                #field_vis fn #field_setters(&mut self, value: #field_types) {
+                    *self.#field_muts() = value;
+                }
+            )*
+
+            // Borrow the whole effective value
+            #vis fn get(&self) -> &#name #ty_generics {
+                self.cell.get()
+            }
+
+            // Names of the fields written since creation or the last revert, without
+            // comparing any values
+            #vis fn diff(&self) -> ::std::vec::Vec<&'static str> {
+                let labels: [&'static str; #field_count] = [#(#field_labels),*];
+                labels
+                    .iter()
+                    .zip(self.dirty.iter())
+                    .filter(|(_, dirty)| **dirty)
+                    .map(|(label, _)| *label)
+                    .collect()
+            }
+
+            // Whether any field has been written since creation or the last revert
+            #vis fn is_modified(&self) -> bool {
+                self.dirty.iter().any(|dirty| *dirty)
+            }
+
+            // Discard every modification and clear the dirty bits
+            #vis fn revert(&mut self) {
+                self.cell.revert();
+                self.dirty = [false; #field_count];
+            }
+
+            // Write the modified struct back into the original
+            #vis fn commit(self) {
+                self.cell.commit();
+            }
+        }
+    })
+}
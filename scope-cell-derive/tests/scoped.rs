@@ -0,0 +1,58 @@
+use scope_cell::Scoped;
+
+#[derive(Scoped, Clone, Debug, PartialEq)]
+struct Config {
+    name: String,
+    retries: u32,
+    tags: Vec<String>,
+}
+
+#[derive(Scoped)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn config() -> Config {
+    Config {
+        name: String::from("service"),
+        retries: 3,
+        tags: vec![String::from("prod")],
+    }
+}
+
+#[test]
+fn test_commit_all() {
+    let mut config = config();
+    {
+        let mut scope = config.scope();
+        scope.name.get_mut().push_str("-staging");
+        *scope.retries.get_mut() = 5;
+        assert_eq!(scope.diff(), vec!["name", "retries"]);
+        scope.commit_all();
+    }
+    assert_eq!(config.name, "service-staging");
+    assert_eq!(config.retries, 5);
+    assert_eq!(config.tags, vec![String::from("prod")]);
+}
+
+#[test]
+fn test_revert_all_and_drop() {
+    let mut config = config();
+    {
+        let mut scope = config.scope();
+        scope.tags.get_mut().clear();
+        scope.revert_all();
+        assert!(scope.diff().is_empty());
+        *scope.retries.get_mut() = 0;
+    } // Dropped without commit
+    assert_eq!(config, self::config());
+}
+
+#[test]
+fn test_generic_struct() {
+    let mut wrapper = Wrapper { inner: vec![1, 2] };
+    let mut scope = wrapper.scope();
+    scope.inner.get_mut().push(3);
+    scope.commit_all();
+    assert_eq!(wrapper.inner, vec![1, 2, 3]);
+}
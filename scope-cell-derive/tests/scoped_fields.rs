@@ -0,0 +1,67 @@
+use scope_cell::scoped_fields;
+
+#[scoped_fields]
+#[derive(Clone, Debug)]
+struct Body {
+    position: [f32; 2],
+    mass: f32,
+    blob: Vec<u8>,
+}
+
+#[scoped_fields]
+#[derive(Clone)]
+struct Tagged<T> {
+    tag: T,
+    count: u32,
+}
+
+fn body() -> Body {
+    Body {
+        position: [0.0, 0.0],
+        mass: f32::NAN, // Never equal to itself, so only dirty bits can tell
+        blob: vec![0; 1024],
+    }
+}
+
+#[test]
+fn test_diff_reports_written_fields() {
+    let mut body = body();
+    {
+        let mut scope = body.scope_fields();
+        assert!(!scope.is_modified());
+        scope.position_mut()[0] = 1.5;
+        scope.set_mass(f32::NAN); // Written, even though the value compares unequal either way
+        assert_eq!(scope.diff(), vec!["position", "mass"]);
+        assert_eq!(scope.blob().len(), 1024);
+        scope.commit();
+    }
+    assert_eq!(body.position, [1.5, 0.0]);
+}
+
+#[test]
+fn test_revert_clears_dirty_bits() {
+    let mut body = body();
+    {
+        let mut scope = body.scope_fields();
+        scope.blob_mut().clear();
+        scope.revert();
+        assert!(scope.diff().is_empty());
+        assert_eq!(scope.get().blob.len(), 1024);
+        scope.blob_mut().push(1);
+    } // Dropped without commit
+    assert_eq!(body.blob.len(), 1024);
+}
+
+#[test]
+fn test_generic_struct() {
+    let mut tagged = Tagged {
+        tag: String::from("a"),
+        count: 0,
+    };
+    let mut scope = tagged.scope_fields();
+    scope.tag_mut().push('b');
+    assert_eq!(scope.diff(), vec!["tag"]);
+    scope.commit();
+    assert_eq!(tagged.tag, "ab");
+    assert_eq!(tagged.count, 0);
+}
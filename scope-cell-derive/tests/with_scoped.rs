@@ -0,0 +1,40 @@
+use scope_cell::{with_scoped, ScopedLocalKey, ScopedStatic, ScopedThreadLocal};
+
+static LEVEL: ScopedStatic<u32> = ScopedStatic::new(1);
+static MODE: ScopedStatic<&str> = ScopedStatic::new("prod");
+// Tests run in parallel, so each static is overridden by one test only
+static DEPTH: ScopedStatic<u32> = ScopedStatic::new(1);
+
+thread_local! {
+    static USER: ScopedThreadLocal<String> = const { ScopedThreadLocal::new(String::new()) };
+}
+
+#[with_scoped(LEVEL = 3, USER = String::from("tester"))]
+fn overridden() -> (u32, String) {
+    (LEVEL.get(), USER.get())
+}
+
+#[with_scoped(MODE = "test")]
+fn failing() {
+    assert_eq!(MODE.get(), "test");
+    panic!("test body failed");
+}
+
+#[test]
+#[with_scoped(DEPTH = 7)]
+fn test_attribute_on_test_function() {
+    assert_eq!(DEPTH.get(), 7);
+}
+
+#[test]
+fn test_overrides_removed_after_return() {
+    assert_eq!(overridden(), (3, String::from("tester")));
+    assert_eq!(LEVEL.get(), 1);
+    assert_eq!(USER.get(), "");
+}
+
+#[test]
+fn test_overrides_removed_after_panic() {
+    assert!(std::panic::catch_unwind(failing).is_err());
+    assert_eq!(MODE.get(), "prod");
+}
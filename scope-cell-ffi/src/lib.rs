@@ -0,0 +1,5 @@
+//! Builds the C API of `scope_cell::ffi` as a `staticlib` and `cdylib` for C and C++ hosts
+//! to link, so that the scope-cell library itself stays an ordinary rlib.  The prototypes
+//! are in `include/ghost_scope.h` at the root of the repository.
+
+pub use scope_cell::ffi::*;
@@ -0,0 +1,325 @@
+//! Where the owned and collection cells allocate their scratch data.  `OwnedScopeCell`,
+//! `ScopeVec` and `ScopeHashMap` take an allocator parameter `A` that defaults to `Global`,
+//! which keeps using the standard library's own `Vec` and `HashMap`.  With the
+//! `allocator_api` feature, any `Allocator` from `allocator-api2` can be chosen instead, and
+//! the cells then work over `allocator_api2::vec::Vec<T, A>` and
+//! `hashbrown::HashMap<K, V, DefaultHashBuilder, A>`; on nightly, enabling `allocator-api2`'s
+//! `nightly` feature makes these the standard library's own `Allocator`, `Box` and `Vec`.
+
+use std::borrow::{Borrow, BorrowMut};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::DerefMut;
+
+#[cfg(feature = "allocator_api")]
+pub use allocator_api2::alloc::Allocator;
+
+/// The global allocator, reached through the standard library's collections.  This is the
+/// default allocator parameter of the owned and collection cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Global;
+
+/// An allocator the owned and collection cells can keep their scratch data in, along with
+/// the containers they use with it.  Implemented by `Global` and, with the `allocator_api`
+/// feature, by every `allocator-api2` `Allocator`.
+pub trait ScratchAlloc: Clone {
+    // The modified copy an OwnedScopeCell keeps
+    type Box<T: Clone>: Clone + Borrow<T> + BorrowMut<T>;
+    // The vector a ScopeVec edits, and its overlay
+    type Vec<T>: DerefMut<Target = [T]> + IntoIterator<Item = T>;
+    // The map a ScopeHashMap edits, and its overlay
+    type Map<K, V>: IntoIterator<Item = (K, V)>;
+
+    // Move `value` into this allocator
+    fn boxed<T: Clone>(&self, value: T) -> Self::Box<T>;
+
+    // A new, empty vector in this allocator
+    fn new_vec<T>(&self) -> Self::Vec<T>;
+
+    // The allocator `vec` lives in
+    fn vec_allocator<T>(vec: &Self::Vec<T>) -> Self;
+
+    // Append an element
+    fn vec_push<T>(vec: &mut Self::Vec<T>, value: T);
+
+    // Remove and return the last element
+    fn vec_pop<T>(vec: &mut Self::Vec<T>) -> Option<T>;
+
+    // Shorten the vector to `len` elements
+    fn vec_truncate<T>(vec: &mut Self::Vec<T>, len: usize);
+
+    // Insert an element at `index`, shifting the rest along
+    fn vec_insert<T>(vec: &mut Self::Vec<T>, index: usize, value: T);
+
+    // Remove and return the element at `index`, shifting the rest back
+    fn vec_remove<T>(vec: &mut Self::Vec<T>, index: usize) -> T;
+
+    // Move every element of `other` onto the end of `vec`
+    fn vec_append<T>(vec: &mut Self::Vec<T>, other: &mut Self::Vec<T>);
+
+    // A new, empty map in this allocator
+    fn new_map<K, V>(&self) -> Self::Map<K, V>;
+
+    // The allocator `map` lives in
+    fn map_allocator<K, V>(map: &Self::Map<K, V>) -> Self;
+
+    // Number of entries
+    fn map_len<K, V>(map: &Self::Map<K, V>) -> usize;
+
+    // Look up an entry along with its stored key
+    fn map_get_key_value<'m, K, V, Q>(map: &'m Self::Map<K, V>, key: &Q) -> Option<(&'m K, &'m V)>
+    where
+        K: Borrow<Q> + Eq + Hash,
+        Q: Hash + Eq + ?Sized;
+
+    // Mutably borrow a value
+    fn map_get_mut<'m, K, V, Q>(map: &'m mut Self::Map<K, V>, key: &Q) -> Option<&'m mut V>
+    where
+        K: Borrow<Q> + Eq + Hash,
+        Q: Hash + Eq + ?Sized;
+
+    // Insert or update an entry, returning the previous value
+    fn map_insert<K: Eq + Hash, V>(map: &mut Self::Map<K, V>, key: K, value: V) -> Option<V>;
+
+    // Remove an entry, returning its value
+    fn map_remove<K, V, Q>(map: &mut Self::Map<K, V>, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q> + Eq + Hash,
+        Q: Hash + Eq + ?Sized;
+
+    // Iterate over the entries, in no particular order
+    fn map_iter<'m, K: 'm, V: 'm>(map: &'m Self::Map<K, V>)
+        -> impl Iterator<Item = (&'m K, &'m V)>;
+}
+
+// The container operations read the same on the standard library's collections and on their
+// allocator-aware counterparts, so one body serves both impls.
+macro_rules! impl_container_ops {
+    () => {
+        fn vec_push<T>(vec: &mut Self::Vec<T>, value: T) {
+            vec.push(value);
+        }
+
+        fn vec_pop<T>(vec: &mut Self::Vec<T>) -> Option<T> {
+            vec.pop()
+        }
+
+        fn vec_truncate<T>(vec: &mut Self::Vec<T>, len: usize) {
+            vec.truncate(len);
+        }
+
+        fn vec_insert<T>(vec: &mut Self::Vec<T>, index: usize, value: T) {
+            vec.insert(index, value);
+        }
+
+        fn vec_remove<T>(vec: &mut Self::Vec<T>, index: usize) -> T {
+            vec.remove(index)
+        }
+
+        fn vec_append<T>(vec: &mut Self::Vec<T>, other: &mut Self::Vec<T>) {
+            vec.append(other);
+        }
+
+        fn map_len<K, V>(map: &Self::Map<K, V>) -> usize {
+            map.len()
+        }
+
+        fn map_get_key_value<'m, K, V, Q>(
+            map: &'m Self::Map<K, V>,
+            key: &Q,
+        ) -> Option<(&'m K, &'m V)>
+        where
+            K: Borrow<Q> + Eq + Hash,
+            Q: Hash + Eq + ?Sized,
+        {
+            map.get_key_value(key)
+        }
+
+        fn map_get_mut<'m, K, V, Q>(map: &'m mut Self::Map<K, V>, key: &Q) -> Option<&'m mut V>
+        where
+            K: Borrow<Q> + Eq + Hash,
+            Q: Hash + Eq + ?Sized,
+        {
+            map.get_mut(key)
+        }
+
+        fn map_insert<K: Eq + Hash, V>(map: &mut Self::Map<K, V>, key: K, value: V) -> Option<V> {
+            map.insert(key, value)
+        }
+
+        fn map_remove<K, V, Q>(map: &mut Self::Map<K, V>, key: &Q) -> Option<V>
+        where
+            K: Borrow<Q> + Eq + Hash,
+            Q: Hash + Eq + ?Sized,
+        {
+            map.remove(key)
+        }
+
+        fn map_iter<'m, K: 'm, V: 'm>(
+            map: &'m Self::Map<K, V>,
+        ) -> impl Iterator<Item = (&'m K, &'m V)> {
+            map.iter()
+        }
+    };
+}
+
+// The modified copy stays inline, as it always has for the std-backed cells
+impl ScratchAlloc for Global {
+    type Box<T: Clone> = T;
+    type Vec<T> = Vec<T>;
+    type Map<K, V> = HashMap<K, V>;
+
+    fn boxed<T: Clone>(&self, value: T) -> T {
+        value
+    }
+
+    fn new_vec<T>(&self) -> Vec<T> {
+        Vec::new()
+    }
+
+    fn vec_allocator<T>(_: &Vec<T>) -> Self {
+        Global
+    }
+
+    fn new_map<K, V>(&self) -> HashMap<K, V> {
+        HashMap::new()
+    }
+
+    fn map_allocator<K, V>(_: &HashMap<K, V>) -> Self {
+        Global
+    }
+
+    impl_container_ops!();
+}
+
+#[cfg(feature = "allocator_api")]
+impl<A: Allocator + Clone> ScratchAlloc for A {
+    type Box<T: Clone> = allocator_api2::boxed::Box<T, A>;
+    type Vec<T> = allocator_api2::vec::Vec<T, A>;
+    type Map<K, V> = hashbrown::HashMap<K, V, hashbrown::DefaultHashBuilder, A>;
+
+    fn boxed<T: Clone>(&self, value: T) -> Self::Box<T> {
+        allocator_api2::boxed::Box::new_in(value, self.clone())
+    }
+
+    fn new_vec<T>(&self) -> Self::Vec<T> {
+        allocator_api2::vec::Vec::new_in(self.clone())
+    }
+
+    fn vec_allocator<T>(vec: &Self::Vec<T>) -> Self {
+        vec.allocator().clone()
+    }
+
+    fn new_map<K, V>(&self) -> Self::Map<K, V> {
+        hashbrown::HashMap::new_in(self.clone())
+    }
+
+    fn map_allocator<K, V>(map: &Self::Map<K, V>) -> Self {
+        map.allocator().clone()
+    }
+
+    impl_container_ops!();
+}
+
+#[cfg(all(test, feature = "allocator_api"))]
+mod tests {
+    use crate::{OwnedScopeCell, ScopeHashMap, ScopeVec};
+    use allocator_api2::alloc::{AllocError, Allocator, Global};
+    use std::alloc::Layout;
+    use std::ptr::NonNull;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Forwards to the global allocator, counting live allocations
+    #[derive(Clone, Default)]
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Counting {
+        fn live(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.fetch_sub(1, Ordering::Relaxed);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_owned_copy_in_allocator() {
+        let alloc = Counting::default();
+        let mut cell = OwnedScopeCell::new_in([0u64; 8], alloc.clone());
+        cell.get_mut()[0] = 1;
+        assert_eq!(alloc.live(), 1);
+        cell.reset();
+        assert_eq!(alloc.live(), 0);
+        cell.get_mut()[1] = 2;
+        cell.rebase();
+        assert_eq!(
+            (cell.baseline()[1], cell.version(), alloc.live()),
+            (2, 1, 0)
+        );
+        cell.set([3; 8]);
+        assert_eq!(cell.diff(), Some((&[0, 2, 0, 0, 0, 0, 0, 0], &[3; 8])));
+        assert_eq!(
+            format!("{:?}", OwnedScopeCell::new_in(1, alloc.clone())),
+            "OwnedScopeCell { original: 1, pending: None, dirty: false }"
+        );
+        assert_eq!(cell.into_inner(), [3; 8]);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn test_scope_vec_in_allocator() {
+        let alloc = Counting::default();
+        let mut data = allocator_api2::vec::Vec::new_in(alloc.clone());
+        data.extend([1, 2, 3, 4]);
+        {
+            let mut scope = ScopeVec::new_in(&mut data);
+            scope.set(3, 40);
+            *scope.get_mut(0).unwrap() = 10;
+            scope.truncate(3); // Drops the overwrite at index 3
+            scope.push(5);
+            assert_eq!(scope.pop(), Some(5));
+            assert_eq!(scope.pop(), Some(3));
+            scope.push(6);
+            assert_eq!(scope.to_vec(), [10, 2, 6]);
+            assert_eq!(alloc.live(), 3); // Original, overrides, appended
+            scope.commit();
+        }
+        assert_eq!(data.as_slice(), [10, 2, 6]);
+        let mut scope = ScopeVec::new_in(&mut data);
+        scope.set(1, 20);
+        scope.revert();
+        assert!(!scope.is_modified());
+        assert_eq!(scope.get(1), Some(&2));
+    }
+
+    #[test]
+    fn test_scope_hash_map_in_allocator() {
+        let alloc = Counting::default();
+        let mut data = hashbrown::HashMap::new_in(alloc.clone());
+        data.insert("a", 1);
+        data.insert("b", 2);
+        let before = alloc.live();
+        {
+            let mut scope = ScopeHashMap::new_in(&mut data);
+            scope.insert("c", 3);
+            assert_eq!(scope.remove("a"), Some(1));
+            *scope.get_mut("b").unwrap() += 20;
+            assert_eq!((scope.len(), scope.get("b")), (2, Some(&22)));
+            assert_eq!(alloc.live(), before + 1); // The overlay
+            scope.commit();
+        }
+        let mut entries: Vec<_> = data.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort();
+        assert_eq!(entries, [("b", 22), ("c", 3)]);
+    }
+}
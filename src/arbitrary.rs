@@ -0,0 +1,156 @@
+// `Arbitrary` for the owned cells and for `CellOp`, with the **proptest** and **quickcheck**
+// features.  A generated cell has an arbitrary baseline and, half the time, a pending edit.
+
+#[cfg(feature = "proptest")]
+mod proptest_impls {
+    use proptest::arbitrary::{any, Arbitrary};
+    use proptest::strategy::{BoxedStrategy, Just, Strategy};
+    use std::sync::Arc;
+
+    use crate::{CellOp, CowScopeCell, OwnedScopeCell};
+
+    impl<T: Arbitrary + Clone + 'static> Arbitrary for OwnedScopeCell<T> {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (any::<T>(), any::<Option<T>>())
+                .prop_map(|(baseline, pending)| {
+                    let mut cell = OwnedScopeCell::new(baseline);
+                    if let Some(pending) = pending {
+                        *cell.get_mut() = pending;
+                    }
+                    cell
+                })
+                .boxed()
+        }
+    }
+
+    impl<T: Arbitrary + Clone + 'static> Arbitrary for CowScopeCell<T> {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (any::<T>(), any::<Option<T>>())
+                .prop_map(|(baseline, pending)| {
+                    let mut cell = CowScopeCell::new(Arc::new(baseline));
+                    if let Some(pending) = pending {
+                        *cell.get_mut() = pending;
+                    }
+                    cell
+                })
+                .boxed()
+        }
+    }
+
+    // Writes are generated as often as the other three operations together
+    impl<T: Arbitrary + Clone + 'static> Arbitrary for CellOp<T> {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            proptest::prop_oneof![
+                1 => Just(CellOp::Get),
+                3 => any::<T>().prop_map(CellOp::Write),
+                1 => Just(CellOp::Revert),
+                1 => Just(CellOp::Commit),
+            ]
+            .boxed()
+        }
+    }
+}
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_impls {
+    use quickcheck::{Arbitrary, Gen};
+    use std::sync::Arc;
+
+    use crate::{CellOp, CowScopeCell, OwnedScopeCell};
+
+    impl<T: Arbitrary> Arbitrary for OwnedScopeCell<T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let mut cell = OwnedScopeCell::new(T::arbitrary(g));
+            if let Some(pending) = Option::<T>::arbitrary(g) {
+                *cell.get_mut() = pending;
+            }
+            cell
+        }
+
+        // Shrinks the baseline and the pending edit, dropping the edit first
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            let pending = self.is_modified().then(|| self.get().clone());
+            let parts = (self.baseline().clone(), pending);
+            Box::new(parts.shrink().map(|(baseline, pending)| {
+                let mut cell = OwnedScopeCell::new(baseline);
+                if let Some(pending) = pending {
+                    *cell.get_mut() = pending;
+                }
+                cell
+            }))
+        }
+    }
+
+    impl<T: Arbitrary> Arbitrary for CowScopeCell<T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let mut cell = CowScopeCell::new(Arc::new(T::arbitrary(g)));
+            if let Some(pending) = Option::<T>::arbitrary(g) {
+                *cell.get_mut() = pending;
+            }
+            cell
+        }
+    }
+
+    impl<T: Arbitrary> Arbitrary for CellOp<T> {
+        fn arbitrary(g: &mut Gen) -> Self {
+            match g.choose(&[0, 1, 1, 1, 2, 3]).unwrap() {
+                0 => CellOp::Get,
+                1 => CellOp::Write(T::arbitrary(g)),
+                2 => CellOp::Revert,
+                _ => CellOp::Commit,
+            }
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            match self {
+                CellOp::Write(value) => Box::new(value.shrink().map(CellOp::Write)),
+                _ => Box::new(std::iter::empty()),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "proptest"))]
+mod proptest_tests {
+    use crate::{check_ops, CellOp, CowScopeCell, HistoryCell, OwnedScopeCell};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_owned_matches_model(mut cell: OwnedScopeCell<Vec<u8>>, ops: Vec<CellOp<Vec<u8>>>) {
+            check_ops(&mut cell, &ops);
+        }
+
+        #[test]
+        fn test_cow_matches_model(mut cell: CowScopeCell<String>, ops: Vec<CellOp<String>>) {
+            check_ops(&mut cell, &ops);
+        }
+
+        #[test]
+        fn test_history_matches_model(start: i32, ops: Vec<CellOp<i32>>) {
+            check_ops(&mut HistoryCell::new(start, 8), &ops);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "quickcheck"))]
+mod quickcheck_tests {
+    use crate::{check_ops, CellOp, OwnedScopeCell};
+
+    quickcheck::quickcheck! {
+        fn test_owned_matches_model_qc(cell: OwnedScopeCell<Vec<u8>>, ops: Vec<CellOp<Vec<u8>>>) -> bool {
+            let mut cell = cell;
+            check_ops(&mut cell, &ops);
+            true
+        }
+    }
+}
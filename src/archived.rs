@@ -0,0 +1,147 @@
+use rkyv::api::high::{HighDeserializer, HighSerializer, HighValidator};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor::Error;
+use rkyv::ser::allocator::ArenaHandle;
+use rkyv::util::AlignedVec;
+use rkyv::{Archive, Deserialize, Portable, Serialize};
+
+/// An ArchivedScopeCell makes scoped edits to an `rkyv` archive, such as a memory-mapped
+/// game asset, without deserializing it up front.  Reads go to the archived value in place;
+/// the first write deserializes it into a native pending value, and `commit()` serializes
+/// that back into fresh bytes for the caller to write out.  Changes are discarded on drop.
+pub struct ArchivedScopeCell<'a, T: Archive> {
+    archived: &'a T::Archived,
+    modified_data: Option<T>, // Holds the deserialized, modified value
+}
+
+impl<'a, T: Archive> ArchivedScopeCell<'a, T> {
+    // Create a new ArchivedScopeCell over an archived value
+    pub fn new(archived: &'a T::Archived) -> Self {
+        ArchivedScopeCell {
+            archived,
+            modified_data: None,
+        }
+    }
+
+    // Create a new ArchivedScopeCell over the archive in `bytes`, validating it first
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error>
+    where
+        T::Archived: Portable + for<'v> CheckBytes<HighValidator<'v, Error>>,
+    {
+        Ok(ArchivedScopeCell::new(rkyv::access::<T::Archived, Error>(
+            bytes,
+        )?))
+    }
+
+    // The archived baseline, ignoring any modification
+    pub fn archived(&self) -> &'a T::Archived {
+        self.archived
+    }
+
+    // The pending native value, if the baseline has been deserialized or replaced
+    pub fn modified(&self) -> Option<&T> {
+        self.modified_data.as_ref()
+    }
+
+    // Mutably borrow the pending value, deserializing the baseline on first use
+    pub fn try_get_mut(&mut self) -> Result<&mut T, Error>
+    where
+        T::Archived: Deserialize<T, HighDeserializer<Error>>,
+    {
+        if self.modified_data.is_none() {
+            self.modified_data = Some(rkyv::deserialize::<T, Error>(self.archived)?);
+        }
+        Ok(self.modified_data.as_mut().unwrap())
+    }
+
+    // Mutably borrow the pending value like `try_get_mut()`.  Panics if the baseline fails
+    // to deserialize.
+    pub fn get_mut(&mut self) -> &mut T
+    where
+        T::Archived: Deserialize<T, HighDeserializer<Error>>,
+    {
+        match self.try_get_mut() {
+            Ok(value) => value,
+            Err(err) => panic!("ArchivedScopeCell failed to deserialize: {}", err),
+        }
+    }
+
+    // Replace the data without deserializing the baseline
+    pub fn set(&mut self, value: T) {
+        self.modified_data = Some(value);
+    }
+
+    // Whether a pending value exists
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes by dropping the pending value
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Serialize the pending value into a new archive, or return `None` if there is nothing
+    // to write because the baseline was never modified
+    pub fn commit(self) -> Result<Option<AlignedVec>, Error>
+    where
+        T: for<'s> Serialize<HighSerializer<AlignedVec, ArenaHandle<'s>, Error>>,
+    {
+        self.modified_data
+            .map(|modified| rkyv::to_bytes::<Error>(&modified))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Archive, Serialize, Deserialize, Debug, PartialEq)]
+    struct Asset {
+        name: String,
+        frames: Vec<u32>,
+    }
+
+    fn archive() -> AlignedVec {
+        let asset = Asset {
+            name: String::from("hero"),
+            frames: vec![1, 2, 3],
+        };
+        rkyv::to_bytes::<Error>(&asset).unwrap()
+    }
+
+    #[test]
+    fn test_edit_and_commit_archive() {
+        let bytes = archive();
+        let mut cell = ArchivedScopeCell::<Asset>::from_bytes(&bytes).unwrap();
+        assert_eq!(cell.archived().name, "hero");
+        assert!(cell.modified().is_none());
+        cell.get_mut().frames.push(4);
+        assert_eq!(cell.modified().unwrap().frames, [1, 2, 3, 4]);
+        assert_eq!(cell.archived().frames.len(), 3); // The archive is untouched
+        let committed = cell.commit().unwrap().unwrap();
+        let reread = ArchivedScopeCell::<Asset>::from_bytes(&committed).unwrap();
+        assert_eq!(reread.archived().frames.as_slice(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_revert_and_untouched_commit() {
+        let bytes = archive();
+        let archived = rkyv::access::<ArchivedAsset, Error>(&bytes).unwrap();
+        let mut cell = ArchivedScopeCell::<Asset>::new(archived);
+        cell.try_get_mut().unwrap().name.clear();
+        cell.revert();
+        assert!(!cell.is_modified());
+        assert!(cell.commit().unwrap().is_none());
+        let mut cell = ArchivedScopeCell::<Asset>::new(archived);
+        cell.set(Asset {
+            name: String::from("villain"),
+            frames: Vec::new(),
+        });
+        let committed = cell.commit().unwrap().unwrap();
+        let asset = rkyv::from_bytes::<Asset, Error>(&committed).unwrap();
+        assert_eq!(asset.name, "villain");
+        assert!(ArchivedScopeCell::<Asset>::from_bytes(&[0xff; 3]).is_err());
+    }
+}
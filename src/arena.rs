@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Keys of a ScopedArena.  Implement it for a newtype to get typed keys per arena.
+pub trait ArenaKey: Copy {
+    fn from_index(index: usize) -> Self;
+
+    fn index(self) -> usize;
+}
+
+impl ArenaKey for usize {
+    fn from_index(index: usize) -> Self {
+        index
+    }
+
+    fn index(self) -> usize {
+        self
+    }
+}
+
+/// A saved set of pending edits, taken with `ScopedArena::checkpoint`.
+pub struct ArenaCheckpoint<V> {
+    edits: HashMap<usize, (u64, V)>, // Each edit with the stamp of the value it was made to
+}
+
+/// A ScopedArena stores values in slots addressed by key, like a slab, where each slot can
+/// be scoped-edited independently: `edit(key)` works on a copy of that slot alone, so
+/// reverting or committing one slot never touches the others.  `checkpoint()` saves the
+/// pending edits of the whole arena and `restore()` returns to them.  Removed slots are
+/// reused by later insertions, so keys should not outlive the values they name.
+pub struct ScopedArena<K: ArenaKey, V: Clone> {
+    slots: Vec<Option<V>>, // Committed values; `None` marks a free slot
+    stamps: Vec<u64>,      // Which insertion each slot's value came from
+    next_stamp: u64,
+    free: Vec<usize>,         // Free slots, reused last freed first
+    edits: HashMap<usize, V>, // Pending per-slot copies
+    marker: PhantomData<fn(K)>,
+}
+
+impl<K: ArenaKey, V: Clone> ScopedArena<K, V> {
+    // Create an empty ScopedArena
+    pub fn new() -> Self {
+        ScopedArena {
+            slots: Vec::new(),
+            stamps: Vec::new(),
+            next_stamp: 0,
+            free: Vec::new(),
+            edits: HashMap::new(),
+            marker: PhantomData,
+        }
+    }
+
+    // Store a committed value, returning its key
+    pub fn insert(&mut self, value: V) -> K {
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = Some(value);
+                self.stamps[index] = stamp;
+                index
+            }
+            None => {
+                self.slots.push(Some(value));
+                self.stamps.push(stamp);
+                self.slots.len() - 1
+            }
+        };
+        K::from_index(index)
+    }
+
+    // Remove a slot, discarding any pending edit.  Returns the effective value.
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let index = key.index();
+        let committed = self.slots.get_mut(index)?.take()?;
+        self.free.push(index);
+        Some(self.edits.remove(&index).unwrap_or(committed))
+    }
+
+    // Borrow a slot, showing its pending edit if it has one
+    pub fn get(&self, key: K) -> Option<&V> {
+        let index = key.index();
+        self.edits
+            .get(&index)
+            .or_else(|| self.slots.get(index)?.as_ref())
+    }
+
+    // Borrow a slot's committed value, ignoring any pending edit
+    pub fn committed(&self, key: K) -> Option<&V> {
+        self.slots.get(key.index())?.as_ref()
+    }
+
+    // Mutably borrow a slot, copying its committed value on first use
+    pub fn edit(&mut self, key: K) -> Option<&mut V> {
+        let index = key.index();
+        let committed = self.slots.get(index)?.as_ref()?;
+        Some(self.edits.entry(index).or_insert_with(|| committed.clone()))
+    }
+
+    // Whether a slot has a pending edit
+    pub fn is_modified(&self, key: K) -> bool {
+        self.edits.contains_key(&key.index())
+    }
+
+    // Keys of the slots with pending edits, in no particular order
+    pub fn modified_keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.edits.keys().map(|&index| K::from_index(index))
+    }
+
+    // Discard one slot's pending edit
+    pub fn revert(&mut self, key: K) {
+        self.edits.remove(&key.index());
+    }
+
+    // Discard every pending edit
+    pub fn revert_all(&mut self) {
+        self.edits.clear();
+    }
+
+    // Make one slot's pending edit its committed value.  Returns whether there was one.
+    pub fn commit(&mut self, key: K) -> bool {
+        let index = key.index();
+        match self.edits.remove(&index) {
+            Some(value) => {
+                self.slots[index] = Some(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Commit every pending edit
+    pub fn commit_all(&mut self) {
+        for (index, value) in self.edits.drain() {
+            self.slots[index] = Some(value);
+        }
+    }
+
+    // Save the pending edits of the whole arena
+    pub fn checkpoint(&self) -> ArenaCheckpoint<V> {
+        let edits = self.edits.iter();
+        ArenaCheckpoint {
+            edits: edits
+                .map(|(&index, value)| (index, (self.stamps[index], value.clone())))
+                .collect(),
+        }
+    }
+
+    // Return the pending edits to a checkpoint.  Edits saved for values removed since are
+    // dropped, even if their slot has been reused.
+    pub fn restore(&mut self, checkpoint: &ArenaCheckpoint<V>) {
+        self.edits = checkpoint
+            .edits
+            .iter()
+            .filter(|(&index, (stamp, _))| {
+                self.slots[index].is_some() && self.stamps[index] == *stamp
+            })
+            .map(|(&index, (_, value))| (index, value.clone()))
+            .collect();
+    }
+
+    // Number of occupied slots
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    // Whether no slots are occupied
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: ArenaKey, V: Clone> Default for ScopedArena<K, V> {
+    fn default() -> Self {
+        ScopedArena::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Entity(usize);
+
+    impl ArenaKey for Entity {
+        fn from_index(index: usize) -> Self {
+            Entity(index)
+        }
+
+        fn index(self) -> usize {
+            self.0
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(i32, i32);
+
+    #[test]
+    fn test_slots_edit_independently() {
+        let mut positions: ScopedArena<Entity, Position> = ScopedArena::new();
+        let player = positions.insert(Position(0, 0));
+        let enemy = positions.insert(Position(5, 5));
+        positions.edit(player).unwrap().0 += 1;
+        positions.edit(enemy).unwrap().1 -= 1;
+        positions.revert(enemy);
+        assert_eq!(positions.get(player), Some(&Position(1, 0)));
+        assert_eq!(positions.get(enemy), Some(&Position(5, 5))); // Only the enemy reverted
+        assert_eq!(positions.committed(player), Some(&Position(0, 0)));
+        assert_eq!(positions.modified_keys().collect::<Vec<_>>(), vec![player]);
+        assert!(positions.commit(player));
+        assert!(!positions.is_modified(player));
+        assert_eq!(positions.committed(player), Some(&Position(1, 0)));
+    }
+
+    #[test]
+    fn test_checkpoint_and_restore() {
+        let mut hp: ScopedArena<usize, u32> = ScopedArena::default();
+        let a = hp.insert(10);
+        let b = hp.insert(20);
+        *hp.edit(a).unwrap() = 5;
+        let saved = hp.checkpoint();
+        *hp.edit(a).unwrap() = 0;
+        *hp.edit(b).unwrap() = 0;
+        hp.restore(&saved);
+        assert_eq!((hp.get(a), hp.get(b)), (Some(&5), Some(&20)));
+        hp.commit_all();
+        hp.revert_all();
+        assert_eq!(hp.get(a), Some(&5));
+    }
+
+    #[test]
+    fn test_remove_reuses_slots() {
+        let mut arena: ScopedArena<usize, String> = ScopedArena::new();
+        let first = arena.insert(String::from("a"));
+        arena.edit(first).unwrap().push('!');
+        let saved = arena.checkpoint();
+        assert_eq!(arena.remove(first).as_deref(), Some("a!"));
+        assert!(arena.edit(first).is_none());
+        assert!(arena.is_empty());
+        let second = arena.insert(String::from("b"));
+        assert_eq!(second, first);
+        arena.restore(&saved); // The saved edit belonged to the removed value
+        assert_eq!(arena.get(second).map(String::as_str), Some("b"));
+        assert_eq!(arena.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::ScopeCellMut;
+
+impl<'a, T: Clone> ScopeCellMut<'a, T> {
+    // Async counterpart of `scope`: the changes are committed only if the future resolves
+    // to `Ok`.  The cell lives inside the returned future, so if that future is dropped
+    // before completing (cancelled), the cell is dropped with it and the changes are
+    // reverted; the original data is never touched before the commit.
+    pub async fn scope_async<R, E, F>(data: &'a mut T, f: F) -> Result<R, E>
+    where
+        F: AsyncFnOnce(&mut ScopeCellMut<'a, T>) -> Result<R, E>,
+    {
+        let mut cell = ScopeCellMut::new(data);
+        let result = f(&mut cell).await?;
+        cell.commit();
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    // A future that is pending on its first poll and ready afterwards
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_commits_on_ok() {
+        let mut data = vec![1, 2, 3];
+        let result = block_on(ScopeCellMut::scope_async(&mut data, async |cell| {
+            cell.get_mut().push(4);
+            YieldOnce(false).await;
+            Ok::<_, ()>(cell.get().len())
+        }));
+        assert_eq!(result, Ok(4));
+        assert_eq!(data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reverts_on_err() {
+        let mut data = vec![1, 2, 3];
+        let result: Result<(), &str> =
+            block_on(ScopeCellMut::scope_async(&mut data, async |cell| {
+                cell.get_mut().push(4);
+                YieldOnce(false).await;
+                Err("rejected")
+            }));
+        assert_eq!(result, Err("rejected"));
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_reverts_on_cancellation() {
+        let mut data = vec![1, 2, 3];
+        {
+            let future = ScopeCellMut::scope_async(&mut data, async |cell| {
+                cell.get_mut().push(4);
+                YieldOnce(false).await; // Cancelled while suspended here
+                Ok::<_, ()>(())
+            });
+            let mut future = pin!(future);
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+        } // Future dropped before completion
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+}
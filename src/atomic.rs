@@ -0,0 +1,203 @@
+use std::sync::atomic::Ordering;
+
+use crate::primitives::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16,
+    AtomicU32, AtomicU64, AtomicU8, AtomicUsize,
+};
+
+/// Primitive values with a matching std atomic type, usable in an `AtomicScopeCell`.
+pub trait AtomicValue: Copy {
+    type Atomic: Send + Sync;
+
+    fn new_atomic(value: Self) -> Self::Atomic;
+    fn load(atomic: &Self::Atomic, order: Ordering) -> Self;
+    fn store(atomic: &Self::Atomic, value: Self, order: Ordering);
+    fn swap(atomic: &Self::Atomic, value: Self, order: Ordering) -> Self;
+}
+
+macro_rules! impl_atomic_value {
+    ($($value:ty => $atomic:ty),* $(,)?) => {
+        $(
+            impl AtomicValue for $value {
+                type Atomic = $atomic;
+
+                fn new_atomic(value: Self) -> Self::Atomic {
+                    <$atomic>::new(value)
+                }
+
+                fn load(atomic: &Self::Atomic, order: Ordering) -> Self {
+                    atomic.load(order)
+                }
+
+                fn store(atomic: &Self::Atomic, value: Self, order: Ordering) {
+                    atomic.store(value, order)
+                }
+
+                fn swap(atomic: &Self::Atomic, value: Self, order: Ordering) -> Self {
+                    atomic.swap(value, order)
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_value! {
+    bool => AtomicBool,
+    u8 => AtomicU8,
+    u16 => AtomicU16,
+    u32 => AtomicU32,
+    u64 => AtomicU64,
+    usize => AtomicUsize,
+    i8 => AtomicI8,
+    i16 => AtomicI16,
+    i32 => AtomicI32,
+    i64 => AtomicI64,
+    isize => AtomicIsize,
+}
+
+impl<P> AtomicValue for *mut P {
+    type Atomic = AtomicPtr<P>;
+
+    fn new_atomic(value: Self) -> Self::Atomic {
+        AtomicPtr::new(value)
+    }
+
+    fn load(atomic: &Self::Atomic, order: Ordering) -> Self {
+        atomic.load(order)
+    }
+
+    fn store(atomic: &Self::Atomic, value: Self, order: Ordering) {
+        atomic.store(value, order)
+    }
+
+    fn swap(atomic: &Self::Atomic, value: Self, order: Ordering) -> Self {
+        atomic.swap(value, order)
+    }
+}
+
+/// An AtomicScopeCell holds a primitive value in an atomic, so that reads, writes and
+/// scoped overrides are lock-free.  `set_scoped()` swaps in a new value and returns a
+/// guard that stores the previous value back when dropped.  Overrides are expected to be
+/// dropped in reverse order of creation; concurrent overlapping overrides from several
+/// threads restore whichever value each guard displaced.
+pub struct AtomicScopeCell<T: AtomicValue> {
+    value: T::Atomic,
+}
+
+impl<T: AtomicValue> AtomicScopeCell<T> {
+    // Create a new AtomicScopeCell holding `value`
+    pub fn new(value: T) -> Self {
+        AtomicScopeCell {
+            value: T::new_atomic(value),
+        }
+    }
+
+    // Read the current value
+    pub fn get(&self) -> T {
+        T::load(&self.value, Ordering::Acquire)
+    }
+
+    // Overwrite the current value permanently
+    pub fn set(&self, value: T) {
+        T::store(&self.value, value, Ordering::Release);
+    }
+
+    // Override the value until the returned guard is dropped
+    pub fn set_scoped(&self, value: T) -> AtomicOverride<'_, T> {
+        let previous = T::swap(&self.value, value, Ordering::AcqRel);
+        AtomicOverride {
+            cell: self,
+            previous,
+        }
+    }
+}
+
+/// Guard returned by `AtomicScopeCell::set_scoped`; restores the displaced value on drop.
+pub struct AtomicOverride<'c, T: AtomicValue> {
+    cell: &'c AtomicScopeCell<T>,
+    previous: T,
+}
+
+impl<'c, T: AtomicValue> AtomicOverride<'c, T> {
+    // The value that will be restored when this guard is dropped
+    pub fn previous(&self) -> T {
+        self.previous
+    }
+}
+
+impl<'c, T: AtomicValue> Drop for AtomicOverride<'c, T> {
+    fn drop(&mut self) {
+        T::store(&self.cell.value, self.previous, Ordering::Release);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_nested_overrides() {
+        let cell = AtomicScopeCell::new(1u32);
+        {
+            let outer = cell.set_scoped(2);
+            assert_eq!(outer.previous(), 1);
+            {
+                let _inner = cell.set_scoped(3);
+                assert_eq!(cell.get(), 3);
+            }
+            assert_eq!(cell.get(), 2);
+        }
+        assert_eq!(cell.get(), 1);
+    }
+
+    #[test]
+    fn test_flag_flip_seen_by_threads() {
+        let flag = &AtomicScopeCell::new(false);
+        let guard = flag.set_scoped(true);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(move || assert!(flag.get()));
+            }
+        });
+        drop(guard);
+        assert!(!flag.get());
+    }
+
+    #[test]
+    fn test_pointer_override() {
+        let mut a = 1;
+        let mut b = 2;
+        let cell = AtomicScopeCell::new(&mut a as *mut i32);
+        {
+            let _guard = cell.set_scoped(&mut b as *mut i32);
+            assert_eq!(unsafe { *cell.get() }, 2);
+        }
+        assert_eq!(unsafe { *cell.get() }, 1);
+        cell.set(std::ptr::null_mut());
+        assert!(cell.get().is_null());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use crate::primitives::thread;
+    use loom::sync::Arc;
+
+    #[test]
+    fn test_override_restored_loom() {
+        loom::model(|| {
+            let cell = Arc::new(AtomicScopeCell::new(1u32));
+            let reader = Arc::clone(&cell);
+            let handle = thread::spawn(move || reader.get());
+            {
+                let _guard = cell.set_scoped(2);
+                assert_eq!(cell.get(), 2);
+            }
+            let seen = handle.join().unwrap();
+            assert!(seen == 1 || seen == 2);
+            assert_eq!(cell.get(), 1);
+        });
+    }
+}
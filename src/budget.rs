@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::ScopeError;
+
+/// An estimate of the heap memory a value owns, not counting the value itself.  Used by
+/// `CloneBudget` to price a copy as `size_of::<T>() + heap_size()`.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_heap_size_zero {
+    ($($ty:ty),*) => {
+        $(
+            impl HeapSize for $ty {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_size_zero!(
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    (),
+    &'static str
+);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize, const N: usize> HeapSize for [T; N] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(T::heap_size).sum()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + T::heap_size(self)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        let entries = self.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+        self.capacity() * size_of::<(K, V)>() + entries.sum::<usize>()
+    }
+}
+
+// B-tree nodes are not exposed, so this counts the entries alone
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        let entries = self.iter().map(|(k, v)| k.heap_size() + v.heap_size());
+        self.len() * size_of::<(K, V)>() + entries.sum::<usize>()
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+/// A CloneBudget caps the lazy copies made by the cells it is attached to with
+/// `set_budget()`: a number of copies, a total of estimated bytes, or both.  Once a copy
+/// would exceed it, `try_get_mut()` and `try_update()` fail with
+/// `ScopeError::BudgetExceeded` (and `get_mut()` panics) instead of cloning, which keeps a
+/// server from making runaway copies of huge documents.  One budget can be shared by any
+/// number of cells, across threads, to cap a whole group.
+#[derive(Debug, Default)]
+pub struct CloneBudget {
+    max_clones: Option<usize>,
+    max_bytes: Option<usize>,
+    clones: AtomicUsize, // Copies charged so far
+    bytes: AtomicUsize,  // Bytes charged so far
+}
+
+impl CloneBudget {
+    // Create a budget with no limits, which only counts
+    pub fn new() -> Self {
+        CloneBudget::default()
+    }
+
+    // Allow at most `max` copies
+    pub fn with_max_clones(mut self, max: usize) -> Self {
+        self.max_clones = Some(max);
+        self
+    }
+
+    // Allow at most `max` bytes of copies in total
+    pub fn with_max_bytes(mut self, max: usize) -> Self {
+        self.max_bytes = Some(max);
+        self
+    }
+
+    // Account for one copy of `bytes` bytes, or fail without charging anything if that
+    // would exceed a limit
+    pub fn try_charge(&self, bytes: usize) -> Result<(), ScopeError> {
+        let within = |used: usize, amount: usize, max: Option<usize>| {
+            let total = used.checked_add(amount)?;
+            max.is_none_or(|max| total <= max).then_some(total)
+        };
+        self.clones
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                within(used, 1, self.max_clones)
+            })
+            .map_err(|_| ScopeError::BudgetExceeded)?;
+        let charged = self
+            .bytes
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                within(used, bytes, self.max_bytes)
+            });
+        if charged.is_err() {
+            self.clones.fetch_sub(1, Ordering::AcqRel);
+            return Err(ScopeError::BudgetExceeded);
+        }
+        Ok(())
+    }
+
+    // Number of copies charged so far
+    pub fn clones(&self) -> usize {
+        self.clones.load(Ordering::Acquire)
+    }
+
+    // Bytes charged so far
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Acquire)
+    }
+
+    // Forget every charge, e.g. at the start of the next request
+    pub fn reset(&self) {
+        self.clones.store(0, Ordering::Release);
+        self.bytes.store(0, Ordering::Release);
+    }
+}
+
+// A cell's budget and how it prices a copy of its value
+pub(crate) type Pricing<'a, T> = Option<(&'a CloneBudget, fn(&T) -> usize)>;
+
+// Price a copy by its shallow size
+pub(crate) fn shallow_size<T>(_: &T) -> usize {
+    size_of::<T>()
+}
+
+// Price a copy by its shallow size and the heap memory it owns
+pub(crate) fn deep_size<T: HeapSize>(value: &T) -> usize {
+    size_of::<T>() + value.heap_size()
+}
+
+// Charge the budget, if any, for a copy of `value`
+pub(crate) fn charge<T>(pricing: &Pricing<'_, T>, value: &T) -> Result<(), ScopeError> {
+    match pricing {
+        Some((budget, price)) => budget.try_charge(price(value)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScopeCell, ScopeCellMut};
+
+    #[test]
+    fn test_clone_limit() {
+        let budget = CloneBudget::new().with_max_clones(2);
+        let document = vec![1u8; 16];
+        let mut first = ScopeCell::new(&document);
+        first.set_budget(&budget);
+        first.try_get_mut().unwrap().push(2);
+        first.try_get_mut().unwrap().push(3); // Already copied, so not charged again
+        let mut copy = document.clone();
+        let mut second = ScopeCellMut::new(&mut copy);
+        second.set_budget(&budget);
+        second.try_get_mut().unwrap().clear();
+        let mut third = ScopeCell::new(&document);
+        third.set_budget(&budget);
+        assert_eq!(third.try_get_mut().err(), Some(ScopeError::BudgetExceeded));
+        assert!(!third.is_modified());
+        third.set(Vec::new()); // Setting a value needs no copy
+        assert_eq!(budget.clones(), 2);
+        budget.reset();
+        assert!(ScopeCell::new(&document).try_get_mut().is_ok()); // No budget attached
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let budget = CloneBudget::new().with_max_bytes(200);
+        let small = String::with_capacity(10);
+        let large = String::with_capacity(1000);
+        let mut cell = ScopeCell::new(&large);
+        cell.set_heap_budget(&budget);
+        assert_eq!(cell.try_get_mut().err(), Some(ScopeError::BudgetExceeded));
+        assert_eq!((budget.clones(), budget.bytes()), (0, 0)); // Nothing charged
+        let mut cell = ScopeCell::new(&small);
+        cell.set_heap_budget(&budget);
+        cell.try_get_mut().unwrap().push('x');
+        assert_eq!(budget.bytes(), size_of::<String>() + 10);
+        assert_eq!(
+            vec![String::from("ab")].heap_size(),
+            size_of::<String>() + "ab".len()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ScopeCellMut clone budget exceeded")]
+    fn test_get_mut_panics_over_budget() {
+        let budget = CloneBudget::new().with_max_clones(0);
+        let mut value = 1;
+        let mut cell = ScopeCellMut::new(&mut value);
+        cell.set_budget(&budget);
+        *cell.get_mut() += 1;
+    }
+}
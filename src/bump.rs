@@ -0,0 +1,105 @@
+use bumpalo::boxed::Box;
+use bumpalo::Bump;
+
+/// A BumpScopeCell works like `ScopeCell`, but allocates its modified copy in a
+/// caller-provided `bumpalo::Bump` arena instead of on the global heap, so the many
+/// short-lived edits of a frame cost a pointer bump each and are freed together by
+/// `Bump::reset()`.  A discarded copy is dropped right away, but its arena memory is only
+/// reclaimed by that reset.  Heap data owned by a clone of `T` (such as a `Vec`'s buffer)
+/// still comes from the global allocator.
+pub struct BumpScopeCell<'a, 'bump, T: Clone> {
+    original_data: &'a T,
+    arena: &'bump Bump,
+    modified_data: Option<Box<'bump, T>>, // Holds the temporary copy, in the arena
+}
+
+impl<'a, 'bump, T: Clone> BumpScopeCell<'a, 'bump, T> {
+    // Create a new BumpScopeCell whose modified copy will live in `arena`
+    pub fn new_in(data: &'a T, arena: &'bump Bump) -> Self {
+        BumpScopeCell {
+            original_data: data,
+            arena,
+            modified_data: None,
+        }
+    }
+
+    // The arena the modified copy is allocated in
+    pub fn arena(&self) -> &'bump Bump {
+        self.arena
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        self.modified_data.as_deref().unwrap_or(self.original_data)
+    }
+
+    // Mutably borrow the data, copying the original into the arena on first use
+    pub fn get_mut(&mut self) -> &mut T {
+        let arena = self.arena;
+        let original = self.original_data;
+        self.modified_data
+            .get_or_insert_with(|| Box::new_in(original.clone(), arena))
+    }
+
+    // Replace the data without copying the original
+    pub fn set(&mut self, value: T) {
+        self.modified_data = Some(Box::new_in(value, self.arena));
+    }
+
+    // Whether a copy has been made or set since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes by dropping the copy
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Consume the cell and return the modified data, or a copy of the original if there is
+    // none, moved out of the arena
+    pub fn into_inner(self) -> T {
+        match self.modified_data {
+            Some(modified) => Box::into_inner(modified),
+            None => self.original_data.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copies_live_in_the_arena() {
+        let mut arena = Bump::new();
+        let positions = [[0.0f32; 3]; 64];
+        for frame in 0..3 {
+            for i in 0..100 {
+                let mut cell = BumpScopeCell::new_in(&positions, &arena);
+                cell.get_mut()[i % 64][0] += 1.0;
+                assert_eq!(cell.get()[i % 64][0], 1.0);
+            }
+            assert!(arena.allocated_bytes() >= 100 * size_of_val(&positions));
+            if frame < 2 {
+                arena.reset();
+            }
+        }
+        assert_eq!(positions[0][0], 0.0);
+    }
+
+    #[test]
+    fn test_revert_set_and_into_inner() {
+        let arena = Bump::new();
+        let names = vec![String::from("a")];
+        let mut cell = BumpScopeCell::new_in(&names, &arena);
+        assert!(std::ptr::eq(cell.arena(), &arena));
+        cell.get_mut().push(String::from("b"));
+        assert!(cell.is_modified());
+        cell.revert();
+        assert_eq!(cell.get(), &names);
+        cell.set(vec![String::from("c")]);
+        assert_eq!(cell.into_inner(), vec![String::from("c")]);
+        assert_eq!(BumpScopeCell::new_in(&names, &arena).into_inner(), names);
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// A CachedScopeCell is a `ScopeCell` tuned for read-mostly inner loops: it keeps a pointer
+/// to whichever of the original or the pending copy is current, updated on write and
+/// revert, so `get()` is a single dereference with no `Option` check.  The pending copy is
+/// boxed so the pointer stays valid when the cell moves; an inline copy, as `ScopeCell`
+/// keeps, would leave it dangling.  Changes are discarded when the cell is dropped.
+pub struct CachedScopeCell<'a, T: Clone> {
+    current: NonNull<T>, // Points at `original_data` or at the pending copy
+    original_data: &'a T,
+    pending: Option<NonNull<T>>, // Holds the temporary copy, from `Box::into_raw`
+    marker: PhantomData<Box<T>>, // The cell owns the pending copy
+}
+
+// The cell shares `&'a T` and owns a `Box<T>`, so it is as thread-safe as those are
+unsafe impl<'a, T: Clone + Send + Sync> Send for CachedScopeCell<'a, T> {}
+unsafe impl<'a, T: Clone + Sync> Sync for CachedScopeCell<'a, T> {}
+
+impl<'a, T: Clone> CachedScopeCell<'a, T> {
+    // Create a new CachedScopeCell from an immutable reference
+    pub fn new(data: &'a T) -> Self {
+        CachedScopeCell {
+            current: NonNull::from(data),
+            original_data: data,
+            pending: None,
+            marker: PhantomData,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    #[inline]
+    pub fn get(&self) -> &T {
+        // `current` points at the original or at the pending copy, which only `&mut self`
+        // methods replace or free
+        unsafe { self.current.as_ref() }
+    }
+
+    // Borrow the original, ignoring any modification
+    pub fn original(&self) -> &T {
+        self.original_data
+    }
+
+    // Mutably borrow the data, copying the original to the heap on first use
+    pub fn get_mut(&mut self) -> &mut T {
+        let pending = match self.pending {
+            Some(pending) => pending,
+            None => self.install(self.original_data.clone()),
+        };
+        // The pending copy is owned by the cell and `&mut self` rules out any other borrow
+        unsafe { &mut *pending.as_ptr() }
+    }
+
+    // Replace the data without copying the original
+    pub fn set(&mut self, value: T) {
+        match self.pending {
+            Some(_) => *self.get_mut() = value,
+            None => {
+                self.install(value);
+            }
+        }
+    }
+
+    fn install(&mut self, value: T) -> NonNull<T> {
+        let pending = NonNull::from(Box::leak(Box::new(value)));
+        self.pending = Some(pending);
+        self.current = pending;
+        pending
+    }
+
+    // Whether a copy has been made or set since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // Revert the changes by dropping the copy and pointing back at the original
+    pub fn revert(&mut self) {
+        self.current = NonNull::from(self.original_data);
+        if let Some(pending) = self.pending.take() {
+            // The pointer came from `Box::leak` and has just been forgotten by the cell
+            drop(unsafe { Box::from_raw(pending.as_ptr()) });
+        }
+    }
+
+    // Consume the cell and return the modified data, or a copy of the original if there is
+    // none
+    pub fn into_inner(mut self) -> T {
+        match self.pending.take() {
+            // As in `revert()`; the drop that follows sees no pending copy
+            Some(pending) => *unsafe { Box::from_raw(pending.as_ptr()) },
+            None => self.original_data.clone(),
+        }
+    }
+}
+
+impl<'a, T: Clone> Drop for CachedScopeCell<'a, T> {
+    fn drop(&mut self) {
+        self.revert();
+    }
+}
+
+// Shows the original and the pending copy, if any
+impl<'a, T: Clone + fmt::Debug> fmt::Debug for CachedScopeCell<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pending = self.pending.map(|pending| unsafe { pending.as_ref() });
+        f.debug_struct("CachedScopeCell")
+            .field("original", self.original_data)
+            .field("pending", &pending)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_follows_writes_and_reverts() {
+        let image = vec![0u8; 16];
+        let mut cell = CachedScopeCell::new(&image);
+        assert!(std::ptr::eq(cell.get(), &image));
+        cell.get_mut()[3] = 9;
+        assert_eq!(cell.get()[3], 9);
+        assert_eq!(cell.original()[3], 0);
+        cell.revert();
+        assert!(!cell.is_modified());
+        assert!(std::ptr::eq(cell.get(), &image));
+        cell.set(vec![1]);
+        cell.set(vec![2]);
+        assert_eq!(format!("{:?}", cell), "CachedScopeCell { original: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], pending: Some([2]) }");
+        assert_eq!(cell.into_inner(), vec![2]);
+    }
+
+    #[test]
+    fn test_pointer_survives_moves() {
+        let base = String::from("base");
+        let mut cells = Vec::new();
+        for i in 0..8 {
+            let mut cell = CachedScopeCell::new(&base);
+            if i % 2 == 1 {
+                cell.get_mut().push('!');
+            }
+            cells.push(cell); // Moves the cell, and later reallocations move it again
+        }
+        let shown: Vec<&str> = cells.iter().map(|cell| cell.get().as_str()).collect();
+        assert_eq!(shown[..2], ["base", "base!"]);
+        assert_eq!(shown.iter().filter(|s| s.ends_with('!')).count(), 4);
+        assert_eq!(CachedScopeCell::new(&base).into_inner(), base);
+    }
+}
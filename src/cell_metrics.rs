@@ -0,0 +1,158 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Receives counts of what the cells do, keyed by the cell's value type and label, to find
+/// the hot spots where lazy copies are frequent or large enough that a `ScopeVec` or a
+/// diff-based cell would pay off.  Every method defaults to doing nothing, so an
+/// implementation only overrides what it tracks.  Install one with `set_metrics`.
+pub trait CellMetrics: Send + Sync {
+    // The first write cloned a baseline of `bytes` bytes (its `size_of`, not counting heap data)
+    fn copied(&self, type_name: &'static str, label: Option<&'static str>, bytes: usize) {
+        let _ = (type_name, label, bytes);
+    }
+
+    // A modified copy was discarded
+    fn reverted(&self, type_name: &'static str, label: Option<&'static str>) {
+        let _ = (type_name, label);
+    }
+
+    // The effective value was written back or extracted
+    fn committed(&self, type_name: &'static str, label: Option<&'static str>) {
+        let _ = (type_name, label);
+    }
+
+    // A commit found the baseline changed under it
+    fn conflicted(&self, type_name: &'static str, label: Option<&'static str>) {
+        let _ = (type_name, label);
+    }
+}
+
+/// The CellMetrics that ignores everything, in effect until `set_metrics` is called.
+pub struct NoMetrics;
+
+impl CellMetrics for NoMetrics {}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static METRICS: RwLock<Option<Arc<dyn CellMetrics>>> = RwLock::new(None);
+
+// Install `metrics` as the process-wide receiver of cell counts, returning the previous one
+pub fn set_metrics<M: CellMetrics + 'static>(metrics: M) -> Option<Arc<dyn CellMetrics>> {
+    let mut slot = METRICS.write().unwrap_or_else(PoisonError::into_inner);
+    INSTALLED.store(true, Ordering::Release);
+    slot.replace(Arc::new(metrics))
+}
+
+// Uninstall the receiver of cell counts, returning it
+pub fn take_metrics() -> Option<Arc<dyn CellMetrics>> {
+    let mut slot = METRICS.write().unwrap_or_else(PoisonError::into_inner);
+    INSTALLED.store(false, Ordering::Release);
+    slot.take()
+}
+
+// The installed receiver, if any.  The flag keeps the common uninstalled case lock-free.
+pub(crate) fn installed() -> Option<Arc<dyn CellMetrics>> {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return None;
+    }
+    METRICS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// A CellMetrics reporting to the `metrics` crate's global recorder: the counters
+/// `scope_cell_copies_total`, `scope_cell_reverts_total`, `scope_cell_commits_total` and
+/// `scope_cell_conflicts_total`, and the histogram `scope_cell_copy_bytes`, all carrying a
+/// `type` label and, for labelled cells, a `cell` label.
+#[cfg(feature = "metrics")]
+pub struct MetricsRecorder;
+
+#[cfg(feature = "metrics")]
+impl MetricsRecorder {
+    fn labels(type_name: &'static str, label: Option<&'static str>) -> Vec<::metrics::Label> {
+        let mut labels = vec![::metrics::Label::from_static_parts("type", type_name)];
+        if let Some(label) = label {
+            labels.push(::metrics::Label::from_static_parts("cell", label));
+        }
+        labels
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl CellMetrics for MetricsRecorder {
+    fn copied(&self, type_name: &'static str, label: Option<&'static str>, bytes: usize) {
+        let labels = Self::labels(type_name, label);
+        ::metrics::counter!("scope_cell_copies_total", labels.clone()).increment(1);
+        ::metrics::histogram!("scope_cell_copy_bytes", labels).record(bytes as f64);
+    }
+
+    fn reverted(&self, type_name: &'static str, label: Option<&'static str>) {
+        let labels = Self::labels(type_name, label);
+        ::metrics::counter!("scope_cell_reverts_total", labels).increment(1);
+    }
+
+    fn committed(&self, type_name: &'static str, label: Option<&'static str>) {
+        let labels = Self::labels(type_name, label);
+        ::metrics::counter!("scope_cell_commits_total", labels).increment(1);
+    }
+
+    fn conflicted(&self, type_name: &'static str, label: Option<&'static str>) {
+        let labels = Self::labels(type_name, label);
+        ::metrics::counter!("scope_cell_conflicts_total", labels).increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScopeCell, ScopeCellMut};
+    use std::sync::Mutex;
+
+    // Counts the events of one type; other tests' cells report here too while it is installed
+    #[derive(Clone, Default)]
+    struct Counts(Arc<Mutex<(usize, usize, usize, usize)>>);
+
+    #[derive(Clone)]
+    struct Tracked([u64; 4]);
+
+    impl CellMetrics for Counts {
+        fn copied(&self, type_name: &'static str, _: Option<&'static str>, bytes: usize) {
+            if type_name.ends_with("Tracked") {
+                let mut counts = self.0.lock().unwrap();
+                counts.0 += 1;
+                counts.1 += bytes;
+            }
+        }
+
+        fn reverted(&self, type_name: &'static str, _: Option<&'static str>) {
+            if type_name.ends_with("Tracked") {
+                self.0.lock().unwrap().2 += 1;
+            }
+        }
+
+        fn committed(&self, type_name: &'static str, _: Option<&'static str>) {
+            if type_name.ends_with("Tracked") {
+                self.0.lock().unwrap().3 += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_metrics_counts_per_type() {
+        let counts = Counts::default();
+        set_metrics(counts.clone());
+        let base = Tracked([0; 4]);
+        let mut cell = ScopeCell::new(&base);
+        cell.get_mut().0[0] = 1;
+        cell.revert();
+        cell.get_mut().0[1] = 1;
+        drop(cell);
+        let mut owned = base.clone();
+        let mut cell = ScopeCellMut::new(&mut owned);
+        cell.get_mut().0[2] = 1;
+        cell.commit();
+        take_metrics();
+        ScopeCell::new(&base).get_mut().0[3] = 1; // No longer counted
+        assert_eq!(*counts.0.lock().unwrap(), (3, 96, 2, 1));
+    }
+}
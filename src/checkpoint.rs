@@ -0,0 +1,267 @@
+use std::any::Any;
+use std::marker::PhantomData;
+
+use crate::OwnedScopeCell;
+
+/// A value whose state can be captured and later put back.  OwnedScopeCell implements it, and
+/// any `Clone` type can with `type State = Self`, `snapshot() -> self.clone()` and
+/// `restore(state) -> self.clone_from(state)`.
+pub trait Snapshot {
+    type State: 'static;
+
+    // Capture the current state
+    fn snapshot(&self) -> Self::State;
+
+    // Put a captured state back
+    fn restore(&mut self, state: &Self::State);
+}
+
+// Object-safe form of Snapshot, so a manager can hold participants of different types
+trait AnySnapshot {
+    fn snapshot_any(&self) -> Box<dyn Any>;
+
+    fn restore_any(&mut self, state: &dyn Any);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<S: Snapshot + 'static> AnySnapshot for S {
+    fn snapshot_any(&self) -> Box<dyn Any> {
+        Box::new(self.snapshot())
+    }
+
+    fn restore_any(&mut self, state: &dyn Any) {
+        self.restore(state.downcast_ref().unwrap());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// A labeled checkpoint captured by a CheckpointManager
+struct Checkpoint {
+    label: String,
+    states: Vec<Box<dyn Any>>, // One per participant registered when it was taken
+}
+
+/// Typed handle to a participant owned by a `CheckpointManager`.
+pub struct Handle<S> {
+    index: usize,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for Handle<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Handle<S> {}
+
+/// A CheckpointManager owns many snapshot-able values, such as the cells making up a game
+/// world, captures labeled checkpoints across all of them, and can restore the whole set to
+/// any checkpoint.  Participants are reached through the handles returned by `add()`.
+/// Participants added after a checkpoint are left alone when it is restored.
+pub struct CheckpointManager {
+    participants: Vec<Box<dyn AnySnapshot>>,
+    checkpoints: Vec<Checkpoint>, // Oldest first
+}
+
+impl CheckpointManager {
+    // Create a manager with no participants
+    pub fn new() -> Self {
+        CheckpointManager {
+            participants: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    // Take ownership of a participant, returning its handle
+    pub fn add<S: Snapshot + 'static>(&mut self, participant: S) -> Handle<S> {
+        self.participants.push(Box::new(participant));
+        Handle {
+            index: self.participants.len() - 1,
+            marker: PhantomData,
+        }
+    }
+
+    // Borrow a participant.  Panics if the handle came from another manager.
+    pub fn get<S: 'static>(&self, handle: Handle<S>) -> &S {
+        self.participants[handle.index]
+            .as_any()
+            .downcast_ref()
+            .unwrap()
+    }
+
+    // Mutably borrow a participant
+    pub fn get_mut<S: 'static>(&mut self, handle: Handle<S>) -> &mut S {
+        self.participants[handle.index]
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // Number of participants
+    pub fn len(&self) -> usize {
+        self.participants.len()
+    }
+
+    // Whether there are no participants
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    // Capture every participant under `label`.  Labels need not be unique; restoring by
+    // label uses the most recent checkpoint with it.
+    pub fn checkpoint<L: Into<String>>(&mut self, label: L) {
+        let states = self
+            .participants
+            .iter()
+            .map(|participant| participant.snapshot_any())
+            .collect();
+        self.checkpoints.push(Checkpoint {
+            label: label.into(),
+            states,
+        });
+    }
+
+    // Restore every participant to the most recent checkpoint named `label`, keeping the
+    // checkpoint for later restores.  Returns false if there is no such checkpoint.
+    pub fn restore(&mut self, label: &str) -> bool {
+        let Some(index) = self.position(label) else {
+            return false;
+        };
+        let states = &self.checkpoints[index].states;
+        for (participant, state) in self.participants.iter_mut().zip(states) {
+            participant.restore_any(state.as_ref());
+        }
+        true
+    }
+
+    // Restore to `label` and drop every checkpoint taken after it
+    pub fn rewind(&mut self, label: &str) -> bool {
+        let Some(index) = self.position(label) else {
+            return false;
+        };
+        self.restore(label);
+        self.checkpoints.truncate(index + 1);
+        true
+    }
+
+    // Drop the most recent checkpoint named `label`
+    pub fn discard(&mut self, label: &str) -> bool {
+        let Some(index) = self.position(label) else {
+            return false;
+        };
+        self.checkpoints.remove(index);
+        true
+    }
+
+    // Labels of the stored checkpoints, oldest first
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.checkpoints
+            .iter()
+            .map(|checkpoint| checkpoint.label.as_str())
+    }
+
+    // Number of stored checkpoints
+    pub fn checkpoint_count(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    fn position(&self, label: &str) -> Option<usize> {
+        self.checkpoints
+            .iter()
+            .rposition(|checkpoint| checkpoint.label == label)
+    }
+}
+
+impl Default for CheckpointManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The baseline and any pending edit are captured together
+impl<T: Clone + 'static> Snapshot for OwnedScopeCell<T> {
+    type State = OwnedScopeCell<T>;
+
+    fn snapshot(&self) -> Self::State {
+        self.clone()
+    }
+
+    fn restore(&mut self, state: &Self::State) {
+        self.clone_from(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Monster {
+        hp: u32,
+    }
+
+    impl Snapshot for Monster {
+        type State = Monster;
+
+        fn snapshot(&self) -> Self::State {
+            self.clone()
+        }
+
+        fn restore(&mut self, state: &Self::State) {
+            self.clone_from(state);
+        }
+    }
+
+    #[test]
+    fn test_preview_turn_and_restore() {
+        let mut world = CheckpointManager::new();
+        let monster = world.add(Monster { hp: 10 });
+        let turn = world.add(OwnedScopeCell::new(1u32));
+        world.checkpoint("turn 1");
+
+        world.get_mut(monster).hp = 4; // Preview the turn
+        *world.get_mut(turn).get_mut() = 2;
+        world.checkpoint("turn 2");
+        world.get_mut(monster).hp = 0;
+
+        assert!(world.restore("turn 1"));
+        assert_eq!(world.get(monster).hp, 10);
+        assert!(!world.get(turn).is_modified()); // Back to the untouched cell
+        assert!(world.restore("turn 2"));
+        assert_eq!(world.get(monster).hp, 4);
+        assert_eq!(*world.get(turn).get(), 2);
+        assert!(!world.restore("missing"));
+    }
+
+    #[test]
+    fn test_rewind_and_discard() {
+        let mut world = CheckpointManager::default();
+        let monster = world.add(Monster { hp: 1 });
+        world.checkpoint("a");
+        world.checkpoint("b");
+        world.checkpoint("c");
+        world.get_mut(monster).hp = 9;
+        assert!(world.rewind("a"));
+        assert_eq!(world.labels().collect::<Vec<_>>(), vec!["a"]);
+        assert!(world.discard("a"));
+        assert_eq!(world.checkpoint_count(), 0);
+        assert_eq!(world.get(monster).hp, 1);
+
+        world.checkpoint("late");
+        let added = world.add(Monster { hp: 5 }); // Not part of "late"
+        world.get_mut(added).hp = 6;
+        world.restore("late");
+        assert_eq!(world.get(added).hp, 6);
+    }
+}
@@ -0,0 +1,327 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Deref;
+
+/// One layer of a ConfigStack.  Entries use the same overlay encoding as `ScopeHashMap`:
+/// `Some(value)` sets a key and `None` masks whatever the layers below say about it.  The
+/// layers cannot simply be ScopeHashMaps, since each of those mutably borrows the single map
+/// it overlays: a stack of them could not live in one owned value, and a lower layer could
+/// not be edited while a higher one exists.
+pub struct ConfigLayer<K, V> {
+    name: String,
+    entries: HashMap<K, Option<V>>,
+}
+
+impl<K: Eq + Hash, V> ConfigLayer<K, V> {
+    // The layer's name, as reported by `ConfigStack::source`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Set a key in this layer, returning the value it previously set here
+    pub fn set(&mut self, key: K, value: V) -> Option<V> {
+        self.entries.insert(key, Some(value)).flatten()
+    }
+
+    // Hide a key from the layers below, as if it were not configured at all
+    pub fn unset(&mut self, key: K) {
+        self.entries.insert(key, None);
+    }
+
+    // Drop this layer's say about a key, so lookups fall through to the layers below
+    pub fn clear<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.remove(key);
+    }
+
+    // Number of keys this layer sets or unsets
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Whether this layer says nothing about any key
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A ConfigStack resolves configuration keys through named layers, such as defaults <
+/// file < env, where a higher layer overrides the ones below it.  `scoped()` pushes a
+/// temporary layer that is popped, reverting its keys, when its guard drops, and
+/// `overlay()` gives one request its own overrides on top of a shared stack.
+pub struct ConfigStack<K, V> {
+    layers: Vec<ConfigLayer<K, V>>, // Lowest priority first
+}
+
+impl<K: Eq + Hash, V> ConfigStack<K, V> {
+    // Create an empty ConfigStack
+    pub fn new() -> Self {
+        ConfigStack { layers: Vec::new() }
+    }
+
+    // Push a layer on top, holding `entries`, and return it for further edits
+    pub fn push_layer<N, I>(&mut self, name: N, entries: I) -> &mut ConfigLayer<K, V>
+    where
+        N: Into<String>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.layers.push(ConfigLayer {
+            name: name.into(),
+            entries: entries
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect(),
+        });
+        self.layers.last_mut().unwrap()
+    }
+
+    // Remove the top layer, reverting every key it set or unset
+    pub fn pop_layer(&mut self) -> Option<ConfigLayer<K, V>> {
+        self.layers.pop()
+    }
+
+    // The topmost layer with the given name
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut ConfigLayer<K, V>> {
+        self.layers
+            .iter_mut()
+            .rev()
+            .find(|layer| layer.name == name)
+    }
+
+    // Names of the layers, lowest priority first
+    pub fn layer_names(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect()
+    }
+
+    // Push a temporary layer, popped again when the returned guard drops.  The guard
+    // derefs to the stack for lookups, and nests further scoped layers with `scoped()`.
+    pub fn scoped<N: Into<String>>(&mut self, name: N) -> ScopedLayer<'_, K, V> {
+        let depth = self.layers.len();
+        self.push_layer(name, []);
+        ScopedLayer {
+            stack: self,
+            depth,
+            floor: depth,
+        }
+    }
+
+    // Start a private set of overrides on top of the stack, discarded when dropped
+    pub fn overlay(&self) -> ConfigOverlay<'_, K, V> {
+        ConfigOverlay {
+            stack: self,
+            entries: HashMap::new(),
+        }
+    }
+
+    // Resolve a key top-down
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lookup(key)?.1
+    }
+
+    // Name of the layer that decides a key, whether it sets or unsets it
+    pub fn source<Q>(&self, key: &Q) -> Option<&str>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Some(self.lookup(key)?.0)
+    }
+
+    // Flatten every layer into the effective configuration
+    pub fn resolve(&self) -> HashMap<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut resolved = HashMap::new();
+        for layer in &self.layers {
+            for (key, entry) in &layer.entries {
+                match entry {
+                    Some(value) => resolved.insert(key.clone(), value.clone()),
+                    None => resolved.remove(key),
+                };
+            }
+        }
+        resolved
+    }
+
+    // The deciding layer's name and entry for a key
+    fn lookup<Q>(&self, key: &Q) -> Option<(&str, Option<&V>)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.layers.iter().rev().find_map(|layer| {
+            let entry = layer.entries.get(key)?;
+            Some((layer.name.as_str(), entry.as_ref()))
+        })
+    }
+}
+
+impl<K: Eq + Hash, V> Default for ConfigStack<K, V> {
+    fn default() -> Self {
+        ConfigStack::new()
+    }
+}
+
+/// Guard returned by `ConfigStack::scoped`; pops its layer, and any layer pushed above
+/// it, when dropped.
+pub struct ScopedLayer<'s, K: Eq + Hash, V> {
+    stack: &'s mut ConfigStack<K, V>,
+    depth: usize, // Number of layers below this one
+    floor: usize, // Number of layers below the outermost guard, which stay read-only
+}
+
+impl<'s, K: Eq + Hash, V> ScopedLayer<'s, K, V> {
+    // The scoped layer itself, for setting and unsetting keys
+    pub fn layer(&mut self) -> &mut ConfigLayer<K, V> {
+        &mut self.stack.layers[self.depth]
+    }
+
+    // Push a further temporary layer above this one, like `ConfigStack::scoped`
+    pub fn scoped<N: Into<String>>(&mut self, name: N) -> ScopedLayer<'_, K, V> {
+        let mut nested = self.stack.scoped(name);
+        nested.floor = self.floor;
+        nested
+    }
+
+    // The topmost temporary layer with the given name, like `ConfigStack::layer_mut` but
+    // limited to the layers pushed by this guard and the guards it is nested in, so edits
+    // through the guard are always reverted.  The stack itself is only reachable immutably,
+    // so no layer can be popped from under the guard either.
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut ConfigLayer<K, V>> {
+        self.stack.layers[self.floor..]
+            .iter_mut()
+            .rev()
+            .find(|layer| layer.name == name)
+    }
+}
+
+impl<'s, K: Eq + Hash, V> Deref for ScopedLayer<'s, K, V> {
+    type Target = ConfigStack<K, V>;
+
+    fn deref(&self) -> &Self::Target {
+        self.stack
+    }
+}
+
+impl<'s, K: Eq + Hash, V> Drop for ScopedLayer<'s, K, V> {
+    fn drop(&mut self) {
+        self.stack.layers.truncate(self.depth);
+    }
+}
+
+/// Private overrides on top of a shared ConfigStack, such as one web request's.  Reads fall
+/// through to the stack; writes stay in the overlay and are discarded when it drops.
+pub struct ConfigOverlay<'s, K, V> {
+    stack: &'s ConfigStack<K, V>,
+    entries: HashMap<K, Option<V>>, // `None` masks a key
+}
+
+impl<'s, K: Eq + Hash, V> ConfigOverlay<'s, K, V> {
+    // Override a key for this overlay only
+    pub fn set(&mut self, key: K, value: V) {
+        self.entries.insert(key, Some(value));
+    }
+
+    // Hide a key for this overlay only
+    pub fn unset(&mut self, key: K) {
+        self.entries.insert(key, None);
+    }
+
+    // Resolve a key, checking the overlay before the stack
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.entries.get(key) {
+            Some(entry) => entry.as_ref(),
+            None => self.stack.get(key),
+        }
+    }
+
+    // Whether the overlay overrides anything
+    pub fn is_modified(&self) -> bool {
+        !self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack() -> ConfigStack<&'static str, String> {
+        let mut stack = ConfigStack::new();
+        stack.push_layer(
+            "defaults",
+            [
+                ("port", String::from("80")),
+                ("host", String::from("localhost")),
+            ],
+        );
+        stack.push_layer("file", [("port", String::from("8080"))]);
+        stack.push_layer("env", [("debug", String::from("1"))]);
+        stack
+    }
+
+    #[test]
+    fn test_top_down_lookup() {
+        let mut stack = stack();
+        assert_eq!(stack.get("port").unwrap(), "8080");
+        assert_eq!(stack.source("port"), Some("file"));
+        assert_eq!(stack.get("host").unwrap(), "localhost");
+        stack.layer_mut("env").unwrap().unset("host");
+        assert_eq!(stack.get("host"), None); // Masked, even though defaults sets it
+        assert_eq!(stack.source("host"), Some("env"));
+        assert_eq!(stack.resolve().len(), 2);
+        assert_eq!(stack.layer_names(), vec!["defaults", "file", "env"]);
+    }
+
+    #[test]
+    fn test_scoped_layer_reverts() {
+        let mut stack = stack();
+        {
+            let mut request = stack.scoped("request");
+            request.layer().set("port", String::from("9000"));
+            assert_eq!(request.get("port").unwrap(), "9000");
+            {
+                let mut nested = request.scoped("nested");
+                nested.layer().unset("debug");
+                assert_eq!(nested.get("debug"), None);
+                nested.layer_mut("request").unwrap().clear("port");
+                assert!(nested.layer_mut("env").is_none()); // Permanent layers stay read-only
+            }
+            assert_eq!(request.get("debug").unwrap(), "1");
+            assert_eq!(request.get("port").unwrap(), "8080");
+        }
+        assert_eq!(stack.get("port").unwrap(), "8080");
+        assert_eq!(stack.layer_names().len(), 3);
+        assert_eq!(stack.pop_layer().unwrap().name(), "env");
+        assert_eq!(stack.get("debug"), None);
+    }
+
+    #[test]
+    fn test_overlays_share_the_stack() {
+        let stack = stack();
+        let mut first = stack.overlay();
+        let second = stack.overlay();
+        first.set("port", String::from("1"));
+        first.unset("host");
+        assert_eq!(first.get("port").unwrap(), "1");
+        assert_eq!(first.get("host"), None);
+        assert_eq!(second.get("port").unwrap(), "8080"); // Unaffected by `first`
+        assert!(!second.is_modified());
+    }
+}
@@ -0,0 +1,98 @@
+use std::cell::Cell;
+
+/// A CopyScopeCell is a `ScopeCell` specialised for `Copy` types.  The scratch value is
+/// stored inline and initialised from the original up front, so reads and writes never
+/// go through `clone()` or an `Option` check.  Changes are discarded on drop.
+pub struct CopyScopeCell<'a, T: Copy> {
+    original_data: &'a T,
+    current: Cell<T>,  // Effective value, starts as a copy of the original
+    dirty: Cell<bool>, // Set on mutation, cleared by revert/reset_tracking
+}
+
+impl<'a, T: Copy> CopyScopeCell<'a, T> {
+    // Create a new CopyScopeCell from an immutable reference
+    pub fn new(data: &'a T) -> Self {
+        CopyScopeCell {
+            original_data: data,
+            current: Cell::new(*data),
+            dirty: Cell::new(false),
+        }
+    }
+
+    // Read the effective value
+    pub fn get(&self) -> T {
+        self.current.get()
+    }
+
+    // Read the original value
+    pub fn original(&self) -> T {
+        *self.original_data
+    }
+
+    // Overwrite the effective value
+    pub fn set(&self, value: T) {
+        self.current.set(value);
+        self.dirty.set(true);
+    }
+
+    // Transform the effective value in place
+    pub fn update<F: FnOnce(T) -> T>(&self, f: F) {
+        self.set(f(self.current.get()));
+    }
+
+    // Mutably borrow the effective value
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty.set(true);
+        self.current.get_mut()
+    }
+
+    // Revert to the original value
+    pub fn revert(&mut self) {
+        self.current.set(*self.original_data);
+        self.dirty.set(false);
+    }
+
+    // Whether the value has been mutated since creation, the last revert, or the last reset_tracking
+    pub fn is_modified(&self) -> bool {
+        self.dirty.get()
+    }
+
+    // Clear the dirty flag without discarding the modified value
+    pub fn reset_tracking(&mut self) {
+        self.dirty.set(false);
+    }
+
+    // Consume the cell and return the effective value
+    pub fn into_inner(self) -> T {
+        self.current.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_revert() {
+        let data = 1.5f64;
+        let mut cell = CopyScopeCell::new(&data);
+        assert_eq!(cell.get(), 1.5);
+        cell.set(2.5);
+        cell.update(|v| v * 2.0);
+        assert_eq!(cell.get(), 5.0);
+        assert_eq!(cell.original(), 1.5);
+        assert!(cell.is_modified());
+        cell.revert();
+        assert_eq!(cell.get(), 1.5);
+        assert!(!cell.is_modified());
+    }
+
+    #[test]
+    fn test_get_mut_and_into_inner() {
+        let data = (1, 2);
+        let mut cell = CopyScopeCell::new(&data);
+        cell.get_mut().0 = 10;
+        assert_eq!(cell.into_inner(), (10, 2));
+        assert_eq!(data, (1, 2)); // Original data should not change
+    }
+}
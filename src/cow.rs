@@ -0,0 +1,107 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// A CowScopeCell shares its baseline through an `Arc<T>`, so any number of cells (and
+/// clones of a cell) can view a large value cheaply.  The first write clones the value via
+/// `Arc::make_mut`; later writes mutate that private copy in place.
+#[derive(Clone)]
+pub struct CowScopeCell<T: Clone> {
+    original_data: Arc<T>,
+    current: Arc<T>, // Same allocation as the original until the first write
+}
+
+impl<T: Clone> CowScopeCell<T> {
+    // Create a new CowScopeCell sharing `data` as its baseline
+    pub fn new(data: Arc<T>) -> Self {
+        CowScopeCell {
+            current: Arc::clone(&data),
+            original_data: data,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        &self.current
+    }
+
+    // Mutably borrow the data, cloning it if it is still shared
+    pub fn get_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.current)
+    }
+
+    // The shared baseline
+    pub fn original(&self) -> &Arc<T> {
+        &self.original_data
+    }
+
+    // A shared handle to the effective value.  Writing to the cell afterwards clones again,
+    // so the handle keeps seeing the value as it was.
+    pub fn share(&self) -> Arc<T> {
+        Arc::clone(&self.current)
+    }
+
+    // Whether the cell no longer shares the baseline allocation
+    pub fn is_modified(&self) -> bool {
+        !Arc::ptr_eq(&self.original_data, &self.current)
+    }
+
+    // Go back to sharing the baseline
+    pub fn revert(&mut self) {
+        self.current = Arc::clone(&self.original_data);
+    }
+
+    // Adopt the effective value as the new baseline
+    pub fn rebase(&mut self) {
+        self.original_data = Arc::clone(&self.current);
+    }
+
+    // Consume the cell and return the effective value as an `Arc`
+    pub fn into_inner(self) -> Arc<T> {
+        self.current
+    }
+}
+
+// Shows the baseline and the effective value, and whether they are still shared
+impl<T: Clone + fmt::Debug> fmt::Debug for CowScopeCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CowScopeCell")
+            .field("original", &self.original_data)
+            .field("current", &self.current)
+            .field("modified", &self.is_modified())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_views_share_until_written() {
+        let config = Arc::new(vec![1, 2, 3]);
+        let mut a = CowScopeCell::new(Arc::clone(&config));
+        let b = a.clone();
+        assert!(Arc::ptr_eq(&a.share(), &b.share())); // No clone yet
+
+        a.get_mut().push(4);
+        assert!(a.is_modified());
+        assert!(!b.is_modified());
+        assert_eq!(*a.get(), vec![1, 2, 3, 4]);
+        assert_eq!(*b.get(), vec![1, 2, 3]);
+        assert_eq!(*config, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_revert_and_into_inner() {
+        let mut cell = CowScopeCell::new(Arc::new(String::from("base")));
+        cell.get_mut().push('!');
+        let snapshot = cell.share();
+        cell.get_mut().push('?'); // Shared snapshot forces another copy
+        assert_eq!(*snapshot, "base!");
+        assert_eq!(cell.get(), "base!?");
+        cell.revert();
+        assert!(!cell.is_modified());
+        let original = Arc::clone(cell.original());
+        assert!(Arc::ptr_eq(&cell.into_inner(), &original)); // Back to the shared baseline
+    }
+}
@@ -0,0 +1,98 @@
+/// A ScopeDefer runs a closure when it is dropped, including during a panic, unless it was
+/// cancelled first.  The `defer!` macro creates one for the rest of the enclosing block.
+/// For cleanup that needs a cell's final value, use the cells' `on_drop` instead.
+#[must_use = "the action runs as soon as the ScopeDefer is dropped"]
+pub struct ScopeDefer<F: FnOnce()> {
+    action: Option<F>,
+}
+
+impl<F: FnOnce()> ScopeDefer<F> {
+    // Run `action` when the guard is dropped
+    pub fn new(action: F) -> Self {
+        ScopeDefer {
+            action: Some(action),
+        }
+    }
+
+    // Drop the guard without running the action
+    pub fn cancel(mut self) {
+        self.action = None;
+    }
+
+    // Run the action now rather than at the end of the scope
+    pub fn run(mut self) {
+        if let Some(action) = self.action.take() {
+            action();
+        }
+    }
+}
+
+impl<F: FnOnce()> Drop for ScopeDefer<F> {
+    fn drop(&mut self) {
+        if let Some(action) = self.action.take() {
+            action();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScopeCell, ScopeCellMut};
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_runs_in_reverse_order() {
+        let log = RefCell::new(Vec::new());
+        {
+            crate::defer!(log.borrow_mut().push("first registered"));
+            crate::defer!(log.borrow_mut().push("second registered"));
+            log.borrow_mut().push("body");
+        }
+        assert_eq!(
+            *log.borrow(),
+            vec!["body", "second registered", "first registered"]
+        );
+    }
+
+    #[test]
+    fn test_cancel_and_run() {
+        let log = RefCell::new(Vec::new());
+        ScopeDefer::new(|| log.borrow_mut().push("cancelled")).cancel();
+        let guard = ScopeDefer::new(|| log.borrow_mut().push("ran early"));
+        guard.run();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = ScopeDefer::new(|| log.borrow_mut().push("unwound"));
+            panic!("test failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), vec!["ran early", "unwound"]);
+    }
+
+    #[test]
+    fn test_cell_on_drop_sees_final_value() {
+        let seen = Mutex::new(Vec::new());
+        let mut data = vec![1];
+        {
+            let mut cell = ScopeCellMut::new(&mut data);
+            cell.on_drop(|value| seen.lock().unwrap().push(value.clone()));
+            cell.get_mut().push(2);
+            cell.commit();
+        }
+        {
+            let mut cell = ScopeCellMut::new(&mut data);
+            cell.on_drop(|value| seen.lock().unwrap().push(value.clone()));
+            cell.get_mut().push(3); // Reverted on drop
+        }
+        {
+            let mut cell = ScopeCell::new(&data);
+            cell.on_drop(|value| seen.lock().unwrap().push(value.clone()));
+            cell.get_mut().clear();
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![vec![1, 2], vec![1, 2], vec![1, 2]]
+        );
+    }
+}
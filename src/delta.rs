@@ -0,0 +1,115 @@
+/// The pending changes of a cell, exported with `export_delta()` to cross a process
+/// boundary instead of the full value.  `base_version` names the baseline the changes were
+/// made against, and `import_delta()` refuses a delta whose version does not match its own.
+/// With the `serde` feature a Delta is `Serialize` and `Deserialize`, provided its changes
+/// are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delta<C> {
+    pub base_version: u64,
+    pub changes: Vec<C>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EntryChange, JournalCell, Operation, OwnedScopeCell, ScopeError};
+    use std::collections::BTreeMap;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Move {
+        Step(i32),
+        Home,
+    }
+
+    impl Operation<i32> for Move {
+        fn apply(&mut self, target: &mut i32) {
+            match self {
+                Move::Step(n) => *target += *n,
+                Move::Home => *target = 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_journal_delta_sync() {
+        let mut server = JournalCell::new(10);
+        let mut client = JournalCell::new(10);
+        server.apply(Move::Step(5));
+        server.apply(Move::Home);
+        let delta = server.export_delta();
+        assert_eq!(delta.changes, [Move::Step(5), Move::Home]);
+        server.commit();
+        server.commit(); // Nothing to commit, so the version stays
+        client.import_delta(delta.clone()).unwrap();
+        assert_eq!((client.get(), client.version()), (server.get(), 1));
+        assert_eq!(client.import_delta(delta), Err(ScopeError::Conflict)); // Stale baseline
+        client.apply(Move::Step(1));
+        let next = server.export_delta();
+        assert_eq!(client.import_delta(next), Err(ScopeError::Conflict)); // Local edits
+        assert_eq!(*client.get(), 1);
+    }
+
+    fn scores() -> BTreeMap<&'static str, u32> {
+        BTreeMap::from([("ann", 3), ("bob", 5)])
+    }
+
+    #[test]
+    fn test_diff_delta_sync() {
+        let mut server = OwnedScopeCell::new(scores());
+        let mut client = OwnedScopeCell::new(scores());
+        server.get_mut().insert("bob", 6);
+        let delta = server.export_delta();
+        assert_eq!(
+            delta.changes,
+            [EntryChange::Changed {
+                key: "bob",
+                old: 5,
+                new: 6
+            }]
+        );
+        server.rebase();
+        server.rebase(); // A no-op rebase keeps the version
+        assert_eq!(server.version(), 1);
+        client.get_mut().insert("cat", 1);
+        assert_eq!(
+            client.import_delta(delta.clone()),
+            Err(ScopeError::Conflict)
+        ); // Local edit
+        client.reset();
+        client.import_delta(delta).unwrap();
+        assert_eq!((client.baseline(), client.version()), (server.get(), 1));
+        assert!(!client.is_modified());
+        let late = OwnedScopeCell::with_version(scores(), 7).export_delta();
+        assert_eq!(client.import_delta(late), Err(ScopeError::Conflict));
+    }
+
+    #[test]
+    fn test_commit_delta_keeps_versions_in_step() {
+        let mut server = OwnedScopeCell::new(scores());
+        let mut client = OwnedScopeCell::new(scores());
+        server.get_mut().insert("ann", 3); // Touched but unchanged
+        let empty = server.commit_delta();
+        assert!(empty.changes.is_empty() && !server.is_modified());
+        client.import_delta(empty).unwrap();
+        server.get_mut().remove("bob");
+        client.import_delta(server.commit_delta()).unwrap();
+        assert_eq!((server.version(), client.version()), (1, 1));
+        assert_eq!(client.get(), server.get());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_delta_round_trips_through_json() {
+        let mut cell = OwnedScopeCell::new(BTreeMap::from([(String::from("ann"), 3)]));
+        cell.get_mut().remove("ann");
+        let json = serde_json::to_string(&cell.export_delta()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"base_version":0,"changes":[{"Removed":{"key":"ann","value":3}}]}"#
+        );
+        let delta: super::Delta<EntryChange<String, i32>> = serde_json::from_str(&json).unwrap();
+        let mut replica = OwnedScopeCell::new(BTreeMap::from([(String::from("ann"), 3)]));
+        replica.import_delta(delta).unwrap();
+        assert!(replica.get().is_empty());
+    }
+}
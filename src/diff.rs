@@ -0,0 +1,388 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Types that can describe how a modified value differs from its original, as a list of
+/// structured changes.  Used by the cells' `changes()` methods to build audit logs.
+pub trait Diff {
+    type Change;
+
+    // Changes that turn `original` into `self`
+    fn diff_from(&self, original: &Self) -> Vec<Self::Change>;
+}
+
+/// Types that can replay a list of their own changes onto a value, so that an edit
+/// previewed against one instance can be applied to another.  Map changes are keyed, so
+/// they apply cleanly to a target that has drifted from the original in unrelated entries.
+pub trait Patch: Diff {
+    // Apply `changes`, as produced by `diff_from`, to `self`
+    fn apply_changes(&mut self, changes: Vec<Self::Change>);
+}
+
+/// A change to a vector.  `Removed` indices refer to the original vector and `Inserted`
+/// indices to the modified one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementChange<T> {
+    Inserted { index: usize, value: T },
+    Removed { index: usize, value: T },
+}
+
+/// A change to a string.  `Removed` offsets are byte offsets into the original string and
+/// `Inserted` offsets byte offsets into the modified one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextChange {
+    Inserted { offset: usize, text: String },
+    Removed { offset: usize, text: String },
+}
+
+/// A change to a map entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryChange<K, V> {
+    Added { key: K, value: V },
+    Removed { key: K, value: V },
+    Changed { key: K, old: V, new: V },
+}
+
+// One step of an edit script between two sequences.
+pub(crate) enum Step {
+    Keep,
+    Remove(usize),
+    Insert(usize),
+}
+
+// Largest LCS table `edit_script` builds, in entries; about 8 MiB on 64-bit targets
+const MAX_LCS_TABLE: usize = 1 << 20;
+
+// Shortest edit script via longest common subsequence, after trimming the common prefix
+// and suffix so that small edits to long sequences stay cheap.  When what remains would
+// need a table larger than `MAX_LCS_TABLE`, the script replaces the differing middle
+// wholesale instead of searching for the shortest one.
+pub(crate) fn edit_script<T: PartialEq>(original: &[T], modified: &[T]) -> Vec<Step> {
+    let prefix = original
+        .iter()
+        .zip(modified)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(modified[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &original[prefix..original.len() - suffix];
+    let b = &modified[prefix..modified.len() - suffix];
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_LCS_TABLE {
+        let inserts = (0..b.len()).map(|j| Step::Insert(prefix + j));
+        let removes = (0..a.len()).map(|i| Step::Remove(prefix + i));
+        return inserts.chain(removes).collect();
+    }
+
+    // lcs[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            steps.push(Step::Keep);
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            steps.push(Step::Insert(prefix + j));
+            j += 1;
+        } else {
+            steps.push(Step::Remove(prefix + i));
+            i += 1;
+        }
+    }
+    steps
+}
+
+impl<T: Clone + PartialEq> Diff for Vec<T> {
+    type Change = ElementChange<T>;
+
+    fn diff_from(&self, original: &Self) -> Vec<Self::Change> {
+        edit_script(original, self)
+            .into_iter()
+            .filter_map(|step| match step {
+                Step::Keep => None,
+                Step::Remove(index) => Some(ElementChange::Removed {
+                    index,
+                    value: original[index].clone(),
+                }),
+                Step::Insert(index) => Some(ElementChange::Inserted {
+                    index,
+                    value: self[index].clone(),
+                }),
+            })
+            .collect()
+    }
+}
+
+impl Diff for String {
+    type Change = TextChange;
+
+    fn diff_from(&self, original: &Self) -> Vec<Self::Change> {
+        let old: Vec<(usize, char)> = original.char_indices().collect();
+        let new: Vec<(usize, char)> = self.char_indices().collect();
+        let old_chars: Vec<char> = old.iter().map(|&(_, c)| c).collect();
+        let new_chars: Vec<char> = new.iter().map(|&(_, c)| c).collect();
+
+        // Coalesce runs of single-character steps into text changes
+        let mut changes: Vec<TextChange> = Vec::new();
+        let mut last_removed = None;
+        let mut last_inserted = None;
+        for step in edit_script(&old_chars, &new_chars) {
+            match step {
+                Step::Keep => {
+                    last_removed = None;
+                    last_inserted = None;
+                }
+                Step::Remove(index) => {
+                    let (offset, c) = old[index];
+                    match (last_removed, changes.last_mut()) {
+                        (Some(prev), Some(TextChange::Removed { text, .. }))
+                            if prev + 1 == index =>
+                        {
+                            text.push(c)
+                        }
+                        _ => changes.push(TextChange::Removed {
+                            offset,
+                            text: c.to_string(),
+                        }),
+                    }
+                    last_removed = Some(index);
+                    last_inserted = None;
+                }
+                Step::Insert(index) => {
+                    let (offset, c) = new[index];
+                    match (last_inserted, changes.last_mut()) {
+                        (Some(prev), Some(TextChange::Inserted { text, .. }))
+                            if prev + 1 == index =>
+                        {
+                            text.push(c)
+                        }
+                        _ => changes.push(TextChange::Inserted {
+                            offset,
+                            text: c.to_string(),
+                        }),
+                    }
+                    last_inserted = Some(index);
+                    last_removed = None;
+                }
+            }
+        }
+        changes
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq> Diff for HashMap<K, V> {
+    type Change = EntryChange<K, V>;
+
+    fn diff_from(&self, original: &Self) -> Vec<Self::Change> {
+        let mut changes = Vec::new();
+        for (key, old) in original {
+            match self.get(key) {
+                None => changes.push(EntryChange::Removed {
+                    key: key.clone(),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => changes.push(EntryChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, value) in self {
+            if !original.contains_key(key) {
+                changes.push(EntryChange::Added {
+                    key: key.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+impl<K: Clone + Ord, V: Clone + PartialEq> Diff for BTreeMap<K, V> {
+    type Change = EntryChange<K, V>;
+
+    fn diff_from(&self, original: &Self) -> Vec<Self::Change> {
+        let mut changes = Vec::new();
+        for (key, old) in original {
+            match self.get(key) {
+                None => changes.push(EntryChange::Removed {
+                    key: key.clone(),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => changes.push(EntryChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, value) in self {
+            if !original.contains_key(key) {
+                changes.push(EntryChange::Added {
+                    key: key.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone + PartialEq> Patch for HashMap<K, V> {
+    fn apply_changes(&mut self, changes: Vec<Self::Change>) {
+        for change in changes {
+            match change {
+                EntryChange::Added { key, value }
+                | EntryChange::Changed {
+                    key, new: value, ..
+                } => {
+                    self.insert(key, value);
+                }
+                EntryChange::Removed { key, .. } => {
+                    self.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Clone + Ord, V: Clone + PartialEq> Patch for BTreeMap<K, V> {
+    fn apply_changes(&mut self, changes: Vec<Self::Change>) {
+        for change in changes {
+            match change {
+                EntryChange::Added { key, value }
+                | EntryChange::Changed {
+                    key, new: value, ..
+                } => {
+                    self.insert(key, value);
+                }
+                EntryChange::Removed { key, .. } => {
+                    self.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec_diff() {
+        let original = vec![1, 2, 3, 4];
+        let modified = vec![1, 3, 4, 5];
+        assert_eq!(
+            modified.diff_from(&original),
+            vec![
+                ElementChange::Removed { index: 1, value: 2 },
+                ElementChange::Inserted { index: 3, value: 5 },
+            ]
+        );
+        assert!(original.diff_from(&original).is_empty());
+    }
+
+    #[test]
+    fn test_large_diff_falls_back_to_replacing() {
+        let original: Vec<u32> = (0..4000).collect();
+        let mut modified: Vec<u32> = (0..4000).rev().collect();
+        modified.insert(0, 9999);
+        modified.push(9999);
+        let changes = modified.diff_from(&original);
+        assert_eq!(changes.len(), original.len() + modified.len());
+        assert_eq!(
+            changes[0],
+            ElementChange::Inserted {
+                index: 0,
+                value: 9999
+            }
+        );
+        assert_eq!(
+            changes[modified.len()],
+            ElementChange::Removed { index: 0, value: 0 }
+        );
+
+        // The common prefix and suffix are still kept
+        let mut edited = original.clone();
+        edited[2000] = 0;
+        assert_eq!(edited.diff_from(&original).len(), 2);
+    }
+
+    #[test]
+    fn test_string_diff() {
+        let original = String::from("hello world");
+        let modified = String::from("hello big world!");
+        assert_eq!(
+            modified.diff_from(&original),
+            vec![
+                TextChange::Inserted {
+                    offset: 6,
+                    text: String::from("big "),
+                },
+                TextChange::Inserted {
+                    offset: 15,
+                    text: String::from("!"),
+                },
+            ]
+        );
+        assert_eq!(
+            String::from("hlo").diff_from(&String::from("hello")),
+            vec![TextChange::Removed {
+                offset: 1,
+                text: String::from("el"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_map_diff() {
+        let original: BTreeMap<_, _> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        let modified: BTreeMap<_, _> = [("a", 1), ("b", 20), ("d", 4)].into_iter().collect();
+        assert_eq!(
+            modified.diff_from(&original),
+            vec![
+                EntryChange::Changed {
+                    key: "b",
+                    old: 2,
+                    new: 20,
+                },
+                EntryChange::Removed { key: "c", value: 3 },
+                EntryChange::Added { key: "d", value: 4 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_patch_onto_drifted_target() {
+        let original: HashMap<_, _> = [("a", 1), ("b", 2)].into_iter().collect();
+        let mut modified = original.clone();
+        modified.insert("b", 20);
+        modified.remove("a");
+
+        let mut target: HashMap<_, _> = [("a", 1), ("b", 2), ("z", 26)].into_iter().collect();
+        target.apply_changes(modified.diff_from(&original));
+        let expected: HashMap<_, _> = [("b", 20), ("z", 26)].into_iter().collect();
+        assert_eq!(target, expected); // The unrelated entry survives
+    }
+}
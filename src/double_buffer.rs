@@ -0,0 +1,217 @@
+use crate::primitives::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
+use crate::ScopeError;
+use std::ops::{Deref, DerefMut};
+
+/// A DoubleBufferCell keeps a front buffer that readers see and a back buffer that a scoped
+/// writer prepares.  `commit()` swaps the buffers in one step, so readers never observe a
+/// half-finished update; a writer dropped without committing leaves the front untouched.
+pub struct DoubleBufferCell<T: Clone> {
+    front: RwLock<T>, // What readers see
+    back: Mutex<T>,   // Scratch space reused by the next writer
+}
+
+impl<T: Clone> DoubleBufferCell<T> {
+    // Create a new DoubleBufferCell with `value` in both buffers
+    pub fn new(value: T) -> Self {
+        DoubleBufferCell {
+            back: Mutex::new(value.clone()),
+            front: RwLock::new(value),
+        }
+    }
+
+    // Lock the front buffer for reading
+    pub fn read(&self) -> DoubleBufferRead<'_, T> {
+        self.try_read().expect("DoubleBufferCell lock poisoned")
+    }
+
+    // Lock the front buffer for reading, or fail if a commit panicked while holding it
+    pub fn try_read(&self) -> Result<DoubleBufferRead<'_, T>, ScopeError> {
+        let guard = self.front.read().map_err(|_| ScopeError::Poisoned)?;
+        Ok(DoubleBufferRead { guard })
+    }
+
+    // Lock the back buffer for writing.  It starts as a copy of the front buffer, reusing the
+    // back buffer's allocation where `clone_from` allows.
+    pub fn write(&self) -> BackBuffer<'_, T> {
+        self.try_write().expect("DoubleBufferCell lock poisoned")
+    }
+
+    // Lock the back buffer like `write()`, or fail if a writer panicked while holding a lock.
+    // A panicking writer poisons only the back buffer, so readers are unaffected.
+    pub fn try_write(&self) -> Result<BackBuffer<'_, T>, ScopeError> {
+        let mut back = self.back.lock().map_err(|_| ScopeError::Poisoned)?;
+        back.clone_from(&*self.try_read()?);
+        Ok(BackBuffer { cell: self, back })
+    }
+
+    // Run a closure against the back buffer, swapping it to the front if the closure
+    // returns `Ok`
+    pub fn scope<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut back = self.write();
+        let result = f(&mut back)?;
+        back.commit();
+        Ok(result)
+    }
+
+    // Whether a panic poisoned either buffer
+    pub fn is_poisoned(&self) -> bool {
+        #[cfg(not(loom))]
+        return self.front.is_poisoned() || self.back.is_poisoned();
+        #[cfg(loom)]
+        return false;
+    }
+
+    // Allow access again after a panic.  The back buffer is rebuilt from the front by the
+    // next `write()`, so only a panic during a swap can leave anything half-finished.
+    pub fn clear_poison(&self) {
+        #[cfg(not(loom))]
+        {
+            self.front.clear_poison();
+            self.back.clear_poison();
+        }
+    }
+
+    // Clone the front buffer out of the cell
+    pub fn get_cloned(&self) -> T {
+        self.read().clone()
+    }
+
+    // Consume the cell and return the front buffer
+    pub fn into_inner(self) -> T {
+        self.try_into_inner()
+            .expect("DoubleBufferCell lock poisoned")
+    }
+
+    // Consume the cell like `into_inner()`, or fail if the front buffer is poisoned
+    pub fn try_into_inner(self) -> Result<T, ScopeError> {
+        self.front.into_inner().map_err(|_| ScopeError::Poisoned)
+    }
+}
+
+/// A read lock on the front buffer of a DoubleBufferCell.
+pub struct DoubleBufferRead<'b, T> {
+    guard: RwLockReadGuard<'b, T>,
+}
+
+impl<'b, T> Deref for DoubleBufferRead<'b, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+/// Exclusive access to the back buffer of a DoubleBufferCell.  Readers keep seeing the front
+/// buffer until `commit()` swaps the two.
+pub struct BackBuffer<'b, T: Clone> {
+    cell: &'b DoubleBufferCell<T>,
+    back: MutexGuard<'b, T>,
+}
+
+impl<'b, T: Clone> BackBuffer<'b, T> {
+    // Swap the back buffer to the front, waiting for current readers to finish
+    pub fn commit(mut self) {
+        let mut front = self
+            .cell
+            .front
+            .write()
+            .expect("DoubleBufferCell lock poisoned");
+        std::mem::swap(&mut *front, &mut *self.back);
+    }
+}
+
+impl<'b, T: Clone> Deref for BackBuffer<'b, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.back
+    }
+}
+
+impl<'b, T: Clone> DerefMut for BackBuffer<'b, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.back
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_readers_see_front_until_commit() {
+        let cell = DoubleBufferCell::new(vec![0; 3]);
+        let mut back = cell.write();
+        back[0] = 1;
+        assert_eq!(*back, vec![1, 0, 0]);
+        thread::scope(|s| {
+            s.spawn(|| assert_eq!(*cell.read(), vec![0, 0, 0])); // Not swapped yet
+        });
+        back.commit();
+        assert_eq!(*cell.read(), vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn test_dropped_writer_leaves_front() {
+        let cell = DoubleBufferCell::new(String::from("frame 1"));
+        cell.write().push_str(" (discarded)");
+        assert_eq!(*cell.read(), "frame 1");
+    }
+
+    #[test]
+    #[cfg(not(feature = "parking_lot"))]
+    fn test_panicking_writer_poisons_back_only() {
+        let cell = DoubleBufferCell::new(1);
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                let mut back = cell.write();
+                *back = 2;
+                panic!("frame failed");
+            });
+            assert!(writer.join().is_err());
+        });
+        assert_eq!(cell.try_read().map(|v| *v), Ok(1));
+        assert!(matches!(cell.try_write(), Err(ScopeError::Poisoned)));
+        cell.clear_poison();
+        assert!(!cell.is_poisoned());
+        assert_eq!(*cell.write(), 1); // Rebuilt from the front
+        assert_eq!(cell.try_into_inner(), Ok(1));
+    }
+
+    #[test]
+    #[cfg(feature = "parking_lot")]
+    fn test_parking_lot_writer_does_not_poison() {
+        let cell = DoubleBufferCell::new(1);
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                *cell.write() = 2;
+                panic!("frame failed");
+            });
+            assert!(writer.join().is_err());
+        });
+        assert!(!cell.is_poisoned());
+        assert_eq!(*cell.write(), 1); // Rebuilt from the front
+    }
+
+    #[test]
+    fn test_scope_steps_from_latest_front() {
+        let cell = DoubleBufferCell::new(0u32);
+        for _ in 0..3 {
+            cell.scope(|n| {
+                *n += 1;
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        }
+        let rejected: Result<(), &str> = cell.scope(|n| {
+            *n = 100;
+            Err("rejected")
+        });
+        assert!(rejected.is_err());
+        assert_eq!(cell.into_inner(), 3);
+    }
+}
@@ -0,0 +1,1023 @@
+use crate::alloc::ScratchAlloc;
+#[cfg(feature = "bumpalo")]
+use crate::BumpScopeCell;
+#[cfg(feature = "testing")]
+use crate::TrivialScopeCell;
+use crate::{
+    CachedScopeCell, CopyScopeCell, CowScopeCell, GuardedScopeCell, HistoryCell, OwnedScopeCell,
+    PinScopeCell, PooledScopeCell, Recycle, RecycleScopeCell, ScopeBorrow, ScopeCell, ScopeCellMut,
+    ScopeCow, ScopeHashMap, ScopeSlice, ScopeVec, SignalEdit, SyncScopeCell, SyncScopeRead,
+    SyncScopeWrite, TakeScopeCell, UnsizedScopeCell, ValidatedScopeCell,
+};
+#[cfg(feature = "im")]
+use crate::{Persistent, PersistentScopeCell};
+#[cfg(feature = "tokio")]
+use crate::{WatchRef, WatchScopeCell};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// The operations every scoped-edit cell shares: reading the effective value, a mutable
+/// borrow that copies on first use, discarding or keeping the edit, and asking whether there
+/// is one.  Generic code, such as an undo stack, can be written against this instead of a
+/// concrete cell type.
+///
+/// Reads and writes go through the `Ref` and `Mut` guards: plain references for most cells,
+/// lock guards for the shared ones, and a `Copied` or `Materialized` copy for the cells
+/// that cannot lend out the value itself (`CopyScopeCell`, `ScopeVec`, `ScopeHashMap`).
+/// `commit()` keeps the edit in place, written back to the target of cells over a `&mut T`
+/// or adopted as the baseline of cells that own one.  Cells over a read-only `&T` have
+/// nowhere to write it, so they keep it as their effective value and still report
+/// `is_modified()`.
+///
+/// Cells whose writes can fail, expire, go through operations or end the cell
+/// (`TryScopeCell`, `TimedScopeCell`, `JournalCell`, `SwapScopeCell`), and the shared cells
+/// edited through separate handles (`OptimisticCell`, `VersionedCell`, `DoubleBufferCell`),
+/// keep their own APIs; `SignalCell` takes part through its `SignalEdit`.
+pub trait ScopedEdit {
+    type Target: ?Sized;
+    type Ref<'g>: Deref<Target = Self::Target>
+    where
+        Self: 'g;
+    type Mut<'g>: DerefMut<Target = Self::Target>
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_>;
+
+    fn get_mut(&mut self) -> Self::Mut<'_>;
+
+    fn revert(&mut self);
+
+    fn commit(&mut self);
+
+    fn is_modified(&self) -> bool;
+}
+
+/// An owned copy of a cell's effective value, the `ScopedEdit::Ref` of the cells that cannot
+/// lend out a reference to it.
+pub struct Copied<T>(T);
+
+impl<T> Deref for Copied<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// A materialized copy of a collection cell's edited view, the `ScopedEdit::Mut` of
+/// `ScopeVec` and `ScopeHashMap`.  Dropping it stores the copy back into the cell.
+pub struct Materialized<'g, C, V: Default> {
+    cell: &'g mut C,
+    value: V,
+    store: fn(&mut C, V), // Puts the edited copy back into the cell
+}
+
+impl<'g, C, V: Default> Materialized<'g, C, V> {
+    fn new(cell: &'g mut C, value: V, store: fn(&mut C, V)) -> Self {
+        Materialized { cell, value, store }
+    }
+}
+
+impl<'g, C, V: Default> Deref for Materialized<'g, C, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.value
+    }
+}
+
+impl<'g, C, V: Default> DerefMut for Materialized<'g, C, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.value
+    }
+}
+
+impl<'g, C, V: Default> Drop for Materialized<'g, C, V> {
+    fn drop(&mut self) {
+        (self.store)(self.cell, mem::take(&mut self.value));
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for ScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = ScopeBorrow<'g, T>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        self.borrow()
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        ScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        ScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        ScopeCell::is_modified(self)
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for ScopeCellMut<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        ScopeCellMut::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        ScopeCellMut::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        ScopeCellMut::revert(self);
+    }
+
+    // Panics if the cell is poisoned, like `ScopeCellMut::commit()`
+    fn commit(&mut self) {
+        if self.is_poisoned() {
+            panic!("ScopeCellMut poisoned");
+        }
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ScopeCellMut::is_modified(self)
+    }
+}
+
+impl<T: Clone, A: ScratchAlloc> ScopedEdit for OwnedScopeCell<T, A> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        OwnedScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        OwnedScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        self.reset();
+    }
+
+    fn commit(&mut self) {
+        self.rebase();
+    }
+
+    fn is_modified(&self) -> bool {
+        OwnedScopeCell::is_modified(self)
+    }
+}
+
+impl<T: Clone> ScopedEdit for CowScopeCell<T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        CowScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        CowScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        CowScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.rebase();
+    }
+
+    fn is_modified(&self) -> bool {
+        CowScopeCell::is_modified(self)
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for CachedScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        CachedScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        CachedScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        CachedScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        CachedScopeCell::is_modified(self)
+    }
+}
+
+#[cfg(feature = "bumpalo")]
+impl<'a, 'bump, T: Clone> ScopedEdit for BumpScopeCell<'a, 'bump, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        BumpScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        BumpScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        BumpScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        BumpScopeCell::is_modified(self)
+    }
+}
+
+impl<T: Clone> ScopedEdit for HistoryCell<T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        HistoryCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        HistoryCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        HistoryCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        HistoryCell::commit(self);
+    }
+
+    fn is_modified(&self) -> bool {
+        HistoryCell::is_modified(self)
+    }
+}
+
+#[cfg(feature = "im")]
+impl<C: Persistent> ScopedEdit for PersistentScopeCell<C> {
+    type Target = C;
+    type Ref<'g>
+        = &'g C
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut C
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        PersistentScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        PersistentScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        PersistentScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        PersistentScopeCell::commit(self);
+    }
+
+    fn is_modified(&self) -> bool {
+        PersistentScopeCell::is_modified(self)
+    }
+}
+
+impl<'a, T: Recycle> ScopedEdit for RecycleScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        RecycleScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        RecycleScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        RecycleScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        RecycleScopeCell::commit(self);
+    }
+
+    fn is_modified(&self) -> bool {
+        RecycleScopeCell::is_modified(self)
+    }
+}
+
+impl<'p, 'a, T: Recycle> ScopedEdit for PooledScopeCell<'p, 'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        PooledScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        PooledScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        PooledScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        PooledScopeCell::is_modified(self)
+    }
+}
+
+// Plain mutable access needs `Unpin`, as with `PinScopeCell::get_mut`
+impl<'a, T: Clone + Unpin> ScopedEdit for PinScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        PinScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        PinScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        PinScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        PinScopeCell::is_modified(self)
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for ScopeSlice<'a, T> {
+    type Target = [T];
+    type Ref<'g>
+        = &'g [T]
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut [T]
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        ScopeSlice::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        ScopeSlice::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        ScopeSlice::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ScopeSlice::is_modified(self)
+    }
+}
+
+impl<'a, T: Default> ScopedEdit for TakeScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        TakeScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        TakeScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        TakeScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        TakeScopeCell::is_modified(self)
+    }
+}
+
+// `commit()` keeps the edit only if it passes the validator, and reverts it otherwise;
+// the inherent `commit()` reports the validator's error
+impl<'a, T: Clone, E> ScopedEdit for ValidatedScopeCell<'a, T, E> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        ValidatedScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        ValidatedScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        ValidatedScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        let _ = self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ValidatedScopeCell::is_modified(self)
+    }
+}
+
+#[cfg(feature = "testing")]
+impl<'a, T: Clone> ScopedEdit for TrivialScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        TrivialScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        TrivialScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        TrivialScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        TrivialScopeCell::is_modified(self)
+    }
+}
+
+impl<G> ScopedEdit for GuardedScopeCell<G>
+where
+    G: DerefMut,
+    G::Target: Clone,
+{
+    type Target = G::Target;
+    type Ref<'g>
+        = &'g G::Target
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut G::Target
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        GuardedScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        GuardedScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        GuardedScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        GuardedScopeCell::is_modified(self)
+    }
+}
+
+impl<'c, 'b, T: Clone> ScopedEdit for ScopeCow<'c, 'b, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        ScopeCow::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        ScopeCow::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        ScopeCow::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ScopeCow::is_modified(self)
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for UnsizedScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        UnsizedScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        UnsizedScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        UnsizedScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        UnsizedScopeCell::is_modified(self)
+    }
+}
+
+// Reads by value, since the current value lives in a `Cell`
+impl<'a, T: Copy> ScopedEdit for CopyScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = Copied<T>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        Copied(CopyScopeCell::get(self))
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        CopyScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        CopyScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        CopyScopeCell::is_modified(self)
+    }
+}
+
+impl<'a, T: Clone> ScopedEdit for SyncScopeCell<'a, T> {
+    type Target = T;
+    type Ref<'g>
+        = SyncScopeRead<'g, T>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = SyncScopeWrite<'g, T>
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        self.read()
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        self.write()
+    }
+
+    fn revert(&mut self) {
+        SyncScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {}
+
+    fn is_modified(&self) -> bool {
+        SyncScopeCell::is_modified(self)
+    }
+}
+
+// Committing publishes the edit to the subscribers and keeps it open
+impl<'s, T: Clone> ScopedEdit for SignalEdit<'s, T> {
+    type Target = T;
+    type Ref<'g>
+        = &'g T
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        self
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        self
+    }
+
+    fn revert(&mut self) {
+        SignalEdit::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        SignalEdit::is_modified(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+// Committing publishes the edit on the channel
+impl<T: Clone> ScopedEdit for WatchScopeCell<T> {
+    type Target = T;
+    type Ref<'g>
+        = WatchRef<'g, T>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = &'g mut T
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        WatchScopeCell::get(self)
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        WatchScopeCell::get_mut(self)
+    }
+
+    fn revert(&mut self) {
+        WatchScopeCell::revert(self);
+    }
+
+    fn commit(&mut self) {
+        WatchScopeCell::commit(self);
+    }
+
+    fn is_modified(&self) -> bool {
+        WatchScopeCell::is_modified(self)
+    }
+}
+
+// The collection cells materialize their edited view; writes through the guard are
+// stored back as overrides of just the elements that differ
+impl<'a, T: Clone + PartialEq, A: ScratchAlloc> ScopedEdit for ScopeVec<'a, T, A> {
+    type Target = Vec<T>;
+    type Ref<'g>
+        = Copied<Vec<T>>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = Materialized<'g, Self, Vec<T>>
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        Copied(self.to_vec())
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        let value = self.to_vec();
+        Materialized::new(self, value, ScopeVec::replace_all)
+    }
+
+    fn revert(&mut self) {
+        ScopeVec::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ScopeVec::is_modified(self)
+    }
+}
+
+impl<'a, K, V, A> ScopedEdit for ScopeHashMap<'a, K, V, A>
+where
+    K: Clone + Eq + Hash,
+    V: Clone + PartialEq,
+    A: ScratchAlloc,
+{
+    type Target = HashMap<K, V>;
+    type Ref<'g>
+        = Copied<HashMap<K, V>>
+    where
+        Self: 'g;
+    type Mut<'g>
+        = Materialized<'g, Self, HashMap<K, V>>
+    where
+        Self: 'g;
+
+    fn get(&self) -> Self::Ref<'_> {
+        Copied(self.to_map())
+    }
+
+    fn get_mut(&mut self) -> Self::Mut<'_> {
+        let value = self.to_map();
+        Materialized::new(self, value, ScopeHashMap::replace_all)
+    }
+
+    fn revert(&mut self) {
+        ScopeHashMap::revert(self);
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn is_modified(&self) -> bool {
+        ScopeHashMap::is_modified(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SignalCell;
+    use std::sync::Mutex;
+
+    // A minimal undo stack over any cell, as downstream code would write it
+    struct Undo<C: ScopedEdit>
+    where
+        C::Target: Clone,
+    {
+        cell: C,
+        past: Vec<C::Target>,
+    }
+
+    impl<C: ScopedEdit> Undo<C>
+    where
+        C::Target: Clone,
+    {
+        fn apply(&mut self, f: impl FnOnce(&mut C::Target)) {
+            self.past.push(C::Target::clone(&self.cell.get()));
+            f(&mut self.cell.get_mut());
+            self.cell.commit();
+        }
+
+        fn undo(&mut self) {
+            if let Some(previous) = self.past.pop() {
+                *self.cell.get_mut() = previous;
+                self.cell.commit();
+            }
+        }
+    }
+
+    fn bump<C: ScopedEdit<Target = u32>>(cell: &mut C) -> u32 {
+        *cell.get_mut() += 1;
+        let bumped = *cell.get();
+        cell.revert();
+        bumped
+    }
+
+    #[test]
+    fn test_generic_edit_over_cells() {
+        let base = 1;
+        let mut target = 1;
+        assert_eq!(bump(&mut ScopeCell::new(&base)), 2);
+        assert_eq!(bump(&mut ScopeCellMut::new(&mut target)), 2);
+        assert_eq!(bump(&mut OwnedScopeCell::new(1)), 2);
+        let mut cow = CowScopeCell::new(std::sync::Arc::new(1));
+        assert_eq!(bump(&mut cow), 2);
+        assert!(!ScopedEdit::is_modified(&cow));
+        let mut borrowed = std::borrow::Cow::Borrowed(&base);
+        assert_eq!(bump(&mut ScopeCow::new(&mut borrowed)), 2);
+        assert!(matches!(borrowed, std::borrow::Cow::Borrowed(_)));
+        assert_eq!(bump(&mut UnsizedScopeCell::new(&base)), 2);
+        assert_eq!(bump(&mut CopyScopeCell::new(&base)), 2);
+        assert_eq!(bump(&mut SyncScopeCell::new(&base)), 2);
+        let signal = SignalCell::new(1);
+        assert_eq!(bump(&mut signal.edit()), 2);
+        assert_eq!(target, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ScopeCellMut poisoned")]
+    fn test_poisoned_cell_refuses_generic_commit() {
+        let mut target = 1;
+        let mut cell = ScopeCellMut::new(&mut target);
+        let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cell.update(|n| {
+                *n += 1;
+                panic!("half-way");
+            })
+        }));
+        assert!(caught.is_err());
+        ScopedEdit::commit(&mut cell);
+    }
+
+    #[test]
+    fn test_undo_over_committing_cells() {
+        let mut target = vec![1];
+        let mut undo = Undo {
+            cell: ScopeCellMut::new(&mut target),
+            past: Vec::new(),
+        };
+        undo.apply(|v| v.push(2));
+        undo.apply(|v| v.push(3));
+        undo.undo();
+        assert!(!ScopedEdit::is_modified(&undo.cell));
+        drop(undo);
+        assert_eq!(target, vec![1, 2]);
+
+        let mutex = Mutex::new(String::from("a"));
+        let mut undo = Undo {
+            cell: GuardedScopeCell::from_guard(mutex.lock().unwrap()),
+            past: Vec::new(),
+        };
+        undo.apply(|s| s.push('b'));
+        undo.undo();
+        undo.apply(|s| s.push('c'));
+        drop(undo);
+        assert_eq!(*mutex.lock().unwrap(), "ac");
+    }
+
+    #[test]
+    fn test_undo_over_collection_and_shared_cells() {
+        let mut list = vec![1, 2];
+        let mut undo = Undo {
+            cell: ScopeVec::new(&mut list),
+            past: Vec::new(),
+        };
+        undo.apply(|v| v.push(3));
+        undo.apply(|v| v[0] = 10);
+        undo.undo();
+        assert!(!ScopedEdit::is_modified(&undo.cell));
+        drop(undo);
+        assert_eq!(list, vec![1, 2, 3]);
+
+        let mut map = HashMap::from([("a", 1), ("b", 2)]);
+        let mut scope = ScopeHashMap::new(&mut map);
+        ScopedEdit::get_mut(&mut scope).remove("a");
+        assert_eq!(ScopedEdit::get(&scope).len(), 1);
+        ScopedEdit::get_mut(&mut scope).insert("b", 2); // Unchanged entries are not overlaid
+        assert!(scope.contains_key("b") && !scope.contains_key("a"));
+        ScopedEdit::commit(&mut scope);
+        drop(scope);
+        assert_eq!(map, HashMap::from([("b", 2)]));
+
+        let signal = SignalCell::new(0);
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&seen);
+        signal.subscribe(move |v| sink.lock().unwrap().push(*v));
+        let mut undo = Undo {
+            cell: signal.edit(),
+            past: Vec::new(),
+        };
+        undo.apply(|v| *v = 5);
+        undo.undo();
+        assert_eq!(*seen.lock().unwrap(), vec![5, 0]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::sync::{Mutex, PoisonError};
+
+// Serializes every ScopedEnv change and restore, so concurrent guards do not interleave
+// their snapshots.  The only panic possible under the lock is `std::env` rejecting a
+// change before making it, which leaves the environment untouched, so poisoning is ignored.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// A ScopedEnv sets or removes process environment variables and restores their previous
+/// values when dropped, including during a panic.  Variables changed by one guard are
+/// restored in reverse order, so setting the same key twice still restores the original.
+/// A change `std::env` rejects (an empty key, `=` or NUL) panics without being recorded,
+/// so the unwinding restore only replays changes that were made and cannot panic again.
+///
+/// The environment is process-wide: other threads see the change while the guard lives,
+/// and code that reads the environment without going through `std::env` may race with it.
+#[must_use = "the variables are restored as soon as the ScopedEnv is dropped"]
+pub struct ScopedEnv {
+    saved: Vec<(OsString, Option<OsString>)>, // Previous values, in the order they changed
+}
+
+impl ScopedEnv {
+    // Set `key` to `value` until the guard is dropped
+    pub fn set<K: AsRef<OsStr>, V: AsRef<OsStr>>(key: K, value: V) -> Self {
+        ScopedEnv { saved: Vec::new() }.and_set(key, value)
+    }
+
+    // Remove `key` until the guard is dropped
+    pub fn remove<K: AsRef<OsStr>>(key: K) -> Self {
+        ScopedEnv { saved: Vec::new() }.and_remove(key)
+    }
+
+    // Apply several changes at once: `Some(value)` sets a variable, `None` removes it
+    pub fn batch<I, K, V>(changes: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Option<V>)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let mut guard = ScopedEnv { saved: Vec::new() };
+        for (key, value) in changes {
+            guard.change(key.as_ref(), value.as_ref().map(AsRef::as_ref));
+        }
+        guard
+    }
+
+    // Also set `key` to `value`, restored together with the guard's other changes
+    pub fn and_set<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.change(key.as_ref(), Some(value.as_ref()));
+        self
+    }
+
+    // Also remove `key`, restored together with the guard's other changes
+    pub fn and_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.change(key.as_ref(), None);
+        self
+    }
+
+    // Number of changes the guard will undo
+    pub fn len(&self) -> usize {
+        self.saved.len()
+    }
+
+    // Whether the guard has nothing to undo
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_empty()
+    }
+
+    // Snapshot the current value of `key`, apply the change, and only then record it
+    fn change(&mut self, key: &OsStr, value: Option<&OsStr>) {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = env::var_os(key);
+        match value {
+            Some(value) => env::set_var(key, value),
+            None => env::remove_var(key),
+        }
+        self.saved.push((key.to_owned(), previous));
+    }
+}
+
+impl Drop for ScopedEnv {
+    fn drop(&mut self) {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        for (key, previous) in self.saved.drain(..).rev() {
+            match previous {
+                Some(value) => env::set_var(&key, value),
+                None => env::remove_var(&key),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_restore() {
+        env::set_var("SCOPE_CELL_TEST_SET", "original");
+        {
+            let _env = ScopedEnv::set("SCOPE_CELL_TEST_SET", "scoped");
+            assert_eq!(env::var("SCOPE_CELL_TEST_SET").unwrap(), "scoped");
+        }
+        assert_eq!(env::var("SCOPE_CELL_TEST_SET").unwrap(), "original");
+        env::remove_var("SCOPE_CELL_TEST_SET");
+    }
+
+    #[test]
+    fn test_batch_and_chained_changes() {
+        env::set_var("SCOPE_CELL_TEST_B", "b");
+        {
+            let env_guard = ScopedEnv::batch([
+                ("SCOPE_CELL_TEST_A", Some("1")),
+                ("SCOPE_CELL_TEST_B", None),
+            ])
+            .and_set("SCOPE_CELL_TEST_A", "2"); // Same key twice
+            assert_eq!(env_guard.len(), 3);
+            assert_eq!(env::var("SCOPE_CELL_TEST_A").unwrap(), "2");
+            assert!(env::var_os("SCOPE_CELL_TEST_B").is_none());
+        }
+        assert!(env::var_os("SCOPE_CELL_TEST_A").is_none()); // Back to unset
+        assert_eq!(env::var("SCOPE_CELL_TEST_B").unwrap(), "b");
+        env::remove_var("SCOPE_CELL_TEST_B");
+    }
+
+    #[test]
+    fn test_restored_after_panic() {
+        env::set_var("SCOPE_CELL_TEST_KEPT", "kept");
+        let result = std::panic::catch_unwind(|| {
+            let _env =
+                ScopedEnv::remove("SCOPE_CELL_TEST_KEPT").and_set("SCOPE_CELL_TEST_PANIC", "1");
+            panic!("test failed");
+        });
+        assert!(result.is_err());
+        assert_eq!(env::var("SCOPE_CELL_TEST_KEPT").unwrap(), "kept");
+        assert!(env::var_os("SCOPE_CELL_TEST_PANIC").is_none());
+        env::remove_var("SCOPE_CELL_TEST_KEPT");
+    }
+
+    #[test]
+    fn test_rejected_change_is_not_restored() {
+        let result = std::panic::catch_unwind(|| {
+            // Restoring the invalid key while unwinding would panic again and abort
+            let _env = ScopedEnv::set("SCOPE_CELL_TEST_VALID", "1").and_set("SCOPE=CELL", "2");
+        });
+        assert!(result.is_err());
+        assert!(env::var_os("SCOPE_CELL_TEST_VALID").is_none());
+        assert_eq!(ScopedEnv::set("SCOPE_CELL_TEST_VALID", "3").len(), 1); // Lock still usable
+    }
+}
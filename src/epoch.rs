@@ -0,0 +1,302 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// Identifies one epoch opened by `Epochs::begin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EpochId(u64);
+
+/// The set of currently open epochs shared by a group of `EpochCell`s.  Epochs need not
+/// nest: any open epoch can be ended at any time, and doing so reverts every edit made
+/// during it in every cell tied to this `Epochs`, keeping the edits of other epochs.
+pub struct Epochs {
+    next: Cell<u64>,              // Source of EpochIds; ids are never reused
+    live: RefCell<BTreeSet<u64>>, // Epochs begun but not yet ended
+    ended: Cell<u64>,             // Number of epochs ended, so cells can tell they are stale
+}
+
+impl Epochs {
+    // Create a new group with no open epochs
+    pub fn new() -> Self {
+        Epochs {
+            next: Cell::new(0),
+            live: RefCell::new(BTreeSet::new()),
+            ended: Cell::new(0),
+        }
+    }
+
+    // Open a new epoch
+    pub fn begin(&self) -> EpochId {
+        let id = self.next.get();
+        self.next.set(id + 1);
+        self.live.borrow_mut().insert(id);
+        EpochId(id)
+    }
+
+    // End an epoch, reverting its overrides in every cell.  Returns false if it was not open.
+    pub fn end_epoch(&self, epoch: EpochId) -> bool {
+        let ended = self.live.borrow_mut().remove(&epoch.0);
+        if ended {
+            self.ended.set(self.ended.get() + 1);
+        }
+        ended
+    }
+
+    // Whether `epoch` has begun and not yet ended
+    pub fn is_live(&self, epoch: EpochId) -> bool {
+        self.live.borrow().contains(&epoch.0)
+    }
+
+    // Number of open epochs
+    pub fn live_count(&self) -> usize {
+        self.live.borrow().len()
+    }
+}
+
+impl Default for Epochs {
+    fn default() -> Self {
+        Epochs::new()
+    }
+}
+
+type Edit<'e, T> = Box<dyn Fn(&mut T) + 'e>;
+
+/// An EpochCell holds a baseline plus a log of edits, each tagged with the epoch it was
+/// made in.  The effective value is the baseline with the edits of the still-open epochs
+/// replayed in order, so ending an epoch through `Epochs::end_epoch` reverts exactly its own
+/// edits, even when epochs end in a different order than they began, without the cell having
+/// to be registered anywhere.  Edits are closures rather than values so they can be replayed.
+/// `edit` applies each one to the effective value as it is made, and the first `get` after an
+/// epoch ends rebuilds the value and drops the edits of ended epochs from the log.
+pub struct EpochCell<'e, T: Clone> {
+    epochs: &'e Epochs,
+    baseline: T,
+    edits: RefCell<Vec<(EpochId, Edit<'e, T>)>>, // Oldest first
+    values: RefCell<Vec<Rc<T>>>,                 // Effective values, newest last; see `get`
+    synced: Cell<u64>,                           // `Epochs::ended` at the last rebuild
+}
+
+impl<'e, T: Clone> EpochCell<'e, T> {
+    // Create a new EpochCell with `baseline` as the value seen outside any epoch
+    pub fn new(epochs: &'e Epochs, baseline: T) -> Self {
+        EpochCell {
+            epochs,
+            baseline,
+            edits: RefCell::new(Vec::new()),
+            values: RefCell::new(Vec::new()),
+            synced: Cell::new(epochs.ended.get()),
+        }
+    }
+
+    // The effective value: the baseline with the live epochs' edits applied
+    pub fn get(&self) -> &T {
+        self.sync();
+        if self.edits.borrow().is_empty() {
+            return &self.baseline;
+        }
+        let values = self.values.borrow();
+        let newest = values.last().unwrap();
+        // A rebuild behind `&self` pushes a new value rather than replacing this one, and
+        // values are only mutated or dropped through `&mut self`, so the reference stays
+        // valid for as long as `self` is borrowed
+        unsafe { &*Rc::as_ptr(newest) }
+    }
+
+    // The value seen outside any epoch
+    pub fn baseline(&self) -> &T {
+        &self.baseline
+    }
+
+    // Override the value for the duration of `epoch`.  Panics if the epoch is not open.
+    pub fn set(&mut self, epoch: EpochId, value: T)
+    where
+        T: 'e,
+    {
+        self.edit(epoch, move |current| current.clone_from(&value));
+    }
+
+    // Apply `edit` to the value for the duration of `epoch`.  The closure is kept and run
+    // again whenever the value is rebuilt after another epoch ends.  Panics if the epoch is
+    // not open.
+    pub fn edit<F: Fn(&mut T) + 'e>(&mut self, epoch: EpochId, edit: F) {
+        if !self.epochs.is_live(epoch) {
+            panic!("epoch is not open");
+        }
+        self.prune();
+        let values = self.values.get_mut();
+        if values.is_empty() {
+            values.push(Rc::new(self.baseline.clone()));
+        }
+        // Pruning left a single value, and `&mut self` rules out any reference to it
+        edit(Rc::get_mut(values.last_mut().unwrap()).unwrap());
+        self.edits.get_mut().push((epoch, Box::new(edit)));
+    }
+
+    // Whether any live edit exists
+    pub fn is_overridden(&self) -> bool {
+        self.edits
+            .borrow()
+            .iter()
+            .any(|(epoch, _)| self.epochs.is_live(*epoch))
+    }
+
+    // Number of edits held, including ones whose epoch has ended since the last rebuild
+    pub fn override_count(&self) -> usize {
+        self.edits.borrow().len()
+    }
+
+    // Drop the edits of ended epochs and every value but the effective one
+    pub fn prune(&mut self) {
+        self.sync();
+        let values = self.values.get_mut();
+        if self.edits.get_mut().is_empty() {
+            values.clear();
+        } else {
+            values.drain(..values.len() - 1);
+        }
+    }
+
+    // Consume the cell and return the effective value
+    pub fn into_inner(mut self) -> T {
+        self.prune();
+        match self.values.get_mut().pop() {
+            Some(value) => Rc::try_unwrap(value).ok().unwrap(),
+            None => self.baseline,
+        }
+    }
+
+    // If an epoch ended since the last rebuild, drop the edits of ended epochs and replay the
+    // rest over the baseline as a new effective value
+    fn sync(&self) {
+        let ended = self.epochs.ended.get();
+        if self.synced.get() == ended {
+            return;
+        }
+        self.synced.set(ended);
+        let mut edits = self.edits.borrow_mut();
+        let before = edits.len();
+        edits.retain(|(epoch, _)| self.epochs.is_live(*epoch));
+        if edits.len() == before || edits.is_empty() {
+            return;
+        }
+        let mut value = self.baseline.clone();
+        for (_, edit) in edits.iter() {
+            edit(&mut value);
+        }
+        self.values.borrow_mut().push(Rc::new(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_end_epoch_reverts_across_cells() {
+        let epochs = Epochs::new();
+        let mut speed = EpochCell::new(&epochs, 1.0);
+        let mut label = EpochCell::new(&epochs, String::from("idle"));
+
+        let frame = epochs.begin();
+        speed.set(frame, 2.0);
+        label.edit(frame, |label| label.push_str(" (boosted)"));
+        assert_eq!(*speed.get(), 2.0);
+        assert_eq!(label.get(), "idle (boosted)");
+
+        assert!(epochs.end_epoch(frame));
+        assert_eq!(*speed.get(), 1.0); // Both cells reverted by one call
+        assert_eq!(label.get(), "idle");
+        assert!(!epochs.end_epoch(frame));
+    }
+
+    #[test]
+    fn test_non_nested_epochs() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, 0);
+        let a = epochs.begin();
+        let b = epochs.begin();
+        cell.set(a, 1);
+        cell.set(b, 2);
+        epochs.end_epoch(a); // Ends the outer epoch first
+        assert_eq!(*cell.get(), 2);
+        epochs.end_epoch(b);
+        assert_eq!(*cell.get(), 0);
+        assert!(!cell.is_overridden());
+
+        cell.prune();
+        assert_eq!(cell.override_count(), 0);
+    }
+
+    #[test]
+    fn test_same_epoch_accumulates_edits() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, vec![1]);
+        let e = epochs.begin();
+        cell.edit(e, |v| v.push(2));
+        cell.edit(e, |v| v.push(3));
+        assert_eq!(cell.override_count(), 2);
+        assert_eq!(cell.into_inner(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ending_older_epoch_keeps_newer_edits() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, Vec::new());
+        let a = epochs.begin();
+        let b = epochs.begin();
+        cell.edit(a, |v| v.push(1));
+        cell.edit(b, |v| v.push(2));
+        cell.edit(a, |v| v.push(3));
+        assert_eq!(*cell.get(), vec![1, 2, 3]);
+        epochs.end_epoch(a); // Ends the older epoch first
+        assert_eq!(*cell.get(), vec![2]);
+        assert_eq!(cell.override_count(), 1);
+        epochs.end_epoch(b);
+        assert!(cell.get().is_empty());
+    }
+
+    #[test]
+    fn test_get_through_shared_reference() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, vec![0]);
+        let a = epochs.begin();
+        let b = epochs.begin();
+        cell.edit(a, |v| v.push(1));
+        cell.edit(b, |v| v.push(2));
+
+        let cell = &cell;
+        let before = cell.get();
+        epochs.end_epoch(a);
+        let after = cell.get(); // Rebuilt without invalidating `before`
+        assert_eq!(*before, vec![0, 1, 2]);
+        assert_eq!(*after, vec![0, 2]);
+        assert_eq!(cell.override_count(), 1);
+    }
+
+    #[test]
+    fn test_log_compacts_once_epochs_end() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, 0);
+        let e = epochs.begin();
+        for i in 1..=3 {
+            cell.set(e, i);
+        }
+        assert_eq!(cell.override_count(), 3);
+        epochs.end_epoch(e);
+        assert_eq!(*cell.get(), 0);
+        assert_eq!(cell.override_count(), 0); // No closures kept for ended epochs
+        assert_eq!(cell.values.borrow().len(), 1);
+        cell.prune();
+        assert!(cell.values.borrow().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "epoch is not open")]
+    fn test_write_to_ended_epoch_panics() {
+        let epochs = Epochs::new();
+        let mut cell = EpochCell::new(&epochs, 0);
+        let e = epochs.begin();
+        epochs.end_epoch(e);
+        cell.set(e, 1);
+    }
+}
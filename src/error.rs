@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// Errors returned by the `try_` methods of the cells, in place of a panic.  Only cells whose
+/// accessors can fail have `try_` methods; infallible ones such as `OwnedScopeCell` do not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopeError {
+    /// The cell is already borrowed in a way that conflicts with the request.
+    AlreadyBorrowed,
+    /// The cell is frozen and refuses edits until `unfreeze()` is called.
+    Frozen,
+    /// The edited value was rejected by the cell's validator.
+    ValidationFailed,
+    /// A panic happened while the cell was being mutated.
+    Poisoned,
+    /// The shared value changed after the edit started, so committing would lose an update.
+    Conflict,
+    /// Copying the value would exceed the cell's clone budget.
+    BudgetExceeded,
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeError::AlreadyBorrowed => f.write_str("cell already borrowed"),
+            ScopeError::Frozen => f.write_str("cell is frozen"),
+            ScopeError::ValidationFailed => f.write_str("validation failed"),
+            ScopeError::Poisoned => f.write_str("cell poisoned by a panic"),
+            ScopeError::Conflict => f.write_str("value changed since the edit started"),
+            ScopeError::BudgetExceeded => f.write_str("clone budget exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for ScopeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_error() {
+        let err: Box<dyn std::error::Error> = Box::new(ScopeError::Poisoned);
+        assert_eq!(err.to_string(), "cell poisoned by a panic");
+        assert_eq!(
+            ScopeError::AlreadyBorrowed.to_string(),
+            "cell already borrowed"
+        );
+    }
+}
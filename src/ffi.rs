@@ -0,0 +1,231 @@
+//! A C API for scoped edits of byte blobs owned by Rust, such as shared config buffers
+//! driven by a C or C++ host.  A `GhostBlob` holds the committed bytes; a `GhostScope`
+//! opened over it edits a private copy that `ghost_scope_commit` writes back and
+//! `ghost_scope_rollback` discards.  Both are opaque handles that the host frees exactly
+//! once: a blob with `ghost_blob_free`, after every scope over it has been committed or
+//! rolled back, and a scope by committing or rolling it back.  The prototypes are in
+//! `include/ghost_scope.h`, and the `scope-cell-ffi` crate builds them into a static or
+//! dynamic library for the host to link.  Functions returning `c_int` return 0 on success
+//! and -1 on a null handle.
+
+use std::ffi::c_int;
+use std::ptr;
+use std::slice;
+use std::sync::{Mutex, PoisonError};
+
+use crate::OwnedScopeCell;
+
+/// The committed contents of a shared blob.
+pub struct GhostBlob {
+    data: Mutex<Vec<u8>>,
+}
+
+/// A scoped edit of a GhostBlob, starting from the contents it had when it was opened.
+pub struct GhostScope {
+    blob: *const GhostBlob,
+    cell: OwnedScopeCell<Vec<u8>>, // Baseline snapshot plus the pending edit
+}
+
+// Borrow `len` bytes at `data`, treating a null pointer as empty
+unsafe fn bytes<'b>(data: *const u8, len: usize) -> &'b [u8] {
+    if data.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+// Copy as much of `source` as fits into `out`, returning the full length
+unsafe fn copy_out(source: &[u8], out: *mut u8, capacity: usize) -> usize {
+    if !out.is_null() {
+        ptr::copy_nonoverlapping(source.as_ptr(), out, source.len().min(capacity));
+    }
+    source.len()
+}
+
+impl GhostBlob {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<u8>> {
+        self.data.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Create a blob holding a copy of `len` bytes at `data`.
+///
+/// # Safety
+/// `data` must be null or valid for reading `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_blob_new(data: *const u8, len: usize) -> *mut GhostBlob {
+    let blob = GhostBlob {
+        data: Mutex::new(bytes(data, len).to_vec()),
+    };
+    Box::into_raw(Box::new(blob))
+}
+
+/// Free a blob.  Null is ignored.
+///
+/// # Safety
+/// `blob` must be null or come from `ghost_blob_new`, not be freed yet, and have no open
+/// scopes.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_blob_free(blob: *mut GhostBlob) {
+    if !blob.is_null() {
+        drop(Box::from_raw(blob));
+    }
+}
+
+/// Copy up to `capacity` committed bytes into `out`, returning the blob's full length so
+/// the host can retry with a larger buffer.  Returns 0 for a null blob.
+///
+/// # Safety
+/// `blob` must be null or a live blob, and `out` null or valid for writing `capacity` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_blob_read(
+    blob: *const GhostBlob,
+    out: *mut u8,
+    capacity: usize,
+) -> usize {
+    match blob.as_ref() {
+        Some(blob) => copy_out(&blob.lock(), out, capacity),
+        None => 0,
+    }
+}
+
+/// Open a scoped edit of `blob`.  Returns null for a null blob.
+///
+/// # Safety
+/// `blob` must be null or a live blob, which must outlive the scope.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_begin(blob: *const GhostBlob) -> *mut GhostScope {
+    let Some(shared) = blob.as_ref() else {
+        return ptr::null_mut();
+    };
+    let scope = GhostScope {
+        blob,
+        cell: OwnedScopeCell::new(shared.lock().clone()),
+    };
+    Box::into_raw(Box::new(scope))
+}
+
+/// Replace the scope's pending contents with a copy of `len` bytes at `data`.
+///
+/// # Safety
+/// `scope` must be null or an open scope, and `data` null or valid for reading `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_set_bytes(
+    scope: *mut GhostScope,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let Some(scope) = scope.as_mut() else {
+        return -1;
+    };
+    scope.cell.set(bytes(data, len).to_vec());
+    0
+}
+
+/// Copy up to `capacity` of the scope's effective bytes into `out`, returning their full
+/// length.  Returns 0 for a null scope.
+///
+/// # Safety
+/// `scope` must be null or an open scope, and `out` null or valid for writing `capacity`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_read(
+    scope: *const GhostScope,
+    out: *mut u8,
+    capacity: usize,
+) -> usize {
+    match scope.as_ref() {
+        Some(scope) => copy_out(scope.cell.get(), out, capacity),
+        None => 0,
+    }
+}
+
+/// Whether the scope has pending contents.  False for a null scope.
+///
+/// # Safety
+/// `scope` must be null or an open scope.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_is_modified(scope: *const GhostScope) -> bool {
+    scope.as_ref().is_some_and(|scope| scope.cell.is_modified())
+}
+
+/// Write the scope's pending contents, if any, into its blob and close the scope.
+///
+/// # Safety
+/// `scope` must be null or an open scope, whose blob is still live.  It is freed.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_commit(scope: *mut GhostScope) -> c_int {
+    if scope.is_null() {
+        return -1;
+    }
+    let scope = Box::from_raw(scope);
+    if scope.cell.is_modified() {
+        *(*scope.blob).lock() = scope.cell.into_inner();
+    }
+    0
+}
+
+/// Discard the scope's pending contents and close the scope.
+///
+/// # Safety
+/// `scope` must be null or an open scope.  It is freed.
+#[no_mangle]
+pub unsafe extern "C" fn ghost_scope_rollback(scope: *mut GhostScope) -> c_int {
+    if scope.is_null() {
+        return -1;
+    }
+    drop(Box::from_raw(scope));
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(blob: *const GhostBlob) -> Vec<u8> {
+        unsafe {
+            let mut out = vec![0; ghost_blob_read(blob, ptr::null_mut(), 0)];
+            ghost_blob_read(blob, out.as_mut_ptr(), out.len());
+            out
+        }
+    }
+
+    #[test]
+    fn test_commit_and_rollback() {
+        unsafe {
+            let blob = ghost_blob_new(b"port=80".as_ptr(), 7);
+            let scope = ghost_scope_begin(blob);
+            assert!(!ghost_scope_is_modified(scope));
+            assert_eq!(ghost_scope_set_bytes(scope, b"port=8080".as_ptr(), 9), 0);
+            let mut view = [0u8; 4];
+            assert_eq!(ghost_scope_read(scope, view.as_mut_ptr(), view.len()), 9);
+            assert_eq!(&view, b"port"); // Truncated to the buffer
+            assert_eq!(read(blob), b"port=80"); // Not committed yet
+            assert_eq!(ghost_scope_commit(scope), 0);
+            assert_eq!(read(blob), b"port=8080");
+
+            let scope = ghost_scope_begin(blob);
+            ghost_scope_set_bytes(scope, ptr::null(), 0);
+            assert!(ghost_scope_is_modified(scope));
+            assert_eq!(ghost_scope_rollback(scope), 0);
+            assert_eq!(read(blob), b"port=8080");
+            ghost_blob_free(blob);
+        }
+    }
+
+    #[test]
+    fn test_null_handles() {
+        unsafe {
+            assert!(ghost_scope_begin(ptr::null()).is_null());
+            assert_eq!(ghost_scope_set_bytes(ptr::null_mut(), ptr::null(), 0), -1);
+            assert_eq!(ghost_scope_commit(ptr::null_mut()), -1);
+            assert_eq!(ghost_scope_rollback(ptr::null_mut()), -1);
+            assert_eq!(ghost_blob_read(ptr::null(), ptr::null_mut(), 0), 0);
+            ghost_blob_free(ptr::null_mut());
+            let blob = ghost_blob_new(ptr::null(), 0);
+            assert!(read(blob).is_empty());
+            ghost_blob_free(blob);
+        }
+    }
+}
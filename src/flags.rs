@@ -0,0 +1,328 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The value of a flag, whatever its type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlagValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+/// Types a flag can hold.  `Default` is the form of the value usable in a `const` flag
+/// declaration, such as `&'static str` for string flags.
+pub trait FlagType: Sized {
+    type Default: Copy;
+
+    fn from_default(default: Self::Default) -> Self;
+
+    fn into_value(self) -> FlagValue;
+
+    fn from_value(value: &FlagValue) -> Option<Self>;
+}
+
+impl FlagType for bool {
+    type Default = bool;
+
+    fn from_default(default: bool) -> Self {
+        default
+    }
+
+    fn into_value(self) -> FlagValue {
+        FlagValue::Bool(self)
+    }
+
+    fn from_value(value: &FlagValue) -> Option<Self> {
+        match value {
+            FlagValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FlagType for i64 {
+    type Default = i64;
+
+    fn from_default(default: i64) -> Self {
+        default
+    }
+
+    fn into_value(self) -> FlagValue {
+        FlagValue::Int(self)
+    }
+
+    fn from_value(value: &FlagValue) -> Option<Self> {
+        match value {
+            FlagValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+impl FlagType for String {
+    type Default = &'static str;
+
+    fn from_default(default: &'static str) -> Self {
+        default.to_owned()
+    }
+
+    fn into_value(self) -> FlagValue {
+        FlagValue::Str(self)
+    }
+
+    fn from_value(value: &FlagValue) -> Option<Self> {
+        match value {
+            FlagValue::Str(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A typed flag declaration: `const DARK_MODE: Flag<bool> = Flag::new("dark_mode", false);`
+pub struct Flag<T: FlagType> {
+    name: &'static str,
+    default: T::Default,
+}
+
+impl<T: FlagType> Flag<T> {
+    // Declare a flag with the value it has when nothing sets or overrides it
+    pub const fn new(name: &'static str, default: T::Default) -> Self {
+        Flag { name, default }
+    }
+
+    // The flag's name
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+// Overrides made with `override_local`, tagged with the address of their FlagSet
+type LocalEntry = (usize, u64, &'static str, FlagValue);
+
+thread_local! {
+    static LOCAL_OVERRIDES: RefCell<Vec<LocalEntry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A FlagSet is a registry of typed feature flags with scoped overrides.  A flag's value
+/// is, in order of precedence, the most recent live `override_local` on the current
+/// thread, the most recent live `override_scoped`, the value given to `set`, and finally
+/// the flag's declared default.  Overrides nest like `ScopedStatic` overrides and are
+/// removed when their guards drop.  It can be declared as a `static` since `new` is
+/// `const`.
+pub struct FlagSet {
+    values: Mutex<Vec<(&'static str, FlagValue)>>, // Set with `set`
+    overrides: Mutex<Vec<(u64, &'static str, FlagValue)>>, // Live overrides, oldest first
+    next_id: AtomicU64,
+}
+
+impl FlagSet {
+    // Create a FlagSet where every flag has its default
+    pub const fn new() -> Self {
+        FlagSet {
+            values: Mutex::new(Vec::new()),
+            overrides: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    // The locks are only held for short, non-panicking sections, so poisoning can be ignored
+    fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+        mutex.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Identifies this set's entries in the thread-local override list
+    fn key(&self) -> usize {
+        self as *const FlagSet as usize
+    }
+
+    // Set the flag's base value, beneath any overrides
+    pub fn set<T: FlagType>(&self, flag: &Flag<T>, value: T) {
+        let mut values = Self::lock(&self.values);
+        let value = value.into_value();
+        match values.iter_mut().find(|(name, _)| *name == flag.name) {
+            Some((_, slot)) => *slot = value,
+            None => values.push((flag.name, value)),
+        }
+    }
+
+    // Record the flag's default, so `snapshot()` includes it before anything sets it
+    pub fn register<T: FlagType>(&self, flag: &Flag<T>) {
+        let mut values = Self::lock(&self.values);
+        if !values.iter().any(|(name, _)| *name == flag.name) {
+            values.push((flag.name, T::from_default(flag.default).into_value()));
+        }
+    }
+
+    // The effective value of the flag on the current thread
+    pub fn get<T: FlagType>(&self, flag: &Flag<T>) -> T {
+        self.lookup(flag.name)
+            .and_then(|value| T::from_value(&value))
+            .unwrap_or_else(|| T::from_default(flag.default))
+    }
+
+    // Override the flag for every thread until the returned guard is dropped
+    pub fn override_scoped<T: FlagType>(&self, flag: &Flag<T>, value: T) -> FlagOverride<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        Self::lock(&self.overrides).push((id, flag.name, value.into_value()));
+        FlagOverride { owner: self, id }
+    }
+
+    // Override the flag for the current thread only until the returned guard is dropped
+    pub fn override_local<T: FlagType>(&self, flag: &Flag<T>, value: T) -> LocalFlagOverride<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        LOCAL_OVERRIDES.with(|local| {
+            local
+                .borrow_mut()
+                .push((self.key(), id, flag.name, value.into_value()))
+        });
+        LocalFlagOverride {
+            owner: self,
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    // The effective value of every flag that has been registered, set or overridden, as
+    // seen from the current thread
+    pub fn snapshot(&self) -> HashMap<&'static str, FlagValue> {
+        let mut snapshot: HashMap<_, _> = Self::lock(&self.values).iter().cloned().collect();
+        for (_, name, value) in Self::lock(&self.overrides).iter() {
+            snapshot.insert(*name, value.clone());
+        }
+        LOCAL_OVERRIDES.with(|local| {
+            for (key, _, name, value) in local.borrow().iter() {
+                if *key == self.key() {
+                    snapshot.insert(*name, value.clone());
+                }
+            }
+        });
+        snapshot
+    }
+
+    fn lookup(&self, name: &str) -> Option<FlagValue> {
+        let local = LOCAL_OVERRIDES.with(|local| {
+            local
+                .borrow()
+                .iter()
+                .rev()
+                .find(|(key, _, flag, _)| *key == self.key() && *flag == name)
+                .map(|(_, _, _, value)| value.clone())
+        });
+        if local.is_some() {
+            return local;
+        }
+        let overrides = Self::lock(&self.overrides);
+        if let Some((_, _, value)) = overrides.iter().rev().find(|(_, flag, _)| *flag == name) {
+            return Some(value.clone());
+        }
+        drop(overrides);
+        let values = Self::lock(&self.values);
+        values
+            .iter()
+            .find(|(flag, _)| *flag == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+impl Default for FlagSet {
+    fn default() -> Self {
+        FlagSet::new()
+    }
+}
+
+/// Guard returned by `FlagSet::override_scoped`; removes its override when dropped.
+pub struct FlagOverride<'s> {
+    owner: &'s FlagSet,
+    id: u64,
+}
+
+impl<'s> Drop for FlagOverride<'s> {
+    fn drop(&mut self) {
+        // Guards may be dropped out of order, so remove this override wherever it sits
+        let mut overrides = FlagSet::lock(&self.owner.overrides);
+        if let Some(index) = overrides.iter().rposition(|(id, _, _)| *id == self.id) {
+            overrides.remove(index);
+        }
+    }
+}
+
+/// Guard returned by `FlagSet::override_local`; removes its override when dropped.  It
+/// must be dropped on the thread that created it, so it is neither `Send` nor `Sync`.
+pub struct LocalFlagOverride<'s> {
+    owner: &'s FlagSet,
+    id: u64,
+    marker: PhantomData<*const ()>,
+}
+
+impl<'s> Drop for LocalFlagOverride<'s> {
+    fn drop(&mut self) {
+        let key = self.owner.key();
+        // The thread-local may already be gone if the guard is dropped during thread exit
+        let _ = LOCAL_OVERRIDES.try_with(|local| {
+            let mut local = local.borrow_mut();
+            if let Some(index) = local
+                .iter()
+                .rposition(|(owner, id, _, _)| *owner == key && *id == self.id)
+            {
+                local.remove(index);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const DARK_MODE: Flag<bool> = Flag::new("dark_mode", false);
+    const MAX_ITEMS: Flag<i64> = Flag::new("max_items", 10);
+    const GREETING: Flag<String> = Flag::new("greeting", "hello");
+
+    #[test]
+    fn test_precedence_and_nesting() {
+        let flags = FlagSet::new();
+        assert_eq!(flags.get(&MAX_ITEMS), 10);
+        flags.set(&MAX_ITEMS, 20);
+        {
+            let _outer = flags.override_scoped(&MAX_ITEMS, 30);
+            {
+                let _inner = flags.override_scoped(&MAX_ITEMS, 40);
+                assert_eq!(flags.get(&MAX_ITEMS), 40);
+                let _local = flags.override_local(&MAX_ITEMS, 50);
+                assert_eq!(flags.get(&MAX_ITEMS), 50);
+            }
+            assert_eq!(flags.get(&MAX_ITEMS), 30);
+        }
+        assert_eq!(flags.get(&MAX_ITEMS), 20);
+        assert_eq!(flags.get(&GREETING), "hello");
+    }
+
+    #[test]
+    fn test_local_and_global_modes() {
+        static FLAGS: FlagSet = FlagSet::new();
+        let _local = FLAGS.override_local(&DARK_MODE, true);
+        let _global = FLAGS.override_scoped(&GREETING, String::from("hi"));
+        assert!(FLAGS.get(&DARK_MODE));
+        let other = thread::spawn(|| (FLAGS.get(&DARK_MODE), FLAGS.get(&GREETING)));
+        assert_eq!(other.join().unwrap(), (false, String::from("hi")));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let flags = FlagSet::default();
+        flags.register(&DARK_MODE);
+        flags.set(&MAX_ITEMS, 3);
+        let _local = flags.override_local(&GREETING, String::from("hey"));
+        let other = FlagSet::new();
+        let _unrelated = other.override_local(&DARK_MODE, true); // Another set's override
+        let snapshot = flags.snapshot();
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot["dark_mode"], FlagValue::Bool(false));
+        assert_eq!(snapshot["max_items"], FlagValue::Int(3));
+        assert_eq!(snapshot["greeting"], FlagValue::Str(String::from("hey")));
+    }
+}
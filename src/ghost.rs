@@ -0,0 +1,119 @@
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+
+// An invariant lifetime marker: the brand can neither grow nor shrink.
+type InvariantLifetime<'brand> = PhantomData<fn(&'brand ()) -> &'brand ()>;
+
+/// A GhostToken is the single key to every `GhostCell` of the same brand.  Shared access
+/// to the token grants shared access to the cells, and unique access to the token grants
+/// mutable access, so the borrow checker enforces aliasing rules with zero runtime cost.
+pub struct GhostToken<'brand> {
+    _marker: InvariantLifetime<'brand>,
+}
+
+impl<'brand> GhostToken<'brand> {
+    // Create a fresh token with a unique brand, valid only inside the closure
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<R, F>(f: F) -> R
+    where
+        F: for<'new_brand> FnOnce(GhostToken<'new_brand>) -> R,
+    {
+        f(GhostToken {
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A GhostCell holds a value whose access is mediated by a `GhostToken` of the same brand.
+#[repr(transparent)]
+pub struct GhostCell<'brand, T: ?Sized> {
+    _marker: InvariantLifetime<'brand>,
+    value: UnsafeCell<T>,
+}
+
+// The token, not the cell, decides who may access the value, so sharing a cell across
+// threads is as safe as sharing a `RwLock<T>`.
+unsafe impl<'brand, T: ?Sized + Send> Send for GhostCell<'brand, T> {}
+unsafe impl<'brand, T: ?Sized + Send + Sync> Sync for GhostCell<'brand, T> {}
+
+impl<'brand, T> GhostCell<'brand, T> {
+    // Create a new GhostCell owning `value`
+    pub fn new(value: T) -> Self {
+        GhostCell {
+            _marker: PhantomData,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    // Consume the cell and return the value
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<'brand, T: ?Sized> GhostCell<'brand, T> {
+    // Borrow the value through a shared token
+    pub fn borrow<'a>(&'a self, _token: &'a GhostToken<'brand>) -> &'a T {
+        // Shared token access rules out any mutable borrow of a same-brand cell
+        unsafe { &*self.value.get() }
+    }
+
+    // Mutably borrow the value through a unique token
+    #[allow(clippy::mut_from_ref)]
+    pub fn borrow_mut<'a>(&'a self, _token: &'a mut GhostToken<'brand>) -> &'a mut T {
+        // Unique token access rules out any other borrow of a same-brand cell
+        unsafe { &mut *self.value.get() }
+    }
+
+    // Mutably borrow the value when the cell itself is uniquely borrowed
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    // Reinterpret a unique reference as a GhostCell of any brand
+    pub fn from_mut(value: &mut T) -> &mut Self {
+        // GhostCell<T> has the same layout as UnsafeCell<T>, which has the same layout as T
+        unsafe { &mut *(value as *mut T as *mut Self) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_grants_access() {
+        let total = GhostToken::new(|mut token| {
+            let a = GhostCell::new(1);
+            let b = GhostCell::new(2);
+            *a.borrow_mut(&mut token) += 10; // Mutate through the token
+            *b.borrow_mut(&mut token) += 20;
+            *a.borrow(&token) + *b.borrow(&token)
+        });
+        assert_eq!(total, 33);
+    }
+
+    #[test]
+    fn test_shared_cells_in_collection() {
+        GhostToken::new(|mut token| {
+            let cell = GhostCell::new(vec![1, 2, 3]);
+            let views = [&cell, &cell]; // Aliasing cell references is fine
+            for view in views.iter() {
+                view.borrow_mut(&mut token).push(4);
+            }
+            assert_eq!(cell.borrow(&token).len(), 5);
+            assert_eq!(cell.into_inner(), vec![1, 2, 3, 4, 4]);
+        });
+    }
+
+    #[test]
+    fn test_from_mut_and_get_mut() {
+        let mut data = 5;
+        GhostToken::new(|token| {
+            let cell = GhostCell::from_mut(&mut data);
+            *cell.get_mut() = 6;
+            assert_eq!(*cell.borrow(&token), 6);
+        });
+        assert_eq!(data, 6);
+    }
+}
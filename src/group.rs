@@ -0,0 +1,266 @@
+use crate::{ScopeCellMut, ScopeError};
+
+// A type-erased cell registered with a ScopeGroup.
+trait Member<E> {
+    fn is_poisoned(&self) -> bool;
+
+    fn validate(&self) -> Result<(), E>;
+    fn commit(&mut self);
+    fn revert(&mut self);
+}
+
+type Validator<'g, T, E> = Box<dyn Fn(&T) -> Result<(), E> + 'g>;
+
+struct CellMember<'g, 'a, T: Clone, E> {
+    cell: &'g mut ScopeCellMut<'a, T>,
+    validator: Option<Validator<'g, T, E>>,
+}
+
+impl<'g, 'a, T: Clone, E> Member<E> for CellMember<'g, 'a, T, E> {
+    fn is_poisoned(&self) -> bool {
+        self.cell.is_poisoned()
+    }
+
+    fn validate(&self) -> Result<(), E> {
+        match &self.validator {
+            Some(validator) => validator(self.cell.get()),
+            None => Ok(()),
+        }
+    }
+
+    fn commit(&mut self) {
+        self.cell.write_back();
+    }
+
+    fn revert(&mut self) {
+        self.cell.revert();
+    }
+}
+
+/// A ScopeGroup gives all-or-nothing semantics over several `ScopeCellMut`s.
+/// `commit_all()` checks that no cell is poisoned and runs every registered validator first,
+/// and only writes any cell back if all of them pass; otherwise every cell is reverted.
+pub struct ScopeGroup<'g, E = ScopeError> {
+    members: Vec<Box<dyn Member<E> + 'g>>,
+}
+
+impl<'g, E: 'g> ScopeGroup<'g, E> {
+    // Create an empty group
+    pub fn new() -> Self {
+        ScopeGroup {
+            members: Vec::new(),
+        }
+    }
+
+    // Register a cell without a validator
+    pub fn add<'a, T: Clone>(&mut self, cell: &'g mut ScopeCellMut<'a, T>) -> &mut Self {
+        self.members.push(Box::new(CellMember {
+            cell,
+            validator: None,
+        }));
+        self
+    }
+
+    // Register a cell whose effective value must pass `validator` for the group to commit
+    pub fn add_validated<'a, T: Clone, F>(
+        &mut self,
+        cell: &'g mut ScopeCellMut<'a, T>,
+        validator: F,
+    ) -> &mut Self
+    where
+        F: Fn(&T) -> Result<(), E> + 'g,
+    {
+        self.members.push(Box::new(CellMember {
+            cell,
+            validator: Some(Box::new(validator)),
+        }));
+        self
+    }
+
+    // Number of registered cells
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    // Whether no cells are registered
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    // Validate every cell, then commit them all.  If any cell is poisoned or any validation
+    // fails, every cell is reverted and the first error is returned.
+    pub fn commit_all(mut self) -> Result<(), E>
+    where
+        E: From<ScopeError>,
+    {
+        let checked = self.members.iter().try_for_each(|member| {
+            if member.is_poisoned() {
+                return Err(ScopeError::Poisoned.into());
+            }
+            member.validate()
+        });
+        if let Err(err) = checked {
+            self.revert_members();
+            return Err(err);
+        }
+        for member in self.members.iter_mut() {
+            member.commit();
+        }
+        Ok(())
+    }
+
+    // Revert every registered cell
+    pub fn revert_all(mut self) {
+        self.revert_members();
+    }
+
+    fn revert_members(&mut self) {
+        for member in self.members.iter_mut() {
+            member.revert();
+        }
+    }
+}
+
+impl<'g, E: 'g> Default for ScopeGroup<'g, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Run a closure against one ScopeCellMut per target, committing every cell if the closure
+// returns `Ok` and reverting them all if it returns `Err` or panics, or if it left any cell
+// poisoned.  Saves creating the cells and a ScopeGroup by hand when the targets share a type.
+pub fn edit_many<'a, T, I, R, E, F>(targets: I, f: F) -> Result<R, E>
+where
+    T: Clone + 'a,
+    I: IntoIterator<Item = &'a mut T>,
+    E: From<ScopeError>,
+    F: FnOnce(&mut [ScopeCellMut<'a, T>]) -> Result<R, E>,
+{
+    let mut cells: Vec<_> = targets.into_iter().map(ScopeCellMut::new).collect();
+    let result = f(&mut cells)?;
+    if cells.iter().any(ScopeCellMut::is_poisoned) {
+        return Err(ScopeError::Poisoned.into()); // Dropping the cells reverts them all
+    }
+    for cell in cells {
+        cell.commit();
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_all() {
+        let mut names = vec!["a"];
+        let mut count = 1;
+        {
+            let mut names_cell = ScopeCellMut::new(&mut names);
+            let mut count_cell = ScopeCellMut::new(&mut count);
+            names_cell.get_mut().push("b");
+            *count_cell.get_mut() += 1;
+
+            let mut group: ScopeGroup = ScopeGroup::new();
+            group.add(&mut names_cell).add(&mut count_cell);
+            assert_eq!(group.len(), 2);
+            group.commit_all().unwrap();
+        }
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_failed_validation_commits_nothing() {
+        let mut names = vec!["a"];
+        let mut count = 1;
+        {
+            let mut names_cell = ScopeCellMut::new(&mut names);
+            let mut count_cell = ScopeCellMut::new(&mut count);
+            names_cell.get_mut().push("b");
+            *count_cell.get_mut() = 5;
+
+            let mut group = ScopeGroup::new();
+            group.add(&mut names_cell);
+            group.add_validated(&mut count_cell, |count: &i32| {
+                if *count < 3 {
+                    Ok(())
+                } else {
+                    Err(ScopeError::ValidationFailed)
+                }
+            });
+            assert_eq!(group.commit_all(), Err(ScopeError::ValidationFailed));
+            assert!(!names_cell.is_modified()); // Every cell was reverted
+            assert_eq!(*count_cell.get(), 1);
+        }
+        assert_eq!(names, vec!["a"]); // No partial commit
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_edit_many() {
+        let (mut a, mut b, mut c) = (vec![1], vec![2], vec![3]);
+        let moved = edit_many([&mut a, &mut b, &mut c], |views| {
+            let last = views[0].get_mut().pop().unwrap();
+            views[2].get_mut().push(last);
+            Ok::<_, ScopeError>(last)
+        });
+        assert_eq!(moved, Ok(1));
+        assert_eq!((&a, &b, &c), (&vec![], &vec![2], &vec![3, 1]));
+        let result = edit_many(vec![&mut a, &mut b, &mut c], |views| {
+            for view in views.iter_mut() {
+                view.get_mut().clear();
+            }
+            Err::<(), _>(ScopeError::ValidationFailed)
+        });
+        assert_eq!(result, Err(ScopeError::ValidationFailed));
+        assert_eq!((&b, &c), (&vec![2], &vec![3, 1])); // Nothing was committed
+    }
+
+    // Leave the cell poisoned by a panic in the middle of an update
+    fn poison(cell: &mut ScopeCellMut<'_, Vec<i32>>) {
+        let interrupted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cell.update(|v| {
+                v.push(9);
+                panic!("interrupted")
+            })
+        }));
+        assert!(interrupted.is_err() && cell.is_poisoned());
+    }
+
+    #[test]
+    fn test_poisoned_cell_commits_nothing() {
+        let (mut a, mut b, mut c) = (vec![1], vec![2], vec![3]);
+        let result = edit_many([&mut a, &mut b, &mut c], |views| {
+            views[0].get_mut().push(10);
+            poison(&mut views[1]);
+            views[2].get_mut().push(30);
+            Ok(())
+        });
+        assert_eq!(result, Err(ScopeError::Poisoned));
+        assert_eq!((&a, &b, &c), (&vec![1], &vec![2], &vec![3]));
+
+        let mut first_cell = ScopeCellMut::new(&mut a);
+        let mut second_cell = ScopeCellMut::new(&mut b);
+        first_cell.get_mut().push(10);
+        poison(&mut second_cell);
+        let mut group: ScopeGroup = ScopeGroup::new();
+        group.add(&mut first_cell).add(&mut second_cell);
+        assert_eq!(group.commit_all(), Err(ScopeError::Poisoned));
+        assert!(!first_cell.is_modified());
+        drop((first_cell, second_cell));
+        assert_eq!((&a, &b), (&vec![1], &vec![2]));
+    }
+
+    #[test]
+    fn test_revert_all() {
+        let mut data = String::from("hello");
+        let mut cell = ScopeCellMut::new(&mut data);
+        cell.get_mut().push_str(" world");
+        let mut group: ScopeGroup = ScopeGroup::new();
+        group.add(&mut cell);
+        group.revert_all();
+        assert_eq!(cell.get(), "hello");
+    }
+}
@@ -0,0 +1,142 @@
+use std::ops::DerefMut;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+
+use crate::ScopeError;
+
+/// A GuardedScopeCell makes speculative edits to data behind a lock guard it holds, such
+/// as a `MutexGuard` or `RwLockWriteGuard`.  Edits go to a temporary copy; `commit()` writes
+/// them back through the same guard, so the lock is never released or taken twice in
+/// between.  Dropping the cell discards the edits and releases the lock.
+pub struct GuardedScopeCell<G>
+where
+    G: DerefMut,
+    G::Target: Clone,
+{
+    guard: G,
+    modified_data: Option<G::Target>, // Holds temporary modified data
+}
+
+impl<G> GuardedScopeCell<G>
+where
+    G: DerefMut,
+    G::Target: Clone,
+{
+    // Create a new GuardedScopeCell over the data behind an already acquired guard
+    pub fn from_guard(guard: G) -> Self {
+        GuardedScopeCell {
+            guard,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &G::Target {
+        match self.modified_data {
+            Some(ref modified) => modified,
+            None => &self.guard,
+        }
+    }
+
+    // Mutably borrow the data, creating a temporary mutable copy if necessary
+    pub fn get_mut(&mut self) -> &mut G::Target {
+        let original = &*self.guard;
+        self.modified_data.get_or_insert_with(|| original.clone())
+    }
+
+    // Whether the data has been mutably accessed since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes made to the data by dropping the modified data
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Write the modified data back under the guard and hand the guard back, still locked
+    pub fn commit(mut self) -> G {
+        self.write_back();
+        self.guard
+    }
+
+    // Discard the changes and hand the guard back, still locked
+    pub fn into_guard(self) -> G {
+        self.guard
+    }
+
+    // Write the modified data back under the guard, leaving the cell unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            *self.guard = modified;
+        }
+    }
+}
+
+impl<'l, T: Clone> GuardedScopeCell<MutexGuard<'l, T>> {
+    // Lock `mutex` and open a scoped edit of its data, or fail if the mutex is poisoned
+    pub fn lock(mutex: &'l Mutex<T>) -> Result<Self, ScopeError> {
+        let guard = mutex.lock().map_err(|_| ScopeError::Poisoned)?;
+        Ok(GuardedScopeCell::from_guard(guard))
+    }
+}
+
+impl<'l, T: Clone> GuardedScopeCell<RwLockWriteGuard<'l, T>> {
+    // Write-lock `lock` and open a scoped edit of its data, or fail if the lock is poisoned
+    pub fn write(lock: &'l RwLock<T>) -> Result<Self, ScopeError> {
+        let guard = lock.write().map_err(|_| ScopeError::Poisoned)?;
+        Ok(GuardedScopeCell::from_guard(guard))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_under_same_guard() {
+        let shared = Mutex::new(vec![1, 2, 3]);
+        let mut cell = GuardedScopeCell::from_guard(shared.lock().unwrap());
+        cell.get_mut().push(4);
+        assert_eq!(cell.get().len(), 4);
+        let guard = cell.commit();
+        assert_eq!(*guard, vec![1, 2, 3, 4]); // Written back, lock still held
+        assert!(shared.try_lock().is_err());
+        drop(guard);
+        assert_eq!(*shared.lock().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_drop_discards_and_unlocks() {
+        let shared = Mutex::new(String::from("base"));
+        {
+            let mut cell = GuardedScopeCell::lock(&shared).unwrap();
+            cell.get_mut().push('!');
+        } // Dropped without commit
+        assert_eq!(*shared.lock().unwrap(), "base");
+    }
+
+    #[test]
+    fn test_rwlock_write_and_revert() {
+        let shared = RwLock::new(10);
+        let mut cell = GuardedScopeCell::write(&shared).unwrap();
+        *cell.get_mut() = 20;
+        cell.revert();
+        assert!(!cell.is_modified());
+        *cell.get_mut() = 30;
+        drop(cell.into_guard()); // Discards the edit
+        assert_eq!(*shared.read().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_poisoned_lock() {
+        let shared = Mutex::new(0);
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = shared.lock().unwrap();
+            panic!("holder failed");
+        });
+        assert_eq!(
+            GuardedScopeCell::lock(&shared).err(),
+            Some(ScopeError::Poisoned)
+        );
+    }
+}
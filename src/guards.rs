@@ -0,0 +1,196 @@
+//! Small RAII guards for process-wide state, each following the crate's revert-on-drop
+//! contract: the change lasts until the guard is dropped, including during a panic.
+
+use std::env;
+use std::io;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+pub use crate::ScopedEnv;
+
+// Serializes working-directory changes and restores made by ScopedCurrentDir
+static DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// A ScopedCurrentDir changes the process working directory and changes it back when
+/// dropped.  Like the environment, the working directory is shared by every thread.
+#[must_use = "the working directory is restored as soon as the guard is dropped"]
+pub struct ScopedCurrentDir {
+    previous: PathBuf,
+}
+
+impl ScopedCurrentDir {
+    // Change into `path` until the guard is dropped
+    pub fn change<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let _lock = DIR_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = env::current_dir()?;
+        env::set_current_dir(path)?;
+        Ok(ScopedCurrentDir { previous })
+    }
+
+    // The directory restored on drop
+    pub fn previous(&self) -> &Path {
+        &self.previous
+    }
+}
+
+impl Drop for ScopedCurrentDir {
+    fn drop(&mut self) {
+        let _lock = DIR_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        // Nothing sensible to do if the old directory has since been removed
+        let _ = env::set_current_dir(&self.previous);
+    }
+}
+
+/// A ScopedReplace swaps a value into a place for the duration of a scope and puts the
+/// original back on drop.  Seeding an RNG for one block is the typical use:
+/// `let mut rng = ScopedReplace::new(&mut rng, StdRng::seed_from_u64(42));` draws from the
+/// seeded generator, and the original generator state is back once the guard drops.
+pub struct ScopedReplace<'a, T> {
+    place: &'a mut T,
+    saved: Option<T>, // The original value, swapped back on drop
+}
+
+impl<'a, T> ScopedReplace<'a, T> {
+    // Put `value` into `place` until the guard is dropped
+    pub fn new(place: &'a mut T, value: T) -> Self {
+        let saved = mem::replace(place, value);
+        ScopedReplace {
+            place,
+            saved: Some(saved),
+        }
+    }
+
+    // The value restored on drop
+    pub fn saved(&self) -> &T {
+        self.saved.as_ref().unwrap()
+    }
+
+    // Mutable access to the value restored on drop
+    pub fn saved_mut(&mut self) -> &mut T {
+        self.saved.as_mut().unwrap()
+    }
+
+    // Put the original value back now, returning the replacement
+    pub fn restore(mut self) -> T {
+        let saved = self.saved.take().unwrap();
+        mem::replace(self.place, saved)
+    }
+
+    // Keep the replacement, returning the original value instead of restoring it
+    pub fn keep(mut self) -> T {
+        self.saved.take().unwrap()
+    }
+}
+
+impl<'a, T> Deref for ScopedReplace<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.place
+    }
+}
+
+impl<'a, T> DerefMut for ScopedReplace<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.place
+    }
+}
+
+impl<'a, T> Drop for ScopedReplace<'a, T> {
+    fn drop(&mut self) {
+        if let Some(saved) = self.saved.take() {
+            *self.place = saved;
+        }
+    }
+}
+
+/// A ScopedLogLevel overrides the `log` crate's global maximum level and restores the
+/// previous one when dropped.
+#[cfg(feature = "log")]
+#[must_use = "the log level is restored as soon as the guard is dropped"]
+pub struct ScopedLogLevel {
+    previous: log::LevelFilter,
+}
+
+#[cfg(feature = "log")]
+impl ScopedLogLevel {
+    // Set the maximum log level until the guard is dropped
+    pub fn set(level: log::LevelFilter) -> Self {
+        let previous = log::max_level();
+        log::set_max_level(level);
+        ScopedLogLevel { previous }
+    }
+
+    // The level restored on drop
+    pub fn previous(&self) -> log::LevelFilter {
+        self.previous
+    }
+}
+
+#[cfg(feature = "log")]
+impl Drop for ScopedLogLevel {
+    fn drop(&mut self) {
+        log::set_max_level(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_dir_restored() {
+        let before = env::current_dir().unwrap();
+        let target = env::temp_dir().canonicalize().unwrap();
+        {
+            let guard = ScopedCurrentDir::change(&target).unwrap();
+            assert_eq!(guard.previous(), before);
+            assert_eq!(env::current_dir().unwrap().canonicalize().unwrap(), target);
+        }
+        assert_eq!(env::current_dir().unwrap(), before);
+        assert!(ScopedCurrentDir::change("/definitely/not/a/dir").is_err());
+        assert_eq!(env::current_dir().unwrap(), before);
+    }
+
+    // Stand-in for a seedable generator
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1);
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_scoped_seed() {
+        let mut rng = Lcg(7);
+        rng.next();
+        let state = rng.0;
+        {
+            let mut seeded = ScopedReplace::new(&mut rng, Lcg(42));
+            let first = seeded.next();
+            assert_eq!(first, Lcg(42).next()); // Deterministic inside the scope
+            assert_eq!(seeded.saved().0, state);
+        }
+        assert_eq!(rng.0, state); // Original generator state is back
+
+        let replaced = ScopedReplace::new(&mut rng, Lcg(1)).keep();
+        assert_eq!(replaced.0, state);
+        assert_eq!(rng.0, 1);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_level_override() {
+        log::set_max_level(log::LevelFilter::Info);
+        {
+            let guard = ScopedLogLevel::set(log::LevelFilter::Trace);
+            assert_eq!(log::max_level(), log::LevelFilter::Trace);
+            assert_eq!(guard.previous(), log::LevelFilter::Info);
+        }
+        assert_eq!(log::max_level(), log::LevelFilter::Info);
+    }
+}
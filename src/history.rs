@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+
+/// A HistoryCell owns a value and records every committed mutation into a bounded
+/// history, supporting `undo()` and `redo()`.  Edits are made to a pending copy first and
+/// only enter the history on `commit()`; `revert()` discards them.
+pub struct HistoryCell<T: Clone> {
+    current: T,
+    pending: Option<T>, // Uncommitted modified data
+    undo: VecDeque<T>,  // Past committed states, oldest first
+    redo: Vec<T>,       // Undone states, most recently undone last
+    limit: usize,       // Maximum number of past states kept
+}
+
+impl<T: Clone> HistoryCell<T> {
+    // Create a new HistoryCell that keeps at most `limit` past states
+    pub fn new(value: T, limit: usize) -> Self {
+        HistoryCell {
+            current: value,
+            pending: None,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    // Borrow the data, showing the pending edit if one exists
+    pub fn get(&self) -> &T {
+        self.pending.as_ref().unwrap_or(&self.current)
+    }
+
+    // Borrow the last committed value, ignoring the pending edit
+    pub fn committed(&self) -> &T {
+        &self.current
+    }
+
+    // Mutably borrow the data, creating a pending copy if necessary
+    pub fn get_mut(&mut self) -> &mut T {
+        let current = &self.current;
+        self.pending.get_or_insert_with(|| current.clone())
+    }
+
+    // Whether a pending edit exists
+    pub fn is_modified(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // Discard the pending edit
+    pub fn revert(&mut self) {
+        self.pending = None;
+    }
+
+    // Make the pending edit the current value, recording the previous one in the history.
+    // Returns false if there was nothing to commit.
+    pub fn commit(&mut self) -> bool {
+        let Some(pending) = self.pending.take() else {
+            return false;
+        };
+        let previous = std::mem::replace(&mut self.current, pending);
+        self.push_undo(previous);
+        self.redo.clear();
+        true
+    }
+
+    // Go back to the previous committed state, discarding any pending edit.
+    // Returns false if there is no history to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.undo.pop_back() else {
+            return false;
+        };
+        self.pending = None;
+        let undone = std::mem::replace(&mut self.current, previous);
+        self.redo.push(undone);
+        true
+    }
+
+    // Re-apply the most recently undone state, discarding any pending edit.
+    // Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.pending = None;
+        let previous = std::mem::replace(&mut self.current, next);
+        self.push_undo(previous);
+        true
+    }
+
+    // Number of past states that can be undone
+    pub fn history_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    // Number of undone states that can be redone
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    // Iterate over past committed states, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &T> {
+        self.undo.iter()
+    }
+
+    // Consume the cell and return the effective value
+    pub fn into_inner(self) -> T {
+        self.pending.unwrap_or(self.current)
+    }
+
+    fn push_undo(&mut self, state: T) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.undo.len() == self.limit {
+            self.undo.pop_front(); // Forget the oldest state
+        }
+        self.undo.push_back(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut cell = HistoryCell::new(String::from("a"), 10);
+        cell.get_mut().push('b');
+        cell.commit();
+        cell.get_mut().push('c');
+        cell.commit();
+        assert_eq!(cell.history_len(), 2);
+
+        assert!(cell.undo());
+        assert_eq!(cell.get(), "ab");
+        assert!(cell.undo());
+        assert_eq!(cell.get(), "a");
+        assert!(!cell.undo()); // Nothing left to undo
+
+        assert!(cell.redo());
+        assert_eq!(cell.get(), "ab");
+        assert_eq!(cell.redo_len(), 1);
+    }
+
+    #[test]
+    fn test_commit_clears_redo() {
+        let mut cell = HistoryCell::new(1, 10);
+        *cell.get_mut() = 2;
+        cell.commit();
+        cell.undo();
+        *cell.get_mut() = 3;
+        cell.commit(); // New branch of history
+        assert!(!cell.redo());
+        assert_eq!(cell.history().copied().collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_pending_edits_are_not_history() {
+        let mut cell = HistoryCell::new(vec![1], 10);
+        assert!(!cell.commit()); // Nothing pending
+        cell.get_mut().push(2);
+        cell.revert();
+        assert_eq!(cell.history_len(), 0);
+        cell.get_mut().push(3);
+        cell.undo(); // Nothing to undo, pending edit is kept
+        assert_eq!(*cell.get(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut cell = HistoryCell::new(0, 2);
+        for i in 1..=5 {
+            *cell.get_mut() = i;
+            cell.commit();
+        }
+        assert_eq!(cell.history().copied().collect::<Vec<_>>(), vec![3, 4]); // Oldest dropped
+        cell.undo();
+        cell.undo();
+        assert!(!cell.undo());
+        assert_eq!(cell.into_inner(), 3);
+    }
+}
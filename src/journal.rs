@@ -0,0 +1,220 @@
+use crate::{Delta, ScopeError};
+
+/// A single recorded mutation.  Closures `FnMut(&mut T)` are operations, and so is any
+/// user enum implementing this trait, which keeps the log inspectable and serializable.
+pub trait Operation<T> {
+    fn apply(&mut self, target: &mut T);
+}
+
+impl<T, F: FnMut(&mut T)> Operation<T> for F {
+    fn apply(&mut self, target: &mut T) {
+        self(target)
+    }
+}
+
+/// The default operation type: any boxed closure.
+pub type BoxedOp<T> = Box<dyn FnMut(&mut T) + Send>;
+
+/// A JournalCell records every mutation as an operation applied on top of a baseline.  The
+/// current value is kept up to date, while the log can be inspected, truncated (rebuilding
+/// the value from the baseline), or replayed onto another value for reproducible debugging.
+pub struct JournalCell<T: Clone, O: Operation<T> = BoxedOp<T>> {
+    baseline: T,
+    current: T, // Baseline with every logged operation applied
+    log: Vec<O>,
+    version: u64, // Bumped on every commit of a non-empty log, naming the baseline in deltas
+}
+
+impl<T: Clone, O: Operation<T>> JournalCell<T, O> {
+    // Create a new JournalCell with an empty log
+    pub fn new(baseline: T) -> Self {
+        JournalCell::with_version(baseline, 0)
+    }
+
+    // Create a new JournalCell whose baseline is the given version, as when joining a delta
+    // sync from a snapshot
+    pub fn with_version(baseline: T, version: u64) -> Self {
+        JournalCell {
+            current: baseline.clone(),
+            baseline,
+            log: Vec::new(),
+            version,
+        }
+    }
+
+    // Apply an operation to the current value and record it
+    pub fn apply(&mut self, mut op: O) {
+        op.apply(&mut self.current);
+        self.log.push(op);
+    }
+
+    // The current value, with every logged operation applied
+    pub fn get(&self) -> &T {
+        &self.current
+    }
+
+    // The value the log starts from
+    pub fn baseline(&self) -> &T {
+        &self.baseline
+    }
+
+    // The recorded operations, oldest first
+    pub fn log(&self) -> &[O] {
+        &self.log
+    }
+
+    // Number of recorded operations
+    pub fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    // Whether no operation has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+
+    // Keep only the first `len` operations, rebuilding the current value from the baseline
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.log.len() {
+            return;
+        }
+        self.log.truncate(len);
+        self.current = self.baseline.clone();
+        for op in &mut self.log {
+            op.apply(&mut self.current);
+        }
+    }
+
+    // Drop the last operation, returning it.  The value is rebuilt from the baseline.
+    pub fn undo(&mut self) -> Option<O> {
+        let op = self.log.pop()?;
+        self.current = self.baseline.clone();
+        for op in &mut self.log {
+            op.apply(&mut self.current);
+        }
+        Some(op)
+    }
+
+    // Apply every logged operation, in order, to another value
+    pub fn replay_onto(&mut self, target: &mut T) {
+        for op in &mut self.log {
+            op.apply(target);
+        }
+    }
+
+    // Discard the log, going back to the baseline
+    pub fn revert(&mut self) {
+        self.log.clear();
+        self.current = self.baseline.clone();
+    }
+
+    // Adopt the current value as the new baseline and clear the log, moving to the next
+    // version if the log was not empty
+    pub fn commit(&mut self) {
+        if self.log.is_empty() {
+            return;
+        }
+        self.log.clear();
+        self.baseline = self.current.clone();
+        self.version += 1;
+    }
+
+    // Number of non-empty commits since the baseline version the cell was created with
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    // The logged operations, tagged with the baseline's version, to send to a replica
+    // instead of the full value
+    pub fn export_delta(&self) -> Delta<O>
+    where
+        O: Clone,
+    {
+        Delta {
+            base_version: self.version,
+            changes: self.log.clone(),
+        }
+    }
+
+    // Apply a replica's operations and commit them, moving to the same version as the
+    // exporting side's `commit()`.  Fails with `ScopeError::Conflict` if they were recorded
+    // against a different baseline version, or if this cell has uncommitted operations of
+    // its own.
+    pub fn import_delta(&mut self, delta: Delta<O>) -> Result<(), ScopeError> {
+        if delta.base_version != self.version || !self.log.is_empty() {
+            return Err(ScopeError::Conflict);
+        }
+        delta.changes.into_iter().for_each(|op| self.apply(op));
+        self.commit();
+        Ok(())
+    }
+
+    // Consume the cell and return the log
+    pub fn into_log(self) -> Vec<O> {
+        self.log
+    }
+
+    // Consume the cell and return the current value
+    pub fn into_inner(self) -> T {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Edit {
+        Push(i32),
+        Double,
+    }
+
+    impl Operation<Vec<i32>> for Edit {
+        fn apply(&mut self, target: &mut Vec<i32>) {
+            match self {
+                Edit::Push(n) => target.push(*n),
+                Edit::Double => target.iter_mut().for_each(|n| *n *= 2),
+            }
+        }
+    }
+
+    #[test]
+    fn test_enum_log_and_replay() {
+        let mut journal = JournalCell::new(vec![1]);
+        journal.apply(Edit::Push(2));
+        journal.apply(Edit::Double);
+        assert_eq!(*journal.get(), vec![2, 4]);
+        assert_eq!(journal.log(), &[Edit::Push(2), Edit::Double]);
+
+        let mut other = vec![10];
+        journal.replay_onto(&mut other);
+        assert_eq!(other, vec![20, 4]);
+    }
+
+    #[test]
+    fn test_truncate_and_undo() {
+        let mut journal = JournalCell::new(0);
+        for n in 1..=4 {
+            journal.apply(Box::new(move |v: &mut i32| *v += n) as BoxedOp<i32>);
+        }
+        assert_eq!(*journal.get(), 10);
+        journal.truncate(2);
+        assert_eq!(*journal.get(), 3); // Rebuilt from the baseline
+        assert!(journal.undo().is_some());
+        assert_eq!(*journal.get(), 1);
+        assert_eq!(journal.len(), 1);
+    }
+
+    #[test]
+    fn test_commit_and_revert() {
+        let mut journal: JournalCell<String> = JournalCell::new(String::from("a"));
+        journal.apply(Box::new(|s: &mut String| s.push('b')));
+        journal.commit();
+        assert!(journal.is_empty());
+        assert_eq!(journal.baseline(), "ab");
+        journal.apply(Box::new(|s: &mut String| s.clear()));
+        journal.revert();
+        assert_eq!(journal.into_inner(), "ab");
+    }
+}
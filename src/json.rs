@@ -0,0 +1,564 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{OwnedScopeCell, ScopeCell, ScopeCellMut};
+
+/// Why a JSON Patch or merge patch could not be produced or applied.
+#[derive(Debug)]
+pub enum JsonPatchError {
+    /// An operation is malformed, such as an unknown `op` or a missing `path`.
+    InvalidOperation(String),
+    /// A `path` or `from` pointer names a location that does not exist.
+    PathNotFound(String),
+    /// A `test` operation found a different value at its path.
+    TestFailed(String),
+    /// The value could not be converted to or from JSON.
+    Serde(serde_json::Error),
+}
+
+impl fmt::Display for JsonPatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonPatchError::InvalidOperation(op) => write!(f, "invalid patch operation: {}", op),
+            JsonPatchError::PathNotFound(path) => write!(f, "path not found: {}", path),
+            JsonPatchError::TestFailed(path) => write!(f, "test failed at {}", path),
+            JsonPatchError::Serde(err) => write!(f, "JSON conversion failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for JsonPatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonPatchError::Serde(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonPatchError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonPatchError::Serde(err)
+    }
+}
+
+// The RFC 6902 patch, as a JSON array of operations, that turns `before` into `after`.
+// Objects are compared key by key and arrays index by index, with elements appended or
+// removed at the end, so the patch is small for typical edits, though not always minimal.
+pub fn json_patch(before: &Value, after: &Value) -> Value {
+    let mut ops = Vec::new();
+    diff_values(&mut String::new(), before, after, &mut ops);
+    Value::Array(ops)
+}
+
+fn diff_values(path: &mut String, before: &Value, after: &Value, ops: &mut Vec<Value>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, old) in before {
+                let len = push_token(path, key);
+                match after.get(key) {
+                    Some(new) => diff_values(path, old, new, ops),
+                    None => ops.push(json!({ "op": "remove", "path": path })),
+                }
+                path.truncate(len);
+            }
+            for (key, new) in after {
+                if !before.contains_key(key) {
+                    let len = push_token(path, key);
+                    ops.push(json!({ "op": "add", "path": path, "value": new }));
+                    path.truncate(len);
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            let common = before.len().min(after.len());
+            for (index, (old, new)) in before.iter().zip(after).enumerate() {
+                let len = push_token(path, &index.to_string());
+                diff_values(path, old, new, ops);
+                path.truncate(len);
+            }
+            // Remove from the end first, so each index is still valid when it is applied
+            for index in (common..before.len()).rev() {
+                let len = push_token(path, &index.to_string());
+                ops.push(json!({ "op": "remove", "path": path }));
+                path.truncate(len);
+            }
+            for (index, new) in after.iter().enumerate().skip(common) {
+                let len = push_token(path, &index.to_string());
+                ops.push(json!({ "op": "add", "path": path, "value": new }));
+                path.truncate(len);
+            }
+        }
+        _ if before != after => ops.push(json!({ "op": "replace", "path": path, "value": after })),
+        _ => {}
+    }
+}
+
+// Append a JSON Pointer reference token, returning the path's previous length
+fn push_token(path: &mut String, token: &str) -> usize {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    len
+}
+
+// Split a JSON Pointer into its unescaped reference tokens
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, JsonPatchError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(JsonPatchError::InvalidOperation(pointer.to_string()));
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+// Apply an RFC 6902 patch to `target`.  The operations are applied in order to a copy, so
+// on error `target` is left as it was.
+pub fn apply_json_patch(target: &mut Value, patch: &Value) -> Result<(), JsonPatchError> {
+    let ops = patch
+        .as_array()
+        .ok_or_else(|| JsonPatchError::InvalidOperation(patch.to_string()))?;
+    let mut patched = target.clone();
+    for op in ops {
+        apply_op(&mut patched, op)?;
+    }
+    *target = patched;
+    Ok(())
+}
+
+fn apply_op(target: &mut Value, op: &Value) -> Result<(), JsonPatchError> {
+    let invalid = || JsonPatchError::InvalidOperation(op.to_string());
+    let field = |name: &str| op.get(name).and_then(Value::as_str).ok_or_else(invalid);
+    let path = field("path")?;
+    let value = || op.get("value").cloned().ok_or_else(invalid);
+    match field("op")? {
+        "add" => add(target, path, value()?),
+        "remove" => remove(target, path).map(drop),
+        "replace" => {
+            *get_mut(target, path)? = value()?;
+            Ok(())
+        }
+        "move" => {
+            let from = field("from")?;
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(invalid()); // Cannot move a value into one of its children
+            }
+            let moved = remove(target, from)?;
+            add(target, path, moved)
+        }
+        "copy" => {
+            let copied = get_mut(target, field("from")?)?.clone();
+            add(target, path, copied)
+        }
+        "test" if *get_mut(target, path)? == value()? => Ok(()),
+        "test" => Err(JsonPatchError::TestFailed(path.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+// The value at `pointer`
+fn get_mut<'v>(target: &'v mut Value, pointer: &str) -> Result<&'v mut Value, JsonPatchError> {
+    target
+        .pointer_mut(pointer)
+        .ok_or_else(|| JsonPatchError::PathNotFound(pointer.to_string()))
+}
+
+// The container holding the location `pointer` names, and the last token
+fn parent<'v>(
+    target: &'v mut Value,
+    pointer: &str,
+) -> Result<(&'v mut Value, String), JsonPatchError> {
+    let mut tokens = parse_pointer(pointer)?;
+    let last = tokens
+        .pop()
+        .ok_or_else(|| JsonPatchError::InvalidOperation(pointer.to_string()))?;
+    let mut container = target;
+    for token in &tokens {
+        container = match container {
+            Value::Object(map) => map.get_mut(token),
+            Value::Array(items) => token.parse().ok().and_then(|i: usize| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| JsonPatchError::PathNotFound(pointer.to_string()))?;
+    }
+    Ok((container, last))
+}
+
+fn add(target: &mut Value, pointer: &str, value: Value) -> Result<(), JsonPatchError> {
+    if pointer.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+    let not_found = || JsonPatchError::PathNotFound(pointer.to_string());
+    match parent(target, pointer)? {
+        (Value::Object(map), key) => {
+            map.insert(key, value);
+        }
+        (Value::Array(items), token) if token == "-" => items.push(value),
+        (Value::Array(items), token) => match token.parse() {
+            Ok(index) if index <= items.len() => items.insert(index, value),
+            _ => return Err(not_found()),
+        },
+        _ => return Err(not_found()),
+    }
+    Ok(())
+}
+
+fn remove(target: &mut Value, pointer: &str) -> Result<Value, JsonPatchError> {
+    let not_found = || JsonPatchError::PathNotFound(pointer.to_string());
+    match parent(target, pointer)? {
+        (Value::Object(map), key) => map.remove(&key).ok_or_else(not_found),
+        (Value::Array(items), token) => match token.parse() {
+            Ok(index) if index < items.len() => Ok(items.remove(index)),
+            _ => Err(not_found()),
+        },
+        _ => Err(not_found()),
+    }
+}
+
+// The minimal RFC 7386 merge patch that turns `before` into `after`: only the object
+// members that changed, with removed members set to `null`.  Arrays and other values are
+// replaced whole.  Merge patches cannot set a member to `null`, so a `null` in `after` where
+// `before` had something else comes out as a removal.
+pub fn json_merge_patch(before: &Value, after: &Value) -> Value {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut patch = Map::new();
+            for key in before.keys().filter(|key| !after.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, new) in after {
+                match before.get(key) {
+                    Some(old) if old == new => {}
+                    Some(old) => {
+                        patch.insert(key.clone(), json_merge_patch(old, new));
+                    }
+                    None => {
+                        patch.insert(key.clone(), strip_nulls(new));
+                    }
+                }
+            }
+            Value::Object(patch)
+        }
+        _ => strip_nulls(after),
+    }
+}
+
+// An object as a merge patch adding it, where `null` members would read as removals
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), strip_nulls(value)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+// Apply an RFC 7386 merge patch to `target`
+pub fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.remove(key);
+            } else {
+                apply_merge_patch(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// Patch the JSON form of `value`, converting the result back
+fn patched<T: Serialize + DeserializeOwned>(value: &T, patch: &Value) -> Result<T, JsonPatchError> {
+    let mut json = serde_json::to_value(value)?;
+    apply_json_patch(&mut json, patch)?;
+    Ok(serde_json::from_value(json)?)
+}
+
+// Merge-patch the JSON form of `value`, converting the result back
+fn merged<T: Serialize + DeserializeOwned>(value: &T, patch: &Value) -> Result<T, JsonPatchError> {
+    let mut json = serde_json::to_value(value)?;
+    apply_merge_patch(&mut json, patch);
+    Ok(serde_json::from_value(json)?)
+}
+
+impl<'a, T: Clone + Serialize> ScopeCell<'a, T> {
+    // The RFC 6902 patch that turns the original into the effective value, e.g. to send a
+    // scoped edit to a collaborative client
+    pub fn diff_json_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.original_data)?;
+        Ok(json_patch(&before, &serde_json::to_value(&*self.borrow())?))
+    }
+
+    // Apply an RFC 6902 patch to the effective value.  On error the cell is left untouched.
+    pub fn apply_json_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = patched(self.get(), patch)?;
+        self.set(value);
+        Ok(())
+    }
+
+    // The minimal RFC 7386 merge patch that turns the original into the effective value
+    pub fn diff_merge_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.original_data)?;
+        Ok(json_merge_patch(
+            &before,
+            &serde_json::to_value(&*self.borrow())?,
+        ))
+    }
+
+    // Apply an RFC 7386 merge patch to the effective value.  On error the cell is left
+    // untouched.
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = merged(self.get(), patch)?;
+        self.set(value);
+        Ok(())
+    }
+}
+
+impl<'a, T: Clone + Serialize> ScopeCellMut<'a, T> {
+    // The RFC 6902 patch that turns the original into the effective value
+    pub fn diff_json_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.original())?;
+        Ok(json_patch(&before, &serde_json::to_value(self.get())?))
+    }
+
+    // Apply an RFC 6902 patch to the effective value.  On error the cell is left untouched.
+    pub fn apply_json_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = patched(self.get(), patch)?;
+        self.set(value);
+        Ok(())
+    }
+
+    // The minimal RFC 7386 merge patch that turns the original into the effective value
+    pub fn diff_merge_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.original())?;
+        Ok(json_merge_patch(
+            &before,
+            &serde_json::to_value(self.get())?,
+        ))
+    }
+
+    // Apply an RFC 7386 merge patch to the effective value.  On error the cell is left
+    // untouched.
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = merged(self.get(), patch)?;
+        self.set(value);
+        Ok(())
+    }
+}
+
+impl<T: Clone + Serialize> OwnedScopeCell<T> {
+    // The RFC 6902 patch that turns the baseline into the effective value
+    pub fn diff_json_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.baseline())?;
+        Ok(json_patch(&before, &serde_json::to_value(self.get())?))
+    }
+
+    // Apply an RFC 6902 patch to the effective value.  On error the cell is left untouched.
+    pub fn apply_json_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = patched(self.get(), patch)?;
+        *self.get_mut() = value;
+        Ok(())
+    }
+
+    // The minimal RFC 7386 merge patch that turns the baseline into the effective value
+    pub fn diff_merge_patch(&self) -> Result<Value, JsonPatchError> {
+        let before = serde_json::to_value(self.baseline())?;
+        Ok(json_merge_patch(
+            &before,
+            &serde_json::to_value(self.get())?,
+        ))
+    }
+
+    // Apply an RFC 7386 merge patch to the effective value.  On error the cell is left
+    // untouched.
+    pub fn apply_merge_patch(&mut self, patch: &Value) -> Result<(), JsonPatchError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = merged(self.get(), patch)?;
+        *self.get_mut() = value;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        title: String,
+        tags: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        owner: Option<String>,
+    }
+
+    fn doc() -> Doc {
+        Doc {
+            title: String::from("Draft"),
+            tags: vec![String::from("a"), String::from("b"), String::from("c")],
+            owner: Some(String::from("sam")),
+        }
+    }
+
+    #[test]
+    fn test_diff_and_apply_round_trip() {
+        let original = doc();
+        let mut cell = ScopeCell::new(&original);
+        cell.get_mut().title.push_str(" 2");
+        cell.get_mut().tags.truncate(1);
+        cell.get_mut().owner = None;
+        let patch = cell.diff_json_patch().unwrap();
+        assert_eq!(
+            patch,
+            json!([
+                { "op": "remove", "path": "/owner" },
+                { "op": "remove", "path": "/tags/2" },
+                { "op": "remove", "path": "/tags/1" },
+                { "op": "replace", "path": "/title", "value": "Draft 2" },
+            ])
+        );
+        let mut replica = original.clone();
+        let mut client = ScopeCellMut::new(&mut replica);
+        client.apply_json_patch(&patch).unwrap();
+        client.commit();
+        assert_eq!(&replica, cell.get());
+        assert_eq!(
+            ScopeCell::new(&original).diff_json_patch().unwrap(),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn test_borrow_mut_after_diff() {
+        let original = doc();
+        let cell = ScopeCell::new(&original);
+        assert_eq!(cell.diff_merge_patch().unwrap(), json!({}));
+        assert_eq!(cell.diff_json_patch().unwrap(), json!([]));
+        cell.borrow_mut().owner = None;
+        assert_eq!(cell.diff_merge_patch().unwrap(), json!({ "owner": null }));
+    }
+
+    #[test]
+    fn test_apply_operations() {
+        let mut value = json!({ "a/b": [1, 2], "c": { "d": 1 } });
+        let patch = json!([
+            { "op": "add", "path": "/a~1b/-", "value": 3 },
+            { "op": "add", "path": "/a~1b/0", "value": 0 },
+            { "op": "copy", "from": "/c", "path": "/e" },
+            { "op": "move", "from": "/c/d", "path": "/f" },
+            { "op": "test", "path": "/e/d", "value": 1 },
+        ]);
+        apply_json_patch(&mut value, &patch).unwrap();
+        assert_eq!(
+            value,
+            json!({ "a/b": [0, 1, 2, 3], "c": {}, "e": { "d": 1 }, "f": 1 })
+        );
+        let failing = json!([
+            { "op": "remove", "path": "/f" },
+            { "op": "test", "path": "/e/d", "value": 2 },
+        ]);
+        let err = apply_json_patch(&mut value, &failing).unwrap_err();
+        assert!(matches!(err, JsonPatchError::TestFailed(ref path) if path == "/e/d"));
+        assert_eq!(value["f"], 1); // The failed patch changed nothing
+        assert!(matches!(
+            apply_json_patch(&mut value, &json!([{ "op": "remove", "path": "/x/y" }])),
+            Err(JsonPatchError::PathNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_merge_patch_round_trip() {
+        let original = doc();
+        let mut cell = ScopeCell::new(&original);
+        cell.get_mut().tags.push(String::from("d"));
+        cell.get_mut().owner = None;
+        let patch = cell.diff_merge_patch().unwrap();
+        assert_eq!(
+            patch,
+            json!({ "owner": null, "tags": ["a", "b", "c", "d"] })
+        );
+        let mut replica = OwnedScopeCell::new(original.clone());
+        replica.apply_merge_patch(&patch).unwrap();
+        assert_eq!(replica.get(), cell.get());
+        assert_eq!(
+            ScopeCell::new(&original).diff_merge_patch().unwrap(),
+            json!({})
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_values() {
+        let before = json!({ "a": { "b": 1, "c": 2 }, "d": [1], "e": "x" });
+        let after =
+            json!({ "a": { "b": 1, "c": 3, "n": { "keep": 1, "drop": null } }, "d": [1, 2] });
+        let patch = json_merge_patch(&before, &after);
+        assert_eq!(
+            patch,
+            json!({ "a": { "c": 3, "n": { "keep": 1 } }, "d": [1, 2], "e": null })
+        );
+        let mut value = before.clone();
+        apply_merge_patch(&mut value, &patch);
+        assert_eq!(
+            value,
+            json!({ "a": { "b": 1, "c": 3, "n": { "keep": 1 } }, "d": [1, 2] })
+        );
+        apply_merge_patch(&mut value, &json!({ "a": 5 }));
+        assert_eq!(value["a"], 5);
+        apply_merge_patch(&mut value, &json!([true]));
+        assert_eq!(value, json!([true]));
+        let mut scalar = json!(1);
+        apply_merge_patch(&mut scalar, &json!({ "x": { "y": null, "z": 1 } }));
+        assert_eq!(scalar, json!({ "x": { "z": 1 } }));
+    }
+
+    #[test]
+    fn test_patch_rejected_by_type_leaves_cell() {
+        let mut cell = OwnedScopeCell::new(doc());
+        let patch = json!([{ "op": "replace", "path": "/title", "value": 5 }]);
+        assert!(matches!(
+            cell.apply_json_patch(&patch),
+            Err(JsonPatchError::Serde(_))
+        ));
+        assert!(!cell.is_modified());
+        let patch = json!([{ "op": "add", "path": "/tags/-", "value": "d" }]);
+        cell.apply_json_patch(&patch).unwrap();
+        assert_eq!(
+            cell.diff_json_patch().unwrap(),
+            json!([{ "op": "add", "path": "/tags/3", "value": "d" }])
+        );
+    }
+}
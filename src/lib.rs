@@ -1,14 +1,210 @@
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
+use std::fmt;
 //use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
 
+use budget::Pricing;
+use observe::Hooks;
+use pending::Pending;
+
+pub mod alloc;
+#[cfg(any(feature = "proptest", feature = "quickcheck"))]
+mod arbitrary;
+#[cfg(feature = "rkyv")]
+mod archived;
+mod arena;
+mod async_scope;
+mod atomic;
+mod budget;
+#[cfg(feature = "bumpalo")]
+mod bump;
+mod cached;
+mod cell_metrics;
+mod checkpoint;
+mod config;
+mod copy;
+mod cow;
+mod defer;
+mod delta;
+mod diff;
+mod double_buffer;
+mod edit;
+mod env;
+mod epoch;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod flags;
+mod ghost;
+mod group;
+mod guarded;
+pub mod guards;
+mod history;
+mod journal;
+#[cfg(feature = "json")]
+mod json;
+mod lifecycle;
+mod macros;
+mod merge;
+mod model;
+mod observe;
+mod optimistic;
+mod owned;
+mod participant;
+mod pending;
+#[cfg(feature = "im")]
+mod persistent;
+mod pinned;
+mod pool;
+pub mod prelude;
+mod primitives;
+#[cfg(feature = "arc-swap")]
+mod rcu;
+mod recycle;
+mod registry;
+mod request;
+mod scope_cell_mut;
+mod scope_cow;
+mod scope_map;
+mod scope_option;
+mod scope_slice;
+mod scope_string;
+mod scope_vec;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod signal;
+mod slot;
+mod speculate;
+mod statics;
+mod std_impls;
+mod stm;
+mod swap;
+mod sync;
+mod take;
+#[cfg(feature = "tokio")]
+mod task_local;
+mod timed;
+mod transaction;
+#[cfg(feature = "testing")]
+mod trivial;
+mod try_clone;
+mod unsized_cell;
+mod validated;
+mod versioned;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "tokio")]
+mod watch;
+mod world;
+
+#[cfg(feature = "derive")]
+pub use scope_cell_derive::{scoped_fields, with_scoped, Scoped};
+
+#[cfg(feature = "rkyv")]
+pub use archived::ArchivedScopeCell;
+pub use arena::{ArenaCheckpoint, ArenaKey, ScopedArena};
+pub use atomic::{AtomicOverride, AtomicScopeCell, AtomicValue};
+pub use budget::{CloneBudget, HeapSize};
+#[cfg(feature = "bumpalo")]
+pub use bump::BumpScopeCell;
+pub use cached::CachedScopeCell;
+#[cfg(feature = "metrics")]
+pub use cell_metrics::MetricsRecorder;
+pub use cell_metrics::{set_metrics, take_metrics, CellMetrics, NoMetrics};
+pub use checkpoint::{CheckpointManager, Handle, Snapshot};
+pub use config::{ConfigLayer, ConfigOverlay, ConfigStack, ScopedLayer};
+pub use copy::CopyScopeCell;
+pub use cow::CowScopeCell;
+pub use defer::ScopeDefer;
+pub use delta::Delta;
+pub use diff::{Diff, ElementChange, EntryChange, Patch, TextChange};
+pub use double_buffer::{BackBuffer, DoubleBufferCell, DoubleBufferRead};
+pub use edit::{Copied, Materialized, ScopedEdit};
+pub use env::ScopedEnv;
+pub use epoch::{EpochCell, EpochId, Epochs};
+pub use error::ScopeError;
+pub use flags::{Flag, FlagOverride, FlagSet, FlagType, FlagValue, LocalFlagOverride};
+pub use ghost::{GhostCell, GhostToken};
+pub use group::{edit_many, ScopeGroup};
+pub use guarded::GuardedScopeCell;
+pub use history::HistoryCell;
+pub use journal::{BoxedOp, JournalCell, Operation};
+#[cfg(feature = "json")]
+pub use json::{apply_json_patch, apply_merge_patch, json_merge_patch, json_patch, JsonPatchError};
+pub use lifecycle::{MutationEvent, MutationKind, MutationLog};
+pub use merge::Merge;
+pub use model::{check_ops, CellModel, CellOp, ModelCell};
+pub use optimistic::{OptimisticCell, OptimisticEdit};
+pub use owned::OwnedScopeCell;
+pub use participant::{Coordinator, Participant};
+#[cfg(feature = "im")]
+pub use persistent::{Persistent, PersistentScopeCell};
+pub use pinned::PinScopeCell;
+pub use pool::{PooledScopeCell, ScopePool};
+#[cfg(feature = "arc-swap")]
+pub use rcu::{ArcSwapEdit, ArcSwapScopeCell};
+pub use recycle::{Recycle, RecycleScopeCell};
+pub use registry::{RegistryOverride, ScopedRegistry};
+pub use request::RequestScope;
+pub use scope_cell_mut::{DropPolicy, ScopeCellMut};
+pub use scope_cow::ScopeCow;
+pub use scope_map::ScopeHashMap;
+pub use scope_option::ScopeOption;
+pub use scope_slice::ScopeSlice;
+pub use scope_string::ScopeString;
+pub use scope_vec::ScopeVec;
+pub use signal::{SignalCell, SignalEdit, SubscriptionId};
+pub use slot::{ScopedSlot, SlotOverride};
+pub use speculate::speculate;
+#[cfg(feature = "rayon")]
+pub use speculate::{par_speculate, Outcome};
+pub use statics::{LocalOverride, ScopedLocalKey, ScopedStatic, ScopedThreadLocal, StaticOverride};
+pub use stm::{atomically, StmError, TVar, Tx};
+pub use swap::SwapScopeCell;
+pub use sync::{SyncScopeCell, SyncScopeRead, SyncScopeWrite};
+pub use take::TakeScopeCell;
+#[cfg(feature = "tokio")]
+pub use task_local::TaskScopedStatic;
+#[cfg(feature = "tokio")]
+pub use timed::spawn_expiry;
+pub use timed::TimedScopeCell;
+pub use transaction::{Savepoint, Transaction};
+#[cfg(feature = "testing")]
+pub use trivial::TrivialScopeCell;
+pub use try_clone::{TryClone, TryScopeCell};
+pub use unsized_cell::UnsizedScopeCell;
+pub use validated::ValidatedScopeCell;
+pub use versioned::{VersionedCell, VersionedWrite};
+#[cfg(feature = "wasm")]
+pub use wasm::JsScopeCell;
+#[cfg(feature = "tokio")]
+pub use watch::{WatchRef, WatchScopeCell};
+pub use world::WorldOverride;
+
 /// A ScopeCell allows temporary, scope-bound mutations to a value.  The underlying
-/// data must implement `Copy` so that the original value can be efficiently stored
-/// and restored.  Changes made within the ScopeCell's scope are reverted when the
-/// ScopeCell is dropped.
+/// data must implement `Clone` so that a private copy can be made on the first write.
+/// Changes made within the ScopeCell's scope are reverted when the ScopeCell is dropped.
+///
+/// Mutable access goes through either `&mut self` methods such as `get_mut()`, or the
+/// `borrow_mut()` guard which is checked at runtime like `RefCell`.  References handed out
+/// by `get()` count as shared borrows until the next `&mut self` access proves they are
+/// gone, so no `&mut T` can ever alias a live `&T`.
+///
+/// The pending copy is stored inline, in a `MaybeUninit<T>` slot and a flag inside the cell,
+/// so the cell itself never allocates: editing a value that owns no heap memory, such as a
+/// `Copy` type, costs no allocation at all.
 pub struct ScopeCell<'a, T: Clone> {
     original_data: &'a T,
-    modified_data: UnsafeCell<Option<T>>, // Holds temporary modified data
+    modified_data: UnsafeCell<Pending<T>>, // Holds temporary modified data
+    dirty: Cell<bool>, // Set on mutable access, cleared by revert/reset_tracking
+    borrow_state: Cell<isize>, // Live ScopeBorrow guards, or -1 while mutably borrowed
+    shared_escaped: Cell<bool>, // Set when get() hands out a plain reference
+    hooks: Hooks<'a, T>, // Lifecycle callbacks
+    snapshots: Vec<Option<T>>, // Restore points; None stands for the original
+    frozen: Cell<bool>, // Refuse edits while set
+    poisoned: Cell<bool>, // Set when a panic interrupted a mutation
+    generation: Cell<u64>, // Bumped on every mutation, revert or restore
+    budget: Pricing<'a, T>, // Caps the lazy copy, if set
 }
 
 impl<'a, T: Clone> ScopeCell<'a, T> {
@@ -16,48 +212,495 @@ impl<'a, T: Clone> ScopeCell<'a, T> {
     pub fn new(data: &'a T) -> Self {
         ScopeCell {
             original_data: data,
-            modified_data: UnsafeCell::new(None),
+            modified_data: UnsafeCell::new(Pending::empty()),
+            dirty: Cell::new(false),
+            borrow_state: Cell::new(0),
+            shared_escaped: Cell::new(false),
+            hooks: Hooks::new(),
+            snapshots: Vec::new(),
+            frozen: Cell::new(false),
+            poisoned: Cell::new(false),
+            generation: Cell::new(0),
+            budget: None,
         }
     }
 
+    // Create a new ScopeCell labelled for the lifecycle events it reports
+    pub fn named(data: &'a T, label: &'static str) -> Self {
+        let mut cell = ScopeCell::new(data);
+        cell.set_label(label);
+        cell
+    }
+
+    // Name the cell in the lifecycle events it reports, such as `tracing` events
+    pub fn set_label(&mut self, label: &'static str) -> &mut Self {
+        self.hooks.set_label(label);
+        self
+    }
+
+    // The cell's label, if it has one
+    pub fn label(&self) -> Option<&'static str> {
+        self.hooks.label()
+    }
+
+    // Charge the lazy copy to `budget`, priced at `size_of::<T>()`
+    pub fn set_budget(&mut self, budget: &'a CloneBudget) -> &mut Self {
+        self.budget = Some((budget, budget::shallow_size));
+        self
+    }
+
+    // Charge the lazy copy to `budget`, priced with the heap memory it owns as well
+    pub fn set_heap_budget(&mut self, budget: &'a CloneBudget) -> &mut Self
+    where
+        T: HeapSize,
+    {
+        self.budget = Some((budget, budget::deep_size));
+        self
+    }
+
+    // Register a callback fired with the fresh copy whenever the modified data is created
+    pub fn on_modify<F: Fn(&T) + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_modify(Box::new(hook));
+        self
+    }
+
+    // Register a callback fired whenever modified data is discarded, by `revert()` or on drop
+    pub fn on_revert<F: Fn() + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_revert(Box::new(hook));
+        self
+    }
+
+    // Register a callback fired with the effective value when it is extracted by `into_inner()`
+    pub fn on_commit<F: Fn(&T) + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_commit(Box::new(hook));
+        self
+    }
+
+    // Register a cleanup action run once the cell is dropped, after its changes have been
+    // discarded, with the untouched original
+    pub fn on_drop<F: FnOnce(&T) + Send + 'a>(&mut self, action: F) -> &mut Self {
+        self.hooks.add_drop(Box::new(action));
+        self
+    }
+
+    // Run a closure against a new ScopeCell over `data`.  If the closure returns `Ok`, the
+    // effective value is returned alongside the closure's result; on `Err` the changes are reverted.
+    pub fn scope<R, E, F>(data: &'a T, f: F) -> Result<(T, R), E>
+    where
+        F: FnOnce(&mut ScopeCell<'a, T>) -> Result<R, E>,
+    {
+        let mut cell = ScopeCell::new(data);
+        let result = f(&mut cell)?;
+        Ok((cell.into_inner(), result))
+    }
+
     // Consume the ScopeCell and return the inner modified data if it exists, otherwise return the original data
-    pub fn into_inner(self) -> T {
-        if let Some(modified) = unsafe { (*self.modified_data.get()).take() } {
+    pub fn into_inner(mut self) -> T {
+        let value = if let Some(modified) = self.modified_data.get_mut().take() {
             modified
         } else {
             self.original_data.clone()
+        };
+        self.hooks.committed(&value);
+        value
+    }
+
+    // Consume the ScopeCell like `into_inner()`, or fail if the cell is poisoned
+    pub fn try_into_inner(self) -> Result<T, ScopeError> {
+        if self.poisoned.get() {
+            return Err(ScopeError::Poisoned);
         }
+        Ok(self.into_inner())
+    }
+
+    // Consume the ScopeCell and transform its effective value
+    pub fn map_into<U, F: FnOnce(T) -> U>(self, f: F) -> U {
+        f(self.into_inner())
     }
 
-    // Revert the changes made to the data by dropping the modified data
+    // Revert the changes made to the data by dropping the modified data and any snapshots
     pub fn revert(&mut self) {
-        unsafe {
-            *self.modified_data.get() = None;
+        if self.modified_data.get_mut().take().is_some() {
+            self.hooks.reverted();
         }
+        self.snapshots.clear();
+        self.shared_escaped.set(false);
+        self.dirty.set(false);
+        self.bump();
+    }
+
+    // Record the effective value as a restore point, returning the number of snapshots held
+    pub fn push_snapshot(&mut self) -> usize {
+        let snapshot = self.modified_data.get_mut().cloned();
+        self.snapshots.push(snapshot);
+        self.snapshots.len()
+    }
+
+    // Discard the most recent snapshot, keeping the current state.  Returns false if there
+    // was no snapshot.
+    pub fn pop_snapshot(&mut self) -> bool {
+        self.snapshots.pop().is_some()
+    }
+
+    // Restore the effective value recorded by the most recent snapshot, which stays on the
+    // stack so it can be returned to again.  Returns false if there is no snapshot.
+    pub fn revert_to_last_snapshot(&mut self) -> bool {
+        let Some(snapshot) = self.snapshots.last() else {
+            return false;
+        };
+        let restored = snapshot.clone();
+        self.dirty.set(restored.is_some());
+        self.restore(restored);
+        true
+    }
+
+    // Number of snapshots currently on the stack
+    pub fn snapshot_count(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    // Whether the data has been mutably accessed since creation, the last revert, or the last reset_tracking
+    pub fn is_modified(&self) -> bool {
+        self.dirty.get()
+    }
+
+    // Whether a pending copy exists and lives entirely in the cell's inline slot, owning no
+    // heap memory, for tests and benchmarks verifying that editing does not allocate
+    pub fn is_inline(&mut self) -> bool
+    where
+        T: HeapSize,
+    {
+        let modified = self.modified_data.get_mut().as_ref();
+        modified.is_some_and(|value| value.heap_size() == 0)
+    }
+
+    // Clear the dirty flag without discarding the modified data, e.g. after syncing it elsewhere
+    pub fn reset_tracking(&mut self) {
+        self.dirty.set(false);
+    }
+
+    // Counter bumped on every mutable access, revert and restore.  Compare it with an
+    // earlier value to detect staleness without comparing the data itself.
+    pub fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
+    // Whether the cell may have changed since `generation` was read
+    pub fn changed_since(&self, generation: u64) -> bool {
+        self.generation.get() != generation
     }
 
-    // Borrow the data, showing either the original or the modified version
+    // Borrow the data, showing either the original or the modified version.
+    // Panics if the cell is currently mutably borrowed through `borrow_mut()`, or poisoned.
     pub fn get(&self) -> &T {
-        if let Some(ref modified) = unsafe { &*self.modified_data.get() } {
-            modified
-        } else {
-            self.original_data
+        match self.try_get() {
+            Ok(value) => value,
+            Err(ScopeError::Poisoned) => panic!("ScopeCell poisoned"),
+            Err(_) => panic!("ScopeCell already mutably borrowed"),
         }
     }
 
+    // Borrow the data like `get()`, or fail if the cell is mutably borrowed or poisoned
+    pub fn try_get(&self) -> Result<&T, ScopeError> {
+        if self.poisoned.get() {
+            return Err(ScopeError::Poisoned);
+        }
+        if self.borrow_state.get() < 0 {
+            return Err(ScopeError::AlreadyBorrowed);
+        }
+        self.shared_escaped.set(true);
+        Ok(unsafe { self.current() })
+    }
+
     // Mutably borrow the data, creating a temporary mutable copy if necessary
-    pub fn get_mut(&self) -> &mut T {
-        if unsafe { &*self.modified_data.get() }.is_none() {
-            // If no modification exists, clone the original data
-            unsafe {
-                *self.modified_data.get() = Some(self.original_data.clone());
+    pub fn get_mut(&mut self) -> &mut T {
+        // Exclusive access means no guard or reference from get() can still be alive
+        self.shared_escaped.set(false);
+        // Exclusive access also rules out any other reference to the modified data
+        unsafe { self.modified_mut() }
+    }
+
+    // Mutably borrow the data like `get_mut()`, or fail if the cell is frozen, poisoned or
+    // its copy would exceed its clone budget
+    pub fn try_get_mut(&mut self) -> Result<&mut T, ScopeError> {
+        self.check_writable()?;
+        if self.modified_data.get_mut().is_none() {
+            budget::charge(&self.budget, self.original_data)?;
+            // Exclusive access rules out any other reference to the modified data
+            unsafe { self.install(self.original_data.clone()) };
+        }
+        Ok(self.get_mut())
+    }
+
+    // Overwrite the effective value like `set()`, or fail if the cell is frozen or poisoned
+    pub fn try_set(&mut self, value: T) -> Result<(), ScopeError> {
+        self.check_writable()?;
+        self.set(value);
+        Ok(())
+    }
+
+    // Make the cell read-only: edits panic, and the `try_` methods return
+    // `ScopeError::Frozen`, until `unfreeze()` is called.  Reverting is still allowed.
+    pub fn freeze(&self) {
+        self.frozen.set(true);
+    }
+
+    // Allow edits again
+    pub fn unfreeze(&self) {
+        self.frozen.set(false);
+    }
+
+    // Whether the cell is currently read-only
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.get()
+    }
+
+    // Whether a panic interrupted a mutation through `update()` or a `borrow_mut()` guard.
+    // A poisoned cell refuses access, like a poisoned `Mutex`, until `clear_poison()`.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.get()
+    }
+
+    // Accept the possibly half-updated value (or `revert()` first) and allow access again
+    pub fn clear_poison(&self) {
+        self.poisoned.set(false);
+    }
+
+    // Apply a closure to the modified data, cloning the original first if necessary.
+    // If the closure panics, the cell is poisoned.
+    pub fn update<R, F: FnOnce(&mut T) -> R>(&mut self, f: F) -> R {
+        self.get_mut();
+        self.poisoned.set(true); // Cleared again unless `f` unwinds
+        let result = f(self.modified_data.get_mut().as_mut().unwrap());
+        self.poisoned.set(false);
+        result
+    }
+
+    // Overwrite the effective value without cloning the original first
+    pub fn set(&mut self, value: T) {
+        self.shared_escaped.set(false);
+        // Exclusive access rules out any other reference to the modified data
+        unsafe { self.install(value) };
+    }
+
+    // Overwrite the effective value, returning the previous one (a clone of the original if
+    // the cell was untouched)
+    pub fn replace(&mut self, value: T) -> T {
+        self.shared_escaped.set(false);
+        // Exclusive access rules out any other reference to the modified data
+        unsafe { self.install(value) }.unwrap_or_else(|| self.original_data.clone())
+    }
+
+    // The original and modified values, if a modified copy exists and differs from the original
+    pub fn diff(&self) -> Option<(&T, &T)>
+    where
+        T: PartialEq,
+    {
+        let modified = self.get();
+        if std::ptr::eq(modified, self.original_data) || modified == self.original_data {
+            None
+        } else {
+            Some((self.original_data, modified))
+        }
+    }
+
+    // Structured description of how the effective value differs from the original
+    pub fn changes(&self) -> Vec<T::Change>
+    where
+        T: Diff,
+    {
+        self.get().diff_from(self.original_data)
+    }
+
+    // Overwrite `other` with the effective value if the cell holds a modified copy, e.g. to
+    // apply an edit previewed here to the authoritative instance.  Returns whether anything
+    // was written.
+    pub fn apply_changes_to(&self, other: &mut T) -> bool {
+        let value = self.get();
+        if std::ptr::eq(value, self.original_data) {
+            return false;
+        }
+        other.clone_from(value);
+        true
+    }
+
+    // Replay the structured changes onto `other`, leaving parts of it the edit never touched
+    // alone.  Returns whether there was anything to apply.
+    pub fn apply_diff_to(&self, other: &mut T) -> bool
+    where
+        T: Patch,
+    {
+        let changes = self.changes();
+        let changed = !changes.is_empty();
+        other.apply_changes(changes);
+        changed
+    }
+
+    // Open a detached view over a single field of the effective value, read-only as far as
+    // this cell is concerned: edits made through it only clone the field and never reach this
+    // cell.  Use `ScopeCellMut::project` to edit a field through its parent.
+    pub fn project<F, P>(&self, project: P) -> ScopeCell<'_, F>
+    where
+        F: Clone,
+        P: FnOnce(&T) -> &F,
+    {
+        ScopeCell::new(project(self.get()))
+    }
+
+    // Run a fallible update against a copy of the effective value.  On `Ok` the copy becomes
+    // the modified data; on `Err` it is discarded and the cell is left exactly as it was.
+    pub fn try_update<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut scratch = self.get_shared().clone();
+        let result = f(&mut scratch)?;
+        self.set(scratch);
+        Ok(result)
+    }
+
+    // Obtain a shared borrow guard.  Panics if the cell is currently mutably borrowed.
+    pub fn borrow(&self) -> ScopeBorrow<'_, T> {
+        match self.try_borrow() {
+            Ok(guard) => guard,
+            Err(ScopeError::Poisoned) => panic!("ScopeCell poisoned"),
+            Err(_) => panic!("ScopeCell already mutably borrowed"),
+        }
+    }
+
+    // Obtain a shared borrow guard, or fail if the cell is mutably borrowed or poisoned
+    pub fn try_borrow(&self) -> Result<ScopeBorrow<'_, T>, ScopeError> {
+        if self.poisoned.get() {
+            return Err(ScopeError::Poisoned);
+        }
+        let state = self.borrow_state.get();
+        if state < 0 {
+            return Err(ScopeError::AlreadyBorrowed);
+        }
+        self.borrow_state.set(state + 1);
+        Ok(ScopeBorrow { cell: self })
+    }
+
+    // Obtain a mutable borrow guard.  Panics if any other borrow (guard or `get()` reference)
+    // may still be alive, or the cell is poisoned.  Unwinding while the guard is live
+    // poisons the cell.
+    pub fn borrow_mut(&self) -> ScopeBorrowMut<'_, T> {
+        if self.poisoned.get() {
+            panic!("ScopeCell poisoned");
+        }
+        if self.borrow_state.get() != 0 || self.shared_escaped.get() {
+            panic!("ScopeCell already borrowed");
+        }
+        self.borrow_state.set(-1);
+        ScopeBorrowMut { cell: self }
+    }
+
+    // Obtain a mutable borrow guard, or fail if another borrow may still be alive, or the
+    // cell is frozen (every write through the guard would panic) or poisoned
+    pub fn try_borrow_mut(&self) -> Result<ScopeBorrowMut<'_, T>, ScopeError> {
+        self.check_writable()?;
+        if self.borrow_state.get() != 0 || self.shared_escaped.get() {
+            return Err(ScopeError::AlreadyBorrowed);
+        }
+        Ok(self.borrow_mut())
+    }
+
+    // Start a single edit.  The returned guard derefs to the effective value; `commit()` keeps
+    // the edit, while dropping the guard undoes just that edit and leaves earlier changes.
+    // Because an unwinding edit is undone, it does not poison the cell.
+    pub fn begin(&mut self) -> EditGuard<'_, 'a, T> {
+        let saved = (self.modified_data.get_mut().cloned(), self.dirty.get());
+        EditGuard {
+            cell: self,
+            saved: Some(saved),
+        }
+    }
+
+    fn bump(&self) {
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    fn check_writable(&self) -> Result<(), ScopeError> {
+        if self.poisoned.get() {
+            Err(ScopeError::Poisoned)
+        } else if self.frozen.get() {
+            Err(ScopeError::Frozen)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn assert_writable(&self) {
+        match self.check_writable() {
+            Ok(()) => {}
+            Err(ScopeError::Poisoned) => panic!("ScopeCell poisoned"),
+            Err(_) => panic!("ScopeCell is frozen"),
+        }
+    }
+
+    // Put back an earlier modified state, firing the revert hook if that drops the copy
+    fn restore(&mut self, state: Option<T>) {
+        self.shared_escaped.set(false);
+        self.bump();
+        let previous = self.modified_data.get_mut().replace(state);
+        if previous.is_some() && self.modified_data.get_mut().is_none() {
+            self.hooks.reverted();
+        }
+    }
+
+    // Shared view of the effective value through exclusive access
+    fn get_shared(&mut self) -> &T {
+        match self.modified_data.get_mut().as_ref() {
+            Some(modified) => modified,
+            None => self.original_data,
+        }
+    }
+
+    // Mutable view of the modified data, cloning the original first if necessary.
+    // Callers must ensure no other access to the data is live.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn modified_mut(&self) -> &mut T {
+        self.assert_writable();
+        self.dirty.set(true);
+        self.bump();
+        let modified = &mut *self.modified_data.get();
+        if modified.is_none() {
+            if budget::charge(&self.budget, self.original_data).is_err() {
+                panic!("ScopeCell clone budget exceeded");
             }
+            let copy = modified.insert(self.original_data.clone());
+            self.hooks.modified(copy);
         }
+        modified.as_mut().unwrap()
+    }
 
-        unsafe { (*self.modified_data.get()).as_mut().unwrap() }
+    // Store `value` as the modified data, returning the previous modified data if any.
+    // Callers must ensure no other access to the data is live.
+    unsafe fn install(&self, value: T) -> Option<T> {
+        self.assert_writable();
+        self.dirty.set(true);
+        self.bump();
+        let modified = &mut *self.modified_data.get();
+        let previous = modified.take();
+        let installed = modified.insert(value);
+        if previous.is_none() {
+            self.hooks.modified(installed);
+        }
+        previous
+    }
+
+    // Shared view of the effective value, without touching the borrow flags.
+    // Callers must ensure no mutable access is live.
+    unsafe fn current(&self) -> &T {
+        if let Some(modified) = (*self.modified_data.get()).as_ref() {
+            modified
+        } else {
+            self.original_data
+        }
     }
 }
 
+/// A shared borrow of a ScopeCell, released when dropped.
 pub struct ScopeBorrow<'b, T: Clone> {
     cell: &'b ScopeCell<'b, T>,
 }
@@ -66,32 +709,124 @@ impl<'b, T: Clone> Deref for ScopeBorrow<'b, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        self.cell.get()
+        // The live guard keeps the borrow count positive, so no mutable access can exist
+        unsafe { self.cell.current() }
+    }
+}
+
+impl<'b, T: Clone> Drop for ScopeBorrow<'b, T> {
+    fn drop(&mut self) {
+        self.cell.borrow_state.set(self.cell.borrow_state.get() - 1);
     }
 }
 
+/// An exclusive borrow of a ScopeCell, released when dropped.  The temporary copy is
+/// only created on the first mutable access through the guard.
 pub struct ScopeBorrowMut<'b, T: Clone> {
-    cell: &'b mut ScopeCell<'b, T>,
+    cell: &'b ScopeCell<'b, T>,
+}
+
+impl<'b, T: Clone> ScopeBorrowMut<'b, T> {
+    // Overwrite the effective value without cloning the original first
+    pub fn set(&mut self, value: T) {
+        // This guard is the only live borrow of the cell
+        unsafe { self.cell.install(value) };
+    }
+
+    // Overwrite the effective value, returning the previous one
+    pub fn replace(&mut self, value: T) -> T {
+        // This guard is the only live borrow of the cell
+        unsafe { self.cell.install(value) }.unwrap_or_else(|| self.cell.original_data.clone())
+    }
 }
 
 impl<'b, T: Clone> Deref for ScopeBorrowMut<'b, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        self.cell.get()
+        // This guard is the only live borrow of the cell
+        unsafe { self.cell.current() }
     }
 }
 
 impl<'b, T: Clone> DerefMut for ScopeBorrowMut<'b, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // This guard is the only live borrow of the cell, so the modified data is unaliased
+        unsafe { self.cell.modified_mut() }
+    }
+}
+
+impl<'b, T: Clone> Drop for ScopeBorrowMut<'b, T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.cell.poisoned.set(true);
+        }
+        self.cell.borrow_state.set(0);
+    }
+}
+
+/// A single edit to a ScopeCell, started by `begin()`.  Dropping the guard without calling
+/// `commit()` restores the state the cell had when the edit began.
+pub struct EditGuard<'e, 'a, T: Clone> {
+    cell: &'e mut ScopeCell<'a, T>,
+    saved: Option<(Option<T>, bool)>, // Modified data and dirty flag before the edit
+}
+
+impl<'e, 'a, T: Clone> EditGuard<'e, 'a, T> {
+    // Keep the edit in the cell
+    pub fn commit(mut self) {
+        self.saved = None;
+    }
+}
+
+impl<'e, 'a, T: Clone> Deref for EditGuard<'e, 'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // The guard holds the cell exclusively, so no mutable access can be live
+        unsafe { self.cell.current() }
+    }
+}
+
+impl<'e, 'a, T: Clone> DerefMut for EditGuard<'e, 'a, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.cell.get_mut()
     }
 }
 
+impl<'e, 'a, T: Clone> Drop for EditGuard<'e, 'a, T> {
+    fn drop(&mut self) {
+        if let Some((state, dirty)) = self.saved.take() {
+            self.cell.restore(state);
+            self.cell.dirty.set(dirty);
+        }
+    }
+}
+
 // When the ScopeCell is dropped, changes are discarded automatically.
 impl<'a, T: Clone> Drop for ScopeCell<'a, T> {
     fn drop(&mut self) {
         self.revert(); // Drop the modified data, reverting any changes.
+        self.hooks.dropped(self.original_data);
+    }
+}
+
+// Shows the baseline, the pending copy (if any) and the dirty flag.  Like `RefCell`, a cell
+// that is currently mutably borrowed prints a placeholder instead of panicking.
+impl<'a, T: Clone + fmt::Debug> fmt::Debug for ScopeCell<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ScopeCell");
+        debug.field("original", self.original_data);
+        let state = self.borrow_state.get();
+        if state < 0 {
+            debug.field("pending", &format_args!("<borrowed>"));
+        } else {
+            // A shared borrow for the duration of the formatting, released even if it panics
+            self.borrow_state.set(state + 1);
+            let _borrow = ScopeBorrow { cell: self };
+            debug.field("pending", unsafe { &*self.modified_data.get() });
+        }
+        debug.field("dirty", &self.dirty.get()).finish()
     }
 }
 
@@ -164,9 +899,9 @@ mod tests {
     fn test_with_copy_type() {
         let data = 10;
         {
-            let mut Scope = ScopeCell::new(&data);
-            *Scope.get_mut() = 20;
-            assert_eq!(*Scope.get(), 20); // Mutated value
+            let mut scope = ScopeCell::new(&data);
+            *scope.get_mut() = 20;
+            assert_eq!(*scope.get(), 20); // Mutated value
         }
         assert_eq!(data, 10); // Reverted to original value
     }
@@ -175,9 +910,9 @@ mod tests {
     fn test_with_needs_drop_type() {
         let data = vec![1, 2, 3];
         {
-            let mut Scope = ScopeCell::new(&data);
-            Scope.get_mut().push(4);
-            assert_eq!(*Scope.get(), vec![1, 2, 3, 4]); // Mutated vector
+            let mut scope = ScopeCell::new(&data);
+            scope.get_mut().push(4);
+            assert_eq!(*scope.get(), vec![1, 2, 3, 4]); // Mutated vector
         }
         assert_eq!(data, vec![1, 2, 3]); // Reverted to original vector
     }
@@ -186,8 +921,8 @@ mod tests {
     fn test_nested_borrows() {
         let data = vec![1, 2, 3];
         {
-            let mut Scope = ScopeCell::new(&data);
-            let mut borrowed = Scope.get_mut();
+            let mut scope = ScopeCell::new(&data);
+            let borrowed = scope.get_mut();
             borrowed.push(4);
             assert_eq!(borrowed.len(), 4); // Ensure the borrow mutates
             borrowed.pop(); // Modify through the mutable borrow
@@ -226,7 +961,7 @@ mod tests {
     fn test_no_mutation_revert() {
         let data = vec![1, 2, 3];
         {
-            let scope = ScopeCell::new(&data);
+            let _scope = ScopeCell::new(&data);
             // No mutation performed
         }
         assert_eq!(data, vec![1, 2, 3]); // Original data should remain unchanged
@@ -236,7 +971,7 @@ mod tests {
     fn test_multiple_borrow_same_scope() {
         let data = vec![1, 2, 3];
         {
-            let mut scope = ScopeCell::new(&data);
+            let scope = ScopeCell::new(&data);
             let borrowed1 = scope.get();
             let borrowed2 = scope.get();
             assert_eq!(borrowed1.len(), 3);
@@ -267,13 +1002,547 @@ mod tests {
             let borrowed = scope.get(); // Immutable borrow
             assert_eq!(borrowed.len(), 3);
 
-            let mut borrowed_mut = scope.get_mut(); // Mutable borrow
+            let borrowed_mut = scope.get_mut(); // Mutable borrow
             borrowed_mut.push(4);
             assert_eq!(borrowed_mut.len(), 4);
         }
         assert_eq!(data, vec![1, 2, 3]); // Original data should remain unchanged
     }
 
+    #[test]
+    fn test_is_modified_tracking() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        assert!(!scope.is_modified()); // Fresh cell is clean
+        scope.get();
+        assert!(!scope.is_modified()); // Reads do not mark the cell dirty
+        scope.get_mut().push(4);
+        assert!(scope.is_modified());
+        scope.revert();
+        assert!(!scope.is_modified()); // Revert clears the dirty flag
+    }
+
+    #[test]
+    fn test_reset_tracking_keeps_modification() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        scope.get_mut().push(4);
+        scope.reset_tracking();
+        assert!(!scope.is_modified());
+        assert_eq!(*scope.get(), vec![1, 2, 3, 4]); // Modified data is kept
+        scope.get_mut().push(5);
+        assert!(scope.is_modified()); // Further edits mark it dirty again
+    }
+
+    #[test]
+    fn test_borrow_guards() {
+        let data = vec![1, 2, 3];
+        let cell = ScopeCell::new(&data);
+        {
+            let b1 = cell.borrow();
+            let b2 = cell.borrow(); // Multiple shared guards are allowed
+            assert_eq!(b1.len(), 3);
+            assert_eq!(b2.len(), 3);
+        }
+        {
+            let mut guard = cell.borrow_mut();
+            guard.push(4);
+            assert_eq!(guard.len(), 4);
+        }
+        assert_eq!(cell.borrow().len(), 4); // Guard released, change visible
+        assert!(cell.is_modified());
+        drop(cell);
+        assert_eq!(data, vec![1, 2, 3]); // Original data should not change
+    }
+
+    #[test]
+    fn test_borrow_mut_without_write_does_not_clone() {
+        let data = vec![1, 2, 3];
+        let cell = ScopeCell::new(&data);
+        assert_eq!(cell.borrow_mut().len(), 3); // Read through the mutable guard
+        assert!(!cell.is_modified());
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn test_borrow_mut_while_borrowed_panics() {
+        let data = vec![1, 2, 3];
+        let cell = ScopeCell::new(&data);
+        let _shared = cell.borrow();
+        let _exclusive = cell.borrow_mut();
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn test_borrow_while_mutably_borrowed_panics() {
+        let data = vec![1, 2, 3];
+        let cell = ScopeCell::new(&data);
+        let _exclusive = cell.borrow_mut();
+        let _shared = cell.borrow();
+    }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn test_borrow_mut_after_get_panics() {
+        let data = vec![1, 2, 3];
+        let cell = ScopeCell::new(&data);
+        let _len = cell.get().len(); // A plain reference may still be alive
+        let _exclusive = cell.borrow_mut();
+    }
+
+    #[test]
+    fn test_borrow_mut_after_exclusive_access() {
+        let data = vec![1, 2, 3];
+        let mut cell = ScopeCell::new(&data);
+        assert_eq!(cell.get().len(), 3);
+        cell.get_mut().push(4); // Exclusive access ends all earlier get() borrows
+        cell.borrow_mut().push(5);
+        assert_eq!(*cell.borrow(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_generation_tracks_changes() {
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        let start = scope.generation();
+        let _ = scope.get();
+        assert!(!scope.changed_since(start)); // Reads do not bump
+        scope.get_mut().push(2);
+        let after_edit = scope.generation();
+        assert!(scope.changed_since(start));
+        scope.borrow_mut().push(3);
+        assert!(scope.changed_since(after_edit));
+        let before_revert = scope.generation();
+        scope.revert();
+        assert!(scope.generation() > before_revert);
+    }
+
+    #[test]
+    fn test_debug_output() {
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        assert_eq!(
+            format!("{:?}", scope),
+            "ScopeCell { original: [1], pending: None, dirty: false }"
+        );
+        scope.get_mut().push(2);
+        assert_eq!(
+            format!("{:?}", scope),
+            "ScopeCell { original: [1], pending: Some([1, 2]), dirty: true }"
+        );
+        let _guard = scope.borrow_mut();
+        assert!(format!("{:?}", scope).contains("pending: <borrowed>"));
+    }
+
+    #[test]
+    fn test_borrow_mut_after_formatting() {
+        let data = vec![1];
+        let cell = ScopeCell::new(&data);
+        let _ = format!("{:?}", cell);
+        cell.borrow_mut().push(2);
+        assert_eq!(*cell.borrow(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_panicking_update_poisons() {
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            scope.update(|v| {
+                v.push(2);
+                panic!("half-way");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(scope.is_poisoned());
+        assert_eq!(scope.try_get().err(), Some(ScopeError::Poisoned));
+        assert_eq!(scope.try_get_mut().err(), Some(ScopeError::Poisoned));
+        assert!(scope.try_borrow().is_err());
+
+        scope.revert();
+        scope.clear_poison();
+        assert_eq!(scope.try_get(), Ok(&vec![1]));
+    }
+
+    #[test]
+    fn test_panicking_guard_poisons() {
+        let data = 1;
+        let cell = ScopeCell::new(&data);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut guard = cell.borrow_mut();
+            *guard = 2;
+            panic!("while borrowed");
+        }));
+        assert!(result.is_err());
+        assert!(matches!(cell.try_borrow_mut(), Err(ScopeError::Poisoned)));
+        cell.clear_poison();
+        assert_eq!(*cell.borrow(), 2); // The half-finished edit is kept
+        assert_eq!(cell.try_into_inner(), Ok(2));
+    }
+
+    #[test]
+    fn test_unwinding_edit_guard_does_not_poison() {
+        let data = 1;
+        let mut scope = ScopeCell::new(&data);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut edit = scope.begin();
+            *edit = 2;
+            panic!("inside edit");
+        }));
+        assert!(result.is_err());
+        assert!(!scope.is_poisoned());
+        assert_eq!(*scope.get(), 1);
+    }
+
+    #[test]
+    fn test_try_borrow_family() {
+        let data = vec![1];
+        let cell = ScopeCell::new(&data);
+        {
+            let _guard = cell.try_borrow_mut().unwrap();
+            assert_eq!(cell.try_get().err(), Some(ScopeError::AlreadyBorrowed));
+            assert!(cell.try_borrow().is_err());
+            assert!(cell.try_borrow_mut().is_err());
+        }
+        assert_eq!(cell.try_borrow().map(|v| v.len()).ok(), Some(1));
+        assert_eq!(cell.try_get().map(Vec::len), Ok(1));
+        assert!(cell.try_borrow_mut().is_err()); // The get() reference may still be alive
+    }
+
+    #[test]
+    fn test_try_borrow_mut_on_frozen_cell() {
+        let data = 1;
+        let cell = ScopeCell::new(&data);
+        cell.freeze();
+        assert_eq!(cell.try_borrow_mut().err(), Some(ScopeError::Frozen));
+    }
+
+    #[test]
+    fn test_freeze_rejects_edits() {
+        let data = 1;
+        let mut scope = ScopeCell::new(&data);
+        *scope.get_mut() = 2;
+        scope.freeze();
+        assert!(scope.is_frozen());
+        assert_eq!(scope.try_get_mut().err(), Some(ScopeError::Frozen));
+        assert_eq!(scope.try_set(3), Err(ScopeError::Frozen));
+        assert_eq!(*scope.get(), 2); // Reads still work
+        scope.unfreeze();
+        assert_eq!(scope.try_set(3), Ok(()));
+        assert_eq!(*scope.get(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "ScopeCell is frozen")]
+    fn test_frozen_guard_write_panics() {
+        let data = vec![1];
+        let cell = ScopeCell::new(&data);
+        cell.freeze();
+        cell.borrow_mut().push(2);
+    }
+
+    #[test]
+    fn test_interleaved_borrows_stay_alias_free() {
+        // Exercises every mutable path next to shared reads; run under Miri to check aliasing
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        {
+            let a = scope.borrow();
+            let b = scope.borrow();
+            assert_eq!(a.len() + b.len(), 2);
+        }
+        {
+            let mut guard = scope.borrow_mut();
+            guard.push(2);
+            let len = guard.len(); // Shared read through the guard after writing
+            guard.push(len as i32 + 1);
+        }
+        let first = scope.get()[0];
+        scope.get_mut().push(first);
+        scope.set(vec![9]);
+        {
+            let mut edit = scope.begin();
+            edit.push(10);
+            assert_eq!(*edit, vec![9, 10]);
+        }
+        let view = scope.get();
+        let again = scope.get();
+        assert_eq!(view, again);
+        assert_eq!(*scope.borrow(), vec![9]);
+    }
+
+    #[test]
+    fn test_edit_guard_commit_and_drop() {
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        scope.get_mut().push(2);
+        {
+            let mut edit = scope.begin();
+            edit.push(3);
+            edit.commit();
+        }
+        {
+            let mut edit = scope.begin();
+            edit.push(4);
+            assert_eq!(*edit, vec![1, 2, 3, 4]);
+        } // Dropped without commit
+        assert_eq!(*scope.get(), vec![1, 2, 3]); // Only the last edit was undone
+    }
+
+    #[test]
+    fn test_edit_guard_on_clean_cell() {
+        let data = 5;
+        let mut scope = ScopeCell::new(&data);
+        *scope.begin() = 6;
+        assert!(!scope.is_modified()); // Dropping the edit restores the clean state
+        assert_eq!(*scope.get(), 5);
+    }
+
+    #[test]
+    fn test_snapshot_stack() {
+        let data = vec![1];
+        let mut scope = ScopeCell::new(&data);
+        assert_eq!(scope.push_snapshot(), 1); // Snapshot of the untouched original
+        scope.get_mut().push(2);
+        assert_eq!(scope.push_snapshot(), 2);
+        scope.get_mut().push(3);
+
+        assert!(scope.revert_to_last_snapshot());
+        assert_eq!(*scope.get(), vec![1, 2]);
+        scope.get_mut().push(4);
+        assert!(scope.revert_to_last_snapshot()); // The snapshot can be reused
+        assert_eq!(*scope.get(), vec![1, 2]);
+
+        assert!(scope.pop_snapshot());
+        assert!(scope.revert_to_last_snapshot());
+        assert_eq!(*scope.get(), vec![1]);
+        assert!(!scope.is_modified()); // Back to the original
+        assert!(scope.pop_snapshot());
+        assert!(!scope.pop_snapshot());
+        assert!(!scope.revert_to_last_snapshot());
+    }
+
+    #[test]
+    fn test_revert_clears_snapshots() {
+        let data = 1;
+        let mut scope = ScopeCell::new(&data);
+        *scope.get_mut() = 2;
+        scope.push_snapshot();
+        scope.revert();
+        assert_eq!(scope.snapshot_count(), 0);
+        assert_eq!(*scope.get(), 1);
+    }
+
+    #[test]
+    fn test_update_and_map_into() {
+        let data = vec![3, 1, 2];
+        let mut scope = ScopeCell::new(&data);
+        scope.update(|v| v.sort());
+        let len = scope.update(|v| {
+            v.push(4);
+            v.len()
+        });
+        assert_eq!(len, 4);
+        let total: i32 = scope.map_into(|v| v.into_iter().sum());
+        assert_eq!(total, 10);
+        assert_eq!(data, vec![3, 1, 2]); // Original untouched
+    }
+
+    #[test]
+    fn test_set_and_replace() {
+        let data = String::from("a");
+        let mut scope = ScopeCell::new(&data);
+        assert_eq!(scope.replace(String::from("b")), "a"); // Clone of the untouched original
+        assert_eq!(scope.replace(String::from("c")), "b");
+        scope.set(String::from("d"));
+        assert_eq!(scope.get(), "d");
+        assert!(scope.is_modified());
+        assert_eq!(data, "a");
+    }
+
+    #[test]
+    fn test_set_through_guard() {
+        let data = 1;
+        let cell = ScopeCell::new(&data);
+        {
+            let mut guard = cell.borrow_mut();
+            guard.set(2);
+            assert_eq!(guard.replace(3), 2);
+        }
+        assert_eq!(*cell.borrow(), 3);
+    }
+
+    #[test]
+    fn test_try_update_ok_keeps_changes() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        let len = scope.try_update(|v| {
+            v.push(4);
+            Ok::<_, ()>(v.len())
+        });
+        assert_eq!(len, Ok(4));
+        assert_eq!(*scope.get(), vec![1, 2, 3, 4]);
+        assert!(scope.is_modified());
+    }
+
+    #[test]
+    fn test_try_update_err_rolls_back() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        scope.get_mut().push(4);
+        let result: Result<(), &str> = scope.try_update(|v| {
+            v.push(5);
+            Err("rejected")
+        });
+        assert_eq!(result, Err("rejected"));
+        assert_eq!(*scope.get(), vec![1, 2, 3, 4]); // Earlier edits survive
+    }
+
+    #[test]
+    fn test_try_update_err_on_clean_cell() {
+        let data = 10;
+        let mut scope = ScopeCell::new(&data);
+        let result: Result<(), ()> = scope.try_update(|v| {
+            *v = 20;
+            Err(())
+        });
+        assert!(result.is_err());
+        assert!(!scope.is_modified()); // No modified copy was kept
+        assert_eq!(*scope.get(), 10);
+    }
+
+    #[test]
+    fn test_try_update_clones_once() {
+        struct Counted(std::rc::Rc<Cell<usize>>);
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.0.set(self.0.get() + 1);
+                Counted(self.0.clone())
+            }
+        }
+
+        let clones = std::rc::Rc::new(Cell::new(0));
+        let data = Counted(clones.clone());
+        let mut scope = ScopeCell::new(&data);
+        scope.try_update(|_| Ok::<_, ()>(())).unwrap();
+        assert_eq!(clones.get(), 1); // Only the scratch copy, which is kept as is
+        assert!(scope.is_modified());
+    }
+
+    #[test]
+    fn test_project_field() {
+        let data = (vec![1, 2, 3], String::from("label"));
+        let scope = ScopeCell::new(&data);
+        {
+            let mut label = scope.project(|d| &d.1);
+            label.get_mut().push_str(" (draft)");
+            assert_eq!(label.get(), "label (draft)");
+        }
+        assert!(!scope.is_modified()); // Parent was never cloned
+        assert_eq!(scope.get().1, "label");
+    }
+
+    #[test]
+    fn test_lifecycle_hooks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let modified = AtomicUsize::new(0);
+        let reverted = AtomicUsize::new(0);
+        let committed = AtomicUsize::new(0);
+        let data = vec![1, 2, 3];
+        {
+            let mut scope = ScopeCell::new(&data);
+            scope
+                .on_modify(|v| {
+                    assert_eq!(*v, vec![1, 2, 3]); // Fresh copy of the original
+                    modified.fetch_add(1, Ordering::SeqCst);
+                })
+                .on_revert(|| {
+                    reverted.fetch_add(1, Ordering::SeqCst);
+                })
+                .on_commit(|v| {
+                    assert_eq!(v.len(), 4);
+                    committed.fetch_add(1, Ordering::SeqCst);
+                });
+            scope.get_mut().push(4);
+            scope.get_mut().push(5); // Copy already exists, no second on_modify
+            scope.revert();
+            scope.revert(); // Nothing left to discard
+            scope.borrow_mut().push(4);
+            scope.into_inner();
+        }
+        assert_eq!(modified.load(Ordering::SeqCst), 2);
+        assert_eq!(reverted.load(Ordering::SeqCst), 1);
+        assert_eq!(committed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_drop_fires_revert_hook() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let reverted = AtomicBool::new(false);
+        let data = 1;
+        {
+            let mut scope = ScopeCell::new(&data);
+            scope.on_revert(|| reverted.store(true, Ordering::SeqCst));
+            *scope.get_mut() = 2;
+        }
+        assert!(reverted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_diff() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        assert_eq!(scope.diff(), None);
+        scope.get_mut().push(4);
+        assert_eq!(scope.diff(), Some((&vec![1, 2, 3], &vec![1, 2, 3, 4])));
+        assert_eq!(
+            scope.changes(),
+            vec![ElementChange::Inserted { index: 3, value: 4 }]
+        );
+        scope.get_mut().pop();
+        assert_eq!(scope.diff(), None); // Modified but equal to the original
+        assert!(scope.changes().is_empty());
+    }
+
+    #[test]
+    fn test_apply_changes_to() {
+        let preview = vec![1, 2, 3];
+        let mut authoritative = vec![0];
+        let mut scope = ScopeCell::new(&preview);
+        assert!(!scope.apply_changes_to(&mut authoritative)); // Nothing to apply yet
+        assert_eq!(authoritative, vec![0]);
+        scope.get_mut().push(4);
+        assert!(scope.apply_changes_to(&mut authoritative));
+        assert_eq!(authoritative, vec![1, 2, 3, 4]);
+        assert_eq!(preview, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_scope_ok_returns_value() {
+        let data = vec![1, 2, 3];
+        let (value, len) = ScopeCell::scope(&data, |cell| {
+            cell.get_mut().push(4);
+            Ok::<_, ()>(cell.get().len())
+        })
+        .unwrap();
+        assert_eq!(value, vec![1, 2, 3, 4]); // Effective value is handed back
+        assert_eq!(len, 4);
+        assert_eq!(data, vec![1, 2, 3]); // Original data should not change
+    }
+
+    #[test]
+    fn test_scope_err_reverts() {
+        let data = vec![1, 2, 3];
+        let result = ScopeCell::scope(&data, |cell| {
+            cell.get_mut().push(4);
+            Err::<(), _>("rejected")
+        });
+        assert_eq!(result, Err("rejected"));
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+
     #[test]
     fn test_nested_scope_cell() {
         let data1 = vec![1, 2, 3];
@@ -0,0 +1,302 @@
+// Lifecycle events reported by the cells to whatever is listening: the installed
+// `CellMetrics`, the process-wide `MutationLog` when it is enabled, and with the **tracing**
+// feature a `tracing` event under the `scope_cell` target.
+
+use std::any::type_name;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::SystemTime;
+
+use crate::cell_metrics;
+
+/// What happened to a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MutationKind {
+    /// The first write cloned the baseline.
+    Copied,
+    /// A modified copy was discarded.
+    Reverted,
+    /// The effective value was written back or extracted.
+    Committed,
+    /// A commit found the baseline changed under it.
+    Conflicted,
+}
+
+/// One entry of the `MutationLog`.
+#[derive(Clone, Debug)]
+pub struct MutationEvent {
+    pub seq: u64,                    // Increases by one per recorded event
+    pub label: &'static str,         // The cell's label
+    pub type_name: &'static str,     // The cell's value type
+    pub kind: MutationKind,          // What happened
+    pub thread: ThreadId,            // The thread it happened on
+    pub thread_name: Option<String>, // That thread's name, if it has one
+    pub at: SystemTime,              // When it happened
+}
+
+// Events of labelled cells, oldest first, while the log is enabled
+struct Ring {
+    events: VecDeque<MutationEvent>,
+    capacity: usize,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(0);
+static RING: Mutex<Ring> = Mutex::new(Ring {
+    events: VecDeque::new(),
+    capacity: 0,
+});
+
+/// The MutationLog is an opt-in, process-wide ring buffer of the lifecycle events of
+/// labelled cells, the ones created with `named()` or given a `set_label()`, for auditing
+/// who changed what at runtime: each entry records the label, kind, thread and time.  It is
+/// off until `enable()` is called, and once full, the oldest entries make room for new ones.
+pub struct MutationLog;
+
+impl MutationLog {
+    // Nothing recorded here can panic midway, so poisoning can be ignored
+    fn lock() -> MutexGuard<'static, Ring> {
+        RING.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Start recording, keeping at most the `capacity` most recent events.  Recording that is
+    // already on keeps its entries, trimmed to the new capacity.  `ENABLED` is only changed
+    // under the lock, so `record` can trust it there.
+    pub fn enable(capacity: usize) {
+        let mut ring = Self::lock();
+        ring.capacity = capacity;
+        let excess = ring.events.len().saturating_sub(capacity);
+        ring.events.drain(..excess);
+        ENABLED.store(capacity > 0, Ordering::Release);
+    }
+
+    // Stop recording.  Entries recorded so far stay queryable.
+    pub fn disable() {
+        let _ring = Self::lock();
+        ENABLED.store(false, Ordering::Release);
+    }
+
+    // Whether events are being recorded
+    pub fn is_enabled() -> bool {
+        ENABLED.load(Ordering::Acquire)
+    }
+
+    // Every recorded event, oldest first
+    pub fn events() -> Vec<MutationEvent> {
+        Self::lock().events.iter().cloned().collect()
+    }
+
+    // The recorded events of the cells labelled `label`, oldest first
+    pub fn events_for(label: &str) -> Vec<MutationEvent> {
+        let ring = Self::lock();
+        ring.events
+            .iter()
+            .filter(|event| event.label == label)
+            .cloned()
+            .collect()
+    }
+
+    // Drop every recorded event
+    pub fn clear() {
+        Self::lock().events.clear();
+    }
+
+    fn record(kind: MutationKind, label: &'static str, type_name: &'static str) {
+        let current = thread::current();
+        let event = MutationEvent {
+            seq: NEXT_SEQ.fetch_add(1, Ordering::Relaxed),
+            label,
+            type_name,
+            kind,
+            thread: current.id(),
+            thread_name: current.name().map(str::to_owned),
+            at: SystemTime::now(),
+        };
+        let mut ring = Self::lock();
+        if !Self::is_enabled() {
+            return; // Disabled while this event was being built
+        }
+        if ring.events.len() == ring.capacity {
+            ring.events.pop_front();
+        }
+        ring.events.push_back(event);
+    }
+}
+
+pub(crate) fn emit<T>(kind: MutationKind, label: Option<&'static str>) {
+    let ty = type_name::<T>();
+    if let Some(metrics) = cell_metrics::installed() {
+        match kind {
+            MutationKind::Copied => metrics.copied(ty, label, size_of::<T>()),
+            MutationKind::Reverted => metrics.reverted(ty, label),
+            MutationKind::Committed => metrics.committed(ty, label),
+            MutationKind::Conflicted => metrics.conflicted(ty, label),
+        }
+    }
+    if let Some(label) = label {
+        if MutationLog::is_enabled() {
+            MutationLog::record(kind, label, ty);
+        }
+    }
+    #[cfg(feature = "tracing")]
+    trace(kind, label, ty);
+}
+
+// Conflicts are rare and worth seeing at a higher level than per-edit churn
+#[cfg(feature = "tracing")]
+fn trace(kind: MutationKind, label: Option<&'static str>, ty: &'static str) {
+    const TARGET: &str = "scope_cell";
+    match kind {
+        MutationKind::Copied => {
+            tracing::trace!(target: TARGET, cell = label, ty, "scoped copy created")
+        }
+        MutationKind::Reverted => {
+            tracing::trace!(target: TARGET, cell = label, ty, "scoped edit reverted")
+        }
+        MutationKind::Committed => {
+            tracing::trace!(target: TARGET, cell = label, ty, "scoped edit committed")
+        }
+        MutationKind::Conflicted => {
+            tracing::debug!(target: TARGET, cell = label, ty, "scoped commit conflicted")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ScopeCell, ScopeCellMut};
+
+    #[test]
+    fn test_mutation_log() {
+        // The log is process-wide, so only look at this test's labels
+        MutationLog::enable(1024);
+        let config = String::from("a");
+        let mut cell = ScopeCell::named(&config, "log_test_config");
+        cell.get_mut().push('b');
+        drop(cell);
+        let mut limits = 1;
+        let mut cell = ScopeCellMut::named(&mut limits, "log_test_limits");
+        *cell.get_mut() = 2;
+        cell.commit();
+        let unnamed = vec![1];
+        ScopeCell::new(&unnamed).get_mut().push(2); // Unlabelled cells are not recorded
+
+        let config_events = MutationLog::events_for("log_test_config");
+        let kinds: Vec<_> = config_events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, [MutationKind::Copied, MutationKind::Reverted]);
+        assert!(config_events[0].seq < config_events[1].seq);
+        assert_eq!(config_events[0].type_name, "alloc::string::String");
+        assert_eq!(config_events[0].thread, thread::current().id());
+        let limits_events = MutationLog::events_for("log_test_limits");
+        assert_eq!(limits_events.last().unwrap().kind, MutationKind::Committed);
+        drop(unnamed);
+
+        MutationLog::disable();
+        MutationLog::record(MutationKind::Copied, "log_test_disabled", "u32");
+        assert!(MutationLog::events_for("log_test_disabled").is_empty());
+        assert_eq!(MutationLog::events_for("log_test_config").len(), 2); // Still queryable
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tracing_tests {
+    use crate::{OptimisticCell, ScopeCell, ScopeCellMut};
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Records "cell: message" for every event it sees
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    #[derive(Default)]
+    struct Fields {
+        cell: String,
+        message: String,
+    }
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "cell" {
+                self.cell = value.to_owned();
+            }
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.message = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "scope_cell"
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let line = format!("{}: {}", fields.cell, fields.message);
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_lifecycle_events_traced() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let config = vec![1];
+            let mut cell = ScopeCell::new(&config);
+            cell.set_label("config");
+            cell.get_mut().push(2);
+            cell.revert();
+            cell.get_mut().push(3);
+            cell.into_inner();
+
+            let mut limits = 5;
+            let mut cell = ScopeCellMut::new(&mut limits);
+            *cell.set_label("limits").get_mut() += 1;
+            cell.commit();
+
+            let mut shared = OptimisticCell::new(0);
+            shared.set_label("counter");
+            let mut stale = shared.edit();
+            *stale += 1;
+            shared.set(7);
+            assert!(stale.commit().is_err());
+        });
+        let lines = recorder.0.lock().unwrap().clone();
+        assert_eq!(
+            lines,
+            vec![
+                "config: scoped copy created",
+                "config: scoped edit reverted",
+                "config: scoped copy created",
+                "config: scoped edit committed",
+                "limits: scoped copy created",
+                "limits: scoped edit committed",
+                "counter: scoped copy created",
+                "counter: scoped commit conflicted",
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+/// Run a block against scoped cells created from a list of bindings, so the scope boundary
+/// is the block itself.
+///
+/// `scope!(cfg = &config, buf = &buffer => { ... })` binds each name to a mutable
+/// `ScopeCell` inside the block; every change is reverted when the block ends.
+/// `scope!(commit cfg = &mut config => { ... })` binds `ScopeCellMut`s instead and commits
+/// all of them once the block finishes normally.  A block left early by `return`, `?` or a
+/// panic reverts in both forms.  The macro evaluates to the value of the block.
+#[macro_export]
+macro_rules! scope {
+    (commit $($name:ident = $src:expr),+ $(,)? => $body:block) => {{
+        $( #[allow(unused_mut)] let mut $name = $crate::ScopeCellMut::new($src); )+
+        let result = $body;
+        $( $name.commit(); )+
+        result
+    }};
+    ($($name:ident = $src:expr),+ $(,)? => $body:block) => {{
+        $( #[allow(unused_mut)] let mut $name = $crate::ScopeCell::new($src); )+
+        $body
+    }};
+}
+
+/// Run the given statements when the enclosing block ends, however it ends:
+/// `defer!(println!("done"));`.  Several `defer!`s run in reverse order.
+#[macro_export]
+macro_rules! defer {
+    ($($body:tt)*) => {
+        let _scope_defer = $crate::ScopeDefer::new(|| {
+            $($body)*;
+        });
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_revert_form() {
+        let config = vec![1, 2];
+        let buffer = String::from("abc");
+        let len = scope!(cfg = &config, buf = &buffer => {
+            cfg.get_mut().push(3);
+            buf.get_mut().clear();
+            assert!(buf.get().is_empty());
+            cfg.get().len()
+        });
+        assert_eq!(len, 3);
+        assert_eq!(config, vec![1, 2]); // Reverted at the end of the block
+        assert_eq!(buffer, "abc");
+    }
+
+    #[test]
+    fn test_commit_form() {
+        let mut config = vec![1, 2];
+        let mut retries = 3;
+        scope!(commit cfg = &mut config, retries = &mut retries => {
+            cfg.get_mut().push(3);
+            *retries.get_mut() += 1;
+        });
+        assert_eq!(config, vec![1, 2, 3]);
+        assert_eq!(retries, 4);
+    }
+
+    #[test]
+    fn test_early_exit_reverts_commit_form() {
+        fn bump(value: &mut u32, fail: bool) -> Result<(), &'static str> {
+            scope!(commit v = value => {
+                *v.get_mut() += 1;
+                if fail {
+                    return Err("failed");
+                }
+            });
+            Ok(())
+        }
+
+        let mut value = 1;
+        assert!(bump(&mut value, true).is_err());
+        assert_eq!(value, 1);
+        assert!(bump(&mut value, false).is_ok());
+        assert_eq!(value, 2);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::diff::{edit_script, Step};
+
+/// Types that can combine two independent edits of a common base, as needed when an
+/// optimistic commit finds that someone else committed first.  `merge` returns `None` when
+/// the edits conflict.  `Vec<T>` merges element by element and `String` line by line, the
+/// way `diff3` does: a region changed on only one side takes that side's version, and a
+/// region changed differently on both sides is a conflict.
+pub trait Merge: Sized {
+    // Combine `theirs` and `mine`, both edited from `base`
+    fn merge(base: &Self, theirs: &Self, mine: &Self) -> Option<Self>;
+}
+
+// For each element of `base`, its index in `side` if the edit script keeps it
+fn kept<T: PartialEq>(base: &[T], side: &[T]) -> Vec<Option<usize>> {
+    // The edit script leaves out the common prefix and suffix, which are kept in place
+    let prefix = base.iter().zip(side).take_while(|(a, b)| a == b).count();
+    let mut kept: Vec<Option<usize>> = (0..prefix).map(Some).collect();
+    let (mut i, mut j) = (prefix, prefix);
+    for step in edit_script(base, side) {
+        match step {
+            Step::Keep => {
+                kept.push(Some(j));
+                i += 1;
+                j += 1;
+            }
+            Step::Remove(_) => {
+                kept.push(None);
+                i += 1;
+            }
+            Step::Insert(_) => j += 1,
+        }
+    }
+    kept.extend((i..base.len()).map(|k| Some(j + k - i)));
+    kept
+}
+
+// Three-way merge of two sequences.  Elements of `base` kept by both sides split the
+// sequences into chunks, and each chunk is resolved on its own.
+fn merge_slices<T: Clone + PartialEq>(base: &[T], theirs: &[T], mine: &[T]) -> Option<Vec<T>> {
+    let in_theirs = kept(base, theirs);
+    let in_mine = kept(base, mine);
+    let mut merged = Vec::new();
+    let (mut b, mut t, mut m) = (0, 0, 0); // Start of the current chunk in each sequence
+    for i in 0..=base.len() {
+        let (ti, mi) = if i == base.len() {
+            (theirs.len(), mine.len())
+        } else {
+            match (in_theirs[i], in_mine[i]) {
+                (Some(ti), Some(mi)) => (ti, mi),
+                _ => continue,
+            }
+        };
+        let (old, ours, yours) = (&base[b..i], &theirs[t..ti], &mine[m..mi]);
+        let chunk = if ours == old || ours == yours {
+            yours
+        } else if yours == old {
+            ours
+        } else {
+            return None;
+        };
+        merged.extend_from_slice(chunk);
+        if i < base.len() {
+            merged.push(base[i].clone());
+            (b, t, m) = (i + 1, ti + 1, mi + 1);
+        }
+    }
+    Some(merged)
+}
+
+impl<T: Clone + PartialEq> Merge for Vec<T> {
+    fn merge(base: &Self, theirs: &Self, mine: &Self) -> Option<Self> {
+        merge_slices(base, theirs, mine)
+    }
+}
+
+impl Merge for String {
+    fn merge(base: &Self, theirs: &Self, mine: &Self) -> Option<Self> {
+        let lines = |s: &Self| {
+            s.split_inclusive('\n')
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        };
+        let merged = merge_slices(&lines(base), &lines(theirs), &lines(mine))?;
+        Some(merged.concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vec_merges_disjoint_edits() {
+        let base = vec![1, 2, 3, 4, 5];
+        let theirs = vec![0, 1, 2, 3, 4, 5]; // Prepended
+        let mine = vec![1, 2, 4, 5, 6]; // Removed 3, appended 6
+        assert_eq!(
+            Vec::merge(&base, &theirs, &mine),
+            Some(vec![0, 1, 2, 4, 5, 6])
+        );
+        assert_eq!(Vec::merge(&base, &mine, &mine), Some(mine.clone())); // Same edit twice
+    }
+
+    #[test]
+    fn test_vec_conflict() {
+        let base = vec![1, 2, 3];
+        assert_eq!(Vec::merge(&base, &vec![1, 9, 3], &vec![1, 8, 3]), None);
+        assert_eq!(
+            Vec::merge(&base, &vec![1, 2, 3, 4], &vec![1, 2, 3, 5]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_string_merges_by_line() {
+        let base = String::from("host = a\nport = 1\nuser = x\n");
+        let theirs = String::from("host = b\nport = 1\nuser = x\n");
+        let mine = String::from("host = a\nport = 1\nuser = y\ndebug = true\n");
+        assert_eq!(
+            String::merge(&base, &theirs, &mine).unwrap(),
+            "host = b\nport = 1\nuser = y\ndebug = true\n"
+        );
+        let clash = String::from("host = c\nport = 1\nuser = x\n");
+        assert_eq!(String::merge(&base, &theirs, &clash), None);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+
+use crate::{CowScopeCell, HistoryCell, OwnedScopeCell};
+
+/// One step of a stateful property test against a cell.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CellOp<T> {
+    /// Read the effective value.
+    Get,
+    /// Overwrite the effective value through mutable access.
+    Write(T),
+    /// Discard the pending edit.
+    Revert,
+    /// Adopt the pending edit as the baseline.
+    Commit,
+}
+
+/// The cells `check_ops` can drive, seen through the four operations of `CellOp`.
+pub trait ModelCell<T> {
+    fn model_get(&self) -> &T;
+
+    fn model_baseline(&self) -> &T;
+
+    fn model_write(&mut self, value: T);
+
+    fn model_revert(&mut self);
+
+    fn model_commit(&mut self);
+}
+
+/// The reference implementation the cells are checked against: a committed value and an
+/// optional pending one, with nothing lazy about either.
+#[derive(Clone, Debug)]
+pub struct CellModel<T> {
+    committed: T,
+    pending: Option<T>,
+}
+
+impl<T: Clone> CellModel<T> {
+    // Create a model whose committed value is `value`
+    pub fn new(value: T) -> Self {
+        CellModel {
+            committed: value,
+            pending: None,
+        }
+    }
+
+    // Create a model with a pending edit of `committed`
+    pub fn with_pending(committed: T, pending: T) -> Self {
+        CellModel {
+            committed,
+            pending: Some(pending),
+        }
+    }
+
+    // Apply one operation, returning the effective value afterwards
+    pub fn apply(&mut self, op: &CellOp<T>) -> &T {
+        match op {
+            CellOp::Get => {}
+            CellOp::Write(value) => self.pending = Some(value.clone()),
+            CellOp::Revert => self.pending = None,
+            CellOp::Commit => {
+                if let Some(pending) = self.pending.take() {
+                    self.committed = pending;
+                }
+            }
+        }
+        self.pending.as_ref().unwrap_or(&self.committed)
+    }
+}
+
+// Run `ops` against `cell` and a CellModel started from the cell's baseline and pending edit,
+// panicking at the first step where the two disagree.  After the last step, a revert
+// checks that both agree on the committed value too.
+pub fn check_ops<T, C>(cell: &mut C, ops: &[CellOp<T>])
+where
+    T: Clone + PartialEq + fmt::Debug,
+    C: ModelCell<T>,
+{
+    let (baseline, current) = (cell.model_baseline(), cell.model_get());
+    let mut model = if current == baseline {
+        CellModel::new(baseline.clone())
+    } else {
+        CellModel::with_pending(baseline.clone(), current.clone())
+    };
+    for (step, op) in ops.iter().chain([&CellOp::Revert]).enumerate() {
+        match op {
+            CellOp::Get => {}
+            CellOp::Write(value) => cell.model_write(value.clone()),
+            CellOp::Revert => cell.model_revert(),
+            CellOp::Commit => cell.model_commit(),
+        }
+        let expected = model.apply(op);
+        assert_eq!(cell.model_get(), expected, "step {} ({:?})", step, op);
+    }
+}
+
+impl<T: Clone> ModelCell<T> for OwnedScopeCell<T> {
+    fn model_get(&self) -> &T {
+        self.get()
+    }
+
+    fn model_baseline(&self) -> &T {
+        self.baseline()
+    }
+
+    fn model_write(&mut self, value: T) {
+        *self.get_mut() = value;
+    }
+
+    fn model_revert(&mut self) {
+        self.reset();
+    }
+
+    fn model_commit(&mut self) {
+        self.rebase();
+    }
+}
+
+impl<T: Clone> ModelCell<T> for CowScopeCell<T> {
+    fn model_get(&self) -> &T {
+        self.get()
+    }
+
+    fn model_baseline(&self) -> &T {
+        self.original()
+    }
+
+    fn model_write(&mut self, value: T) {
+        *self.get_mut() = value;
+    }
+
+    fn model_revert(&mut self) {
+        self.revert();
+    }
+
+    fn model_commit(&mut self) {
+        *self = CowScopeCell::new(self.share());
+    }
+}
+
+impl<T: Clone> ModelCell<T> for HistoryCell<T> {
+    fn model_get(&self) -> &T {
+        self.get()
+    }
+
+    fn model_baseline(&self) -> &T {
+        self.committed()
+    }
+
+    fn model_write(&mut self, value: T) {
+        *self.get_mut() = value;
+    }
+
+    fn model_revert(&mut self) {
+        self.revert();
+    }
+
+    fn model_commit(&mut self) {
+        self.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cells_match_model() {
+        let ops = [
+            CellOp::Write(vec![1]),
+            CellOp::Get,
+            CellOp::Commit,
+            CellOp::Write(vec![2]),
+            CellOp::Revert,
+            CellOp::Commit,
+            CellOp::Write(vec![3]),
+        ];
+        check_ops(&mut OwnedScopeCell::new(Vec::new()), &ops);
+        check_ops(&mut CowScopeCell::new(Arc::new(Vec::new())), &ops);
+        check_ops(&mut HistoryCell::new(Vec::new(), 4), &ops);
+    }
+
+    // A cell that forgets to revert
+    struct Sticky(u8);
+
+    impl ModelCell<u8> for Sticky {
+        fn model_get(&self) -> &u8 {
+            &self.0
+        }
+
+        fn model_baseline(&self) -> &u8 {
+            &self.0
+        }
+
+        fn model_write(&mut self, value: u8) {
+            self.0 = value;
+        }
+
+        fn model_revert(&mut self) {}
+
+        fn model_commit(&mut self) {}
+    }
+
+    #[test]
+    #[should_panic(expected = "step 1 (Revert)")]
+    fn test_divergence_is_reported() {
+        check_ops(&mut Sticky(0), &[CellOp::Write(1)]);
+    }
+}
@@ -0,0 +1,96 @@
+// Lifecycle callbacks shared by the cells that support `on_modify`, `on_revert`,
+// `on_commit` and `on_drop`.  Nothing is allocated until the first callback is registered.
+// Every event is also reported through `lifecycle::emit`, tagged with the cell's label.
+
+use crate::lifecycle::{self, MutationKind};
+
+type ValueHook<'a, T> = Box<dyn Fn(&T) + Send + 'a>;
+type EventHook<'a> = Box<dyn Fn() + Send + 'a>;
+type FinalHook<'a, T> = Box<dyn FnOnce(&T) + Send + 'a>;
+
+struct HookLists<'a, T> {
+    on_modify: Vec<ValueHook<'a, T>>,
+    on_revert: Vec<EventHook<'a>>,
+    on_commit: Vec<ValueHook<'a, T>>,
+    on_drop: Vec<FinalHook<'a, T>>,
+}
+
+pub(crate) struct Hooks<'a, T> {
+    lists: Option<Box<HookLists<'a, T>>>,
+    label: Option<&'static str>,
+}
+
+impl<'a, T> Hooks<'a, T> {
+    pub(crate) fn new() -> Self {
+        Hooks {
+            lists: None,
+            label: None,
+        }
+    }
+
+    pub(crate) fn set_label(&mut self, label: &'static str) {
+        self.label = Some(label);
+    }
+
+    pub(crate) fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    fn lists(&mut self) -> &mut HookLists<'a, T> {
+        self.lists.get_or_insert_with(|| {
+            Box::new(HookLists {
+                on_modify: Vec::new(),
+                on_revert: Vec::new(),
+                on_commit: Vec::new(),
+                on_drop: Vec::new(),
+            })
+        })
+    }
+
+    pub(crate) fn add_modify(&mut self, hook: ValueHook<'a, T>) {
+        self.lists().on_modify.push(hook);
+    }
+
+    pub(crate) fn add_revert(&mut self, hook: EventHook<'a>) {
+        self.lists().on_revert.push(hook);
+    }
+
+    pub(crate) fn add_commit(&mut self, hook: ValueHook<'a, T>) {
+        self.lists().on_commit.push(hook);
+    }
+
+    pub(crate) fn add_drop(&mut self, hook: FinalHook<'a, T>) {
+        self.lists().on_drop.push(hook);
+    }
+
+    // The modified copy was just created
+    pub(crate) fn modified(&self, value: &T) {
+        lifecycle::emit::<T>(MutationKind::Copied, self.label);
+        if let Some(lists) = &self.lists {
+            lists.on_modify.iter().for_each(|hook| hook(value));
+        }
+    }
+
+    // A modified copy was discarded
+    pub(crate) fn reverted(&self) {
+        lifecycle::emit::<T>(MutationKind::Reverted, self.label);
+        if let Some(lists) = &self.lists {
+            lists.on_revert.iter().for_each(|hook| hook());
+        }
+    }
+
+    // The effective value was committed or extracted
+    pub(crate) fn committed(&self, value: &T) {
+        lifecycle::emit::<T>(MutationKind::Committed, self.label);
+        if let Some(lists) = &self.lists {
+            lists.on_commit.iter().for_each(|hook| hook(value));
+        }
+    }
+
+    // The cell is being dropped, leaving `value` in place
+    pub(crate) fn dropped(&mut self, value: &T) {
+        if let Some(lists) = &mut self.lists {
+            lists.on_drop.drain(..).for_each(|hook| hook(value));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, PoisonError};
+
+use crate::lifecycle::{self, MutationKind};
+use crate::primitives::{RwLock, RwLockReadGuard};
+use crate::{Merge, ScopeError};
+
+/// An OptimisticCell is a shared value edited under optimistic concurrency control.  Each
+/// edit records the version it started from and works on a private copy without holding a
+/// lock; `commit()` publishes the copy only if nobody committed in the meantime, and fails
+/// with `ScopeError::Conflict` otherwise.  `commit_with()` resolves conflicts with a
+/// three-way merge instead.
+pub struct OptimisticCell<T: Clone> {
+    current: RwLock<(u64, Arc<T>)>, // Latest version number and value
+    label: Option<&'static str>,    // Names the cell in lifecycle events
+}
+
+impl<T: Clone> OptimisticCell<T> {
+    // Create a new OptimisticCell with `value` as version 0
+    pub fn new(value: T) -> Self {
+        OptimisticCell {
+            current: RwLock::new((0, Arc::new(value))),
+            label: None,
+        }
+    }
+
+    // Create a new OptimisticCell labelled for the lifecycle events its edits report
+    pub fn named(value: T, label: &'static str) -> Self {
+        let mut cell = OptimisticCell::new(value);
+        cell.set_label(label);
+        cell
+    }
+
+    // Name the cell in the lifecycle events its edits report, such as `tracing` events
+    pub fn set_label(&mut self, label: &'static str) -> &mut Self {
+        self.label = Some(label);
+        self
+    }
+
+    // The cell's label, if it has one
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    // Number of the latest committed version
+    pub fn version(&self) -> u64 {
+        self.lock().0
+    }
+
+    // The latest committed value
+    pub fn read(&self) -> Arc<T> {
+        Arc::clone(&self.lock().1)
+    }
+
+    // Start an edit of the latest version.  The value is only cloned on the first mutable
+    // access.
+    pub fn edit(&self) -> OptimisticEdit<'_, T> {
+        let (version, base) = {
+            let current = self.lock();
+            (current.0, Arc::clone(&current.1))
+        };
+        OptimisticEdit {
+            cell: self,
+            version,
+            base,
+            modified_data: None,
+        }
+    }
+
+    // Run `f` against an edit and commit it, starting over from the latest version for as
+    // long as the commit conflicts.  Returns the committed version and the closure's result.
+    pub fn update<R, F: FnMut(&mut T) -> R>(&self, mut f: F) -> (u64, R) {
+        loop {
+            let mut edit = self.edit();
+            let result = f(&mut edit);
+            if let Ok(version) = edit.commit() {
+                return (version, result);
+            }
+        }
+    }
+
+    // Publish a value unconditionally, returning its version
+    pub fn set(&self, value: T) -> u64 {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        *current = (current.0 + 1, Arc::new(value));
+        current.0
+    }
+
+    // Consume the cell and return the latest value
+    pub fn into_inner(self) -> Arc<T> {
+        self.current
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .1
+    }
+
+    // Values are swapped in whole, so a panic cannot leave one half-written
+    fn lock(&self) -> RwLockReadGuard<'_, (u64, Arc<T>)> {
+        self.current.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// An edit of an OptimisticCell.  Dropping it without committing discards it.
+pub struct OptimisticEdit<'c, T: Clone> {
+    cell: &'c OptimisticCell<T>,
+    version: u64,             // Version the edit started from
+    base: Arc<T>,             // Value of that version
+    modified_data: Option<T>, // Holds the private copy
+}
+
+impl<'c, T: Clone> OptimisticEdit<'c, T> {
+    // The version the edit started from
+    pub fn base_version(&self) -> u64 {
+        self.version
+    }
+
+    // The value the edit started from
+    pub fn base(&self) -> &T {
+        &self.base
+    }
+
+    // Whether the edit has been mutably accessed
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Whether another commit has happened since the edit started
+    pub fn is_stale(&self) -> bool {
+        self.cell.version() != self.version
+    }
+
+    // Publish the edit as a new version, or fail with `ScopeError::Conflict` if the cell
+    // changed since the edit started.  An untouched edit publishes nothing and returns the
+    // latest version.
+    pub fn commit(self) -> Result<u64, ScopeError> {
+        self.commit_with(|_, _, _| None)
+    }
+
+    // Publish the edit like `commit()`, but on a conflict call `merge(base, theirs, mine)`
+    // with the value the edit started from, the latest committed value and the edited value.
+    // A merged value is published in place of the edit; `None` reports the conflict.
+    pub fn commit_with<M>(self, merge: M) -> Result<u64, ScopeError>
+    where
+        M: FnOnce(&T, &T, &T) -> Option<T>,
+    {
+        let Some(mine) = self.modified_data else {
+            return Ok(self.cell.version());
+        };
+        let mut current = self
+            .cell
+            .current
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let value = if current.0 == self.version {
+            mine
+        } else {
+            lifecycle::emit::<T>(MutationKind::Conflicted, self.cell.label);
+            merge(&self.base, &current.1, &mine).ok_or(ScopeError::Conflict)?
+        };
+        *current = (current.0 + 1, Arc::new(value));
+        lifecycle::emit::<T>(MutationKind::Committed, self.cell.label);
+        Ok(current.0)
+    }
+
+    // Publish the edit, resolving a conflict with the type's own `Merge` strategy
+    pub fn commit_merge(self) -> Result<u64, ScopeError>
+    where
+        T: Merge,
+    {
+        self.commit_with(T::merge)
+    }
+}
+
+impl<'c, T: Clone> Deref for OptimisticEdit<'c, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.modified_data.as_ref().unwrap_or(&self.base)
+    }
+}
+
+impl<'c, T: Clone> DerefMut for OptimisticEdit<'c, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let (base, label) = (&self.base, self.cell.label);
+        self.modified_data.get_or_insert_with(|| {
+            lifecycle::emit::<T>(MutationKind::Copied, label);
+            T::clone(base)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_stale_commit_conflicts() {
+        let cell = OptimisticCell::new(vec![1, 2]);
+        let mut first = cell.edit();
+        let mut second = cell.edit();
+        first.push(3);
+        second.push(4);
+        assert_eq!(first.commit(), Ok(1));
+        assert!(second.is_stale());
+        assert_eq!(second.commit(), Err(ScopeError::Conflict));
+        assert_eq!(*cell.read(), vec![1, 2, 3]); // The losing edit left no trace
+    }
+
+    #[test]
+    fn test_commit_with_three_way_merge() {
+        let cell = OptimisticCell::new((0, 0));
+        let mut left = cell.edit();
+        let mut right = cell.edit();
+        left.0 = 5;
+        right.1 = 7;
+        left.commit().unwrap();
+        // Take each side's changed field relative to the base
+        let version = right
+            .commit_with(|base, theirs, mine| {
+                let pick = |b: i32, t: i32, m: i32| if m != b { m } else { t };
+                Some((
+                    pick(base.0, theirs.0, mine.0),
+                    pick(base.1, theirs.1, mine.1),
+                ))
+            })
+            .unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(*cell.read(), (5, 7));
+    }
+
+    #[test]
+    fn test_commit_merge_uses_merge_trait() {
+        let cell = OptimisticCell::new(String::from("a\nb\nc\n"));
+        let mut first = cell.edit();
+        let mut second = cell.edit();
+        first.insert_str(0, "header\n");
+        second.push_str("footer\n");
+        first.commit().unwrap();
+        assert_eq!(second.commit_merge(), Ok(2));
+        assert_eq!(*cell.read(), "header\na\nb\nc\nfooter\n");
+    }
+
+    #[test]
+    fn test_untouched_edit_and_set() {
+        let cell = OptimisticCell::new(String::from("a"));
+        let edit = cell.edit();
+        assert_eq!(edit.len(), 1);
+        cell.set(String::from("b"));
+        assert_eq!(edit.commit(), Ok(1)); // Nothing to publish, so nothing conflicts
+        assert_eq!(*cell.into_inner(), "b");
+    }
+
+    #[test]
+    fn test_update_retries_until_committed() {
+        let cell = OptimisticCell::new(0u64);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..50 {
+                        cell.update(|n| *n += 1);
+                    }
+                });
+            }
+        });
+        assert_eq!(*cell.read(), 200); // No lost updates
+        assert_eq!(cell.version(), 200);
+    }
+}
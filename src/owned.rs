@@ -0,0 +1,369 @@
+use std::borrow::{Borrow, BorrowMut};
+use std::fmt;
+use std::mem;
+
+use crate::alloc::{Global, ScratchAlloc};
+use crate::{Delta, Diff, Patch, ScopeError};
+
+/// An OwnedScopeCell owns its baseline value, so it carries no borrow lifetime and can be
+/// stored inside long-lived structs.  Mutations go to a temporary copy which can be
+/// discarded with `reset()` or adopted as the new baseline with `rebase()`.  The copy is
+/// kept inline, or boxed in `A` when the cell is created with `new_in()`.
+#[derive(Clone)]
+pub struct OwnedScopeCell<T: Clone, A: ScratchAlloc = Global> {
+    original_data: T,
+    modified_data: Option<A::Box<T>>, // Holds temporary modified data
+    alloc: A,                         // Where the modified copy is allocated
+    dirty: bool,     // Set on mutable access, cleared by reset/rebase/reset_tracking
+    generation: u64, // Bumped on every mutation, reset or rebase
+    version: u64,    // Bumped when changes are adopted, naming the baseline in deltas
+}
+
+impl<T: Clone> OwnedScopeCell<T> {
+    // Create a new OwnedScopeCell taking ownership of the baseline value
+    pub fn new(data: T) -> Self {
+        OwnedScopeCell::with_version(data, 0)
+    }
+
+    // Create a new OwnedScopeCell whose baseline is the given version, as when joining a
+    // delta sync from a snapshot
+    pub fn with_version(data: T, version: u64) -> Self {
+        let mut cell = OwnedScopeCell::new_in(data, Global);
+        cell.version = version;
+        cell
+    }
+}
+
+impl<T: Clone, A: ScratchAlloc> OwnedScopeCell<T, A> {
+    // Create a new OwnedScopeCell whose modified copy is allocated in `alloc`
+    pub fn new_in(data: T, alloc: A) -> Self {
+        OwnedScopeCell {
+            original_data: data,
+            modified_data: None,
+            alloc,
+            dirty: false,
+            generation: 0,
+            version: 0,
+        }
+    }
+
+    // The allocator the modified copy is allocated in
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    // Borrow the stored baseline, ignoring any modification
+    pub fn baseline(&self) -> &T {
+        &self.original_data
+    }
+
+    // Borrow the data, showing either the baseline or the modified version
+    pub fn get(&self) -> &T {
+        match &self.modified_data {
+            Some(modified) => modified.borrow(),
+            None => &self.original_data,
+        }
+    }
+
+    // Mutably borrow the data, creating a temporary mutable copy if necessary
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        self.generation += 1;
+        let (original, alloc) = (&self.original_data, &self.alloc);
+        self.modified_data
+            .get_or_insert_with(|| alloc.boxed(original.clone()))
+            .borrow_mut()
+    }
+
+    // Overwrite the effective value without cloning the baseline first
+    pub fn set(&mut self, value: T) {
+        self.dirty = true;
+        self.generation += 1;
+        self.modified_data = Some(self.alloc.boxed(value));
+    }
+
+    // The modified copy, if there is one
+    fn modified(&self) -> Option<&T> {
+        self.modified_data.as_ref().map(Borrow::borrow)
+    }
+
+    // Run a fallible update against a copy of the effective value, keeping it on `Ok`
+    // and leaving the cell untouched on `Err`
+    pub fn try_update<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut scratch = self.get().clone();
+        let result = f(&mut scratch)?;
+        self.set(scratch);
+        Ok(result)
+    }
+
+    // The original and modified values, if a modified copy exists and differs from the original
+    pub fn diff(&self) -> Option<(&T, &T)>
+    where
+        T: PartialEq,
+    {
+        let modified = self.modified()?;
+        if modified == &self.original_data {
+            None
+        } else {
+            Some((&self.original_data, modified))
+        }
+    }
+
+    // Structured description of how the effective value differs from the original
+    pub fn changes(&self) -> Vec<T::Change>
+    where
+        T: Diff,
+    {
+        match self.modified() {
+            Some(modified) => modified.diff_from(&self.original_data),
+            None => Vec::new(),
+        }
+    }
+
+    // Overwrite `other` with the modified copy, if there is one.  Returns whether anything
+    // was written.
+    pub fn apply_changes_to(&self, other: &mut T) -> bool {
+        match self.modified() {
+            Some(modified) => {
+                other.clone_from(modified);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Replay the structured changes onto `other`, leaving parts of it the edit never touched
+    // alone.  Returns whether there was anything to apply.
+    pub fn apply_diff_to(&self, other: &mut T) -> bool
+    where
+        T: Patch,
+    {
+        let changes = self.changes();
+        let changed = !changes.is_empty();
+        other.apply_changes(changes);
+        changed
+    }
+
+    // Discard the modified data, going back to the stored baseline
+    pub fn reset(&mut self) {
+        self.modified_data = None;
+        self.dirty = false;
+        self.generation += 1;
+    }
+
+    // Adopt the current value as the new baseline, moving to the next version if there was a
+    // modified copy to adopt
+    pub fn rebase(&mut self) {
+        // Swapping rather than unboxing, which is unstable on std's allocator-aware `Box`
+        if let Some(mut modified) = self.modified_data.take() {
+            mem::swap(&mut self.original_data, modified.borrow_mut());
+            self.version += 1;
+        }
+        self.dirty = false;
+        self.generation += 1;
+    }
+
+    // Number of baselines adopted with changes since the version the cell was created with
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    // The structured changes against the baseline, tagged with the baseline's version, to
+    // send to a replica instead of the full value
+    pub fn export_delta(&self) -> Delta<T::Change>
+    where
+        T: Diff,
+    {
+        Delta {
+            base_version: self.version,
+            changes: self.changes(),
+        }
+    }
+
+    // Export the pending changes like `export_delta()` and adopt them as the new baseline,
+    // moving to the next version only if there were any, as `import_delta()` does on the
+    // replica.  A modified copy equal to the baseline is discarded.
+    pub fn commit_delta(&mut self) -> Delta<T::Change>
+    where
+        T: Diff,
+    {
+        let delta = self.export_delta();
+        if delta.changes.is_empty() {
+            self.reset();
+        } else {
+            self.rebase();
+        }
+        delta
+    }
+
+    // Apply a replica's delta to the baseline, moving to the next version if it carries any
+    // changes.  Fails with `ScopeError::Conflict` if it was made against a different baseline
+    // version, or if this cell has local edits the delta knows nothing about.
+    pub fn import_delta(&mut self, delta: Delta<T::Change>) -> Result<(), ScopeError>
+    where
+        T: Patch,
+    {
+        if delta.base_version != self.version || self.modified_data.is_some() {
+            return Err(ScopeError::Conflict);
+        }
+        if !delta.changes.is_empty() {
+            self.original_data.apply_changes(delta.changes);
+            self.generation += 1;
+            self.version += 1;
+        }
+        Ok(())
+    }
+
+    // Whether the data has been mutably accessed since creation, the last reset/rebase, or the last reset_tracking
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Clear the dirty flag without discarding the modified data
+    pub fn reset_tracking(&mut self) {
+        self.dirty = false;
+    }
+
+    // Counter bumped on every mutable access, reset and rebase.  Compare it with an
+    // earlier value to detect staleness without comparing the data itself.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Whether the cell may have changed since `generation` was read
+    pub fn changed_since(&self, generation: u64) -> bool {
+        self.generation != generation
+    }
+
+    // Consume the cell and return the modified data if it exists, otherwise the baseline
+    pub fn into_inner(self) -> T {
+        let mut value = self.original_data;
+        if let Some(mut modified) = self.modified_data {
+            mem::swap(&mut value, modified.borrow_mut());
+        }
+        value
+    }
+}
+
+// Shows the baseline, the pending copy (if any) and the dirty flag
+impl<T: Clone + fmt::Debug, A: ScratchAlloc> fmt::Debug for OwnedScopeCell<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedScopeCell")
+            .field("original", &self.original_data)
+            .field("pending", &self.modified())
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_restores_baseline() {
+        let mut cell = OwnedScopeCell::new(vec![1, 2, 3]);
+        cell.get_mut().push(4);
+        assert_eq!(*cell.get(), vec![1, 2, 3, 4]);
+        cell.reset();
+        assert_eq!(*cell.get(), vec![1, 2, 3]); // Back to the stored baseline
+        assert!(!cell.is_modified());
+    }
+
+    #[test]
+    fn test_set_skips_the_copy() {
+        let mut cell = OwnedScopeCell::new(vec![1, 2, 3]);
+        cell.set(vec![4]);
+        assert!(cell.is_modified());
+        assert_eq!((cell.baseline(), cell.get()), (&vec![1, 2, 3], &vec![4]));
+    }
+
+    #[test]
+    fn test_rebase_adopts_current_value() {
+        let mut cell = OwnedScopeCell::new(10);
+        *cell.get_mut() = 20;
+        cell.rebase();
+        assert_eq!(*cell.baseline(), 20); // New baseline
+        *cell.get_mut() = 30;
+        cell.reset();
+        assert_eq!(*cell.get(), 20); // Resets go to the rebased value
+    }
+
+    #[test]
+    fn test_generation_for_cache_invalidation() {
+        let mut cell = OwnedScopeCell::new(vec![1, 2]);
+        let cached_at = cell.generation();
+        let cached_len = cell.get().len();
+        cell.get_mut().push(3);
+        assert!(cell.changed_since(cached_at)); // The cached length is stale
+        assert_ne!(cell.get().len(), cached_len);
+        cell.rebase();
+        let rebased_at = cell.generation();
+        assert!(!cell.changed_since(rebased_at));
+    }
+
+    #[test]
+    fn test_debug_output() {
+        let mut cell = OwnedScopeCell::new("a");
+        assert_eq!(
+            format!("{:?}", cell),
+            r#"OwnedScopeCell { original: "a", pending: None, dirty: false }"#
+        );
+        *cell.get_mut() = "b";
+        assert!(format!("{:?}", cell).contains(r#"pending: Some("b")"#));
+    }
+
+    #[test]
+    fn test_try_update() {
+        let mut cell = OwnedScopeCell::new(1);
+        let result: Result<(), &str> = cell.try_update(|v| {
+            *v = 2;
+            Err("rejected")
+        });
+        assert!(result.is_err());
+        assert!(!cell.is_modified());
+        let result = cell.try_update(|v| {
+            *v += 2;
+            Ok::<_, ()>(*v)
+        });
+        assert_eq!(result, Ok(3));
+        assert_eq!(*cell.get(), 3);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut cell = OwnedScopeCell::new(vec![1, 2]);
+        *cell.get_mut() = vec![2, 3];
+        assert_eq!(cell.diff(), Some((&vec![1, 2], &vec![2, 3])));
+        assert_eq!(cell.changes().len(), 2);
+        cell.rebase();
+        assert_eq!(cell.diff(), None);
+    }
+
+    #[test]
+    fn test_apply_changes_to() {
+        let mut cell = OwnedScopeCell::new(String::from("draft"));
+        let mut published = String::from("old");
+        assert!(!cell.apply_changes_to(&mut published));
+        cell.get_mut().push('!');
+        assert!(cell.apply_changes_to(&mut published));
+        assert_eq!(published, "draft!");
+    }
+
+    #[test]
+    fn test_stored_in_struct() {
+        struct Editor {
+            text: OwnedScopeCell<String>,
+        }
+
+        let mut editor = Editor {
+            text: OwnedScopeCell::new(String::from("hello")),
+        };
+        editor.text.get_mut().push_str(" world");
+        assert_eq!(editor.text.baseline(), "hello");
+        assert_eq!(editor.text.into_inner(), "hello world");
+    }
+}
@@ -0,0 +1,299 @@
+use crate::alloc::ScratchAlloc;
+use crate::{
+    GuardedScopeCell, HistoryCell, OwnedScopeCell, ScopeCellMut, ScopeError, ScopeHashMap,
+    ScopeSlice, ScopeVec, TakeScopeCell, Transaction,
+};
+use std::hash::Hash;
+use std::ops::DerefMut;
+
+/// A participant in a two-phase commit run by a `Coordinator`.  `prepare()` may veto the
+/// commit by returning an error; once every participant has prepared successfully, each is
+/// told to `commit()`, otherwise each is told to `abort()`.
+pub trait Participant<E> {
+    // Check whether the pending changes can be committed.  Of the built-in cells only a
+    // poisoned ScopeCellMut and a ValidatedScopeCell holding a rejected value veto.
+    fn prepare(&mut self) -> Result<(), E> {
+        Ok(())
+    }
+
+    // Make the pending changes permanent
+    fn commit(&mut self);
+
+    // Discard the pending changes
+    fn abort(&mut self);
+}
+
+/// A Coordinator runs the two-phase commit protocol over a set of participants of any type.
+pub struct Coordinator<'p, E> {
+    participants: Vec<&'p mut dyn Participant<E>>,
+}
+
+impl<'p, E> Coordinator<'p, E> {
+    // Create a coordinator with no participants
+    pub fn new() -> Self {
+        Coordinator {
+            participants: Vec::new(),
+        }
+    }
+
+    // Enlist a participant
+    pub fn add(&mut self, participant: &'p mut dyn Participant<E>) -> &mut Self {
+        self.participants.push(participant);
+        self
+    }
+
+    // Number of enlisted participants
+    pub fn len(&self) -> usize {
+        self.participants.len()
+    }
+
+    // Whether no participants are enlisted
+    pub fn is_empty(&self) -> bool {
+        self.participants.is_empty()
+    }
+
+    // Prepare every participant, then commit them all.  If any participant vetoes, no
+    // further participants are prepared, every participant is aborted, and the veto is
+    // returned.
+    pub fn run(mut self) -> Result<(), E> {
+        let vetoed = self
+            .participants
+            .iter_mut()
+            .try_for_each(|participant| participant.prepare());
+        match vetoed {
+            Ok(()) => {
+                self.participants
+                    .iter_mut()
+                    .for_each(|participant| participant.commit());
+                Ok(())
+            }
+            Err(err) => {
+                self.abort();
+                Err(err)
+            }
+        }
+    }
+
+    // Abort every participant without preparing
+    pub fn abort(&mut self) {
+        self.participants
+            .iter_mut()
+            .for_each(|participant| participant.abort());
+    }
+}
+
+impl<'p, E> Default for Coordinator<'p, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Clone, E: From<ScopeError>> Participant<E> for ScopeCellMut<'a, T> {
+    // A panic may have left the pending value half-updated, so a poisoned cell vetoes
+    fn prepare(&mut self) -> Result<(), E> {
+        if self.is_poisoned() {
+            return Err(ScopeError::Poisoned.into());
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<T: Clone, A: ScratchAlloc, E> Participant<E> for OwnedScopeCell<T, A> {
+    fn commit(&mut self) {
+        self.rebase();
+    }
+
+    fn abort(&mut self) {
+        self.reset();
+    }
+}
+
+impl<'a, T: Clone, E> Participant<E> for Transaction<'a, T> {
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.rollback();
+    }
+}
+
+impl<G, E> Participant<E> for GuardedScopeCell<G>
+where
+    G: DerefMut,
+    G::Target: Clone,
+{
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<T: Clone, E> Participant<E> for HistoryCell<T> {
+    fn commit(&mut self) {
+        HistoryCell::commit(self);
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<'a, T: Clone, A: ScratchAlloc, E> Participant<E> for ScopeVec<'a, T, A> {
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<'a, T: Clone, E> Participant<E> for ScopeSlice<'a, T> {
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<'a, T: Default, E> Participant<E> for TakeScopeCell<'a, T> {
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+impl<'a, K, V, A, E> Participant<E> for ScopeHashMap<'a, K, V, A>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    A: ScratchAlloc,
+{
+    fn commit(&mut self) {
+        self.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // A participant that vetoes when its value is negative
+    struct NonNegative<'a, 'b>(&'b mut ScopeCellMut<'a, i32>);
+
+    impl<'a, 'b> Participant<String> for NonNegative<'a, 'b> {
+        fn prepare(&mut self) -> Result<(), String> {
+            if *self.0.get() < 0 {
+                Err(format!("{} is negative", self.0.get()))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn commit(&mut self) {
+            Participant::<ScopeError>::commit(self.0);
+        }
+
+        fn abort(&mut self) {
+            self.0.revert();
+        }
+    }
+
+    #[test]
+    fn test_heterogeneous_commit() {
+        let mut list = vec![1, 2];
+        let mut map = HashMap::new();
+        let mut history = HistoryCell::new(0, 4);
+        {
+            let mut list_edit = ScopeVec::new(&mut list);
+            let mut map_edit = ScopeHashMap::new(&mut map);
+            list_edit.push(3);
+            map_edit.insert("key", "value");
+            *history.get_mut() = 1;
+
+            let mut coordinator: Coordinator<()> = Coordinator::new();
+            coordinator
+                .add(&mut list_edit)
+                .add(&mut map_edit)
+                .add(&mut history);
+            assert_eq!(coordinator.len(), 3);
+            coordinator.run().unwrap();
+        }
+        assert_eq!(list, vec![1, 2, 3]);
+        assert_eq!(map.get("key"), Some(&"value"));
+        assert_eq!(*history.get(), 1);
+        assert_eq!(history.history_len(), 1);
+    }
+
+    #[test]
+    fn test_veto_aborts_everyone() {
+        let mut balance = 10;
+        let mut owned = OwnedScopeCell::new(String::from("ledger"));
+        let mut log = vec![String::from("open")];
+        {
+            let mut balance_edit = ScopeCellMut::new(&mut balance);
+            *balance_edit.get_mut() -= 20;
+            owned.get_mut().push_str(" v2");
+            let mut log_tx = Transaction::new(&mut log);
+            log_tx.get_mut().push(String::from("withdraw"));
+
+            let mut checked = NonNegative(&mut balance_edit);
+            let mut coordinator = Coordinator::new();
+            coordinator
+                .add(&mut owned)
+                .add(&mut log_tx)
+                .add(&mut checked);
+            assert_eq!(coordinator.run(), Err(String::from("-10 is negative")));
+            assert_eq!(*log_tx.get(), vec!["open"]); // Aborted in place
+        }
+        assert_eq!(balance, 10);
+        assert_eq!(owned.get(), "ledger");
+        assert_eq!(log, vec!["open"]);
+    }
+
+    #[test]
+    fn test_poisoned_cell_vetoes() {
+        let mut balance = 10;
+        let mut log = vec![String::from("open")];
+        {
+            let mut balance_edit = ScopeCellMut::new(&mut balance);
+            let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                balance_edit.update(|b| {
+                    *b -= 5;
+                    panic!("half-way");
+                })
+            }));
+            assert!(caught.is_err());
+            let mut log_tx = Transaction::new(&mut log);
+            log_tx.get_mut().push(String::from("withdraw"));
+
+            let mut coordinator = Coordinator::new();
+            coordinator.add(&mut log_tx).add(&mut balance_edit);
+            assert_eq!(coordinator.run(), Err(ScopeError::Poisoned));
+        }
+        assert_eq!(balance, 10);
+        assert_eq!(log, vec!["open"]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::mem::MaybeUninit;
+
+/// The slot holding a cell's pending copy: the value itself, stored in place in the cell,
+/// and a flag saying whether it is initialized.  It never allocates and never layers an
+/// `Option` discriminant or niche over `T`, so `is_inline()` can rely on the copy living in
+/// the cell.
+pub(crate) struct Pending<T> {
+    value: MaybeUninit<T>,
+    present: bool, // Whether `value` is initialized
+}
+
+impl<T> Pending<T> {
+    // An empty slot
+    pub(crate) const fn empty() -> Self {
+        Pending {
+            value: MaybeUninit::uninit(),
+            present: false,
+        }
+    }
+
+    pub(crate) fn is_none(&self) -> bool {
+        !self.present
+    }
+
+    pub(crate) fn as_ref(&self) -> Option<&T> {
+        // `present` is only set while `value` is initialized
+        self.present
+            .then(|| unsafe { self.value.assume_init_ref() })
+    }
+
+    pub(crate) fn as_mut(&mut self) -> Option<&mut T> {
+        if self.present {
+            Some(unsafe { self.value.assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    // Move the value out, leaving the slot empty
+    pub(crate) fn take(&mut self) -> Option<T> {
+        if self.present {
+            // Cleared first, so the slot is consistent even if the caller's drop panics
+            self.present = false;
+            Some(unsafe { self.value.assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    // Store `value`, dropping any previous one, and borrow it
+    pub(crate) fn insert(&mut self, value: T) -> &mut T {
+        drop(self.take());
+        self.present = true;
+        self.value.write(value)
+    }
+
+    // Store `value` or empty the slot, returning the previous value
+    pub(crate) fn replace(&mut self, value: Option<T>) -> Option<T> {
+        let previous = self.take();
+        if let Some(value) = value {
+            self.insert(value);
+        }
+        previous
+    }
+
+    // A copy of the slot's contents, for snapshots
+    pub(crate) fn cloned(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.as_ref().cloned()
+    }
+}
+
+impl<T> Drop for Pending<T> {
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_pending_drops_exactly_once() {
+        let value = Rc::new(1);
+        let mut slot = Pending::empty();
+        assert!(slot.is_none());
+        slot.insert(value.clone());
+        slot.insert(value.clone()); // Drops the first copy
+        assert_eq!(Rc::strong_count(&value), 2);
+        assert_eq!(slot.replace(None).map(|v| *v), Some(1));
+        assert_eq!(Rc::strong_count(&value), 1);
+        slot.insert(value.clone());
+        assert!(slot.cloned().is_some());
+        drop(slot);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+}
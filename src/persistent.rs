@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+/// Persistent collections whose `clone()` is O(1) structural sharing, together with the
+/// std collection they convert back into.
+pub trait Persistent: Clone {
+    type Std;
+
+    fn into_std(self) -> Self::Std;
+}
+
+impl<T: Clone> Persistent for im::Vector<T> {
+    type Std = Vec<T>;
+
+    fn into_std(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+}
+
+impl<K, V, S> Persistent for im::HashMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    type Std = HashMap<K, V>;
+
+    fn into_std(self) -> HashMap<K, V> {
+        self.into_iter().collect()
+    }
+}
+
+/// A PersistentScopeCell holds an `im` collection and a working copy of it.  Because the
+/// copy is structurally shared, opening a scoped edit over a huge collection is O(1), and
+/// only the nodes touched by the edit are duplicated.
+#[derive(Clone)]
+pub struct PersistentScopeCell<C: Persistent> {
+    original_data: C,
+    current: C,  // Shares structure with the original until edited
+    dirty: bool, // Set on mutable access, cleared by revert/commit
+}
+
+impl<C: Persistent> PersistentScopeCell<C> {
+    // Create a new PersistentScopeCell with `data` as its baseline
+    pub fn new(data: C) -> Self {
+        PersistentScopeCell {
+            current: data.clone(),
+            original_data: data,
+            dirty: false,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &C {
+        &self.current
+    }
+
+    // Mutably borrow the working copy
+    pub fn get_mut(&mut self) -> &mut C {
+        self.dirty = true;
+        &mut self.current
+    }
+
+    // The baseline the cell reverts to
+    pub fn original(&self) -> &C {
+        &self.original_data
+    }
+
+    // Whether the data has been mutably accessed since creation, the last revert, or the last commit
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Discard the edits, sharing the baseline again
+    pub fn revert(&mut self) {
+        self.current = self.original_data.clone();
+        self.dirty = false;
+    }
+
+    // Make the working copy the new baseline
+    pub fn commit(&mut self) {
+        self.original_data = self.current.clone();
+        self.dirty = false;
+    }
+
+    // Consume the cell and convert the effective value into its std collection
+    pub fn into_inner(self) -> C::Std {
+        self.current.into_std()
+    }
+
+    // Consume the cell and return the effective value as the persistent collection
+    pub fn into_persistent(self) -> C {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_edit_and_revert() {
+        let big: im::Vector<u32> = (0..10_000).collect();
+        let mut cell = PersistentScopeCell::new(big.clone());
+        cell.get_mut().set(0, 42);
+        cell.get_mut().push_back(10_000);
+        assert_eq!(cell.get()[0], 42);
+        assert_eq!(cell.original()[0], 0); // Baseline unaffected
+        cell.revert();
+        assert!(!cell.is_modified());
+        assert_eq!(*cell.get(), big);
+    }
+
+    #[test]
+    fn test_commit_then_into_inner() {
+        let mut cell = PersistentScopeCell::new(im::vector![1, 2]);
+        cell.get_mut().push_back(3);
+        cell.commit();
+        cell.get_mut().push_back(4);
+        cell.revert();
+        assert_eq!(cell.into_inner(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_hashmap_into_std() {
+        let mut cell = PersistentScopeCell::new(im::HashMap::<&str, i32>::new());
+        cell.get_mut().insert("a", 1);
+        let map: HashMap<&str, i32> = cell.into_inner();
+        assert_eq!(map.get("a"), Some(&1));
+    }
+}
@@ -0,0 +1,153 @@
+use std::pin::Pin;
+
+/// A PinScopeCell makes scoped edits to pinned data, such as a future's state or a node of
+/// an intrusive list.  The temporary copy is pinned on the heap, so it never moves once
+/// handed out as `Pin<&mut T>`, and `commit()` writes it back through `Pin::set`, which drops
+/// the old value in place as the pinning contract requires.
+///
+/// Copies are made with `Clone`, so `T::clone` must return a value that is sound to move:
+/// for a self-referential type that means one whose internal pointers are not set up yet,
+/// the way a future is movable until it is first polled.  `project()` takes the usual
+/// structural-pinning projection, such as one generated by `pin-project`; a field may only
+/// be projected as pinned if the type never moves out of it and its `Drop` does not either.
+pub struct PinScopeCell<'a, T: Clone> {
+    original_data: Pin<&'a mut T>,
+    modified_data: Option<Pin<Box<T>>>, // Holds the temporary copy, pinned in place
+}
+
+impl<'a, T: Clone> PinScopeCell<'a, T> {
+    // Create a new PinScopeCell over pinned data
+    pub fn new(data: Pin<&'a mut T>) -> Self {
+        PinScopeCell {
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        match &self.modified_data {
+            Some(modified) => modified,
+            None => &self.original_data,
+        }
+    }
+
+    // Borrow the original, ignoring any modification
+    pub fn original(&self) -> &T {
+        &self.original_data
+    }
+
+    // Pinned mutable access to the data, pinning a temporary copy on first use
+    pub fn as_mut(&mut self) -> Pin<&mut T> {
+        let original = &self.original_data;
+        self.modified_data
+            .get_or_insert_with(|| Box::pin(T::clone(original)))
+            .as_mut()
+    }
+
+    // Project pinned mutable access onto part of the data, creating the copy if necessary
+    pub fn project<U, F>(&mut self, project: F) -> Pin<&mut U>
+    where
+        U: ?Sized,
+        F: FnOnce(Pin<&mut T>) -> Pin<&mut U>,
+    {
+        project(self.as_mut())
+    }
+
+    // Plain mutable access, for types that do not care about pinning
+    pub fn get_mut(&mut self) -> &mut T
+    where
+        T: Unpin,
+    {
+        self.as_mut().get_mut()
+    }
+
+    // Whether the data has been mutably accessed since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes by dropping the copy in place
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Write the modified data back into the pinned original, dropping the old value in place
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Write the modified data back in place, leaving the cell unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            self.original_data.set(T::clone(&modified));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomPinned;
+
+    // A !Unpin type with a pinned projection, written by hand
+    #[derive(Clone)]
+    struct Node {
+        value: u32,
+        links: Vec<u32>,
+        _pin: PhantomPinned,
+    }
+
+    impl Node {
+        fn new(value: u32) -> Self {
+            Node {
+                value,
+                links: Vec::new(),
+                _pin: PhantomPinned,
+            }
+        }
+
+        // `value` is not structurally pinned, so handing out `&mut` to it is fine
+        fn value_mut(self: Pin<&mut Self>) -> &mut u32 {
+            unsafe { &mut self.get_unchecked_mut().value }
+        }
+
+        // `links` is projected as pinned; Node never moves out of it
+        fn links(self: Pin<&mut Self>) -> Pin<&mut Vec<u32>> {
+            unsafe { self.map_unchecked_mut(|node| &mut node.links) }
+        }
+    }
+
+    #[test]
+    fn test_pinned_edit_reverts_and_commits() {
+        let mut node = Box::pin(Node::new(1));
+        {
+            let mut cell = PinScopeCell::new(node.as_mut());
+            *cell.as_mut().value_mut() = 5;
+            let copy: *const Node = cell.get();
+            cell.project(Node::links).push(7);
+            assert_eq!(copy, cell.get() as *const Node); // The copy did not move
+            assert_eq!(cell.original().value, 1);
+        }
+        assert_eq!(node.value, 1); // Dropped without commit
+
+        let mut cell = PinScopeCell::new(node.as_mut());
+        *cell.as_mut().value_mut() = 9;
+        cell.as_mut().links().push(3);
+        assert!(cell.is_modified());
+        cell.commit();
+        assert_eq!((node.value, node.links.clone()), (9, vec![3]));
+    }
+
+    #[test]
+    fn test_unpin_get_mut() {
+        let mut data = vec![1, 2];
+        let mut cell = PinScopeCell::new(Pin::new(&mut data));
+        cell.get_mut().push(3);
+        cell.revert();
+        assert_eq!(cell.get(), &vec![1, 2]);
+        cell.get_mut().clear();
+        cell.commit();
+        assert!(data.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::Recycle;
+
+/// A ScopePool keeps scratch values that short-lived cells borrow instead of cloning.  Each
+/// `PooledScopeCell` takes a buffer from the pool on its first write, refills it with
+/// `recycle_from`, and hands it back when reverted, committed or dropped.
+pub struct ScopePool<T: Recycle> {
+    free: Mutex<Vec<T>>, // Buffers waiting to be lent out
+    limit: usize,        // Most buffers kept; extras are dropped when returned
+}
+
+impl<T: Recycle> ScopePool<T> {
+    // Create an empty pool that keeps any number of returned buffers
+    pub fn new() -> Self {
+        ScopePool::with_limit(usize::MAX)
+    }
+
+    // Create an empty pool that keeps at most `limit` returned buffers
+    pub fn with_limit(limit: usize) -> Self {
+        ScopePool {
+            free: Mutex::new(Vec::new()),
+            limit,
+        }
+    }
+
+    // Open a cell over `data` that draws its scratch copy from this pool
+    pub fn cell<'p, 'a>(&'p self, data: &'a mut T) -> PooledScopeCell<'p, 'a, T> {
+        PooledScopeCell {
+            pool: self,
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Add a pre-allocated buffer to the pool
+    pub fn put(&self, buffer: T) {
+        let mut free = self.lock();
+        if free.len() < self.limit {
+            free.push(buffer);
+        }
+    }
+
+    // Number of buffers waiting to be lent out
+    pub fn available(&self) -> usize {
+        self.lock().len()
+    }
+
+    // Drop every pooled buffer
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn take(&self) -> Option<T> {
+        self.lock().pop()
+    }
+
+    // Buffers are plain scratch space, so a panic elsewhere cannot leave them inconsistent
+    fn lock(&self) -> MutexGuard<'_, Vec<T>> {
+        self.free.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Recycle> Default for ScopePool<T> {
+    fn default() -> Self {
+        ScopePool::new()
+    }
+}
+
+/// A scoped cell whose temporary copy is borrowed from a `ScopePool`.  It reverts on drop
+/// like `ScopeCellMut`, returning the copy to the pool.
+pub struct PooledScopeCell<'p, 'a, T: Recycle> {
+    pool: &'p ScopePool<T>,
+    original_data: &'a mut T,
+    modified_data: Option<T>, // Holds temporary modified data, lent by the pool
+}
+
+impl<'p, 'a, T: Recycle> PooledScopeCell<'p, 'a, T> {
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        self.modified_data.as_ref().unwrap_or(self.original_data)
+    }
+
+    // Mutably borrow the data, refilling a pooled buffer (or cloning if the pool is empty)
+    pub fn get_mut(&mut self) -> &mut T {
+        if self.modified_data.is_none() {
+            let copy = match self.pool.take() {
+                Some(mut buffer) => {
+                    buffer.recycle_from(self.original_data);
+                    buffer
+                }
+                None => self.original_data.clone(),
+            };
+            self.modified_data = Some(copy);
+        }
+        self.modified_data.as_mut().unwrap()
+    }
+
+    // Whether a modified copy exists
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Discard the changes, returning the buffer to the pool
+    pub fn revert(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            self.pool.put(modified);
+        }
+    }
+
+    // Write the modified data back by swapping it into place; the previous value goes to
+    // the pool
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Write the modified data back in place, leaving the cell unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(mut modified) = self.modified_data.take() {
+            std::mem::swap(self.original_data, &mut modified);
+            self.pool.put(modified);
+        }
+    }
+}
+
+impl<'p, 'a, T: Recycle> Drop for PooledScopeCell<'p, 'a, T> {
+    fn drop(&mut self) {
+        self.revert();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_cycle_through_pool() {
+        let pool = ScopePool::new();
+        let mut data = vec![0u8; 4096];
+        for i in 0..100 {
+            let mut cell = pool.cell(&mut data);
+            cell.get_mut()[0] = i;
+            assert_eq!(cell.get()[0], i);
+        } // Each drop returns the buffer
+        assert_eq!(pool.available(), 1); // One buffer served every cell
+        assert_eq!(data[0], 0);
+    }
+
+    #[test]
+    fn test_commit_returns_old_value() {
+        let pool = ScopePool::new();
+        pool.put(String::with_capacity(64));
+        let mut text = String::from("before");
+        let mut cell = pool.cell(&mut text);
+        cell.get_mut().push_str(" after");
+        assert_eq!(pool.available(), 0); // Lent out
+        cell.commit();
+        assert_eq!(text, "before after");
+        assert_eq!(pool.available(), 1);
+    }
+
+    #[test]
+    fn test_limit_and_clear() {
+        let pool = ScopePool::with_limit(1);
+        pool.put(vec![1]);
+        pool.put(vec![2]); // Over the limit, dropped
+        assert_eq!(pool.available(), 1);
+        pool.clear();
+        assert_eq!(pool.available(), 0);
+    }
+}
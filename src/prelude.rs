@@ -0,0 +1,6 @@
+//! The traits and the common cells, for `use scope_cell::prelude::*;`.
+
+pub use crate::{
+    CowScopeCell, HistoryCell, OwnedScopeCell, ScopeCell, ScopeCellMut, ScopeError, ScopeSlice,
+    ScopedEdit,
+};
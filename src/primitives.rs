@@ -0,0 +1,100 @@
+// The synchronization primitives of the concurrent cells (`SyncScopeCell`,
+// `DoubleBufferCell`, `AtomicScopeCell`, the STM and the other cells shared across
+// threads).  Building with `RUSTFLAGS="--cfg loom"` swaps in loom's model-checked versions,
+// so `loom::model` can explore the cells' interleavings; the `parking_lot` feature swaps
+// the locks for `parking_lot`'s, behind std's API; every other build uses std.  `Ordering`
+// and `PoisonError` are std's in all cases.
+
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16,
+    AtomicU32, AtomicU64, AtomicU8, AtomicUsize,
+};
+#[cfg(loom)]
+pub(crate) use loom::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(loom)]
+pub(crate) use loom::thread;
+
+#[cfg(all(not(loom), feature = "parking_lot"))]
+pub(crate) use parking::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicPtr, AtomicU16,
+    AtomicU32, AtomicU64, AtomicU8, AtomicUsize,
+};
+#[cfg(all(not(loom), not(feature = "parking_lot")))]
+pub(crate) use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(loom))]
+pub(crate) use std::thread;
+
+// Declare a static primitive: a plain `static` normally, and under loom a lazy static that
+// loom resets for every execution of a model
+macro_rules! static_primitive {
+    ($(static $name:ident: $ty:ty = $init:expr;)*) => {
+        $(
+            #[cfg(not(loom))]
+            static $name: $ty = $init;
+            #[cfg(loom)]
+            loom::lazy_static! {
+                static ref $name: $ty = $init;
+            }
+        )*
+    };
+}
+
+pub(crate) use static_primitive;
+
+// `parking_lot`'s locks behind std's API.  They never poison: a panicking holder simply
+// releases its lock, so every `LockResult` is `Ok` and `is_poisoned()` is always false.
+#[cfg(all(not(loom), feature = "parking_lot"))]
+mod parking {
+    use std::sync::LockResult;
+
+    pub(crate) use ::parking_lot::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+
+    #[derive(Debug, Default)]
+    pub(crate) struct RwLock<T>(::parking_lot::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            RwLock(::parking_lot::RwLock::new(value))
+        }
+
+        pub(crate) fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+            Ok(self.0.read())
+        }
+
+        pub(crate) fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+            Ok(self.0.write())
+        }
+
+        pub(crate) fn into_inner(self) -> LockResult<T> {
+            Ok(self.0.into_inner())
+        }
+
+        pub(crate) fn is_poisoned(&self) -> bool {
+            false
+        }
+
+        pub(crate) fn clear_poison(&self) {}
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Mutex<T>(::parking_lot::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            Mutex(::parking_lot::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+            Ok(self.0.lock())
+        }
+
+        pub(crate) fn is_poisoned(&self) -> bool {
+            false
+        }
+
+        pub(crate) fn clear_poison(&self) {}
+    }
+}
@@ -0,0 +1,295 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use arc_swap::{ArcSwap, Guard};
+
+use crate::lifecycle::{self, MutationKind};
+use crate::{Merge, ScopeError};
+
+/// An ArcSwapScopeCell keeps its baseline in an `ArcSwap<T>`, read-copy-update style.
+/// Readers load the current snapshot wait-free; a scoped edit clones from the snapshot it
+/// started with and `commit()` atomically swaps the new value in.  Readers holding an older
+/// snapshot keep seeing it until they load again.  With `commit()` the last edit to commit
+/// wins; `try_commit()` instead fails if another edit committed first, and
+/// `try_commit_with()` resolves that with a three-way merge.
+pub struct ArcSwapScopeCell<T: Clone> {
+    baseline: ArcSwap<T>,
+    label: Option<&'static str>, // Names the cell in lifecycle events
+}
+
+impl<T: Clone> ArcSwapScopeCell<T> {
+    // Create a new ArcSwapScopeCell with `value` as its baseline
+    pub fn new(value: T) -> Self {
+        ArcSwapScopeCell::from_arc(Arc::new(value))
+    }
+
+    // Create a new ArcSwapScopeCell sharing an existing `Arc`
+    pub fn from_arc(value: Arc<T>) -> Self {
+        ArcSwapScopeCell {
+            baseline: ArcSwap::new(value),
+            label: None,
+        }
+    }
+
+    // Create a new ArcSwapScopeCell labelled for the lifecycle events its edits report
+    pub fn named(value: T, label: &'static str) -> Self {
+        let mut cell = ArcSwapScopeCell::new(value);
+        cell.set_label(label);
+        cell
+    }
+
+    // Name the cell in the lifecycle events its edits report, such as `tracing` events
+    pub fn set_label(&mut self, label: &'static str) -> &mut Self {
+        self.label = Some(label);
+        self
+    }
+
+    // The cell's label, if it has one
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    // Load the current snapshot without blocking, for short reads
+    pub fn load(&self) -> Guard<Arc<T>> {
+        self.baseline.load()
+    }
+
+    // Load the current snapshot as an owned `Arc`, for reads that outlive a few statements
+    pub fn load_full(&self) -> Arc<T> {
+        self.baseline.load_full()
+    }
+
+    // Start a scoped edit of the current snapshot.  The snapshot is only cloned on the
+    // first mutable access.
+    pub fn edit(&self) -> ArcSwapEdit<'_, T> {
+        ArcSwapEdit {
+            cell: self,
+            snapshot: self.load_full(),
+            modified_data: None,
+        }
+    }
+
+    // Run a closure against a scoped edit, committing it if the closure returns `Ok`
+    pub fn scope<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut edit = self.edit();
+        let result = f(&mut edit)?;
+        edit.commit();
+        Ok(result)
+    }
+
+    // Swap in a new value outright
+    pub fn set(&self, value: T) {
+        self.baseline.store(Arc::new(value));
+    }
+
+    // Consume the cell and return the current snapshot
+    pub fn into_inner(self) -> Arc<T> {
+        self.baseline.into_inner()
+    }
+}
+
+/// A scoped edit of an ArcSwapScopeCell.  Dropping it without `commit()` discards the edit;
+/// readers never see it either way until it commits.
+pub struct ArcSwapEdit<'c, T: Clone> {
+    cell: &'c ArcSwapScopeCell<T>,
+    snapshot: Arc<T>,         // The baseline the edit started from
+    modified_data: Option<T>, // Holds the temporary copy of the snapshot
+}
+
+impl<'c, T: Clone> ArcSwapEdit<'c, T> {
+    // The snapshot the edit started from
+    pub fn snapshot(&self) -> &Arc<T> {
+        &self.snapshot
+    }
+
+    // Whether the edit has been mutably accessed
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Swap the edited value in as the new baseline.  An edit that never touched the value
+    // swaps nothing.  Returns whether a new value was published.
+    pub fn commit(self) -> bool {
+        match self.modified_data {
+            Some(modified) => {
+                self.cell.baseline.store(Arc::new(modified));
+                lifecycle::emit::<T>(MutationKind::Committed, self.cell.label);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Swap the edited value in only if the baseline is still the snapshot the edit started
+    // from, failing with `ScopeError::Conflict` otherwise.  Returns whether a new value was
+    // published.
+    pub fn try_commit(self) -> Result<bool, ScopeError> {
+        self.try_commit_with(|_, _, _| None)
+    }
+
+    // Like `try_commit()`, but on a conflict call `merge(base, theirs, mine)` with the
+    // snapshot, the current baseline and the edited value, publishing the merged value.  If
+    // the baseline moves again while merging, the merge is retried with the value it was
+    // merged against as the base, so changes already folded in are not applied twice.
+    pub fn try_commit_with<M>(self, mut merge: M) -> Result<bool, ScopeError>
+    where
+        M: FnMut(&T, &T, &T) -> Option<T>,
+    {
+        let Some(mine) = self.modified_data else {
+            return Ok(false);
+        };
+        let mut expected = Arc::clone(&self.snapshot);
+        let mut next = Arc::new(mine);
+        loop {
+            let previous = self
+                .cell
+                .baseline
+                .compare_and_swap(&expected, Arc::clone(&next));
+            if Arc::ptr_eq(&previous, &expected) {
+                lifecycle::emit::<T>(MutationKind::Committed, self.cell.label);
+                return Ok(true);
+            }
+            lifecycle::emit::<T>(MutationKind::Conflicted, self.cell.label);
+            let theirs = Guard::into_inner(previous);
+            let merged = merge(&expected, &theirs, &next).ok_or(ScopeError::Conflict)?;
+            expected = theirs;
+            next = Arc::new(merged);
+        }
+    }
+
+    // Like `try_commit()`, resolving a conflict with the type's own `Merge` strategy
+    pub fn try_commit_merge(self) -> Result<bool, ScopeError>
+    where
+        T: Merge,
+    {
+        self.try_commit_with(T::merge)
+    }
+}
+
+impl<'c, T: Clone> Deref for ArcSwapEdit<'c, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.modified_data.as_ref().unwrap_or(&self.snapshot)
+    }
+}
+
+impl<'c, T: Clone> DerefMut for ArcSwapEdit<'c, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let (snapshot, label) = (&self.snapshot, self.cell.label);
+        self.modified_data.get_or_insert_with(|| {
+            lifecycle::emit::<T>(MutationKind::Copied, label);
+            T::clone(snapshot)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_readers_see_snapshot_until_commit() {
+        let routes = ArcSwapScopeCell::new(vec!["a", "b"]);
+        let before = routes.load_full();
+        let mut edit = routes.edit();
+        edit.push("c");
+        assert_eq!(routes.load().len(), 2); // Not published yet
+        assert!(edit.commit());
+        assert_eq!(*routes.load_full(), vec!["a", "b", "c"]);
+        assert_eq!(*before, vec!["a", "b"]); // Old snapshot still valid
+    }
+
+    #[test]
+    fn test_untouched_edit_publishes_nothing() {
+        let cell = ArcSwapScopeCell::new(String::from("base"));
+        let before = cell.load_full();
+        let edit = cell.edit();
+        assert_eq!(edit.len(), 4);
+        assert!(!edit.is_modified());
+        assert!(Arc::ptr_eq(edit.snapshot(), &before));
+        assert!(!edit.commit());
+        assert!(Arc::ptr_eq(&cell.load_full(), &before));
+
+        cell.edit().push('!'); // Dropped without commit
+        assert_eq!(**cell.load(), "base");
+    }
+
+    #[test]
+    fn test_try_commit_detects_conflicts() {
+        let cell = ArcSwapScopeCell::new(vec![1]);
+        let mut first = cell.edit();
+        let mut second = cell.edit();
+        let mut third = cell.edit();
+        first.push(2);
+        second.push(3);
+        third.push(4);
+        assert_eq!(first.try_commit(), Ok(true));
+        assert_eq!(second.try_commit(), Err(ScopeError::Conflict));
+        // Keep their additions and append ours
+        let merged = third.try_commit_with(|base, theirs, mine| {
+            let mut merged = theirs.clone();
+            merged.extend_from_slice(&mine[base.len()..]);
+            Some(merged)
+        });
+        assert_eq!(merged, Ok(true));
+        assert_eq!(*cell.load_full(), vec![1, 2, 4]);
+
+        let mut prepend = cell.edit();
+        prepend.insert(0, 0);
+        cell.set(vec![1, 2, 4, 5]);
+        assert_eq!(prepend.try_commit_merge(), Ok(true));
+        assert_eq!(*cell.load_full(), vec![0, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_merge_retry_uses_previous_baseline() {
+        let cell = ArcSwapScopeCell::new(vec![1]);
+        let mut edit = cell.edit();
+        edit.push(2);
+        cell.set(vec![1, 3]);
+        let mut bases = Vec::new();
+        let merged = edit.try_commit_with(|base, theirs, mine| {
+            if bases.is_empty() {
+                cell.set(vec![1, 3, 4]); // Moves the baseline again mid-merge
+            }
+            bases.push(base.clone());
+            let mut merged = theirs.clone();
+            merged.extend_from_slice(&mine[base.len()..]);
+            Some(merged)
+        });
+        assert_eq!(merged, Ok(true));
+        assert_eq!(bases, vec![vec![1], vec![1, 3]]);
+        assert_eq!(*cell.load_full(), vec![1, 3, 4, 2]);
+    }
+
+    #[test]
+    fn test_scope_with_concurrent_readers() {
+        let cell = ArcSwapScopeCell::new(0u64);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    cell.scope(|n| {
+                        *n += 1;
+                        Ok::<_, ()>(())
+                    })
+                    .unwrap();
+                }
+            });
+            s.spawn(|| {
+                let mut last = 0;
+                for _ in 0..100 {
+                    let now = **cell.load();
+                    assert!(now >= last); // Readers only ever move forward
+                    last = now;
+                }
+            });
+        });
+        assert!(cell.scope(|_| Err::<(), _>("rejected")).is_err());
+        assert_eq!(*cell.into_inner(), 100);
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+
+/// Values that can be overwritten with a copy of another value while keeping their own
+/// allocations, so a scratch buffer can be cleared and refilled instead of re-cloned.
+pub trait Recycle: Clone {
+    // Make `self` a copy of `source`, reusing the capacity already held by `self`
+    fn recycle_from(&mut self, source: &Self);
+}
+
+impl<T: Clone> Recycle for Vec<T> {
+    fn recycle_from(&mut self, source: &Self) {
+        self.clone_from(source);
+    }
+}
+
+impl<T: Clone> Recycle for VecDeque<T> {
+    fn recycle_from(&mut self, source: &Self) {
+        self.clear();
+        self.extend(source.iter().cloned());
+    }
+}
+
+impl Recycle for String {
+    fn recycle_from(&mut self, source: &Self) {
+        self.clear();
+        self.push_str(source);
+    }
+}
+
+impl<K, V, S> Recycle for HashMap<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn recycle_from(&mut self, source: &Self) {
+        self.clear();
+        self.extend(source.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+impl<T, S> Recycle for HashSet<T, S>
+where
+    T: Clone + Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn recycle_from(&mut self, source: &Self) {
+        self.clear();
+        self.extend(source.iter().cloned());
+    }
+}
+
+// BTree collections allocate per node, so there is no capacity to keep; `clone_from` is
+// as good as it gets.
+impl<K: Clone + Ord, V: Clone> Recycle for BTreeMap<K, V> {
+    fn recycle_from(&mut self, source: &Self) {
+        self.clone_from(source);
+    }
+}
+
+impl<T: Clone + Ord> Recycle for BTreeSet<T> {
+    fn recycle_from(&mut self, source: &Self) {
+        self.clone_from(source);
+    }
+}
+
+/// A RecycleScopeCell is a `ScopeCellMut` that never throws a buffer away.  `revert()` keeps
+/// the modified copy as a spare, and the next `get_mut()` refills it with `recycle_from`
+/// instead of allocating a fresh clone.  `commit()` swaps the copy into place, so the old
+/// value becomes the spare.
+pub struct RecycleScopeCell<'a, T: Recycle> {
+    original_data: &'a mut T,
+    modified_data: Option<T>, // Holds temporary modified data
+    spare: Option<T>,         // Discarded copy kept for its allocation
+}
+
+impl<'a, T: Recycle> RecycleScopeCell<'a, T> {
+    // Create a new RecycleScopeCell from a mutable reference
+    pub fn new(data: &'a mut T) -> Self {
+        RecycleScopeCell {
+            original_data: data,
+            modified_data: None,
+            spare: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        self.modified_data.as_ref().unwrap_or(self.original_data)
+    }
+
+    // Mutably borrow the data, refilling the spare buffer (or cloning if there is none)
+    pub fn get_mut(&mut self) -> &mut T {
+        if self.modified_data.is_none() {
+            let copy = match self.spare.take() {
+                Some(mut spare) => {
+                    spare.recycle_from(self.original_data);
+                    spare
+                }
+                None => self.original_data.clone(),
+            };
+            self.modified_data = Some(copy);
+        }
+        self.modified_data.as_mut().unwrap()
+    }
+
+    // Whether a modified copy exists
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Whether a spare buffer is being held for the next edit
+    pub fn has_spare(&self) -> bool {
+        self.spare.is_some()
+    }
+
+    // Discard the changes, keeping the buffer as a spare
+    pub fn revert(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            self.spare = Some(modified);
+        }
+    }
+
+    // Write the modified data back by swapping it into place; the previous value becomes
+    // the spare
+    pub fn commit(&mut self) {
+        if let Some(mut modified) = self.modified_data.take() {
+            std::mem::swap(self.original_data, &mut modified);
+            self.spare = Some(modified);
+        }
+    }
+
+    // Free the spare buffer
+    pub fn release_spare(&mut self) {
+        self.spare = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_keeps_allocation() {
+        let mut data: Vec<u64> = (0..1000).collect();
+        let mut cell = RecycleScopeCell::new(&mut data);
+        cell.get_mut().push(1000);
+        let buffer = cell.get().as_ptr();
+        cell.revert();
+        assert!(cell.has_spare());
+        assert_eq!(cell.get().len(), 1000);
+
+        cell.get_mut().clear();
+        assert_eq!(cell.get().as_ptr(), buffer); // The spare was refilled, not reallocated
+    }
+
+    #[test]
+    fn test_commit_swaps_in_place() {
+        let mut text = String::from("old");
+        {
+            let mut cell = RecycleScopeCell::new(&mut text);
+            cell.get_mut().push_str(" and new");
+            cell.commit();
+            assert_eq!(cell.get(), "old and new");
+            cell.get_mut().push('!');
+            cell.revert();
+            cell.release_spare();
+            assert!(!cell.has_spare());
+        }
+        assert_eq!(text, "old and new");
+    }
+
+    #[test]
+    fn test_collection_impls() {
+        let mut map = HashMap::with_capacity(64);
+        map.insert(1, "stale");
+        let source = HashMap::from([(2, "fresh")]);
+        map.recycle_from(&source);
+        assert_eq!(map, source);
+        assert!(map.capacity() >= 64);
+
+        let mut queue = VecDeque::from([1, 2, 3]);
+        queue.recycle_from(&VecDeque::from([4]));
+        assert_eq!(queue, [4]);
+    }
+}
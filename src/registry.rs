@@ -0,0 +1,184 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+type Service = Arc<dyn Any + Send + Sync>;
+
+// Everything registered for one type
+#[derive(Default)]
+struct Entry {
+    base: Option<Service>,       // Set by `insert`
+    scoped: Vec<(u64, Service)>, // Live scoped insertions, oldest first
+}
+
+impl Entry {
+    fn current(&self) -> Option<&Service> {
+        self.scoped
+            .last()
+            .map(|(_, value)| value)
+            .or(self.base.as_ref())
+    }
+}
+
+/// A ScopedRegistry maps types to shared service values, like a `ScopedSlot` per type.
+/// `insert_scoped::<T>(value)` shadows the current `T` until the returned guard drops,
+/// insertions nest, and `get::<T>()` always sees the most recent live one.  Values
+/// registered with `insert` sit underneath every scoped insertion.
+pub struct ScopedRegistry {
+    entries: Mutex<HashMap<TypeId, Entry>>,
+    next_id: AtomicU64,
+}
+
+impl ScopedRegistry {
+    // Create an empty ScopedRegistry
+    pub fn new() -> Self {
+        ScopedRegistry {
+            entries: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    // The lock is only held for short, non-panicking sections, so poisoning can be ignored
+    fn lock(&self) -> MutexGuard<'_, HashMap<TypeId, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Register `value` permanently, beneath any scoped insertions of the same type.  Returns
+    // the value it replaces.
+    pub fn insert<T: Any + Send + Sync>(&self, value: T) -> Option<Arc<T>> {
+        let mut entries = self.lock();
+        let entry = entries.entry(TypeId::of::<T>()).or_default();
+        let previous = entry.base.replace(Arc::new(value))?;
+        Some(previous.downcast().unwrap())
+    }
+
+    // Shadow the current `T` with `value` until the returned guard is dropped
+    pub fn insert_scoped<T: Any + Send + Sync>(&self, value: T) -> RegistryOverride<'_, T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock()
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .scoped
+            .push((id, Arc::new(value)));
+        RegistryOverride {
+            owner: self,
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    // The current `T`: the most recent live insertion
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let value = self.lock().get(&TypeId::of::<T>())?.current()?.clone();
+        Some(value.downcast().unwrap())
+    }
+
+    // Whether any `T` is registered
+    pub fn contains<T: Any>(&self) -> bool {
+        self.lock().contains_key(&TypeId::of::<T>())
+    }
+
+    // Number of live scoped insertions shadowing `T`
+    pub fn depth<T: Any>(&self) -> usize {
+        self.lock()
+            .get(&TypeId::of::<T>())
+            .map_or(0, |entry| entry.scoped.len())
+    }
+}
+
+impl Default for ScopedRegistry {
+    fn default() -> Self {
+        ScopedRegistry::new()
+    }
+}
+
+/// Guard returned by `ScopedRegistry::insert_scoped`; removes its insertion when dropped.
+pub struct RegistryOverride<'r, T: Any> {
+    owner: &'r ScopedRegistry,
+    id: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'r, T: Any> Drop for RegistryOverride<'r, T> {
+    fn drop(&mut self) {
+        // Guards may be dropped out of order, so remove this insertion wherever it sits
+        let mut entries = self.owner.lock();
+        let key = TypeId::of::<T>();
+        if let Some(entry) = entries.get_mut(&key) {
+            if let Some(index) = entry.scoped.iter().rposition(|(id, _)| *id == self.id) {
+                entry.scoped.remove(index);
+            }
+            if entry.current().is_none() {
+                entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Storage: Send + Sync {
+        fn name(&self) -> &'static str;
+    }
+
+    struct Disk;
+
+    impl Storage for Disk {
+        fn name(&self) -> &'static str {
+            "disk"
+        }
+    }
+
+    struct Memory;
+
+    impl Storage for Memory {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+    }
+
+    #[test]
+    fn test_scoped_insertions_shadow_and_revert() {
+        let registry = ScopedRegistry::new();
+        registry.insert::<Box<dyn Storage>>(Box::new(Disk));
+        {
+            let _plugin = registry.insert_scoped::<Box<dyn Storage>>(Box::new(Memory));
+            assert_eq!(registry.get::<Box<dyn Storage>>().unwrap().name(), "memory");
+            assert_eq!(registry.depth::<Box<dyn Storage>>(), 1);
+        }
+        assert_eq!(registry.get::<Box<dyn Storage>>().unwrap().name(), "disk");
+        assert_eq!(registry.depth::<Box<dyn Storage>>(), 0);
+    }
+
+    #[test]
+    fn test_nesting_and_out_of_order_drops() {
+        let registry = ScopedRegistry::default();
+        let outer = registry.insert_scoped(1u32);
+        let inner = registry.insert_scoped(2u32);
+        registry.insert(0u32); // Lands beneath both scoped insertions
+        assert_eq!(*registry.get::<u32>().unwrap(), 2);
+        drop(outer);
+        assert_eq!(*registry.get::<u32>().unwrap(), 2);
+        drop(inner);
+        assert_eq!(*registry.get::<u32>().unwrap(), 0);
+        assert_eq!(registry.insert(5u32).as_deref(), Some(&0));
+    }
+
+    #[test]
+    fn test_types_are_independent() {
+        let registry = ScopedRegistry::new();
+        let name = registry.insert_scoped(String::from("svc"));
+        assert!(registry.get::<u32>().is_none());
+        assert!(!registry.contains::<u32>());
+        assert_eq!(
+            registry.get::<String>().as_deref().map(String::as_str),
+            Some("svc")
+        );
+        drop(name);
+        assert!(!registry.contains::<String>());
+    }
+}
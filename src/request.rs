@@ -0,0 +1,128 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A RequestScope carries the overrides for one unit of work, typically a web request,
+/// keyed by type.  Handlers resolve a value with `get_or(&shared)`, which prefers the
+/// request's override; `edit()` copies the shared value on first write, like a ScopeCell.
+/// Everything is dropped with the scope, so nothing outlives the request.  The type is
+/// framework-agnostic and `Send + Sync`, so it can be stored in request extensions.
+#[derive(Default)]
+pub struct RequestScope {
+    overrides: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl RequestScope {
+    // Create a RequestScope with no overrides
+    pub fn new() -> Self {
+        RequestScope::default()
+    }
+
+    // Override the value of type `T` for this request, returning any previous override
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        let previous = self.overrides.insert(TypeId::of::<T>(), Box::new(value))?;
+        Some(*previous.downcast().unwrap())
+    }
+
+    // Builder form of `insert`
+    pub fn with<T: Any + Send + Sync>(mut self, value: T) -> Self {
+        self.insert(value);
+        self
+    }
+
+    // The override of type `T`, if any
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.overrides.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    // Mutable access to the override of type `T`, if any
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.overrides.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    // The override of type `T` if there is one, otherwise the shared value
+    pub fn get_or<'r, T: Any>(&'r self, shared: &'r T) -> &'r T {
+        self.get().unwrap_or(shared)
+    }
+
+    // Mutable access to this request's copy of `T`, cloning `shared` on first use
+    pub fn edit<T: Any + Send + Sync + Clone>(&mut self, shared: &T) -> &mut T {
+        self.overrides
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(shared.clone()))
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // Drop the override of type `T`, returning it
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        let value = self.overrides.remove(&TypeId::of::<T>())?;
+        Some(*value.downcast().unwrap())
+    }
+
+    // Whether a value of type `T` is overridden
+    pub fn contains<T: Any>(&self) -> bool {
+        self.overrides.contains_key(&TypeId::of::<T>())
+    }
+
+    // Number of overridden types
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    // Whether nothing is overridden
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    // Drop every override
+    pub fn clear(&mut self) {
+        self.overrides.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Limits {
+        max_items: usize,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Locale(&'static str);
+
+    #[test]
+    fn test_overrides_by_type() {
+        let shared = Limits { max_items: 10 };
+        let mut request = RequestScope::new().with(Locale("de"));
+        assert_eq!(request.get_or(&shared).max_items, 10);
+        assert_eq!(request.get::<Locale>(), Some(&Locale("de")));
+
+        request.insert(Limits { max_items: 1 });
+        assert_eq!(request.get_or(&shared).max_items, 1);
+        assert_eq!(request.insert(Locale("fr")), Some(Locale("de")));
+        assert_eq!(request.len(), 2);
+        assert_eq!(request.remove::<Limits>(), Some(Limits { max_items: 1 }));
+        assert!(!request.contains::<Limits>());
+    }
+
+    #[test]
+    fn test_edit_copies_on_first_write() {
+        let shared = Limits { max_items: 10 };
+        let mut request = RequestScope::new();
+        request.edit(&shared).max_items += 5;
+        request.edit(&shared).max_items += 5; // Second edit reuses the copy
+        assert_eq!(request.get_or(&shared).max_items, 20);
+        assert_eq!(shared.max_items, 10);
+        request.clear();
+        assert!(request.is_empty());
+    }
+
+    #[test]
+    fn test_moves_across_threads() {
+        let request = RequestScope::new().with(Locale("en"));
+        let handle = std::thread::spawn(move || request.get::<Locale>().map(|l| l.0));
+        assert_eq!(handle.join().unwrap(), Some("en"));
+    }
+}
@@ -0,0 +1,822 @@
+use std::fmt;
+
+use crate::budget::{self, Pricing};
+use crate::observe::Hooks;
+use crate::pending::Pending;
+use crate::{CloneBudget, Diff, HeapSize, Patch, ScopeError};
+
+/// What a ScopeCellMut does with uncommitted changes when it is dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Discard the changes (the default).
+    #[default]
+    Revert,
+    /// Commit the changes on a normal drop, but discard them while unwinding from a panic.
+    RevertOnPanicOnly,
+}
+
+/// A ScopeCellMut allows temporary, scope-bound mutations to a value that is
+/// borrowed mutably.  It behaves like `ScopeCell`, reverting on drop, but can
+/// also `commit()` the modified copy back into the original location.  Like `ScopeCell`,
+/// it stores the pending copy inline.
+pub struct ScopeCellMut<'a, T: Clone> {
+    original_data: &'a mut T,
+    modified_data: Pending<T>, // Holds temporary modified data
+    dirty: bool,               // Set on mutable access, cleared by revert/reset_tracking
+    hooks: Hooks<'a, T>,       // Lifecycle callbacks
+    policy: DropPolicy,        // What to do with uncommitted changes on drop
+    frozen: bool,              // Refuse edits while set
+    poisoned: bool,            // Set when a panic interrupted update()
+    generation: u64,           // Bumped on every mutation, revert or commit
+    budget: Pricing<'a, T>,    // Caps the lazy copy, if set
+}
+
+impl<'a, T: Clone> ScopeCellMut<'a, T> {
+    // Create a new ScopeCellMut from a mutable reference
+    pub fn new(data: &'a mut T) -> Self {
+        ScopeCellMut {
+            original_data: data,
+            modified_data: Pending::empty(),
+            dirty: false,
+            hooks: Hooks::new(),
+            policy: DropPolicy::Revert,
+            frozen: false,
+            poisoned: false,
+            generation: 0,
+            budget: None,
+        }
+    }
+
+    // Create a new ScopeCellMut with the given drop policy
+    pub fn with_policy(data: &'a mut T, policy: DropPolicy) -> Self {
+        let mut cell = ScopeCellMut::new(data);
+        cell.policy = policy;
+        cell
+    }
+
+    // The drop policy currently in effect
+    pub fn policy(&self) -> DropPolicy {
+        self.policy
+    }
+
+    // Change the drop policy
+    pub fn set_policy(&mut self, policy: DropPolicy) {
+        self.policy = policy;
+    }
+
+    // Create a new ScopeCellMut labelled for the lifecycle events it reports
+    pub fn named(data: &'a mut T, label: &'static str) -> Self {
+        let mut cell = ScopeCellMut::new(data);
+        cell.set_label(label);
+        cell
+    }
+
+    // Name the cell in the lifecycle events it reports, such as `tracing` events
+    pub fn set_label(&mut self, label: &'static str) -> &mut Self {
+        self.hooks.set_label(label);
+        self
+    }
+
+    // The cell's label, if it has one
+    pub fn label(&self) -> Option<&'static str> {
+        self.hooks.label()
+    }
+
+    // Charge the lazy copy to `budget`, priced at `size_of::<T>()`
+    pub fn set_budget(&mut self, budget: &'a CloneBudget) -> &mut Self {
+        self.budget = Some((budget, budget::shallow_size));
+        self
+    }
+
+    // Charge the lazy copy to `budget`, priced with the heap memory it owns as well
+    pub fn set_heap_budget(&mut self, budget: &'a CloneBudget) -> &mut Self
+    where
+        T: HeapSize,
+    {
+        self.budget = Some((budget, budget::deep_size));
+        self
+    }
+
+    // Register a callback fired with the fresh copy whenever the modified data is created
+    pub fn on_modify<F: Fn(&T) + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_modify(Box::new(hook));
+        self
+    }
+
+    // Register a callback fired whenever modified data is discarded, by `revert()` or on drop
+    pub fn on_revert<F: Fn() + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_revert(Box::new(hook));
+        self
+    }
+
+    // Register a callback fired with the modified data when it is committed or extracted
+    pub fn on_commit<F: Fn(&T) + Send + 'a>(&mut self, hook: F) -> &mut Self {
+        self.hooks.add_commit(Box::new(hook));
+        self
+    }
+
+    // Register a cleanup action run once the cell is dropped, after the commit or revert is
+    // resolved, with the value left in the original location
+    pub fn on_drop<F: FnOnce(&T) + Send + 'a>(&mut self, action: F) -> &mut Self {
+        self.hooks.add_drop(Box::new(action));
+        self
+    }
+
+    // Run a closure against a new ScopeCellMut over `data`, committing the changes if the
+    // closure returns `Ok` and reverting them if it returns `Err`.
+    pub fn scope<R, E, F>(data: &'a mut T, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut ScopeCellMut<'a, T>) -> Result<R, E>,
+    {
+        let mut cell = ScopeCellMut::new(data);
+        let result = f(&mut cell)?;
+        cell.commit();
+        Ok(result)
+    }
+
+    // Write the modified data back into the original location, if it exists
+    // Panics if the cell is poisoned.
+    pub fn commit(mut self) {
+        if self.poisoned {
+            panic!("ScopeCellMut poisoned");
+        }
+        self.write_back();
+    }
+
+    // Commit like `commit()`, or fail if the cell is poisoned; the changes are then reverted
+    pub fn try_commit(self) -> Result<(), ScopeError> {
+        if self.poisoned {
+            return Err(ScopeError::Poisoned);
+        }
+        self.commit();
+        Ok(())
+    }
+
+    // The original data, ignoring any modification
+    #[cfg(feature = "json")]
+    pub(crate) fn original(&self) -> &T {
+        self.original_data
+    }
+
+    // Write the modified data back without consuming the cell, leaving it unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            self.hooks.committed(&modified);
+            *self.original_data = modified;
+        }
+        self.dirty = false;
+        self.generation += 1;
+    }
+
+    // Consume the ScopeCellMut and return the inner modified data if it exists, otherwise return the original data
+    pub fn into_inner(mut self) -> T {
+        let value = if let Some(modified) = self.modified_data.take() {
+            modified
+        } else {
+            self.original_data.clone()
+        };
+        self.hooks.committed(&value);
+        value
+    }
+
+    // Consume the ScopeCellMut and transform its effective value, leaving the original untouched
+    pub fn map_into<U, F: FnOnce(T) -> U>(self, f: F) -> U {
+        f(self.into_inner())
+    }
+
+    // Revert the changes made to the data by dropping the modified data
+    pub fn revert(&mut self) {
+        if self.modified_data.take().is_some() {
+            self.hooks.reverted();
+        }
+        self.dirty = false;
+        self.generation += 1;
+    }
+
+    // Whether the data has been mutably accessed since creation, the last revert, or the last reset_tracking
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Whether a pending copy exists and lives entirely in the cell's inline slot, owning no
+    // heap memory
+    pub fn is_inline(&self) -> bool
+    where
+        T: HeapSize,
+    {
+        let modified = self.modified_data.as_ref();
+        modified.is_some_and(|value| value.heap_size() == 0)
+    }
+
+    // Clear the dirty flag without discarding the modified data
+    pub fn reset_tracking(&mut self) {
+        self.dirty = false;
+    }
+
+    // Counter bumped on every mutable access, revert and commit.  Compare it with an
+    // earlier value to detect staleness without comparing the data itself.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    // Whether the cell may have changed since `generation` was read
+    pub fn changed_since(&self, generation: u64) -> bool {
+        self.generation != generation
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    // Panics if the cell is poisoned.
+    pub fn get(&self) -> &T {
+        match self.try_get() {
+            Ok(value) => value,
+            Err(_) => panic!("ScopeCellMut poisoned"),
+        }
+    }
+
+    // Borrow the data like `get()`, or fail if the cell is poisoned
+    pub fn try_get(&self) -> Result<&T, ScopeError> {
+        if self.poisoned {
+            return Err(ScopeError::Poisoned);
+        }
+        Ok(match self.modified_data.as_ref() {
+            Some(modified) => modified,
+            None => self.original_data,
+        })
+    }
+
+    // Mutably borrow the data, creating a temporary mutable copy if necessary.
+    // Panics if the cell is frozen or poisoned.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.assert_writable();
+        if self.modified_data.is_none() {
+            if budget::charge(&self.budget, self.original_data).is_err() {
+                panic!("ScopeCellMut clone budget exceeded");
+            }
+            // If no modification exists, clone the original data
+            let copy = self.modified_data.insert(self.original_data.clone());
+            self.hooks.modified(copy);
+        }
+        self.dirty = true;
+        self.generation += 1;
+
+        self.modified_data.as_mut().unwrap()
+    }
+
+    // Mutably borrow the data like `get_mut()`, or fail if the cell is frozen, poisoned or
+    // its copy would exceed its clone budget
+    pub fn try_get_mut(&mut self) -> Result<&mut T, ScopeError> {
+        self.check_writable()?;
+        if self.modified_data.is_none() {
+            budget::charge(&self.budget, self.original_data)?;
+            let copy = self.modified_data.insert(self.original_data.clone());
+            self.hooks.modified(copy);
+        }
+        Ok(self.get_mut())
+    }
+
+    // Overwrite the effective value like `set()`, or fail if the cell is frozen or poisoned
+    pub fn try_set(&mut self, value: T) -> Result<(), ScopeError> {
+        self.check_writable()?;
+        self.set(value);
+        Ok(())
+    }
+
+    // Make the cell read-only: edits panic, and the `try_` methods return
+    // `ScopeError::Frozen`, until `unfreeze()` is called.  Reverting and committing
+    // already-made changes are still allowed.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    // Allow edits again
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
+    // Whether the cell is currently read-only
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // Whether a panic interrupted a mutation through `update()`.  A poisoned cell refuses
+    // access, and is reverted rather than committed on drop, until `clear_poison()`.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    // Accept the possibly half-updated value (or `revert()` first) and allow access again
+    pub fn clear_poison(&mut self) {
+        self.poisoned = false;
+    }
+
+    fn check_writable(&self) -> Result<(), ScopeError> {
+        if self.poisoned {
+            Err(ScopeError::Poisoned)
+        } else if self.frozen {
+            Err(ScopeError::Frozen)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn assert_writable(&self) {
+        match self.check_writable() {
+            Ok(()) => {}
+            Err(ScopeError::Poisoned) => panic!("ScopeCellMut poisoned"),
+            Err(_) => panic!("ScopeCellMut is frozen"),
+        }
+    }
+
+    // Apply a closure to the modified data, cloning the original first if necessary.
+    // If the closure panics, the cell is poisoned.
+    pub fn update<R, F: FnOnce(&mut T) -> R>(&mut self, f: F) -> R {
+        self.get_mut();
+        self.poisoned = true; // Cleared again unless `f` unwinds
+        let result = f(self.modified_data.as_mut().unwrap());
+        self.poisoned = false;
+        result
+    }
+
+    // Overwrite the effective value without cloning the original first
+    pub fn set(&mut self, value: T) {
+        self.replace_modified(value);
+    }
+
+    // Overwrite the effective value, returning the previous one (a clone of the original if
+    // the cell was untouched)
+    pub fn replace(&mut self, value: T) -> T {
+        self.replace_modified(value)
+            .unwrap_or_else(|| self.original_data.clone())
+    }
+
+    // Store `value` as the modified data, returning the previous modified data if any
+    fn replace_modified(&mut self, value: T) -> Option<T> {
+        self.assert_writable();
+        self.dirty = true;
+        self.generation += 1;
+        let previous = self.modified_data.take();
+        let installed = self.modified_data.insert(value);
+        if previous.is_none() {
+            self.hooks.modified(installed);
+        }
+        previous
+    }
+
+    // The original and modified values, if a modified copy exists and differs from the original
+    pub fn diff(&self) -> Option<(&T, &T)>
+    where
+        T: PartialEq,
+    {
+        let modified = self.modified_data.as_ref()?;
+        if modified == &*self.original_data {
+            None
+        } else {
+            Some((&*self.original_data, modified))
+        }
+    }
+
+    // Structured description of how the effective value differs from the original
+    pub fn changes(&self) -> Vec<T::Change>
+    where
+        T: Diff,
+    {
+        match self.modified_data.as_ref() {
+            Some(modified) => modified.diff_from(&*self.original_data),
+            None => Vec::new(),
+        }
+    }
+
+    // Overwrite `other` with the modified copy, if there is one.  Returns whether anything
+    // was written.
+    pub fn apply_changes_to(&self, other: &mut T) -> bool {
+        match self.modified_data.as_ref() {
+            Some(modified) => {
+                other.clone_from(modified);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Replay the structured changes onto `other`, leaving parts of it the edit never touched
+    // alone.  Returns whether there was anything to apply.
+    pub fn apply_diff_to(&self, other: &mut T) -> bool
+    where
+        T: Patch,
+    {
+        let changes = self.changes();
+        let changed = !changes.is_empty();
+        other.apply_changes(changes);
+        changed
+    }
+
+    // Open a cell over a single field of this cell's modified copy, creating the copy first.
+    // Committing the projection writes the field into the copy, never the original data, so
+    // this cell's `revert()` or drop still undoes it.
+    pub fn project<F, P>(&mut self, project: P) -> ScopeCellMut<'_, F>
+    where
+        F: Clone,
+        P: FnOnce(&mut T) -> &mut F,
+    {
+        ScopeCellMut::new(project(self.get_mut()))
+    }
+
+    // Run a fallible update against a copy of the effective value, keeping it on `Ok`
+    // and leaving the cell untouched on `Err`
+    pub fn try_update<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut scratch = self.get().clone();
+        let result = f(&mut scratch)?;
+        self.set(scratch);
+        Ok(result)
+    }
+}
+
+// When the ScopeCellMut is dropped without committing, changes are discarded unless the
+// drop policy says to commit them.
+impl<'a, T: Clone> Drop for ScopeCellMut<'a, T> {
+    fn drop(&mut self) {
+        match self.policy {
+            DropPolicy::RevertOnPanicOnly if !std::thread::panicking() && !self.poisoned => {
+                self.write_back()
+            }
+            _ => self.revert(),
+        }
+        self.hooks.dropped(self.original_data);
+    }
+}
+
+// Shows the baseline, the pending copy (if any) and the dirty flag
+impl<'a, T: Clone + fmt::Debug> fmt::Debug for ScopeCellMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeCellMut")
+            .field("original", &*self.original_data)
+            .field("pending", &self.modified_data)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_commit_writes_back() {
+        let mut data = vec![1, 2, 3];
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope.get_mut().push(4);
+            scope.commit(); // Persist the modification
+        }
+        assert_eq!(data, vec![1, 2, 3, 4]); // Original location should be updated
+    }
+
+    #[test]
+    fn test_drop_reverts_without_commit() {
+        let mut data = vec![1, 2, 3];
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope.get_mut().push(4);
+            assert_eq!(*scope.get(), vec![1, 2, 3, 4]);
+        } // Dropped without commit
+        assert_eq!(data, vec![1, 2, 3]); // Original data should be unchanged
+    }
+
+    #[test]
+    fn test_commit_without_mutation() {
+        let mut data = String::from("hello");
+        {
+            let scope = ScopeCellMut::new(&mut data);
+            scope.commit(); // Nothing to write back
+        }
+        assert_eq!(data, "hello");
+    }
+
+    #[test]
+    fn test_revert_then_commit() {
+        let mut data = 10;
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            *scope.get_mut() = 20;
+            scope.revert(); // Discard the first change
+            scope.commit();
+        }
+        assert_eq!(data, 10);
+    }
+
+    #[test]
+    fn test_is_modified_and_reset_tracking() {
+        let mut data = 10;
+        let mut scope = ScopeCellMut::new(&mut data);
+        assert!(!scope.is_modified());
+        *scope.get_mut() = 20;
+        assert!(scope.is_modified());
+        scope.reset_tracking();
+        assert!(!scope.is_modified());
+        assert_eq!(*scope.get(), 20); // Modified data is kept
+    }
+
+    #[test]
+    fn test_generation_bumps_on_commit() {
+        let mut data = 0;
+        let mut scope = ScopeCellMut::new(&mut data);
+        let start = scope.generation();
+        scope.set(1);
+        let edited = scope.generation();
+        assert!(scope.changed_since(start));
+        scope.write_back();
+        assert!(scope.changed_since(edited));
+        let committed = scope.generation();
+        let _ = scope.get();
+        assert!(!scope.changed_since(committed));
+    }
+
+    #[test]
+    fn test_debug_output() {
+        let mut data = 1;
+        let mut scope = ScopeCellMut::new(&mut data);
+        scope.set(2);
+        assert_eq!(
+            format!("{:?}", scope),
+            "ScopeCellMut { original: 1, pending: Some(2), dirty: true }"
+        );
+    }
+
+    #[test]
+    fn test_poisoned_cell_is_not_committed_on_drop() {
+        let mut data = vec![1];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut scope = ScopeCellMut::with_policy(&mut data, DropPolicy::RevertOnPanicOnly);
+            let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                scope.update(|v| {
+                    v.push(2);
+                    panic!("half-way");
+                })
+            }));
+            assert!(caught.is_err());
+            assert!(scope.is_poisoned());
+            assert_eq!(scope.try_get().err(), Some(ScopeError::Poisoned));
+            assert!(scope.try_get_mut().is_err());
+        }));
+        assert!(result.is_ok());
+        assert_eq!(data, vec![1]); // Dropped while poisoned, so reverted
+    }
+
+    #[test]
+    fn test_clear_poison_then_try_commit() {
+        let mut data = 1;
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            let caught = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                scope.update(|v| {
+                    *v = 2;
+                    panic!("late failure");
+                })
+            }));
+            assert!(caught.is_err());
+            scope.clear_poison();
+            assert_eq!(scope.try_commit(), Ok(()));
+        }
+        assert_eq!(data, 2);
+    }
+
+    #[test]
+    fn test_frozen_cell_still_commits() {
+        let mut data = 1;
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            *scope.get_mut() = 2;
+            scope.freeze();
+            assert_eq!(scope.try_set(3), Err(ScopeError::Frozen));
+            assert!(scope.try_get_mut().is_err());
+            scope.commit(); // Committing earlier edits is allowed
+        }
+        assert_eq!(data, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ScopeCellMut is frozen")]
+    fn test_frozen_get_mut_panics() {
+        let mut data = vec![1];
+        let mut scope = ScopeCellMut::new(&mut data);
+        scope.freeze();
+        scope.get_mut().push(2);
+    }
+
+    #[test]
+    fn test_update_then_map_into() {
+        let mut data = String::from("draft");
+        let mut scope = ScopeCellMut::new(&mut data);
+        scope.update(|s| s.push_str(" v2"));
+        let shout = scope.map_into(|s| s.to_uppercase());
+        assert_eq!(shout, "DRAFT V2");
+        assert_eq!(data, "draft"); // map_into does not commit
+    }
+
+    #[test]
+    fn test_set_and_replace() {
+        let mut data = vec![1];
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            assert_eq!(scope.replace(vec![2]), vec![1]);
+            scope.set(vec![3]);
+            assert!(scope.is_modified());
+            scope.commit();
+        }
+        assert_eq!(data, vec![3]);
+    }
+
+    #[test]
+    fn test_try_update() {
+        let mut data = vec![1, 2, 3];
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope
+                .try_update(|v| {
+                    v.push(4);
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+            let failed: Result<(), &str> = scope.try_update(|v| {
+                v.clear();
+                Err("rejected")
+            });
+            assert!(failed.is_err());
+            scope.commit();
+        }
+        assert_eq!(data, vec![1, 2, 3, 4]); // Only the successful update was kept
+    }
+
+    #[test]
+    fn test_project_commits_field() {
+        let mut data = (String::from("name"), vec![0u8; 1024]);
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            let mut name = scope.project(|d| &mut d.0);
+            name.get_mut().push_str("-edited");
+            name.commit();
+            assert!(scope.is_modified());
+            scope.commit();
+        }
+        assert_eq!(data.0, "name-edited");
+    }
+
+    #[test]
+    fn test_project_commit_is_undone_by_parent_revert() {
+        let mut data = (String::from("name"), 1);
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            let mut name = scope.project(|d| &mut d.0);
+            name.get_mut().push_str("-edited");
+            name.commit();
+            assert_eq!(scope.get().0, "name-edited");
+            scope.revert();
+            assert_eq!(scope.get().0, "name");
+        }
+        assert_eq!(data, (String::from("name"), 1));
+    }
+
+    #[test]
+    fn test_project_into_modified_copy() {
+        let mut data = (1, 2);
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope.get_mut().0 = 10;
+            let mut second = scope.project(|d| &mut d.1);
+            *second.get_mut() = 20;
+            second.commit();
+            assert_eq!(*scope.get(), (10, 20));
+        } // Parent dropped without commit: both edits go away
+        assert_eq!(data, (1, 2));
+    }
+
+    #[test]
+    fn test_lifecycle_hooks() {
+        use std::sync::Mutex;
+
+        let events = Mutex::new(Vec::new());
+        let mut data = 1;
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope
+                .on_modify(|v| events.lock().unwrap().push(format!("modify {}", v)))
+                .on_revert(|| events.lock().unwrap().push(String::from("revert")))
+                .on_commit(|v| events.lock().unwrap().push(format!("commit {}", v)));
+            *scope.get_mut() = 2;
+            scope.revert();
+            *scope.get_mut() = 3;
+            scope.commit();
+        }
+        assert_eq!(data, 3);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["modify 1", "revert", "modify 1", "commit 3"]
+        );
+    }
+
+    #[test]
+    fn test_diff_and_changes() {
+        let mut data = String::from("hello");
+        let mut scope = ScopeCellMut::new(&mut data);
+        assert_eq!(scope.diff(), None);
+        scope.get_mut().push_str(" world");
+        assert_eq!(
+            scope.diff(),
+            Some((&String::from("hello"), &String::from("hello world")))
+        );
+        assert_eq!(
+            scope.changes(),
+            vec![crate::TextChange::Inserted {
+                offset: 5,
+                text: String::from(" world"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_apply_diff_to_other_map() {
+        let mut staging: BTreeMap<&str, u32> = [("port", 80), ("workers", 4)].into_iter().collect();
+        let mut live = staging.clone();
+        live.insert("uptime", 99);
+        {
+            let mut scope = ScopeCellMut::new(&mut staging);
+            scope.get_mut().insert("port", 8080);
+            assert!(scope.apply_diff_to(&mut live));
+        } // Preview discarded
+        assert_eq!(staging["port"], 80);
+        assert_eq!(live["port"], 8080);
+        assert_eq!(live["uptime"], 99); // Keys the edit never touched survive
+    }
+
+    #[test]
+    fn test_revert_on_panic_only_commits_on_drop() {
+        let mut data = vec![1, 2, 3];
+        {
+            let mut scope = ScopeCellMut::with_policy(&mut data, DropPolicy::RevertOnPanicOnly);
+            scope.get_mut().push(4);
+        } // Normal drop commits
+        assert_eq!(data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_revert_on_panic_only_reverts_on_unwind() {
+        let mut data = vec![1, 2, 3];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut scope = ScopeCellMut::with_policy(&mut data, DropPolicy::RevertOnPanicOnly);
+            scope.get_mut().push(4);
+            panic!("edit failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(data, vec![1, 2, 3]); // Unwinding reverted the change
+    }
+
+    #[test]
+    fn test_set_policy() {
+        let mut data = 1;
+        {
+            let mut scope = ScopeCellMut::new(&mut data);
+            assert_eq!(scope.policy(), DropPolicy::Revert);
+            *scope.get_mut() = 2;
+            scope.set_policy(DropPolicy::RevertOnPanicOnly);
+        }
+        assert_eq!(data, 2);
+    }
+
+    #[test]
+    fn test_scope_commits_on_ok() {
+        let mut data = vec![1, 2, 3];
+        let result = ScopeCellMut::scope(&mut data, |cell| {
+            cell.get_mut().push(4);
+            Ok::<_, ()>(cell.get().len())
+        });
+        assert_eq!(result, Ok(4));
+        assert_eq!(data, vec![1, 2, 3, 4]); // Committed
+    }
+
+    #[test]
+    fn test_scope_reverts_on_err() {
+        let mut data = vec![1, 2, 3];
+        let result: Result<(), &str> = ScopeCellMut::scope(&mut data, |cell| {
+            cell.get_mut().push(4);
+            Err("rejected")
+        });
+        assert_eq!(result, Err("rejected"));
+        assert_eq!(data, vec![1, 2, 3]); // Reverted
+    }
+
+    #[test]
+    fn test_into_inner_leaves_original() {
+        let mut data = vec![1, 2, 3];
+        let inner = {
+            let mut scope = ScopeCellMut::new(&mut data);
+            scope.get_mut().push(4);
+            scope.into_inner()
+        };
+        assert_eq!(inner, vec![1, 2, 3, 4]);
+        assert_eq!(data, vec![1, 2, 3]); // into_inner does not write back
+    }
+}
@@ -0,0 +1,114 @@
+use std::borrow::{Borrow, Cow};
+
+/// A ScopeCow makes scoped edits to a `Cow` without materializing it up front.  The first
+/// write copies the effective value into its owned form; `revert()`, or dropping the
+/// ScopeCow, leaves the `Cow` exactly as it was, still borrowed if it was borrowed.
+/// `commit()` stores the edit as `Cow::Owned`, and leaves an untouched `Cow` alone, so a
+/// borrowed baseline is only upgraded to owned when it actually changed.
+pub struct ScopeCow<'c, 'b, B: ?Sized + ToOwned> {
+    original_data: &'c mut Cow<'b, B>,
+    modified_data: Option<B::Owned>, // Holds the temporary owned copy
+}
+
+impl<'c, 'b, B: ?Sized + ToOwned> ScopeCow<'c, 'b, B> {
+    // Create a new ScopeCow over a mutable reference to a Cow
+    pub fn new(data: &'c mut Cow<'b, B>) -> Self {
+        ScopeCow {
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &B {
+        match self.modified_data {
+            Some(ref modified) => modified.borrow(),
+            None => self.original_data,
+        }
+    }
+
+    // Mutably borrow the owned copy, creating it from the original if necessary
+    pub fn get_mut(&mut self) -> &mut B::Owned {
+        let original = &**self.original_data;
+        self.modified_data
+            .get_or_insert_with(|| original.to_owned())
+    }
+
+    // Overwrite the effective value without copying the original first
+    pub fn set(&mut self, value: B::Owned) {
+        self.modified_data = Some(value);
+    }
+
+    // The baseline, ignoring any modification
+    pub fn original(&self) -> &B {
+        self.original_data
+    }
+
+    // Whether the baseline Cow is borrowed rather than owned
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.original_data, Cow::Borrowed(_))
+    }
+
+    // Whether an owned copy has been created since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes made to the data by dropping the owned copy
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Store the modified copy in the Cow as `Cow::Owned`, if there is one
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Store the modified copy in place, leaving the ScopeCow unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            *self.original_data = Cow::Owned(modified);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_keeps_the_borrow() {
+        let source = String::from("let x = 1;");
+        let mut token: Cow<str> = Cow::Borrowed(&source[4..5]);
+        {
+            let mut scope = ScopeCow::new(&mut token);
+            assert!(scope.is_borrowed());
+            scope.get_mut().push_str("_renamed");
+            assert_eq!(scope.get(), "x_renamed");
+            assert_eq!(scope.original(), "x");
+        } // Dropped without commit
+        assert!(matches!(token, Cow::Borrowed("x")));
+        let mut scope = ScopeCow::new(&mut token);
+        scope.get_mut().clear();
+        scope.revert();
+        assert!(!scope.is_modified());
+        scope.commit(); // Nothing to commit, so the Cow stays borrowed
+        assert!(matches!(token, Cow::Borrowed("x")));
+    }
+
+    #[test]
+    fn test_commit_upgrades_to_owned() {
+        let words = ["a", "b"];
+        let mut list: Cow<[&str]> = Cow::Borrowed(&words);
+        let mut scope = ScopeCow::new(&mut list);
+        scope.get_mut().push("c");
+        scope.commit();
+        assert!(matches!(list, Cow::Owned(ref owned) if owned == &["a", "b", "c"]));
+        let mut scope = ScopeCow::new(&mut list);
+        assert!(!scope.is_borrowed());
+        scope.set(vec!["d"]);
+        scope.commit();
+        assert_eq!(&*list, ["d"]);
+        assert_eq!(words, ["a", "b"]);
+    }
+}
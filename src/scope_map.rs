@@ -0,0 +1,268 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::alloc::{Global, ScratchAlloc};
+
+/// A ScopeHashMap allows per-entry, scope-bound edits to a map.  Reads fall through to the
+/// original map, while writes go to an overlay of inserted, updated and removed keys, so
+/// only touched entries are ever cloned.  Changes are discarded on drop unless `commit()`
+/// applies them to the original map.  The overlay is allocated in the map's own allocator.
+pub struct ScopeHashMap<'a, K, V, A: ScratchAlloc = Global> {
+    original_data: &'a mut A::Map<K, V>,
+    overlay: A::Map<K, Option<V>>, // `None` marks a removed key
+}
+
+impl<'a, K: Clone + Eq + Hash, V: Clone> ScopeHashMap<'a, K, V> {
+    // Create a new ScopeHashMap over a mutable reference
+    pub fn new(data: &'a mut HashMap<K, V>) -> Self {
+        ScopeHashMap::over(data)
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl<'a, K, V, A> ScopeHashMap<'a, K, V, A>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    A: crate::alloc::Allocator + Clone,
+{
+    // Create a new ScopeHashMap over a map in a custom allocator, keeping the overlay there
+    pub fn new_in(
+        data: &'a mut hashbrown::HashMap<K, V, hashbrown::DefaultHashBuilder, A>,
+    ) -> Self {
+        ScopeHashMap::over(data)
+    }
+}
+
+impl<'a, K: Clone + Eq + Hash, V: Clone, A: ScratchAlloc> ScopeHashMap<'a, K, V, A> {
+    fn over(data: &'a mut A::Map<K, V>) -> Self {
+        ScopeHashMap {
+            overlay: A::map_allocator(data).new_map(),
+            original_data: data,
+        }
+    }
+
+    // Look up a key in the merged view
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match A::map_get_key_value(&self.overlay, key) {
+            Some((_, entry)) => entry.as_ref(),
+            None => A::map_get_key_value(self.original_data, key).map(|(_, value)| value),
+        }
+    }
+
+    // Mutably borrow a value, cloning only that entry if necessary
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if A::map_get_key_value(&self.overlay, key).is_none() {
+            let (key, value) = A::map_get_key_value(self.original_data, key)?;
+            A::map_insert(&mut self.overlay, key.clone(), Some(value.clone()));
+        }
+        A::map_get_mut(&mut self.overlay, key)?.as_mut()
+    }
+
+    // Whether the merged view contains `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    // Insert or update an entry, returning the previous value of the merged view
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let previous = A::map_insert(&mut self.overlay, key.clone(), Some(value));
+        match previous {
+            Some(previous) => previous,
+            None => A::map_get_key_value(self.original_data, &key).map(|(_, value)| value.clone()),
+        }
+    }
+
+    // Remove an entry, returning its value in the merged view
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match A::map_get_mut(&mut self.overlay, key) {
+            Some(entry) => entry.take(),
+            None => {
+                let (key, value) = A::map_get_key_value(self.original_data, key)?;
+                let value = value.clone();
+                A::map_insert(&mut self.overlay, key.clone(), None);
+                Some(value)
+            }
+        }
+    }
+
+    // Number of entries in the merged view
+    pub fn len(&self) -> usize {
+        let mut len = A::map_len(self.original_data);
+        for (key, entry) in A::map_iter(&self.overlay) {
+            let original = A::map_get_key_value(self.original_data, key).is_some();
+            match (entry.is_some(), original) {
+                (true, false) => len += 1,
+                (false, true) => len -= 1,
+                _ => {}
+            }
+        }
+        len
+    }
+
+    // Whether the merged view is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Iterate over the merged view, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let originals = A::map_iter(self.original_data)
+            .filter(move |(key, _)| A::map_get_key_value(&self.overlay, *key).is_none());
+        let overlaid = A::map_iter(&self.overlay)
+            .filter_map(|(key, entry)| entry.as_ref().map(|value| (key, value)));
+        originals.chain(overlaid)
+    }
+
+    // Iterate over the keys of the merged view
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    // Whether any entry has been inserted, updated or removed
+    pub fn is_modified(&self) -> bool {
+        A::map_len(&self.overlay) != 0
+    }
+
+    // Discard every edit
+    pub fn revert(&mut self) {
+        self.take_overlay();
+    }
+
+    // Materialize the merged view as a new map
+    pub fn to_map(&self) -> HashMap<K, V> {
+        self.iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // Apply the overlay to the original map
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Replace the merged view with `value`, overlaying only the entries that differ
+    pub(crate) fn replace_all(&mut self, mut value: HashMap<K, V>)
+    where
+        V: PartialEq,
+    {
+        self.revert();
+        for (key, original) in A::map_iter(self.original_data) {
+            match value.remove(key) {
+                Some(entry) if entry == *original => {}
+                entry => {
+                    A::map_insert(&mut self.overlay, key.clone(), entry);
+                }
+            }
+        }
+        for (key, entry) in value {
+            A::map_insert(&mut self.overlay, key, Some(entry));
+        }
+    }
+
+    // Apply the overlay in place, leaving the ScopeHashMap unmodified
+    pub(crate) fn write_back(&mut self) {
+        for (key, entry) in self.take_overlay() {
+            match entry {
+                Some(value) => {
+                    A::map_insert(self.original_data, key, value);
+                }
+                None => {
+                    A::map_remove(self.original_data, &key);
+                }
+            }
+        }
+    }
+
+    // Empty the overlay, returning what it held
+    fn take_overlay(&mut self) -> A::Map<K, Option<V>> {
+        let empty = A::map_allocator(&self.overlay).new_map();
+        std::mem::replace(&mut self.overlay, empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> HashMap<String, i32> {
+        [("a", 1), ("b", 2), ("c", 3)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect()
+    }
+
+    #[test]
+    fn test_overlay_reads_and_writes() {
+        let mut data = map();
+        {
+            let mut scope = ScopeHashMap::new(&mut data);
+            assert_eq!(scope.insert("d".to_string(), 4), None);
+            assert_eq!(scope.insert("a".to_string(), 10), Some(1));
+            assert_eq!(scope.remove("b"), Some(2));
+            assert_eq!(scope.remove("b"), None);
+            *scope.get_mut("c").unwrap() += 30;
+
+            assert_eq!(scope.get("a"), Some(&10));
+            assert!(!scope.contains_key("b"));
+            assert_eq!(scope.get("c"), Some(&33));
+            assert_eq!(scope.len(), 3);
+            assert!(scope.is_modified());
+        } // Dropped without commit
+        assert_eq!(data, map());
+    }
+
+    #[test]
+    fn test_commit() {
+        let mut data = map();
+        {
+            let mut scope = ScopeHashMap::new(&mut data);
+            scope.insert("d".to_string(), 4);
+            scope.remove("a");
+            scope.commit();
+        }
+        let mut keys: Vec<_> = data.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_merged_iteration_and_revert() {
+        let mut data = map();
+        let mut scope = ScopeHashMap::new(&mut data);
+        scope.insert("b".to_string(), 20);
+        scope.remove("c");
+        scope.insert("c".to_string(), 30); // Re-insert after removal
+        let mut entries: Vec<_> = scope.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("a".to_string(), 1),
+                ("b".to_string(), 20),
+                ("c".to_string(), 30)
+            ]
+        );
+        assert_eq!(scope.to_map().len(), 3);
+        scope.revert();
+        assert!(!scope.is_modified());
+        assert_eq!(scope.get("b"), Some(&2));
+    }
+}
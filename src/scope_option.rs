@@ -0,0 +1,79 @@
+use crate::SwapScopeCell;
+
+/// A ScopeOption temporarily installs a value into, or takes it out of, an `Option`, as
+/// when installing a handler or callback for a scope.  `insert` puts a value in and `take`
+/// empties the slot; either way, dropping the ScopeOption puts the previous contents back,
+/// and `commit()` keeps the current ones.
+pub struct ScopeOption<'a, T> {
+    slot: SwapScopeCell<'a, Option<T>>,
+}
+
+impl<'a, T> ScopeOption<'a, T> {
+    // Put `value` into the slot until the ScopeOption is dropped
+    pub fn insert(slot: &'a mut Option<T>, value: T) -> Self {
+        ScopeOption {
+            slot: SwapScopeCell::new(slot, Some(value)),
+        }
+    }
+
+    // Empty the slot until the ScopeOption is dropped
+    pub fn take(slot: &'a mut Option<T>) -> Self {
+        ScopeOption {
+            slot: SwapScopeCell::new(slot, None),
+        }
+    }
+
+    // The slot's current contents
+    pub fn get(&self) -> Option<&T> {
+        self.slot.get().as_ref()
+    }
+
+    // Mutable access to the slot's current contents
+    pub fn get_mut(&mut self) -> &mut Option<T> {
+        self.slot.get_mut()
+    }
+
+    // The contents put back on drop
+    pub fn previous(&self) -> Option<&T> {
+        self.slot.original().as_ref()
+    }
+
+    // Keep the current contents and return the previous ones
+    pub fn commit(self) -> Option<T> {
+        self.slot.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Handler = Box<dyn Fn(u32) -> u32>;
+
+    #[test]
+    fn test_scoped_insert_reverts_to_none() {
+        let mut handler: Option<Handler> = None;
+        {
+            let installed = ScopeOption::insert(&mut handler, Box::new(|x| x * 2));
+            assert_eq!(installed.get().map(|h| h(4)), Some(8));
+            assert!(installed.previous().is_none());
+        }
+        assert!(handler.is_none());
+    }
+
+    #[test]
+    fn test_scoped_take_restores_value() {
+        let mut callback = Some(String::from("on_click"));
+        {
+            let mut taken = ScopeOption::take(&mut callback);
+            assert!(taken.get().is_none());
+            assert_eq!(taken.previous().map(String::as_str), Some("on_click"));
+            *taken.get_mut() = Some(String::from("temporary"));
+        }
+        assert_eq!(callback.as_deref(), Some("on_click"));
+
+        let previous = ScopeOption::insert(&mut callback, String::from("kept")).commit();
+        assert_eq!(previous.as_deref(), Some("on_click"));
+        assert_eq!(callback.as_deref(), Some("kept"));
+    }
+}
@@ -0,0 +1,142 @@
+use std::ops::{Bound, Range, RangeBounds};
+
+/// A ScopeSlice allows scope-bound edits to one range of a large slice.  Only the elements
+/// in the range are cloned, and only on the first mutable access; `commit()` splices the
+/// edited window back into place.  Changes are discarded on drop otherwise.
+pub struct ScopeSlice<'a, T: Clone> {
+    original_data: &'a mut [T],
+    start: usize,                  // Offset of the window within the original slice
+    len: usize,                    // Length of the window
+    modified_data: Option<Vec<T>>, // Holds the temporary copy of the window
+}
+
+impl<'a, T: Clone> ScopeSlice<'a, T> {
+    // Create a new ScopeSlice over `range` of `data`.  Panics if the range is out of bounds,
+    // like slice indexing.
+    pub fn range<R: RangeBounds<usize>>(data: &'a mut [T], range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => data.len(),
+        };
+        let len = data[start..end].len();
+        ScopeSlice {
+            original_data: data,
+            start,
+            len,
+            modified_data: None,
+        }
+    }
+
+    // The range of the original slice this window covers
+    pub fn bounds(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    // Number of elements in the window
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // Whether the window is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Borrow the window, showing either the original or the modified elements
+    pub fn get(&self) -> &[T] {
+        match self.modified_data {
+            Some(ref modified) => modified,
+            None => &self.original_data[self.bounds()],
+        }
+    }
+
+    // Mutably borrow the window, cloning only its elements if necessary
+    pub fn get_mut(&mut self) -> &mut [T] {
+        let range = self.bounds();
+        let original = &*self.original_data;
+        self.modified_data
+            .get_or_insert_with(|| original[range].to_vec())
+    }
+
+    // Whether the window has been mutably accessed since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Discard the edited window
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Materialize the edited window as a new vector
+    pub fn to_vec(&self) -> Vec<T> {
+        self.get().to_vec()
+    }
+
+    // Splice the edited window back into the original slice
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Splice the edited window back in place, leaving the ScopeSlice unmodified
+    pub(crate) fn write_back(&mut self) {
+        if let Some(modified) = self.modified_data.take() {
+            let range = self.bounds();
+            for (slot, value) in self.original_data[range].iter_mut().zip(modified) {
+                *slot = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_range_of_large_buffer() {
+        let mut samples = vec![0.0f32; 1 << 20];
+        {
+            let mut window = ScopeSlice::range(&mut samples, 1000..1064);
+            assert_eq!(window.len(), 64);
+            for sample in window.get_mut() {
+                *sample = 0.5;
+            }
+            assert_eq!(window.get()[0], 0.5);
+        } // Dropped without commit
+        assert!(samples.iter().all(|&s| s == 0.0));
+
+        let mut window = ScopeSlice::range(&mut samples, 1000..1064);
+        window.get_mut()[63] = 1.0;
+        window.commit();
+        assert_eq!(samples[1063], 1.0);
+        assert_eq!(samples[1064], 0.0); // Outside the window
+    }
+
+    #[test]
+    fn test_bounds_and_revert() {
+        let mut data = vec![1, 2, 3, 4, 5];
+        let mut window = ScopeSlice::range(&mut data, 2..);
+        assert_eq!(window.bounds(), 2..5);
+        assert_eq!(window.get(), &[3, 4, 5]);
+        window.get_mut().reverse();
+        assert_eq!(window.to_vec(), vec![5, 4, 3]);
+        window.revert();
+        assert!(!window.is_modified());
+        assert_eq!(window.get(), &[3, 4, 5]);
+        assert_eq!(ScopeSlice::range(&mut data, ..=0).len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_bounds_range_panics() {
+        let mut data = vec![1, 2, 3];
+        ScopeSlice::range(&mut data, 2..5);
+    }
+}
@@ -0,0 +1,261 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Source {
+    Original,
+    Added,
+}
+
+// A byte range of either the original text or the added buffer.
+#[derive(Clone, Copy, Debug)]
+struct Piece {
+    source: Source,
+    start: usize,
+    len: usize,
+}
+
+/// A ScopeString allows scope-bound edits to a large string without cloning it.  Edits are
+/// recorded in a piece table over the original text and an append-only buffer of inserted
+/// text; the merged view can be read segment by segment and is only materialized by
+/// `to_string()` or `into_inner()`.  All offsets are byte offsets and must lie on `char`
+/// boundaries, as with `String::insert_str`.
+pub struct ScopeString<'a> {
+    original_data: &'a str,
+    added: String,      // Every inserted piece of text, back to back
+    pieces: Vec<Piece>, // The merged view, in order
+}
+
+impl<'a> ScopeString<'a> {
+    // Create a new ScopeString over a string slice
+    pub fn new(data: &'a str) -> Self {
+        ScopeString {
+            original_data: data,
+            added: String::new(),
+            pieces: Self::initial_pieces(data),
+        }
+    }
+
+    fn initial_pieces(data: &str) -> Vec<Piece> {
+        if data.is_empty() {
+            Vec::new()
+        } else {
+            vec![Piece {
+                source: Source::Original,
+                start: 0,
+                len: data.len(),
+            }]
+        }
+    }
+
+    fn piece_str(&self, piece: &Piece) -> &str {
+        let source = match piece.source {
+            Source::Original => self.original_data,
+            Source::Added => &self.added,
+        };
+        &source[piece.start..piece.start + piece.len]
+    }
+
+    // Length of the merged view in bytes
+    pub fn len(&self) -> usize {
+        self.pieces.iter().map(|piece| piece.len).sum()
+    }
+
+    // Whether the merged view is empty
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    // Iterate over the segments making up the merged view
+    pub fn segments(&self) -> impl Iterator<Item = &str> + '_ {
+        self.pieces.iter().map(move |piece| self.piece_str(piece))
+    }
+
+    // Iterate over the characters of the merged view
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.segments().flat_map(str::chars)
+    }
+
+    // Whether `offset` lies on a char boundary of the merged view
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        let mut pos = 0;
+        for piece in &self.pieces {
+            if offset < pos + piece.len {
+                return self.piece_str(piece).is_char_boundary(offset - pos);
+            }
+            pos += piece.len;
+        }
+        offset == pos
+    }
+
+    fn check_boundary(&self, offset: usize) {
+        if !self.is_char_boundary(offset) {
+            panic!(
+                "offset {} is not a char boundary of the ScopeString",
+                offset
+            );
+        }
+    }
+
+    // Split pieces so that one starts exactly at `offset`, returning its index
+    fn split_at(&mut self, offset: usize) -> usize {
+        let mut pos = 0;
+        for index in 0..self.pieces.len() {
+            let piece = self.pieces[index];
+            if offset == pos {
+                return index;
+            }
+            if offset < pos + piece.len {
+                let head = offset - pos;
+                self.pieces[index].len = head;
+                self.pieces.insert(
+                    index + 1,
+                    Piece {
+                        source: piece.source,
+                        start: piece.start + head,
+                        len: piece.len - head,
+                    },
+                );
+                return index + 1;
+            }
+            pos += piece.len;
+        }
+        self.pieces.len()
+    }
+
+    // Insert `text` at byte `offset`
+    pub fn insert_str(&mut self, offset: usize, text: &str) {
+        self.check_boundary(offset);
+        if text.is_empty() {
+            return;
+        }
+        let index = self.split_at(offset);
+        let start = self.added.len();
+        self.added.push_str(text);
+
+        // Extend the previous piece when typing continues where it left off
+        if index > 0 {
+            let previous = &mut self.pieces[index - 1];
+            if previous.source == Source::Added && previous.start + previous.len == start {
+                previous.len += text.len();
+                return;
+            }
+        }
+        self.pieces.insert(
+            index,
+            Piece {
+                source: Source::Added,
+                start,
+                len: text.len(),
+            },
+        );
+    }
+
+    // Append `text` to the end of the merged view
+    pub fn push_str(&mut self, text: &str) {
+        self.insert_str(self.len(), text);
+    }
+
+    // Delete the bytes in `range`
+    pub fn delete(&mut self, range: Range<usize>) {
+        if range.start > range.end || range.end > self.len() {
+            panic!("range {:?} out of bounds for ScopeString", range);
+        }
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        let start = self.split_at(range.start);
+        let end = self.split_at(range.end);
+        self.pieces.drain(start..end);
+    }
+
+    // Replace the bytes in `range` with `text`
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        let start = range.start;
+        self.delete(range);
+        self.insert_str(start, text);
+    }
+
+    // Whether any edit has been recorded
+    pub fn is_modified(&self) -> bool {
+        match self.pieces.as_slice() {
+            [] => !self.original_data.is_empty(),
+            [piece] => {
+                piece.source != Source::Original
+                    || piece.start != 0
+                    || piece.len != self.original_data.len()
+            }
+            _ => true,
+        }
+    }
+
+    // Discard every edit
+    pub fn revert(&mut self) {
+        self.added.clear();
+        self.pieces = Self::initial_pieces(self.original_data);
+    }
+
+    // Consume the ScopeString and materialize the merged view
+    pub fn into_inner(self) -> String {
+        self.to_string()
+    }
+}
+
+impl<'a> std::fmt::Display for ScopeString<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.segments().try_for_each(|segment| f.write_str(segment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_delete() {
+        let data = String::from("hello world");
+        let mut text = ScopeString::new(&data);
+        text.insert_str(5, ",");
+        text.push_str("!");
+        text.delete(0..1);
+        text.insert_str(0, "J");
+        assert_eq!(text.to_string(), "Jello, world!");
+        assert_eq!(text.len(), 13);
+        assert!(text.is_modified());
+        assert_eq!(data, "hello world"); // Original data should not change
+    }
+
+    #[test]
+    fn test_typing_coalesces_segments() {
+        let data = "ab";
+        let mut text = ScopeString::new(data);
+        text.insert_str(1, "x");
+        text.insert_str(2, "y");
+        text.insert_str(3, "z");
+        assert_eq!(text.segments().collect::<Vec<_>>(), vec!["a", "xyz", "b"]);
+    }
+
+    #[test]
+    fn test_replace_range_and_revert() {
+        let data = "the quick fox";
+        let mut text = ScopeString::new(data);
+        text.replace_range(4..9, "slow");
+        assert_eq!(text.to_string(), "the slow fox");
+        text.revert();
+        assert!(!text.is_modified());
+        assert_eq!(text.into_inner(), "the quick fox");
+    }
+
+    #[test]
+    fn test_multibyte_text() {
+        let data = "héllo";
+        let mut text = ScopeString::new(data);
+        text.delete(1..3); // 'é' is two bytes
+        assert_eq!(text.chars().collect::<String>(), "hllo");
+    }
+
+    #[test]
+    #[should_panic(expected = "not a char boundary")]
+    fn test_non_boundary_panics() {
+        let mut text = ScopeString::new("héllo");
+        text.insert_str(2, "x");
+    }
+}
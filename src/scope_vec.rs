@@ -0,0 +1,261 @@
+use crate::alloc::{Global, ScratchAlloc};
+
+/// A ScopeVec allows element-granular, scope-bound edits to a vector.  Instead of cloning
+/// the whole vector on the first write, it keeps an overlay: a visible prefix of the
+/// original, per-index overwrites, and newly pushed elements.  Only touched elements are
+/// ever cloned.  Changes are discarded on drop unless `commit()` applies them in place.
+/// The overlay is allocated in the vector's own allocator, so committing moves pushed
+/// elements across without reallocating them.
+pub struct ScopeVec<'a, T: Clone, A: ScratchAlloc = Global> {
+    original_data: &'a mut A::Vec<T>,
+    prefix_len: usize,             // Number of original elements still visible
+    overrides: A::Vec<(usize, T)>, // Replaced elements within the visible prefix, by index
+    appended: A::Vec<T>,           // Elements pushed after the visible prefix
+}
+
+impl<'a, T: Clone> ScopeVec<'a, T> {
+    // Create a new ScopeVec over a mutable reference
+    pub fn new(data: &'a mut Vec<T>) -> Self {
+        ScopeVec::over(data)
+    }
+}
+
+#[cfg(feature = "allocator_api")]
+impl<'a, T: Clone, A: crate::alloc::Allocator + Clone> ScopeVec<'a, T, A> {
+    // Create a new ScopeVec over a vector in a custom allocator, keeping the overlay there
+    pub fn new_in(data: &'a mut allocator_api2::vec::Vec<T, A>) -> Self {
+        ScopeVec::over(data)
+    }
+}
+
+impl<'a, T: Clone, A: ScratchAlloc> ScopeVec<'a, T, A> {
+    fn over(data: &'a mut A::Vec<T>) -> Self {
+        let alloc = A::vec_allocator(data);
+        ScopeVec {
+            prefix_len: data.len(),
+            original_data: data,
+            overrides: alloc.new_vec(),
+            appended: alloc.new_vec(),
+        }
+    }
+
+    // Position of `index` among the overrides, or where it would be inserted
+    fn find(&self, index: usize) -> Result<usize, usize> {
+        self.overrides.binary_search_by_key(&index, |&(i, _)| i)
+    }
+
+    // Number of elements in the edited view
+    pub fn len(&self) -> usize {
+        self.prefix_len + self.appended.len()
+    }
+
+    // Whether the edited view is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Borrow an element of the edited view
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.prefix_len {
+            match self.find(index) {
+                Ok(at) => Some(&self.overrides[at].1),
+                Err(_) => self.original_data.get(index),
+            }
+        } else {
+            self.appended.get(index - self.prefix_len)
+        }
+    }
+
+    // Mutably borrow an element, cloning only that element if necessary
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.prefix_len {
+            let at = match self.find(index) {
+                Ok(at) => at,
+                Err(at) => {
+                    let copy = self.original_data[index].clone();
+                    A::vec_insert(&mut self.overrides, at, (index, copy));
+                    at
+                }
+            };
+            Some(&mut self.overrides[at].1)
+        } else {
+            self.appended.get_mut(index - self.prefix_len)
+        }
+    }
+
+    // Replace an element, returning the previous value.  Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> T {
+        let len = self.len();
+        match self.get_mut(index) {
+            Some(slot) => std::mem::replace(slot, value),
+            None => panic!(
+                "index {} out of bounds for ScopeVec of length {}",
+                index, len
+            ),
+        }
+    }
+
+    // Append an element
+    pub fn push(&mut self, value: T) {
+        A::vec_push(&mut self.appended, value);
+    }
+
+    // Remove and return the last element
+    pub fn pop(&mut self) -> Option<T> {
+        if let Some(value) = A::vec_pop(&mut self.appended) {
+            return Some(value);
+        }
+        if self.prefix_len == 0 {
+            return None;
+        }
+        self.prefix_len -= 1;
+        let index = self.prefix_len;
+        Some(match self.find(index) {
+            Ok(at) => A::vec_remove(&mut self.overrides, at).1,
+            Err(_) => self.original_data[index].clone(),
+        })
+    }
+
+    // Shorten the edited view to `len` elements
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.prefix_len {
+            A::vec_truncate(&mut self.appended, len - self.prefix_len);
+        } else {
+            A::vec_truncate(&mut self.appended, 0);
+            self.prefix_len = len;
+            let kept = self.find(len).unwrap_or_else(|at| at);
+            A::vec_truncate(&mut self.overrides, kept);
+        }
+    }
+
+    // Iterate over the edited view
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.prefix_len)
+            .map(move |index| self.get(index).unwrap())
+            .chain(self.appended.iter())
+    }
+
+    // Whether the edited view differs structurally from the original
+    pub fn is_modified(&self) -> bool {
+        self.prefix_len != self.original_data.len()
+            || !self.overrides.is_empty()
+            || !self.appended.is_empty()
+    }
+
+    // Discard every edit
+    pub fn revert(&mut self) {
+        self.prefix_len = self.original_data.len();
+        A::vec_truncate(&mut self.overrides, 0);
+        A::vec_truncate(&mut self.appended, 0);
+    }
+
+    // Materialize the edited view as a new vector
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    // Apply the edits to the original vector in place
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Replace the edited view with `value`, overriding only the elements that differ
+    pub(crate) fn replace_all(&mut self, value: Vec<T>)
+    where
+        T: PartialEq,
+    {
+        self.revert();
+        self.prefix_len = self.original_data.len().min(value.len());
+        for (index, element) in value.into_iter().enumerate() {
+            if index >= self.prefix_len {
+                A::vec_push(&mut self.appended, element);
+            } else if self.original_data[index] != element {
+                A::vec_push(&mut self.overrides, (index, element));
+            }
+        }
+    }
+
+    // Apply the edits in place, leaving the ScopeVec unmodified
+    pub(crate) fn write_back(&mut self) {
+        A::vec_truncate(self.original_data, self.prefix_len);
+        let overrides = std::mem::replace(
+            &mut self.overrides,
+            A::vec_allocator(&self.appended).new_vec(),
+        );
+        for (index, value) in overrides {
+            self.original_data[index] = value;
+        }
+        A::vec_append(self.original_data, &mut self.appended);
+        self.prefix_len = self.original_data.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_do_not_touch_original() {
+        let mut data: Vec<u32> = (0..100_000).collect();
+        {
+            let mut scope = ScopeVec::new(&mut data);
+            scope.set(5, 500);
+            scope.push(7);
+            assert_eq!(scope.pop(), Some(7));
+            assert_eq!(scope.pop(), Some(99_999));
+            assert_eq!(scope.len(), 99_999);
+            assert_eq!(scope.get(5), Some(&500));
+            assert!(scope.is_modified());
+        } // Dropped without commit
+        assert_eq!(data.len(), 100_000);
+        assert_eq!(data[5], 5);
+    }
+
+    #[test]
+    fn test_commit_applies_in_place() {
+        let mut data = vec![1, 2, 3, 4];
+        {
+            let mut scope = ScopeVec::new(&mut data);
+            *scope.get_mut(0).unwrap() = 10;
+            scope.set(3, 40);
+            scope.truncate(2); // Drops the overwrite at index 3
+            scope.push(5);
+            assert_eq!(scope.to_vec(), vec![10, 2, 5]);
+            scope.commit();
+        }
+        assert_eq!(data, vec![10, 2, 5]);
+    }
+
+    #[test]
+    fn test_revert_and_iter() {
+        let mut data = vec![String::from("a"), String::from("b")];
+        let mut scope = ScopeVec::new(&mut data);
+        scope.get_mut(1).unwrap().push('!');
+        scope.push(String::from("c"));
+        assert_eq!(
+            scope.iter().cloned().collect::<Vec<_>>(),
+            vec!["a", "b!", "c"]
+        );
+        scope.revert();
+        assert!(!scope.is_modified());
+        assert_eq!(scope.to_vec(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_pop_through_overrides() {
+        let mut data = vec![1, 2];
+        let mut scope = ScopeVec::new(&mut data);
+        scope.set(1, 20);
+        assert_eq!(scope.pop(), Some(20));
+        assert_eq!(scope.pop(), Some(1));
+        assert_eq!(scope.pop(), None);
+        assert!(scope.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_set_out_of_bounds_panics() {
+        let mut data = vec![1];
+        ScopeVec::new(&mut data).set(1, 2);
+    }
+}
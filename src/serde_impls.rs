@@ -0,0 +1,139 @@
+use crate::{JournalCell, Operation, OwnedScopeCell, ScopeCell, ScopeCellMut, SyncScopeCell};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Cells serialize as their effective value: the modified data if present, else the original.
+
+impl<'a, T: Clone + Serialize> Serialize for ScopeCell<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.borrow().serialize(serializer)
+    }
+}
+
+impl<'a, T: Clone + Serialize> Serialize for ScopeCellMut<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'a, T: Clone + Serialize> Serialize for SyncScopeCell<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.read().serialize(serializer)
+    }
+}
+
+impl<T: Clone + Serialize> Serialize for OwnedScopeCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+// A deserialized OwnedScopeCell starts clean, with the value as its baseline.
+impl<'de, T: Clone + Deserialize<'de>> Deserialize<'de> for OwnedScopeCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(OwnedScopeCell::new)
+    }
+}
+
+// A JournalCell serializes as its baseline and log, for operation types that are themselves
+// serializable (typically a user enum).  Deserializing replays the log onto the baseline.
+
+#[derive(Serialize)]
+struct JournalRef<'j, T, O> {
+    baseline: &'j T,
+    log: &'j [O],
+}
+
+#[derive(Deserialize)]
+struct JournalOwned<T, O> {
+    baseline: T,
+    log: Vec<O>,
+}
+
+impl<T, O> Serialize for JournalCell<T, O>
+where
+    T: Clone + Serialize,
+    O: Operation<T> + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        JournalRef {
+            baseline: self.baseline(),
+            log: self.log(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T, O> Deserialize<'de> for JournalCell<T, O>
+where
+    T: Clone + Deserialize<'de>,
+    O: Operation<T> + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let saved = JournalOwned::<T, O>::deserialize(deserializer)?;
+        let mut journal = JournalCell::new(saved.baseline);
+        saved.log.into_iter().for_each(|op| journal.apply(op));
+        Ok(journal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrow_mut_after_serialize() {
+        let data = vec![1];
+        let cell = ScopeCell::new(&data);
+        assert_eq!(serde_json::to_string(&cell).unwrap(), "[1]");
+        cell.borrow_mut().push(2);
+        assert_eq!(serde_json::to_string(&cell).unwrap(), "[1,2]");
+    }
+
+    #[test]
+    fn test_serialize_effective_value() {
+        let data = vec![1, 2, 3];
+        let mut scope = ScopeCell::new(&data);
+        assert_eq!(serde_json::to_string(&scope).unwrap(), "[1,2,3]");
+        scope.get_mut().push(4);
+        assert_eq!(serde_json::to_string(&scope).unwrap(), "[1,2,3,4]");
+    }
+
+    #[test]
+    fn test_owned_round_trip() {
+        let mut cell = OwnedScopeCell::new(String::from("draft"));
+        cell.get_mut().push_str(" v2");
+        let json = serde_json::to_string(&cell).unwrap();
+
+        let restored: OwnedScopeCell<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.baseline(), "draft v2"); // Saved edits become the baseline
+        assert!(!restored.is_modified());
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum Counter {
+        Add(i32),
+        Reset,
+    }
+
+    impl Operation<i32> for Counter {
+        fn apply(&mut self, target: &mut i32) {
+            match self {
+                Counter::Add(n) => *target += *n,
+                Counter::Reset => *target = 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_journal_round_trip() {
+        let mut journal = JournalCell::new(5);
+        journal.apply(Counter::Reset);
+        journal.apply(Counter::Add(3));
+        let json = serde_json::to_string(&journal).unwrap();
+        assert_eq!(json, r#"{"baseline":5,"log":["Reset",{"Add":3}]}"#);
+
+        let restored: JournalCell<i32, Counter> = serde_json::from_str(&json).unwrap();
+        assert_eq!(*restored.get(), 3); // The log was replayed
+        assert_eq!(restored.len(), 2);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::primitives::{Mutex, MutexGuard, RwLock};
+use crate::ScopeError;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+enum Subscriber<T> {
+    Callback(Box<dyn Fn(&T) + Send + Sync>),
+    Channel(Sender<T>),
+}
+
+/// Handle returned by `SignalCell::subscribe`, used to unsubscribe later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// A SignalCell holds a value and a list of subscribers.  Edits are made through a scoped
+/// `SignalEdit` against a private copy; only when an edit commits is the value replaced and
+/// every subscriber notified with the new value.  Discarded edits are never observed.
+///
+/// Subscribers are notified while the subscriber list is locked, so a callback must not
+/// subscribe to or commit an edit on the same signal.  Concurrent commits are published one
+/// at a time, and subscribers see every committed value in the order it was stored.
+pub struct SignalCell<T: Clone> {
+    value: RwLock<T>,
+    subscribers: Mutex<Vec<(SubscriptionId, Subscriber<T>)>>,
+    next_id: AtomicU64, // Source of SubscriptionIds
+}
+
+impl<T: Clone> SignalCell<T> {
+    // Create a new SignalCell with no subscribers
+    pub fn new(value: T) -> Self {
+        SignalCell {
+            value: RwLock::new(value),
+            subscribers: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    // Run a closure against the current value
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        f(&self.value.read().expect("SignalCell lock poisoned"))
+    }
+
+    // Clone the current value out of the cell
+    pub fn get_cloned(&self) -> T {
+        self.with(T::clone)
+    }
+
+    // Clone the current value like `get_cloned()`, or fail if a commit panicked midway
+    pub fn try_get_cloned(&self) -> Result<T, ScopeError> {
+        let value = self.value.read().map_err(|_| ScopeError::Poisoned)?;
+        Ok(value.clone())
+    }
+
+    // Whether a panic (for example in a subscriber callback) poisoned the cell
+    pub fn is_poisoned(&self) -> bool {
+        #[cfg(not(loom))]
+        return self.value.is_poisoned() || self.subscribers.is_poisoned();
+        #[cfg(loom)]
+        return false;
+    }
+
+    // Allow access again after a panic
+    pub fn clear_poison(&self) {
+        #[cfg(not(loom))]
+        {
+            self.value.clear_poison();
+            self.subscribers.clear_poison();
+        }
+    }
+
+    // Register a callback fired with the new value after every commit
+    pub fn subscribe<F: Fn(&T) + Send + Sync + 'static>(&self, callback: F) -> SubscriptionId {
+        self.add(Subscriber::Callback(Box::new(callback)))
+    }
+
+    // Register a channel that receives a clone of the new value after every commit.  The
+    // subscription is dropped automatically once the receiver is gone.
+    pub fn subscribe_channel(&self) -> Receiver<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.add(Subscriber::Channel(sender));
+        receiver
+    }
+
+    // Remove a subscriber.  Returns false if it was already gone.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.lock_subscribers();
+        let before = subscribers.len();
+        subscribers.retain(|(other, _)| *other != id);
+        subscribers.len() != before
+    }
+
+    // Number of registered subscribers
+    pub fn subscriber_count(&self) -> usize {
+        self.lock_subscribers().len()
+    }
+
+    // Start a scoped edit against a copy of the current value
+    pub fn edit(&self) -> SignalEdit<'_, T> {
+        SignalEdit {
+            cell: self,
+            modified_data: self.get_cloned(),
+            dirty: false,
+        }
+    }
+
+    // Run a closure against a scoped edit, committing it if the closure returns `Ok`
+    pub fn scope<R, E, F>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut edit = self.edit();
+        let result = f(&mut edit)?;
+        edit.commit();
+        Ok(result)
+    }
+
+    // Replace the value outright and notify subscribers
+    pub fn set(&self, value: T) {
+        self.publish(value);
+    }
+
+    // Consume the cell and return the current value
+    pub fn into_inner(self) -> T {
+        self.try_into_inner().expect("SignalCell lock poisoned")
+    }
+
+    // Consume the cell like `into_inner()`, or fail if a commit panicked midway
+    pub fn try_into_inner(self) -> Result<T, ScopeError> {
+        self.value.into_inner().map_err(|_| ScopeError::Poisoned)
+    }
+
+    fn add(&self, subscriber: Subscriber<T>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.lock_subscribers().push((id, subscriber));
+        id
+    }
+
+    fn lock_subscribers(&self) -> MutexGuard<'_, Vec<(SubscriptionId, Subscriber<T>)>> {
+        self.subscribers.lock().expect("SignalCell lock poisoned")
+    }
+
+    // Store the committed value, then notify subscribers with it.  The subscriber list stays
+    // locked from the store until every subscriber is notified, so another commit cannot
+    // replace the value in between.
+    fn publish(&self, value: T) {
+        let mut subscribers = self.lock_subscribers();
+        *self.value.write().expect("SignalCell lock poisoned") = value;
+        let value = self.value.read().expect("SignalCell lock poisoned");
+        subscribers.retain(|(_, subscriber)| match subscriber {
+            Subscriber::Callback(callback) => {
+                callback(&value);
+                true
+            }
+            Subscriber::Channel(sender) => sender.send(value.clone()).is_ok(),
+        });
+    }
+}
+
+/// A scoped edit of a SignalCell.  Dropping it without `commit()` discards the edit and
+/// notifies nobody.
+pub struct SignalEdit<'s, T: Clone> {
+    cell: &'s SignalCell<T>,
+    modified_data: T, // Private copy of the value
+    dirty: bool,      // Set on mutable access
+}
+
+impl<'s, T: Clone> SignalEdit<'s, T> {
+    // Publish the edit and notify subscribers.  An edit that never touched the value
+    // publishes nothing.
+    pub fn commit(self) {
+        if self.dirty {
+            self.cell.publish(self.modified_data);
+        }
+    }
+
+    // Discard the edit, starting over from the signal's current value
+    pub fn revert(&mut self) {
+        self.modified_data = self.cell.get_cloned();
+        self.dirty = false;
+    }
+
+    // Whether the edit has been mutably accessed since it started or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Publish a copy of the edit in place, leaving it open for further changes
+    pub(crate) fn write_back(&mut self) {
+        if self.dirty {
+            self.cell.publish(self.modified_data.clone());
+            self.dirty = false;
+        }
+    }
+}
+
+impl<'s, T: Clone> Deref for SignalEdit<'s, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modified_data
+    }
+}
+
+impl<'s, T: Clone> DerefMut for SignalEdit<'s, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty = true;
+        &mut self.modified_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_commit_notifies_callbacks() {
+        let signal = SignalCell::new(1);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        signal.subscribe(move |v| sink.lock().unwrap().push(*v));
+
+        *signal.edit() = 5; // Dropped without commit
+        let mut edit = signal.edit();
+        *edit += 1;
+        edit.commit();
+        signal.set(10);
+        assert_eq!(*seen.lock().unwrap(), vec![2, 10]); // The discarded edit was never seen
+    }
+
+    #[test]
+    fn test_channel_subscription() {
+        let signal = SignalCell::new(String::new());
+        let updates = signal.subscribe_channel();
+        signal
+            .scope(|s| {
+                s.push_str("hello");
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(updates.recv().unwrap(), "hello");
+
+        drop(updates);
+        signal.set(String::from("again"));
+        assert_eq!(signal.subscriber_count(), 0); // Closed channel pruned
+    }
+
+    #[test]
+    fn test_concurrent_commits_notify_in_order() {
+        let signal = SignalCell::new(0);
+        let updates = signal.subscribe_channel();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let signal = &signal;
+                s.spawn(move || (1..=50).for_each(|i| signal.set(t * 100 + i)));
+            }
+        });
+        let seen: Vec<i32> = updates.try_iter().collect();
+        assert_eq!(seen.len(), 200);
+        assert_eq!(seen.last(), Some(&signal.get_cloned()));
+        let mut distinct = seen.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 200); // Every commit was seen exactly once
+    }
+
+    #[test]
+    fn test_try_methods_on_healthy_signal() {
+        let signal = SignalCell::new(3);
+        assert_eq!(signal.try_get_cloned(), Ok(3));
+        assert_eq!(signal.try_into_inner(), Ok(3));
+    }
+
+    #[test]
+    #[cfg(not(feature = "parking_lot"))]
+    fn test_panicking_subscriber_poisons() {
+        let signal = SignalCell::new(0);
+        signal.subscribe(|v| assert!(*v < 10, "too large"));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| signal.set(10)));
+        assert!(result.is_err());
+        assert!(signal.is_poisoned()); // The subscriber list was locked during the panic
+        assert_eq!(signal.try_get_cloned(), Ok(10)); // The value was stored before notifying
+        signal.clear_poison();
+        assert!(!signal.is_poisoned());
+        assert_eq!(signal.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let signal = SignalCell::new(0);
+        let id = signal.subscribe(|_| panic!("unsubscribed callback fired"));
+        assert!(signal.unsubscribe(id));
+        assert!(!signal.unsubscribe(id));
+        signal.set(1);
+        assert_eq!(signal.into_inner(), 1);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use crate::primitives::thread;
+    use loom::sync::Arc;
+
+    #[test]
+    fn test_subscribers_see_commits_in_order_loom() {
+        loom::model(|| {
+            let signal = Arc::new(SignalCell::new(0));
+            let seen = Arc::new(Mutex::new(Vec::new()));
+            let sink = Arc::clone(&seen);
+            signal.subscribe(move |v| sink.lock().unwrap().push(*v));
+            let other = Arc::clone(&signal);
+            let handle = thread::spawn(move || other.set(1));
+            signal.set(2);
+            handle.join().unwrap();
+            let seen = seen.lock().unwrap();
+            assert_eq!(seen.len(), 2);
+            assert_ne!(seen[0], seen[1]);
+            assert_eq!(seen[1], signal.get_cloned());
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// A ScopedSlot holds a shared dependency, typically an `Arc<dyn Trait>`, that code reads
+/// with `get()` and tests can replace for the duration of a scope with `install()`.
+/// Installs nest like `ScopedStatic` overrides, and the most recent live one wins.  It can
+/// be declared as a `static` since `new` is `const`; the production implementation is then
+/// registered once with `set_default()`.
+pub struct ScopedSlot<T: ?Sized> {
+    default: OnceLock<Arc<T>>,
+    overrides: Mutex<Vec<(u64, Arc<T>)>>, // Live installs, oldest first
+    next_id: AtomicU64,
+}
+
+impl<T: ?Sized> ScopedSlot<T> {
+    // Create an empty ScopedSlot.  `get()` panics until a default is set or a value is
+    // installed.
+    pub const fn new() -> Self {
+        ScopedSlot {
+            default: OnceLock::new(),
+            overrides: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    // Create a ScopedSlot that falls back to `default` while nothing is installed
+    pub fn with_default(default: Arc<T>) -> Self {
+        let slot = ScopedSlot::new();
+        slot.set_default(default);
+        slot
+    }
+
+    // Register the value used while nothing is installed.  Returns false, leaving the
+    // existing default in place, if one was already set.
+    pub fn set_default(&self, default: Arc<T>) -> bool {
+        self.default.set(default).is_ok()
+    }
+
+    // The lock is only held for short, non-panicking sections, so poisoning can be ignored
+    fn lock(&self) -> MutexGuard<'_, Vec<(u64, Arc<T>)>> {
+        self.overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Replace the dependency until the returned guard is dropped
+    pub fn install(&self, value: Arc<T>) -> SlotOverride<'_, T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().push((id, value));
+        SlotOverride { owner: self, id }
+    }
+
+    // The current dependency: the most recent install, otherwise the default
+    pub fn try_get(&self) -> Option<Arc<T>> {
+        match self.lock().last() {
+            Some((_, value)) => Some(Arc::clone(value)),
+            None => self.default.get().cloned(),
+        }
+    }
+
+    // The current dependency.  Panics if the slot is empty.
+    pub fn get(&self) -> Arc<T> {
+        self.try_get().expect("ScopedSlot is empty")
+    }
+
+    // Whether an install is currently live
+    pub fn is_overridden(&self) -> bool {
+        !self.lock().is_empty()
+    }
+}
+
+impl<T: ?Sized> Default for ScopedSlot<T> {
+    fn default() -> Self {
+        ScopedSlot::new()
+    }
+}
+
+/// Guard returned by `ScopedSlot::install`; removes its install when dropped.
+pub struct SlotOverride<'s, T: ?Sized> {
+    owner: &'s ScopedSlot<T>,
+    id: u64,
+}
+
+impl<'s, T: ?Sized> Drop for SlotOverride<'s, T> {
+    fn drop(&mut self) {
+        // Guards may be dropped out of order, so remove this install wherever it sits
+        let mut overrides = self.owner.lock();
+        if let Some(index) = overrides.iter().rposition(|(id, _)| *id == self.id) {
+            overrides.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Clock: Send + Sync {
+        fn now(&self) -> u64;
+    }
+
+    struct SystemClock;
+
+    impl Clock for SystemClock {
+        fn now(&self) -> u64 {
+            1_700_000_000
+        }
+    }
+
+    struct FixedClock(u64);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> u64 {
+            self.0
+        }
+    }
+
+    static CLOCK: ScopedSlot<dyn Clock> = ScopedSlot::new();
+
+    fn timestamp() -> u64 {
+        CLOCK.get().now()
+    }
+
+    #[test]
+    fn test_install_mock_for_scope() {
+        CLOCK.set_default(Arc::new(SystemClock));
+        {
+            let _mock = CLOCK.install(Arc::new(FixedClock(42)));
+            assert_eq!(timestamp(), 42);
+            assert!(CLOCK.is_overridden());
+        }
+        assert_eq!(timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    fn test_nested_and_out_of_order() {
+        let slot: ScopedSlot<dyn Clock> = ScopedSlot::with_default(Arc::new(FixedClock(0)));
+        assert!(!slot.set_default(Arc::new(FixedClock(9)))); // Default is set once
+        let first = slot.install(Arc::new(FixedClock(1)));
+        let second = slot.install(Arc::new(FixedClock(2)));
+        drop(first);
+        assert_eq!(slot.get().now(), 2); // The later install still wins
+        drop(second);
+        assert_eq!(slot.get().now(), 0);
+    }
+
+    #[test]
+    fn test_empty_slot() {
+        let slot: ScopedSlot<str> = ScopedSlot::new();
+        assert!(slot.try_get().is_none());
+        let _guard = slot.install(Arc::from("boxed str"));
+        assert_eq!(&*slot.get(), "boxed str");
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::ScopeCell;
+
+/// Run `alternatives` closures, each against its own ScopeCell over the same baseline, and
+/// keep only the best.  The closure gets the alternative's index and its cell and returns a
+/// score; the highest-scoring alternative's value is written into `target` and every other
+/// copy is discarded.  Ties go to the earliest alternative, and scores that do not compare
+/// (such as NaN) never win.  Returns the winning index and score, or `None` if
+/// `alternatives` is zero or nothing could be scored.
+pub fn speculate<T, S, F>(target: &mut T, alternatives: usize, mut f: F) -> Option<(usize, S)>
+where
+    T: Clone,
+    S: PartialOrd,
+    F: FnMut(usize, &mut ScopeCell<'_, T>) -> S,
+{
+    let mut best: Option<(usize, S, Option<T>)> = None;
+    for index in 0..alternatives {
+        let mut cell = ScopeCell::new(&*target);
+        let score = f(index, &mut cell);
+        let wins = match &best {
+            Some((_, best_score, _)) => score > *best_score,
+            None => score.partial_cmp(&score).is_some(), // NaN-like scores cannot win
+        };
+        if wins {
+            let value = cell.is_modified().then(|| cell.into_inner());
+            best = Some((index, score, value));
+        }
+    }
+    let (index, score, value) = best?;
+    if let Some(value) = value {
+        *target = value;
+    }
+    Some((index, score))
+}
+
+/// One alternative's outcome in `par_speculate`: its edited value and what its closure
+/// returned.  The reducer combines two outcomes into one, by picking the better or by
+/// merging their values.
+#[cfg(feature = "rayon")]
+pub struct Outcome<T, R> {
+    pub value: T,
+    pub result: R,
+}
+
+/// Parallel form of `speculate`: runs `alternatives` closures on rayon's thread pool, each
+/// worker against its own ScopeCell over the shared baseline, then folds the outcomes
+/// pairwise with `merge` and writes the final value into `target`.  `merge` may be called
+/// in any grouping, so it should be associative.  Returns the final result, or `None` if
+/// `alternatives` is zero.
+#[cfg(feature = "rayon")]
+pub fn par_speculate<T, R, F, M>(target: &mut T, alternatives: usize, f: F, merge: M) -> Option<R>
+where
+    T: Clone + Send + Sync,
+    R: Send,
+    F: Fn(usize, &mut ScopeCell<'_, T>) -> R + Sync,
+    M: Fn(Outcome<T, R>, Outcome<T, R>) -> Outcome<T, R> + Sync,
+{
+    let baseline = &*target;
+    let outcome = (0..alternatives)
+        .into_par_iter()
+        .map(|index| {
+            let mut cell = ScopeCell::new(baseline);
+            let result = f(index, &mut cell);
+            Outcome {
+                value: cell.into_inner(),
+                result,
+            }
+        })
+        .reduce_with(&merge)?;
+    *target = outcome.value;
+    Some(outcome.result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Board {
+        cells: Vec<u8>,
+    }
+
+    #[test]
+    fn test_commits_only_the_winner() {
+        let mut board = Board {
+            cells: vec![0, 0, 0],
+        };
+        let moves = [(0, 2), (1, 5), (2, 1)];
+        let outcome = speculate(&mut board, moves.len(), |index, cell| {
+            let (at, value) = moves[index];
+            cell.get_mut().cells[at] = value;
+            cell.get().cells.iter().map(|&c| u32::from(c)).sum::<u32>()
+        });
+        assert_eq!(outcome, Some((1, 5)));
+        assert_eq!(board.cells, vec![0, 5, 0]); // Losing moves left no trace
+    }
+
+    #[test]
+    fn test_ties_and_untouched_winner() {
+        let mut value = vec![1];
+        let outcome = speculate(&mut value, 3, |index, cell| {
+            if index > 0 {
+                cell.get_mut().push(index); // Scores the same as leaving it alone
+            }
+            1
+        });
+        assert_eq!(outcome, Some((0, 1)));
+        assert_eq!(value, vec![1]);
+        assert_eq!(speculate(&mut value, 0, |_, _| 1), None);
+    }
+
+    #[test]
+    fn test_incomparable_scores_never_win() {
+        let mut value = 0;
+        let outcome = speculate(&mut value, 3, |index, cell| {
+            *cell.get_mut() = index;
+            if index == 0 {
+                f64::NAN
+            } else {
+                index as f64
+            }
+        });
+        assert_eq!(outcome, Some((2, 2.0)));
+        assert_eq!(value, 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_speculate_picks_best() {
+        let mut board = Board { cells: vec![0; 8] };
+        let best = par_speculate(
+            &mut board,
+            8,
+            |index, cell| {
+                cell.get_mut().cells[index] = index as u8;
+                (index * 7) % 8 // Score
+            },
+            |a, b| if b.result > a.result { b } else { a },
+        );
+        assert_eq!(best, Some(7));
+        assert_eq!(board.cells.iter().filter(|&&c| c != 0).count(), 1);
+        assert_eq!(board.cells[1], 1); // Index 1 scored 7
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_speculate_merges() {
+        let mut found: Vec<usize> = vec![100];
+        let total = par_speculate(
+            &mut found,
+            16,
+            |index, cell| {
+                if index % 3 == 0 {
+                    cell.get_mut().push(index);
+                }
+                1
+            },
+            |mut a, b| {
+                for item in b.value {
+                    if !a.value.contains(&item) {
+                        a.value.push(item);
+                    }
+                }
+                a.result += b.result;
+                a
+            },
+        );
+        found.sort_unstable();
+        assert_eq!(total, Some(16));
+        assert_eq!(found, vec![0, 3, 6, 9, 12, 15, 100]);
+        assert_eq!(par_speculate(&mut found, 0, |_, _| (), |a, _| a), None);
+    }
+}
@@ -0,0 +1,262 @@
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::LocalKey;
+
+/// A ScopedStatic is a process-wide value that can be temporarily overridden:
+/// `set_scoped()` pushes an override and returns a guard that removes it again on drop.
+/// Overrides nest, and the most recent live override wins.  It can be declared as a
+/// `static` since `new` is `const`.
+pub struct ScopedStatic<T> {
+    default: T,
+    overrides: Mutex<Vec<(u64, Arc<T>)>>, // Live overrides, oldest first
+    next_id: AtomicU64,
+}
+
+impl<T> ScopedStatic<T> {
+    // Create a ScopedStatic whose value is `default` while no override is live
+    pub const fn new(default: T) -> Self {
+        ScopedStatic {
+            default,
+            overrides: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    // The lock is only held for short, non-panicking sections, never across a user closure,
+    // so the override stack is always consistent and poisoning can be ignored.
+    fn lock(&self) -> MutexGuard<'_, Vec<(u64, Arc<T>)>> {
+        self.overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Override the value until the returned guard is dropped
+    pub fn set_scoped(&self, value: T) -> StaticOverride<'_, T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().push((id, Arc::new(value)));
+        StaticOverride { owner: self, id }
+    }
+
+    // Run `f` with the effective value.  The override is shared out of the lock first, so `f`
+    // may itself read or override this ScopedStatic.
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        let current = self.lock().last().map(|(_, value)| Arc::clone(value));
+        f(current.as_deref().unwrap_or(&self.default))
+    }
+
+    // Clone the effective value
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    // Number of live overrides
+    pub fn depth(&self) -> usize {
+        self.lock().len()
+    }
+}
+
+/// Guard returned by `ScopedStatic::set_scoped`; removes its override when dropped.
+pub struct StaticOverride<'s, T> {
+    owner: &'s ScopedStatic<T>,
+    id: u64,
+}
+
+impl<'s, T> Drop for StaticOverride<'s, T> {
+    fn drop(&mut self) {
+        // Guards may be dropped out of order, so remove this override wherever it sits
+        let mut overrides = self.owner.lock();
+        if let Some(index) = overrides.iter().rposition(|(id, _)| *id == self.id) {
+            overrides.remove(index);
+        }
+    }
+}
+
+/// A ScopedThreadLocal is the per-thread counterpart of `ScopedStatic`, meant to be
+/// declared inside `thread_local!` and overridden through `ScopedLocalKey::set_scoped`.
+pub struct ScopedThreadLocal<T> {
+    default: T,
+    overrides: RefCell<Vec<(u64, Rc<T>)>>,
+    next_id: Cell<u64>,
+}
+
+impl<T> ScopedThreadLocal<T> {
+    // Create a ScopedThreadLocal whose value is `default` while no override is live
+    pub const fn new(default: T) -> Self {
+        ScopedThreadLocal {
+            default,
+            overrides: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        }
+    }
+
+    // Run `f` with the effective value.  The override is shared out of the `RefCell` first,
+    // so `f` may itself override this ScopedThreadLocal.
+    pub fn with_value<R, F: FnOnce(&T) -> R>(&self, f: F) -> R {
+        let current = self
+            .overrides
+            .borrow()
+            .last()
+            .map(|(_, value)| Rc::clone(value));
+        f(current.as_deref().unwrap_or(&self.default))
+    }
+
+    // Number of live overrides on this thread
+    pub fn depth(&self) -> usize {
+        self.overrides.borrow().len()
+    }
+
+    fn push(&self, value: T) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.overrides.borrow_mut().push((id, Rc::new(value)));
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        let mut overrides = self.overrides.borrow_mut();
+        if let Some(index) = overrides.iter().rposition(|(other, _)| *other == id) {
+            overrides.remove(index);
+        }
+    }
+}
+
+/// Scoped overrides for a `thread_local!` holding a `ScopedThreadLocal`.
+pub trait ScopedLocalKey<T: 'static> {
+    // Override the value on the current thread until the returned guard is dropped
+    fn set_scoped(&'static self, value: T) -> LocalOverride<T>;
+
+    // Clone the effective value on the current thread
+    fn get(&'static self) -> T
+    where
+        T: Clone;
+}
+
+impl<T: 'static> ScopedLocalKey<T> for LocalKey<ScopedThreadLocal<T>> {
+    fn set_scoped(&'static self, value: T) -> LocalOverride<T> {
+        let id = self.with(|local| local.push(value));
+        LocalOverride {
+            key: self,
+            id,
+            _not_send: PhantomData,
+        }
+    }
+
+    fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(|local| local.with_value(T::clone))
+    }
+}
+
+/// Guard returned by `ScopedLocalKey::set_scoped`; removes its override when dropped.
+/// It cannot leave the thread whose value it overrides.
+pub struct LocalOverride<T: 'static> {
+    key: &'static LocalKey<ScopedThreadLocal<T>>,
+    id: u64,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T: 'static> Drop for LocalOverride<T> {
+    fn drop(&mut self) {
+        // The thread-local may already be gone if the thread is exiting
+        let _ = self.key.try_with(|local| local.remove(self.id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LEVEL: ScopedStatic<u32> = ScopedStatic::new(1);
+
+    thread_local! {
+        static NAME: ScopedThreadLocal<String> = const { ScopedThreadLocal::new(String::new()) };
+    }
+
+    #[test]
+    fn test_nested_static_overrides() {
+        let local = ScopedStatic::new(String::from("default"));
+        assert_eq!(local.get(), "default");
+        {
+            let _outer = local.set_scoped(String::from("outer"));
+            {
+                let _inner = local.set_scoped(String::from("inner"));
+                assert_eq!(local.get(), "inner");
+                assert_eq!(local.depth(), 2);
+            }
+            assert_eq!(local.get(), "outer");
+        }
+        assert_eq!(local.get(), "default");
+    }
+
+    #[test]
+    fn test_out_of_order_drop() {
+        let local = ScopedStatic::new(0);
+        let first = local.set_scoped(1);
+        let second = local.set_scoped(2);
+        drop(first);
+        assert_eq!(local.get(), 2); // The later override still wins
+        drop(second);
+        assert_eq!(local.get(), 0);
+    }
+
+    #[test]
+    fn test_static_declaration() {
+        let _guard = LEVEL.set_scoped(3);
+        assert_eq!(LEVEL.with(|level| *level * 2), 6);
+    }
+
+    #[test]
+    fn test_thread_local_overrides() {
+        assert_eq!(NAME.get(), "");
+        let _guard = NAME.set_scoped(String::from("main"));
+        std::thread::spawn(|| {
+            assert_eq!(NAME.get(), ""); // Other threads are unaffected
+            let _guard = NAME.set_scoped(String::from("worker"));
+            assert_eq!(NAME.get(), "worker");
+        })
+        .join()
+        .unwrap();
+        assert_eq!(NAME.get(), "main");
+        drop(_guard);
+        assert_eq!(NAME.with(|name| name.depth()), 0);
+    }
+
+    #[test]
+    fn test_reentrant_overrides_inside_with() {
+        let local = ScopedStatic::new(1);
+        let _outer = local.set_scoped(2);
+        local.with(|outer| {
+            let _inner = local.set_scoped(*outer + 1); // Would deadlock if `with` held the lock
+            assert_eq!(local.get(), 3);
+            drop(_outer);
+            assert_eq!(*outer, 2); // Still alive while `f` runs
+        });
+        assert_eq!(local.get(), 1);
+        NAME.with(|name| {
+            name.with_value(|_| {
+                let _guard = NAME.set_scoped(String::from("nested"));
+                assert_eq!(NAME.get(), "nested");
+            })
+        });
+        assert_eq!(NAME.get(), "");
+    }
+
+    #[test]
+    fn test_restored_after_panic() {
+        let local = ScopedStatic::new(false);
+        let result = std::panic::catch_unwind(|| {
+            let _guard = local.set_scoped(true);
+            panic!("test failed");
+        });
+        assert!(result.is_err());
+        assert!(!local.get());
+    }
+}
@@ -0,0 +1,289 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::{OwnedScopeCell, ScopeCell, ScopeCellMut};
+
+// Cells read as their effective value: the modified data if present, else the original.
+// ScopeCell goes through `get()`, so a deref counts as a shared borrow and panics while a
+// `borrow_mut()` guard is live.
+
+impl<'a, T: Clone> Deref for ScopeCell<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> AsRef<T> for ScopeCell<'a, T> {
+    fn as_ref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> Borrow<T> for ScopeCell<'a, T> {
+    fn borrow(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> Deref for ScopeCellMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> AsRef<T> for ScopeCellMut<'a, T> {
+    fn as_ref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> Borrow<T> for ScopeCellMut<'a, T> {
+    fn borrow(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: Clone> Deref for OwnedScopeCell<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: Clone> AsRef<T> for OwnedScopeCell<T> {
+    fn as_ref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<T: Clone> Borrow<T> for OwnedScopeCell<T> {
+    fn borrow(&self) -> &T {
+        self.get()
+    }
+}
+
+// Comparison and hashing also use the effective value, consistently with `Borrow<T>`, so a
+// cell can be compared against a plain value or used as a key looked up by `&T`.
+
+impl<'a, T: Clone + PartialEq> PartialEq<T> for ScopeCell<'a, T> {
+    fn eq(&self, other: &T) -> bool {
+        *self.borrow() == *other
+    }
+}
+
+impl<'a, 'b, T: Clone + PartialEq> PartialEq<ScopeCell<'b, T>> for ScopeCell<'a, T> {
+    fn eq(&self, other: &ScopeCell<'b, T>) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl<'a, T: Clone + Eq> Eq for ScopeCell<'a, T> {}
+
+impl<'a, 'b, T: Clone + PartialOrd> PartialOrd<ScopeCell<'b, T>> for ScopeCell<'a, T> {
+    fn partial_cmp(&self, other: &ScopeCell<'b, T>) -> Option<Ordering> {
+        self.borrow().partial_cmp(&other.borrow())
+    }
+}
+
+impl<'a, T: Clone + Ord> Ord for ScopeCell<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.borrow().cmp(&other.borrow())
+    }
+}
+
+impl<'a, T: Clone + Hash> Hash for ScopeCell<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.borrow().hash(state)
+    }
+}
+
+impl<'a, T: Clone + PartialEq> PartialEq<T> for ScopeCellMut<'a, T> {
+    fn eq(&self, other: &T) -> bool {
+        self.get() == other
+    }
+}
+
+impl<'a, 'b, T: Clone + PartialEq> PartialEq<ScopeCellMut<'b, T>> for ScopeCellMut<'a, T> {
+    fn eq(&self, other: &ScopeCellMut<'b, T>) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<'a, T: Clone + Eq> Eq for ScopeCellMut<'a, T> {}
+
+impl<'a, 'b, T: Clone + PartialOrd> PartialOrd<ScopeCellMut<'b, T>> for ScopeCellMut<'a, T> {
+    fn partial_cmp(&self, other: &ScopeCellMut<'b, T>) -> Option<Ordering> {
+        self.get().partial_cmp(other.get())
+    }
+}
+
+impl<'a, T: Clone + Ord> Ord for ScopeCellMut<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(other.get())
+    }
+}
+
+impl<'a, T: Clone + Hash> Hash for ScopeCellMut<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state)
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq<T> for OwnedScopeCell<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.get() == other
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for OwnedScopeCell<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T: Clone + Eq> Eq for OwnedScopeCell<T> {}
+
+impl<T: Clone + PartialOrd> PartialOrd for OwnedScopeCell<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.get().partial_cmp(other.get())
+    }
+}
+
+impl<T: Clone + Ord> Ord for OwnedScopeCell<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(other.get())
+    }
+}
+
+impl<T: Clone + Hash> Hash for OwnedScopeCell<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state)
+    }
+}
+
+// Display passes straight through to the effective value.
+
+impl<'a, T: Clone + fmt::Display> fmt::Display for ScopeCell<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (*self.borrow()).fmt(f)
+    }
+}
+
+impl<'a, T: Clone + fmt::Display> fmt::Display for ScopeCellMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl<T: Clone + fmt::Display> fmt::Display for OwnedScopeCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total(values: &[i32]) -> i32 {
+        values.iter().sum()
+    }
+
+    fn shout<S: AsRef<String>>(s: S) -> String {
+        s.as_ref().to_uppercase()
+    }
+
+    #[test]
+    fn test_deref_to_effective_value() {
+        let data = vec![1, 2];
+        let mut scope = ScopeCell::new(&data);
+        scope.get_mut().push(3);
+        assert_eq!(total(&scope), 6); // Deref coercion to &Vec, then &[i32]
+        assert_eq!(scope.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "already mutably borrowed")]
+    fn test_deref_respects_borrow_guard() {
+        let data = 1;
+        let cell = ScopeCell::new(&data);
+        let _guard = cell.borrow_mut();
+        let _ = *cell;
+    }
+
+    #[test]
+    fn test_as_ref_and_borrow() {
+        let mut name = String::from("ada");
+        let mut scope = ScopeCellMut::new(&mut name);
+        scope.get_mut().push_str(" lovelace");
+        assert_eq!(shout(&scope), "ADA LOVELACE");
+        let borrowed: &String = Borrow::borrow(&scope);
+        assert_eq!(borrowed, "ada lovelace");
+
+        let owned = OwnedScopeCell::new(String::from("x"));
+        assert_eq!(shout(&owned), "X");
+        assert_eq!(owned.len(), 1);
+    }
+
+    #[test]
+    fn test_compare_with_values_and_cells() {
+        let a = 1;
+        let b = 2;
+        let mut left = ScopeCell::new(&a);
+        let right = ScopeCell::new(&b);
+        assert!(left != 2);
+        assert!(left < right);
+        *left.get_mut() = 2;
+        assert!(left == 2); // Compares the modified value
+        assert!(left == right);
+    }
+
+    #[test]
+    fn test_cells_as_set_members() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let mut set = HashSet::new();
+        let mut cell = OwnedScopeCell::new(String::from("a"));
+        cell.get_mut().push('b');
+        set.insert(cell);
+        assert!(set.contains(&String::from("ab"))); // Lookup by the plain value
+
+        let sorted: BTreeSet<_> = [3, 1, 2].into_iter().map(OwnedScopeCell::new).collect();
+        let order: Vec<i32> = sorted.iter().map(|c| *c.get()).collect();
+        assert_eq!(order, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_display_passthrough() {
+        let value = 1.5;
+        let mut scope = ScopeCell::new(&value);
+        *scope.get_mut() = 2.25;
+        assert_eq!(format!("{:.1}", scope), "2.2"); // Format flags are forwarded
+        assert_eq!(OwnedScopeCell::new("hi").to_string(), "hi");
+    }
+
+    #[test]
+    fn test_borrow_mut_after_compare_and_display() {
+        use std::collections::hash_map::DefaultHasher;
+        let (a, b) = (vec![1], vec![2]);
+        let (left, right) = (ScopeCell::new(&a), ScopeCell::new(&b));
+        assert!(left != right && left < right && left == vec![1]);
+        left.hash(&mut DefaultHasher::new());
+        left.borrow_mut().push(2);
+        right.borrow_mut().push(3);
+        assert_eq!(*left.borrow(), vec![1, 2]);
+        let label = String::from("x");
+        let cell = ScopeCell::new(&label);
+        assert_eq!(cell.to_string(), "x");
+        cell.borrow_mut().push('y');
+        assert_eq!(cell.to_string(), "xy");
+    }
+}
@@ -0,0 +1,286 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+
+use crate::primitives::{static_primitive, thread, AtomicU64, Mutex, RwLock, RwLockReadGuard};
+
+// Global version clock: the version of the latest fully published commit; the source of
+// TVar ids; and the lock under which commits are validated and published one at a time
+static_primitive! {
+    static CLOCK: AtomicU64 = AtomicU64::new(0);
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    static COMMIT: Mutex<()> = Mutex::new(());
+}
+
+/// Why a transaction attempt stopped early.  Both cause `atomically` to run the closure
+/// again; propagate them with `?` rather than handling them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StmError {
+    Conflict, // Another transaction committed to a variable this one read
+    Retry,    // The transaction asked to wait for a different state, see `Tx::retry`
+}
+
+impl fmt::Display for StmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StmError::Conflict => f.write_str("transaction conflicted with another commit"),
+            StmError::Retry => f.write_str("transaction requested a retry"),
+        }
+    }
+}
+
+impl std::error::Error for StmError {}
+
+struct Versioned<T> {
+    version: u64, // Clock value of the commit that wrote `value`
+    value: T,
+}
+
+/// A TVar is a transactional variable.  It is read and written inside `atomically`, where
+/// every write goes to a speculative copy that is only published if no variable the
+/// transaction read has changed in the meantime.
+pub struct TVar<T> {
+    id: u64,
+    cell: RwLock<Versioned<T>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> TVar<T> {
+    // Create a new TVar holding `value`
+    pub fn new(value: T) -> Self {
+        TVar {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            cell: RwLock::new(Versioned { version: 0, value }),
+        }
+    }
+
+    // Clone the latest committed value, outside of any transaction
+    pub fn get(&self) -> T {
+        self.lock_read().value.clone()
+    }
+
+    // Consume the TVar and return its committed value
+    pub fn into_inner(self) -> T {
+        self.cell
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .value
+    }
+
+    fn lock_read(&self) -> RwLockReadGuard<'_, Versioned<T>> {
+        self.cell.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// A TVar of any type, as seen by the commit protocol
+trait AnyTVar: Sync {
+    fn version(&self) -> u64;
+    fn publish(&self, value: Box<dyn Any + Send>, version: u64);
+}
+
+impl<T: Clone + Send + Sync + 'static> AnyTVar for TVar<T> {
+    fn version(&self) -> u64 {
+        self.lock_read().version
+    }
+
+    fn publish(&self, value: Box<dyn Any + Send>, version: u64) {
+        let mut cell = self.cell.write().unwrap_or_else(PoisonError::into_inner);
+        cell.value = *value.downcast().expect("TVar type mismatch");
+        cell.version = version;
+    }
+}
+
+// A speculative value waiting to be published to its TVar
+type PendingWrite<'v> = (&'v dyn AnyTVar, Box<dyn Any + Send>);
+
+/// One attempt at a transaction, handed to the closure passed to `atomically`.
+pub struct Tx<'v> {
+    start: u64,                             // Clock value at the start of the attempt
+    reads: Vec<&'v dyn AnyTVar>,            // Variables read from their committed value
+    writes: HashMap<u64, PendingWrite<'v>>, // Speculative copies by TVar id
+}
+
+impl<'v> Tx<'v> {
+    fn new() -> Self {
+        Tx {
+            start: CLOCK.load(Ordering::Acquire),
+            reads: Vec::new(),
+            writes: HashMap::new(),
+        }
+    }
+
+    // Read a variable, seeing this transaction's own writes.  Fails with `Conflict` if the
+    // variable was committed to after the transaction started.
+    pub fn read<T: Clone + Send + Sync + 'static>(
+        &mut self,
+        var: &'v TVar<T>,
+    ) -> Result<T, StmError> {
+        if let Some((_, pending)) = self.writes.get(&var.id) {
+            return Ok(pending.downcast_ref::<T>().unwrap().clone());
+        }
+        let cell = var.lock_read();
+        if cell.version > self.start {
+            return Err(StmError::Conflict);
+        }
+        self.reads.push(var);
+        Ok(cell.value.clone())
+    }
+
+    // Write a variable.  The value is only visible to this transaction until it commits.
+    pub fn write<T: Clone + Send + Sync + 'static>(&mut self, var: &'v TVar<T>, value: T) {
+        self.writes.insert(var.id, (var, Box::new(value)));
+    }
+
+    // Read, transform and write back a variable
+    pub fn modify<T, F>(&mut self, var: &'v TVar<T>, f: F) -> Result<(), StmError>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: FnOnce(&mut T),
+    {
+        let mut value = self.read(var)?;
+        f(&mut value);
+        self.write(var, value);
+        Ok(())
+    }
+
+    // Abandon this attempt and run the transaction again later, e.g. because a queue it
+    // wants to pop from is empty
+    pub fn retry<R>(&self) -> Result<R, StmError> {
+        Err(StmError::Retry)
+    }
+
+    // Validate the read set and publish the write set.  Returns false on a conflict.
+    fn commit(self) -> bool {
+        let _commit = COMMIT.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.reads.iter().any(|var| var.version() > self.start) {
+            return false;
+        }
+        if self.writes.is_empty() {
+            return true;
+        }
+        let version = CLOCK.load(Ordering::Relaxed) + 1;
+        for (_, (var, value)) in self.writes {
+            var.publish(value, version);
+        }
+        // Only advance the clock once every write is visible, so that a transaction starting
+        // now cannot mix published and unpublished values
+        CLOCK.store(version, Ordering::Release);
+        true
+    }
+}
+
+// Run `f` as a transaction over any number of TVars, retrying it until it commits without
+// conflicts.  `f` may run several times, so it should have no side effects besides the
+// transaction's own reads and writes.
+pub fn atomically<'v, R, F>(mut f: F) -> R
+where
+    F: FnMut(&mut Tx<'v>) -> Result<R, StmError>,
+{
+    loop {
+        let mut tx = Tx::new();
+        match f(&mut tx) {
+            Ok(result) => {
+                if tx.commit() {
+                    return result;
+                }
+            }
+            // A conflicting read may be waiting for a commit to advance the clock, so give
+            // the committing thread a chance to finish
+            Err(StmError::Retry | StmError::Conflict) => thread::yield_now(),
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfers_preserve_total() {
+        let accounts: Vec<TVar<i64>> = (0..4).map(|_| TVar::new(100)).collect();
+        thread::scope(|s| {
+            for t in 0..4 {
+                let accounts = &accounts;
+                s.spawn(move || {
+                    for i in 0..200 {
+                        let from = &accounts[(t + i) % 4];
+                        let to = &accounts[(t + i + 1) % 4];
+                        atomically(|tx| {
+                            tx.modify(from, |v| *v -= 1)?;
+                            tx.modify(to, |v| *v += 1)
+                        });
+                    }
+                });
+            }
+        });
+        let total: i64 = accounts.iter().map(TVar::get).sum();
+        assert_eq!(total, 400);
+    }
+
+    #[test]
+    fn test_reads_see_own_writes() {
+        let a = TVar::new(1);
+        let b = TVar::new(String::from("x"));
+        let seen = atomically(|tx| {
+            tx.write(&a, 5);
+            tx.modify(&b, |s| s.push('y'))?;
+            Ok((tx.read(&a)?, tx.read(&b)?))
+        });
+        assert_eq!(seen, (5, String::from("xy")));
+        assert_eq!(a.into_inner(), 5);
+        assert_eq!(b.get(), "xy");
+    }
+
+    #[test]
+    fn test_failed_attempt_publishes_nothing() {
+        let a = TVar::new(0);
+        let mut attempts = 0;
+        atomically(|tx| {
+            attempts += 1;
+            tx.write(&a, attempts);
+            if attempts < 3 {
+                return Err(StmError::Conflict); // Simulate losing a race twice
+            }
+            Ok(())
+        });
+        assert_eq!(a.get(), 3);
+    }
+
+    #[test]
+    fn test_retry_waits_for_producer() {
+        let queue = TVar::new(Vec::<u32>::new());
+        thread::scope(|s| {
+            s.spawn(|| atomically(|tx| tx.modify(&queue, |q| q.push(7))));
+            let popped = atomically(|tx| {
+                let mut items = tx.read(&queue)?;
+                let Some(item) = items.pop() else {
+                    return tx.retry();
+                };
+                tx.write(&queue, items);
+                Ok(item)
+            });
+            assert_eq!(popped, 7);
+        });
+        assert!(queue.get().is_empty());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+
+    #[test]
+    fn test_concurrent_increments_loom() {
+        loom::model(|| {
+            let counter = Arc::new(TVar::new(0));
+            let other = Arc::clone(&counter);
+            let handle =
+                thread::spawn(move || atomically(|tx| tx.modify(&other, |value| *value += 1)));
+            atomically(|tx| tx.modify(&counter, |value| *value += 1));
+            handle.join().unwrap();
+            assert_eq!(counter.get(), 2);
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use crate::guards::ScopedReplace;
+
+/// A SwapScopeCell swaps a replacement into a place for the duration of a scope and swaps
+/// the original back when dropped, without cloning either.  It suits temporarily replacing a
+/// sink, logger or buffer: code reaching the place through the cell sees the replacement,
+/// and `commit()` keeps it for good.  It is `guards::ScopedReplace` with the cells' method
+/// names, so the two behave identically.
+pub struct SwapScopeCell<'a, T> {
+    guard: ScopedReplace<'a, T>,
+}
+
+impl<'a, T> SwapScopeCell<'a, T> {
+    // Swap `replacement` into `target` until the cell is dropped
+    pub fn new(target: &'a mut T, replacement: T) -> Self {
+        SwapScopeCell {
+            guard: ScopedReplace::new(target, replacement),
+        }
+    }
+
+    // Borrow the replacement
+    pub fn get(&self) -> &T {
+        &self.guard
+    }
+
+    // Mutably borrow the replacement
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+
+    // Borrow the original, which is swapped back on drop
+    pub fn original(&self) -> &T {
+        self.guard.saved()
+    }
+
+    // Mutably borrow the original while it is swapped out
+    pub fn original_mut(&mut self) -> &mut T {
+        self.guard.saved_mut()
+    }
+
+    // Swap the original back now and return the replacement
+    pub fn restore(self) -> T {
+        self.guard.restore()
+    }
+
+    // Keep the replacement in place and return the original
+    pub fn commit(self) -> T {
+        self.guard.keep()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A sink that records what reaches it
+    #[derive(Default)]
+    struct Sink {
+        lines: Vec<String>,
+    }
+
+    impl Sink {
+        fn log(&mut self, line: &str) {
+            self.lines.push(line.to_owned());
+        }
+    }
+
+    #[test]
+    fn test_swap_sink_for_scope() {
+        let mut sink = Sink::default();
+        sink.log("before");
+        {
+            let mut scoped = SwapScopeCell::new(&mut sink, Sink::default());
+            scoped.get_mut().log("captured");
+            scoped.original_mut().log("passed through");
+            assert_eq!(scoped.get().lines, vec!["captured"]);
+        }
+        assert_eq!(sink.lines, vec!["before", "passed through"]);
+    }
+
+    #[test]
+    fn test_restore_and_commit() {
+        let mut buffer = vec![1, 2];
+        let scratch = SwapScopeCell::new(&mut buffer, Vec::with_capacity(64));
+        assert!(scratch.get().is_empty());
+        assert_eq!(scratch.original(), &vec![1, 2]);
+        let mut replacement = scratch.restore();
+        replacement.push(9);
+        assert_eq!(buffer, vec![1, 2]);
+
+        let original = SwapScopeCell::new(&mut buffer, replacement).commit();
+        assert_eq!(original, vec![1, 2]);
+        assert_eq!(buffer, vec![9]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+
+use crate::primitives::{AtomicBool, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::ScopeError;
+
+/// A SyncScopeCell is the thread-safe counterpart of `ScopeCell`.  The temporary copy lives
+/// behind an `RwLock`, so a scoped override can be shared across worker threads; changes
+/// are still discarded when the cell is dropped.
+pub struct SyncScopeCell<'a, T: Clone> {
+    original_data: &'a T,
+    modified_data: RwLock<Option<T>>, // Holds temporary modified data
+    dirty: AtomicBool,                // Set on mutable access, cleared by revert/reset_tracking
+}
+
+impl<'a, T: Clone> SyncScopeCell<'a, T> {
+    // Create a new SyncScopeCell from an immutable reference
+    pub fn new(data: &'a T) -> Self {
+        SyncScopeCell {
+            original_data: data,
+            modified_data: RwLock::new(None),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    // Lock the cell for reading, showing either the original or the modified version
+    pub fn read(&self) -> SyncScopeRead<'_, T> {
+        self.try_read().expect("SyncScopeCell lock poisoned")
+    }
+
+    // Lock the cell for reading, or fail if a writer panicked while holding the lock
+    pub fn try_read(&self) -> Result<SyncScopeRead<'_, T>, ScopeError> {
+        Ok(SyncScopeRead {
+            guard: self
+                .modified_data
+                .read()
+                .map_err(|_| ScopeError::Poisoned)?,
+            original_data: self.original_data,
+        })
+    }
+
+    // Lock the cell for writing.  The temporary copy is only created on the first mutable
+    // access through the guard.
+    pub fn write(&self) -> SyncScopeWrite<'_, T> {
+        self.try_write().expect("SyncScopeCell lock poisoned")
+    }
+
+    // Lock the cell for writing, or fail if a writer panicked while holding the lock
+    pub fn try_write(&self) -> Result<SyncScopeWrite<'_, T>, ScopeError> {
+        Ok(SyncScopeWrite {
+            guard: self
+                .modified_data
+                .write()
+                .map_err(|_| ScopeError::Poisoned)?,
+            original_data: self.original_data,
+            dirty: &self.dirty,
+        })
+    }
+
+    // Clone the effective value out of the cell
+    pub fn get_cloned(&self) -> T {
+        self.read().clone()
+    }
+
+    // Revert the changes made to the data by dropping the modified data.  This works even
+    // on a poisoned cell, since it throws away whatever the panicking writer left behind.
+    pub fn revert(&self) {
+        let mut modified = self
+            .modified_data
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        *modified = None;
+        self.dirty.store(false, Ordering::Release);
+    }
+
+    // Whether a writer panicked while holding the lock.  Loom's locks never poison, since a
+    // panic fails the whole model.
+    pub fn is_poisoned(&self) -> bool {
+        #[cfg(not(loom))]
+        return self.modified_data.is_poisoned();
+        #[cfg(loom)]
+        return false;
+    }
+
+    // Accept the possibly half-updated value (or `revert()` first) and allow access again
+    pub fn clear_poison(&self) {
+        #[cfg(not(loom))]
+        self.modified_data.clear_poison();
+    }
+
+    // Whether the data has been mutably accessed since creation, the last revert, or the last reset_tracking
+    pub fn is_modified(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    // Clear the dirty flag without discarding the modified data
+    pub fn reset_tracking(&self) {
+        self.dirty.store(false, Ordering::Release);
+    }
+
+    // Consume the cell and return the modified data if it exists, otherwise the original data
+    pub fn into_inner(self) -> T {
+        self.try_into_inner().expect("SyncScopeCell lock poisoned")
+    }
+
+    // Consume the cell like `into_inner()`, or fail if a writer panicked while holding the lock
+    pub fn try_into_inner(self) -> Result<T, ScopeError> {
+        let original = self.original_data;
+        let modified = self
+            .modified_data
+            .into_inner()
+            .map_err(|_| ScopeError::Poisoned)?;
+        Ok(modified.unwrap_or_else(|| original.clone()))
+    }
+}
+
+/// A read lock on a SyncScopeCell.
+pub struct SyncScopeRead<'b, T> {
+    guard: RwLockReadGuard<'b, Option<T>>,
+    original_data: &'b T,
+}
+
+impl<'b, T> Deref for SyncScopeRead<'b, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_ref().unwrap_or(self.original_data)
+    }
+}
+
+/// A write lock on a SyncScopeCell.
+pub struct SyncScopeWrite<'b, T> {
+    guard: RwLockWriteGuard<'b, Option<T>>,
+    original_data: &'b T,
+    dirty: &'b AtomicBool,
+}
+
+impl<'b, T> Deref for SyncScopeWrite<'b, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard.as_ref().unwrap_or(self.original_data)
+    }
+}
+
+impl<'b, T: Clone> DerefMut for SyncScopeWrite<'b, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty.store(true, Ordering::Release);
+        let original = self.original_data;
+        self.guard.get_or_insert_with(|| original.clone())
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_shared_across_threads() {
+        let data = vec![0; 4];
+        {
+            let cell = SyncScopeCell::new(&data);
+            thread::scope(|s| {
+                for i in 0..4 {
+                    let cell = &cell;
+                    s.spawn(move || cell.write()[i] = i + 1);
+                }
+            });
+            assert_eq!(*cell.read(), vec![1, 2, 3, 4]);
+            assert!(cell.is_modified());
+        } // Dropped, changes discarded
+        assert_eq!(data, vec![0; 4]);
+    }
+
+    #[test]
+    fn test_read_only_write_guard_does_not_clone() {
+        let data = String::from("hello");
+        let cell = SyncScopeCell::new(&data);
+        assert_eq!(cell.write().len(), 5);
+        assert!(!cell.is_modified());
+    }
+
+    #[test]
+    #[cfg(not(feature = "parking_lot"))]
+    fn test_try_methods_report_poison() {
+        let data = 1;
+        let cell = SyncScopeCell::new(&data);
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                let _guard = cell.write();
+                panic!("writer failed");
+            });
+            assert!(writer.join().is_err());
+        });
+        assert_eq!(cell.try_read().err(), Some(ScopeError::Poisoned));
+        assert!(cell.try_write().is_err());
+        assert!(cell.is_poisoned());
+        cell.revert();
+        cell.clear_poison();
+        assert_eq!(cell.try_read().map(|v| *v), Ok(1)); // The guard never wrote
+        assert_eq!(cell.try_into_inner(), Ok(1));
+    }
+
+    #[test]
+    #[cfg(feature = "parking_lot")]
+    fn test_parking_lot_writer_does_not_poison() {
+        let data = 1;
+        let cell = SyncScopeCell::new(&data);
+        thread::scope(|s| {
+            let writer = s.spawn(|| {
+                *cell.write() = 2;
+                let _guard = cell.write();
+                panic!("writer failed");
+            });
+            assert!(writer.join().is_err());
+        });
+        assert!(!cell.is_poisoned());
+        assert_eq!(cell.try_read().map(|v| *v), Ok(2)); // The lock was simply released
+    }
+
+    #[test]
+    fn test_revert_and_into_inner() {
+        let data = 10;
+        let cell = SyncScopeCell::new(&data);
+        *cell.write() = 20;
+        assert_eq!(cell.get_cloned(), 20);
+        cell.revert();
+        assert_eq!(*cell.read(), 10);
+        *cell.write() = 30;
+        assert_eq!(cell.into_inner(), 30);
+        assert_eq!(data, 10);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use crate::primitives::thread;
+    use loom::sync::Arc;
+
+    #[test]
+    fn test_reader_sees_whole_writes_loom() {
+        loom::model(|| {
+            let base: &'static Vec<u8> = Box::leak(Box::new(vec![1]));
+            let cell = Arc::new(SyncScopeCell::new(base));
+            let writer = Arc::clone(&cell);
+            let handle = thread::spawn(move || writer.write().push(2));
+            let seen = cell.get_cloned();
+            assert!(seen == [1] || seen == [1, 2]);
+            handle.join().unwrap();
+            assert_eq!(cell.get_cloned(), [1, 2]);
+            assert!(cell.is_modified());
+        });
+    }
+}
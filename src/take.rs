@@ -0,0 +1,124 @@
+use std::mem;
+
+/// A TakeScopeCell scopes edits to values that cannot, or should not, be cloned.  It takes
+/// the original out of the target with `std::mem::take`, and the scope works on a fresh
+/// value left in its place, `T::default()` or one built from the original with `with()`.
+/// Dropping the cell puts the original back; `commit()` keeps the working value instead.
+pub struct TakeScopeCell<'a, T: Default> {
+    target: &'a mut T,   // Holds the working value during the scope
+    original: Option<T>, // Put back on drop, unless committed
+    dirty: bool,         // Set on mutable access, cleared by revert
+}
+
+impl<'a, T: Default> TakeScopeCell<'a, T> {
+    // Take the original out of `target`, leaving `T::default()` to work on
+    pub fn new(target: &'a mut T) -> Self {
+        let original = mem::take(target);
+        TakeScopeCell {
+            target,
+            original: Some(original),
+            dirty: false,
+        }
+    }
+
+    // Take the original out of `target`, leaving the value `build` makes from it to work on
+    pub fn with<F: FnOnce(&T) -> T>(target: &'a mut T, build: F) -> Self {
+        let cell = TakeScopeCell::new(target);
+        *cell.target = build(cell.original());
+        cell
+    }
+
+    // Borrow the working value
+    pub fn get(&self) -> &T {
+        self.target
+    }
+
+    // Mutably borrow the working value
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        self.target
+    }
+
+    // Borrow the original, which is put back on drop.  Panics after a `Participant` commit,
+    // which gives the original up.
+    pub fn original(&self) -> &T {
+        self.original
+            .as_ref()
+            .expect("TakeScopeCell original already committed away")
+    }
+
+    // Whether the working value has been mutably accessed since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Start over from `T::default()`
+    pub fn revert(&mut self) {
+        *self.target = T::default();
+        self.dirty = false;
+    }
+
+    // Keep the working value in the target and return the original
+    pub fn commit(mut self) -> T {
+        self.original.take().unwrap_or_default()
+    }
+
+    // Keep the working value in the target, dropping the original
+    pub(crate) fn write_back(&mut self) {
+        self.original = None;
+    }
+}
+
+impl<'a, T: Default> Drop for TakeScopeCell<'a, T> {
+    fn drop(&mut self) {
+        if let Some(original) = self.original.take() {
+            *self.target = original;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_original_restored_on_drop() {
+        // A channel receiver cannot be cloned
+        let (tx, rx) = mpsc::channel::<u32>();
+        let mut inbox: Option<mpsc::Receiver<u32>> = Some(rx);
+        {
+            let mut cell = TakeScopeCell::new(&mut inbox);
+            assert!(cell.get().is_none());
+            let (_scoped_tx, scoped_rx) = mpsc::channel();
+            *cell.get_mut() = Some(scoped_rx);
+            assert!(cell.is_modified());
+            assert!(cell.original().is_some());
+        }
+        tx.send(7).unwrap();
+        assert_eq!(inbox.unwrap().recv().unwrap(), 7); // The original receiver is back
+    }
+
+    #[test]
+    fn test_commit_keeps_working_value() {
+        let mut log = vec![String::from("old")];
+        let cell = TakeScopeCell::with(&mut log, |original| {
+            vec![format!("{} lines", original.len())]
+        });
+        let original = cell.commit();
+        assert_eq!(original, vec!["old"]);
+        assert_eq!(log, vec!["1 lines"]);
+    }
+
+    #[test]
+    fn test_revert_starts_over() {
+        let mut buffer = String::from("keep");
+        let mut cell = TakeScopeCell::new(&mut buffer);
+        cell.get_mut().push_str("scratch");
+        cell.revert();
+        assert!(cell.get().is_empty());
+        assert!(!cell.is_modified());
+        drop(cell);
+        assert_eq!(buffer, "keep");
+    }
+}
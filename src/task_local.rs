@@ -0,0 +1,95 @@
+use std::future::Future;
+use tokio::task::{futures::TaskLocalFuture, LocalKey};
+
+/// A TaskScopedStatic is the async counterpart of `ScopedStatic`: overrides are pinned to
+/// the current tokio task through a `task_local!` key, so code awaited inside the scope,
+/// however deep in the call stack, sees the override.  The previous value is restored when
+/// the scoped future completes or is dropped.  Outside any override the default is used.
+///
+/// The key is declared with `tokio::task_local!` and wrapped in a `static`:
+/// `static LEVEL: TaskScopedStatic<u32> = TaskScopedStatic::new(&LEVEL_KEY, 0);`
+pub struct TaskScopedStatic<T: 'static> {
+    key: &'static LocalKey<T>,
+    default: T,
+}
+
+impl<T: 'static> TaskScopedStatic<T> {
+    // Wrap a task-local key with the value used while no override is live
+    pub const fn new(key: &'static LocalKey<T>, default: T) -> Self {
+        TaskScopedStatic { key, default }
+    }
+
+    // Run `future` with the value overridden; nested overrides shadow outer ones
+    pub fn set_scoped<F: Future>(&'static self, value: T, future: F) -> TaskLocalFuture<T, F> {
+        self.key.scope(value, future)
+    }
+
+    // Run a synchronous closure with the value overridden
+    pub fn sync_scoped<R, F: FnOnce() -> R>(&'static self, value: T, f: F) -> R {
+        self.key.sync_scope(value, f)
+    }
+
+    // Run `f` with the effective value
+    pub fn with<R, F: FnOnce(&T) -> R>(&'static self, f: F) -> R {
+        let mut f = Some(f);
+        match self.key.try_with(|value| (f.take().unwrap())(value)) {
+            Ok(result) => result,
+            Err(_) => (f.take().unwrap())(&self.default),
+        }
+    }
+
+    // Clone the effective value
+    pub fn get(&'static self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    tokio::task_local! {
+        static LEVEL_KEY: u32;
+    }
+
+    static LEVEL: TaskScopedStatic<u32> = TaskScopedStatic::new(&LEVEL_KEY, 0);
+
+    async fn deep_read() -> u32 {
+        tokio::task::yield_now().await;
+        LEVEL.get()
+    }
+
+    #[tokio::test]
+    async fn test_override_visible_across_awaits() {
+        assert_eq!(LEVEL.get(), 0);
+        let seen = LEVEL
+            .set_scoped(3, async {
+                let outer = deep_read().await;
+                let inner = LEVEL.set_scoped(4, deep_read()).await;
+                (outer, inner, LEVEL.get())
+            })
+            .await;
+        assert_eq!(seen, (3, 4, 3)); // Inner override restored on completion
+        assert_eq!(LEVEL.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_other_tasks_unaffected() {
+        LEVEL
+            .set_scoped(7, async {
+                let other = tokio::spawn(async { LEVEL.get() }).await.unwrap();
+                assert_eq!(other, 0);
+                assert_eq!(LEVEL.get(), 7);
+            })
+            .await;
+    }
+
+    #[test]
+    fn test_sync_scoped() {
+        assert_eq!(LEVEL.sync_scoped(9, || LEVEL.with(|level| level + 1)), 10);
+        assert_eq!(LEVEL.get(), 0);
+    }
+}
@@ -0,0 +1,194 @@
+use std::time::{Duration, Instant};
+
+/// A TimedScopeCell owns a baseline value and an override that expires on its own, so a
+/// temporary change such as a feature-flag override cannot be forgotten.  Expiry is checked
+/// lazily: once the deadline passes, reads see the baseline again and the override is
+/// dropped on the next mutable access.  With the **tokio** feature, `spawn_expiry` reverts
+/// a shared cell actively when the deadline passes.
+#[derive(Clone)]
+pub struct TimedScopeCell<T: Clone> {
+    original_data: T,
+    modified_data: Option<(T, Instant)>, // The override and its deadline
+}
+
+impl<T: Clone> TimedScopeCell<T> {
+    // Create a new TimedScopeCell taking ownership of the baseline value
+    pub fn new(data: T) -> Self {
+        TimedScopeCell {
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the baseline, ignoring any override
+    pub fn baseline(&self) -> &T {
+        &self.original_data
+    }
+
+    // Borrow the live override if there is one, otherwise the baseline
+    pub fn get(&self) -> &T {
+        match &self.modified_data {
+            Some((value, deadline)) if Instant::now() < *deadline => value,
+            _ => &self.original_data,
+        }
+    }
+
+    // Override the value for `ttl`, replacing any existing override
+    pub fn set_for(&mut self, value: T, ttl: Duration) {
+        self.set_until(value, Instant::now() + ttl);
+    }
+
+    // Override the value until `deadline`, replacing any existing override
+    pub fn set_until(&mut self, value: T, deadline: Instant) {
+        self.modified_data = Some((value, deadline));
+    }
+
+    // Mutably borrow the override, starting one from a copy of the baseline that lasts for
+    // `ttl` if none is live.  A live override keeps its deadline.
+    pub fn get_mut_for(&mut self, ttl: Duration) -> &mut T {
+        self.expire();
+        let original = &self.original_data;
+        let (value, _) = self
+            .modified_data
+            .get_or_insert_with(|| (original.clone(), Instant::now() + ttl));
+        value
+    }
+
+    // Mutably borrow the live override, if there is one
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.expire();
+        self.modified_data.as_mut().map(|(value, _)| value)
+    }
+
+    // Push the live override's deadline to `ttl` from now.  Returns false if no override is
+    // live.
+    pub fn extend(&mut self, ttl: Duration) -> bool {
+        self.expire();
+        match &mut self.modified_data {
+            Some((_, deadline)) => {
+                *deadline = Instant::now() + ttl;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // When the live override expires
+    pub fn deadline(&self) -> Option<Instant> {
+        self.modified_data
+            .as_ref()
+            .map(|(_, deadline)| *deadline)
+            .filter(|deadline| Instant::now() < *deadline)
+    }
+
+    // Time left on the live override
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    // Whether an override is live
+    pub fn is_modified(&self) -> bool {
+        self.deadline().is_some()
+    }
+
+    // Drop the override before its deadline
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Drop the override if its deadline has passed.  Returns whether one was dropped.
+    pub fn expire(&mut self) -> bool {
+        let expired = self.modified_data.is_some() && !self.is_modified();
+        if expired {
+            self.modified_data = None;
+        }
+        expired
+    }
+
+    // Consume the cell and return the baseline
+    pub fn into_inner(self) -> T {
+        self.original_data
+    }
+}
+
+/// Revert the cell's override as soon as it expires, from a background tokio task.  The
+/// task follows deadlines pushed back by `extend()` or a new override and finishes once the
+/// cell has no override left, or when the cell is dropped.
+#[cfg(feature = "tokio")]
+pub fn spawn_expiry<T>(
+    cell: &std::sync::Arc<std::sync::Mutex<TimedScopeCell<T>>>,
+) -> tokio::task::JoinHandle<()>
+where
+    T: Clone + Send + 'static,
+{
+    use std::sync::PoisonError;
+
+    let cell = std::sync::Arc::downgrade(cell);
+    tokio::spawn(async move {
+        loop {
+            let deadline = {
+                let Some(cell) = cell.upgrade() else {
+                    return;
+                };
+                let mut cell = cell.lock().unwrap_or_else(PoisonError::into_inner);
+                cell.expire();
+                match cell.modified_data.as_ref() {
+                    Some((_, deadline)) => *deadline,
+                    None => return,
+                }
+            };
+            tokio::time::sleep_until(deadline.into()).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_override_until_deadline() {
+        let mut flag = TimedScopeCell::new(false);
+        flag.set_for(true, HOUR);
+        assert!(*flag.get());
+        assert!(flag.is_modified());
+        assert!(flag.remaining().unwrap() <= HOUR);
+        flag.set_for(true, Duration::ZERO); // Already expired
+        assert!(!*flag.get());
+        assert!(!flag.is_modified());
+        assert!(flag.get_mut().is_none());
+        assert!(!flag.expire()); // Dropped by `get_mut` already
+    }
+
+    #[test]
+    fn test_get_mut_for_and_extend() {
+        let mut limits = TimedScopeCell::new(vec![1, 2]);
+        limits.get_mut_for(Duration::ZERO).push(3);
+        assert_eq!(limits.get(), &vec![1, 2]); // Expired straight away
+        assert!(!limits.extend(HOUR));
+
+        limits.get_mut_for(HOUR).push(3);
+        limits.get_mut_for(Duration::ZERO).push(4); // Keeps the live override's deadline
+        assert_eq!(limits.get(), &vec![1, 2, 3, 4]);
+        assert!(limits.extend(2 * HOUR));
+        assert!(limits.remaining().unwrap() > HOUR);
+        limits.revert();
+        assert_eq!(limits.into_inner(), vec![1, 2]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_spawn_expiry_reverts_actively() {
+        use std::sync::{Arc, Mutex};
+
+        let cell = Arc::new(Mutex::new(TimedScopeCell::new(1)));
+        cell.lock().unwrap().set_for(2, Duration::from_millis(20));
+        let task = spawn_expiry(&cell);
+        assert_eq!(*cell.lock().unwrap().get(), 2);
+        task.await.unwrap();
+        assert!(cell.lock().unwrap().modified_data.is_none()); // Dropped, not just hidden
+    }
+}
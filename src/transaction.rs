@@ -0,0 +1,162 @@
+/// A Transaction collects edits to a mutably borrowed value and only writes them back
+/// on `commit()`.  Savepoints mark intermediate states that can be rolled back to, and
+/// `child()` opens a nested transaction whose changes become visible to its parent only
+/// when the child commits.
+pub struct Transaction<'a, T: Clone> {
+    target: &'a mut T,
+    working: Option<T>,         // Lazily cloned working copy
+    savepoints: Vec<Option<T>>, // Working copy at each savepoint, `None` if untouched
+}
+
+/// A handle to a savepoint created by `Transaction::savepoint()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Savepoint {
+    depth: usize,
+}
+
+impl<'a, T: Clone> Transaction<'a, T> {
+    // Begin a transaction over a mutable reference
+    pub fn new(target: &'a mut T) -> Self {
+        Transaction {
+            target,
+            working: None,
+            savepoints: Vec::new(),
+        }
+    }
+
+    // Borrow the data as seen inside the transaction
+    pub fn get(&self) -> &T {
+        self.working.as_ref().unwrap_or(self.target)
+    }
+
+    // Mutably borrow the data, creating the working copy if necessary
+    pub fn get_mut(&mut self) -> &mut T {
+        let target = &*self.target;
+        self.working.get_or_insert_with(|| target.clone())
+    }
+
+    // Record the current state so it can be restored with `rollback_to`
+    pub fn savepoint(&mut self) -> Savepoint {
+        self.savepoints.push(self.working.clone());
+        Savepoint {
+            depth: self.savepoints.len() - 1,
+        }
+    }
+
+    // Restore the state recorded by `savepoint`, discarding every later savepoint.
+    // The savepoint itself stays valid.  Panics if it was already discarded.
+    pub fn rollback_to(&mut self, savepoint: Savepoint) {
+        if savepoint.depth >= self.savepoints.len() {
+            panic!("savepoint no longer exists");
+        }
+        self.savepoints.truncate(savepoint.depth + 1);
+        self.working = self.savepoints[savepoint.depth].clone();
+    }
+
+    // Discard every change and savepoint made in this transaction
+    pub fn rollback(&mut self) {
+        self.working = None;
+        self.savepoints.clear();
+    }
+
+    // Number of live savepoints
+    pub fn savepoint_count(&self) -> usize {
+        self.savepoints.len()
+    }
+
+    // Open a nested transaction over this transaction's current state
+    pub fn child(&mut self) -> Transaction<'_, T> {
+        Transaction::new(self.get_mut())
+    }
+
+    // Write the changes into the parent transaction, or into the original data for a root transaction
+    pub fn commit(mut self) {
+        self.write_back();
+    }
+
+    // Write the changes back in place, leaving the transaction without changes or savepoints
+    pub(crate) fn write_back(&mut self) {
+        if let Some(working) = self.working.take() {
+            *self.target = working;
+        }
+        self.savepoints.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_and_drop() {
+        let mut data = vec![1, 2, 3];
+        {
+            let mut tx = Transaction::new(&mut data);
+            tx.get_mut().push(4);
+        } // Dropped without commit
+        assert_eq!(data, vec![1, 2, 3]);
+
+        let mut tx = Transaction::new(&mut data);
+        tx.get_mut().push(4);
+        tx.commit();
+        assert_eq!(data, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_savepoints() {
+        let mut data = vec![1];
+        let mut tx = Transaction::new(&mut data);
+        let untouched = tx.savepoint();
+        tx.get_mut().push(2);
+        let two = tx.savepoint();
+        tx.get_mut().push(3);
+        tx.savepoint();
+        tx.get_mut().push(4);
+
+        tx.rollback_to(two);
+        assert_eq!(*tx.get(), vec![1, 2]);
+        assert_eq!(tx.savepoint_count(), 2); // Later savepoint discarded
+
+        tx.get_mut().push(5);
+        tx.rollback_to(two); // Savepoint can be reused
+        assert_eq!(*tx.get(), vec![1, 2]);
+
+        tx.rollback_to(untouched);
+        assert_eq!(*tx.get(), vec![1]);
+        tx.commit();
+        assert_eq!(data, vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "savepoint no longer exists")]
+    fn test_rollback_to_discarded_savepoint_panics() {
+        let mut data = 0;
+        let mut tx = Transaction::new(&mut data);
+        let first = tx.savepoint();
+        let second = tx.savepoint();
+        tx.rollback_to(first);
+        tx.rollback_to(second);
+    }
+
+    #[test]
+    fn test_nested_transactions() {
+        let mut data = vec![1];
+        let mut tx = Transaction::new(&mut data);
+        {
+            let mut child = tx.child();
+            child.get_mut().push(2);
+            child.commit(); // Visible to the parent only
+        }
+        {
+            let mut child = tx.child();
+            child.get_mut().push(3);
+            let mut grandchild = child.child();
+            grandchild.get_mut().push(4);
+            grandchild.commit();
+            assert_eq!(*child.get(), vec![1, 2, 3, 4]);
+        } // Child dropped without commit, grandchild's changes go with it
+        assert_eq!(*tx.get(), vec![1, 2]);
+        tx.commit();
+        assert_eq!(data, vec![1, 2]);
+    }
+}
@@ -0,0 +1,204 @@
+/// A TrivialScopeCell has the core API of `ScopeCell` implemented in the most obvious way:
+/// it clones the data eagerly on creation, keeps the effective value as a plain field and
+/// uses no unsafe code or interior mutability.  It is a reference model, not a cell to use
+/// in production: swap it in for `ScopeCell` to differentially test code built on the
+/// crate, or compare the two step by step in property tests.
+#[derive(Clone, Debug)]
+pub struct TrivialScopeCell<'a, T: Clone> {
+    original_data: &'a T,
+    current: T,                // The effective value, a clone from the start
+    has_copy: bool,            // Whether `ScopeCell` would hold a modified copy
+    dirty: bool,               // Set on mutable access, cleared by revert/reset_tracking
+    snapshots: Vec<(T, bool)>, // Effective values, each with its `has_copy`
+}
+
+impl<'a, T: Clone> TrivialScopeCell<'a, T> {
+    // Create a new TrivialScopeCell, cloning `data` right away
+    pub fn new(data: &'a T) -> Self {
+        TrivialScopeCell {
+            original_data: data,
+            current: data.clone(),
+            has_copy: false,
+            dirty: false,
+            snapshots: Vec::new(),
+        }
+    }
+
+    // Borrow the effective value
+    pub fn get(&self) -> &T {
+        &self.current
+    }
+
+    // Borrow the original, ignoring any modification
+    pub fn original(&self) -> &T {
+        self.original_data
+    }
+
+    // Mutably borrow the effective value
+    pub fn get_mut(&mut self) -> &mut T {
+        self.has_copy = true;
+        self.dirty = true;
+        &mut self.current
+    }
+
+    // Apply a closure to the effective value
+    pub fn update<R, F: FnOnce(&mut T) -> R>(&mut self, f: F) -> R {
+        f(self.get_mut())
+    }
+
+    // Overwrite the effective value
+    pub fn set(&mut self, value: T) {
+        *self.get_mut() = value;
+    }
+
+    // Overwrite the effective value, returning the previous one
+    pub fn replace(&mut self, value: T) -> T {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    // Go back to the original and drop every snapshot
+    pub fn revert(&mut self) {
+        self.current = self.original_data.clone();
+        self.has_copy = false;
+        self.dirty = false;
+        self.snapshots.clear();
+    }
+
+    // Whether the data has been mutably accessed since creation, the last revert or the
+    // last `reset_tracking()`
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    // Clear the modified flag, keeping the effective value
+    pub fn reset_tracking(&mut self) {
+        self.dirty = false;
+    }
+
+    // Record the effective value as a restore point, returning the number of snapshots held
+    pub fn push_snapshot(&mut self) -> usize {
+        self.snapshots.push((self.current.clone(), self.has_copy));
+        self.snapshots.len()
+    }
+
+    // Discard the most recent snapshot.  Returns false if there was none.
+    pub fn pop_snapshot(&mut self) -> bool {
+        self.snapshots.pop().is_some()
+    }
+
+    // Restore the most recent snapshot, keeping it on the stack.  Returns false if there is
+    // none.
+    pub fn revert_to_last_snapshot(&mut self) -> bool {
+        let Some((value, has_copy)) = self.snapshots.last() else {
+            return false;
+        };
+        self.current = value.clone();
+        self.has_copy = *has_copy;
+        self.dirty = *has_copy;
+        true
+    }
+
+    // Number of snapshots currently on the stack
+    pub fn snapshot_count(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    // Consume the cell and return the effective value
+    pub fn into_inner(self) -> T {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ScopeCell;
+
+    // The operations both cells are driven through
+    #[derive(Clone, Debug)]
+    enum Op {
+        Write(u8),
+        Push(u8),
+        Revert,
+        Snapshot,
+        PopSnapshot,
+        RevertToSnapshot,
+        ResetTracking,
+    }
+
+    fn check_against_scope_cell(ops: &[Op]) {
+        let base = vec![0u8];
+        let mut cell = ScopeCell::new(&base);
+        let mut model = TrivialScopeCell::new(&base);
+        for (step, op) in ops.iter().enumerate() {
+            match op {
+                Op::Write(value) => {
+                    cell.set(vec![*value]);
+                    model.set(vec![*value]);
+                }
+                Op::Push(value) => {
+                    cell.get_mut().push(*value);
+                    model.get_mut().push(*value);
+                }
+                Op::Revert => {
+                    cell.revert();
+                    model.revert();
+                }
+                Op::Snapshot => assert_eq!(cell.push_snapshot(), model.push_snapshot()),
+                Op::PopSnapshot => assert_eq!(cell.pop_snapshot(), model.pop_snapshot()),
+                Op::RevertToSnapshot => assert_eq!(
+                    cell.revert_to_last_snapshot(),
+                    model.revert_to_last_snapshot()
+                ),
+                Op::ResetTracking => {
+                    cell.reset_tracking();
+                    model.reset_tracking();
+                }
+            }
+            let context = format!("step {} ({:?})", step, op);
+            assert_eq!(cell.get(), model.get(), "{}", context);
+            assert_eq!(cell.is_modified(), model.is_modified(), "{}", context);
+            assert_eq!(cell.snapshot_count(), model.snapshot_count(), "{}", context);
+        }
+        assert_eq!(cell.into_inner(), model.into_inner());
+    }
+
+    #[test]
+    fn test_matches_scope_cell() {
+        check_against_scope_cell(&[
+            Op::Push(1),
+            Op::Snapshot,
+            Op::ResetTracking,
+            Op::Write(5),
+            Op::RevertToSnapshot,
+            Op::PopSnapshot,
+            Op::RevertToSnapshot,
+            Op::Revert,
+            Op::Snapshot,
+            Op::Push(2),
+            Op::RevertToSnapshot,
+        ]);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn test_matches_scope_cell_generated(ops in proptest::collection::vec(op(), 0..64)) {
+            check_against_scope_cell(&ops);
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    fn op() -> impl proptest::strategy::Strategy<Value = Op> {
+        use proptest::prelude::*;
+        prop_oneof![
+            any::<u8>().prop_map(Op::Write),
+            any::<u8>().prop_map(Op::Push),
+            Just(Op::Revert),
+            Just(Op::Snapshot),
+            Just(Op::PopSnapshot),
+            Just(Op::RevertToSnapshot),
+            Just(Op::ResetTracking),
+        ]
+    }
+}
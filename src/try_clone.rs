@@ -0,0 +1,149 @@
+use std::fs::File;
+use std::io;
+use std::net::{TcpListener, TcpStream, UdpSocket};
+
+/// Types whose copies can fail, such as file handles and sockets, which duplicate an OS
+/// handle, or values whose allocator can run out.
+pub trait TryClone: Sized {
+    type Error;
+
+    fn try_clone(&self) -> Result<Self, Self::Error>;
+}
+
+macro_rules! impl_try_clone_io {
+    ($($ty:ty),*) => {
+        $(
+            impl TryClone for $ty {
+                type Error = io::Error;
+
+                fn try_clone(&self) -> io::Result<Self> {
+                    <$ty>::try_clone(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_try_clone_io!(File, TcpListener, TcpStream, UdpSocket);
+
+/// A TryScopeCell is a `ScopeCell` for types that implement `TryClone` instead of `Clone`.
+/// The first mutable access makes the copy with `try_clone()`, so `try_get_mut()` returns
+/// the clone error instead of panicking; once the copy exists, later accesses cannot fail.
+/// Changes are discarded when the cell is dropped.
+pub struct TryScopeCell<'a, T: TryClone> {
+    original_data: &'a T,
+    modified_data: Option<T>, // Holds the temporary copy
+}
+
+impl<'a, T: TryClone> TryScopeCell<'a, T> {
+    // Create a new TryScopeCell from an immutable reference
+    pub fn new(data: &'a T) -> Self {
+        TryScopeCell {
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        self.modified_data.as_ref().unwrap_or(self.original_data)
+    }
+
+    // Mutably borrow the data, copying the original with `try_clone()` on first use
+    pub fn try_get_mut(&mut self) -> Result<&mut T, T::Error> {
+        if self.modified_data.is_none() {
+            self.modified_data = Some(self.original_data.try_clone()?);
+        }
+        Ok(self.modified_data.as_mut().unwrap())
+    }
+
+    // Replace the data without copying the original, which cannot fail
+    pub fn set(&mut self, value: T) {
+        self.modified_data = Some(value);
+    }
+
+    // Whether a copy has been made or set since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes by dropping the copy
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Consume the cell and return the modified data, copying the original if there is none
+    pub fn try_into_inner(self) -> Result<T, T::Error> {
+        match self.modified_data {
+            Some(modified) => Ok(modified),
+            None => self.original_data.try_clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::io::{Read, Write};
+
+    // A buffer from a pool that can run dry
+    struct Pooled<'p> {
+        data: Vec<u8>,
+        free: &'p Cell<usize>,
+    }
+
+    impl<'p> TryClone for Pooled<'p> {
+        type Error = &'static str;
+
+        fn try_clone(&self) -> Result<Self, Self::Error> {
+            match self.free.get() {
+                0 => Err("pool exhausted"),
+                n => {
+                    self.free.set(n - 1);
+                    Ok(Pooled {
+                        data: self.data.clone(),
+                        free: self.free,
+                    })
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_clone_error_surfaces() {
+        let free = Cell::new(1);
+        let buffer = Pooled {
+            data: vec![1],
+            free: &free,
+        };
+        let mut first = TryScopeCell::new(&buffer);
+        first.try_get_mut().unwrap().data.push(2);
+        first.try_get_mut().unwrap().data.push(3); // The copy exists, nothing to clone
+        assert_eq!(first.get().data, [1, 2, 3]);
+
+        let mut second = TryScopeCell::new(&buffer);
+        assert_eq!(second.try_get_mut().err(), Some("pool exhausted"));
+        assert!(!second.is_modified());
+        assert!(second.try_into_inner().is_err());
+        first.revert();
+        assert_eq!(first.get().data, [1]);
+    }
+
+    #[test]
+    fn test_file_handle_copy() {
+        let path = std::env::temp_dir().join(format!("scope-cell-try-{}", std::process::id()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"hello").unwrap();
+        let file = File::open(&path).unwrap();
+        let mut cell = TryScopeCell::new(&file);
+        let mut contents = String::new();
+        cell.try_get_mut()
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello");
+        assert!(cell.try_into_inner().is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
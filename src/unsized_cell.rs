@@ -0,0 +1,152 @@
+use std::borrow::{Borrow, Cow};
+use std::ops::Deref;
+
+/// An UnsizedScopeCell is a `ScopeCell` for baselines that are not `Sized`, such as `[T]`,
+/// `str` or a trait object implementing `ToOwned`.  The baseline is borrowed as `&B` and
+/// the temporary copy is stored in its owned form (`Vec<T>`, `String`, `Box<dyn Trait>`),
+/// which is what `get_mut()` hands out.  Changes are discarded when the cell is dropped.
+pub struct UnsizedScopeCell<'a, B: ?Sized + ToOwned> {
+    original_data: &'a B,
+    modified_data: Option<B::Owned>, // Holds temporary modified data
+}
+
+impl<'a, B: ?Sized + ToOwned> UnsizedScopeCell<'a, B> {
+    // Create a new UnsizedScopeCell from an immutable reference
+    pub fn new(data: &'a B) -> Self {
+        UnsizedScopeCell {
+            original_data: data,
+            modified_data: None,
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &B {
+        match self.modified_data {
+            Some(ref modified) => modified.borrow(),
+            None => self.original_data,
+        }
+    }
+
+    // Mutably borrow the owned copy, creating it from the original if necessary
+    pub fn get_mut(&mut self) -> &mut B::Owned {
+        let original = self.original_data;
+        self.modified_data
+            .get_or_insert_with(|| original.to_owned())
+    }
+
+    // Overwrite the effective value without copying the original first
+    pub fn set(&mut self, value: B::Owned) {
+        self.modified_data = Some(value);
+    }
+
+    // The borrowed baseline
+    pub fn original(&self) -> &'a B {
+        self.original_data
+    }
+
+    // Whether an owned copy has been created since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Revert the changes made to the data by dropping the owned copy
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Consume the cell and return the effective value in owned form
+    pub fn into_owned(self) -> B::Owned {
+        self.modified_data
+            .unwrap_or_else(|| self.original_data.to_owned())
+    }
+
+    // Consume the cell without copying an untouched baseline
+    pub fn into_cow(self) -> Cow<'a, B> {
+        match self.modified_data {
+            Some(modified) => Cow::Owned(modified),
+            None => Cow::Borrowed(self.original_data),
+        }
+    }
+}
+
+impl<'a, B: ?Sized + ToOwned> Deref for UnsizedScopeCell<'a, B> {
+    type Target = B;
+
+    fn deref(&self) -> &B {
+        self.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_baseline() {
+        let data = [1, 2, 3];
+        let slice: &[i32] = &data;
+        {
+            let mut scope = UnsizedScopeCell::new(slice);
+            assert_eq!(scope.len(), 3); // Slice methods through Deref
+            scope.get_mut().push(4); // The copy is a Vec
+            assert_eq!(scope.get(), &[1, 2, 3, 4]);
+            assert!(scope.is_modified());
+        } // Dropped, changes discarded
+        assert_eq!(data, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_str_into_cow() {
+        let scope = UnsizedScopeCell::new("hello");
+        assert!(matches!(scope.into_cow(), Cow::Borrowed("hello"))); // No copy made
+
+        let mut scope = UnsizedScopeCell::new("hello");
+        scope.get_mut().push_str(" world");
+        assert_eq!(&*scope, "hello world");
+        scope.revert();
+        scope.set(String::from("bye"));
+        assert_eq!(scope.original(), "hello");
+        assert_eq!(scope.into_owned(), "bye");
+    }
+
+    trait Shape {
+        fn area(&self) -> u32;
+        fn scale(&mut self, factor: u32);
+        fn boxed(&self) -> Box<dyn Shape>;
+    }
+
+    #[derive(Clone)]
+    struct Square(u32);
+
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+
+        fn scale(&mut self, factor: u32) {
+            self.0 *= factor;
+        }
+
+        fn boxed(&self) -> Box<dyn Shape> {
+            Box::new(self.clone())
+        }
+    }
+
+    impl ToOwned for dyn Shape {
+        type Owned = Box<dyn Shape>;
+
+        fn to_owned(&self) -> Box<dyn Shape> {
+            self.boxed()
+        }
+    }
+
+    #[test]
+    fn test_trait_object_baseline() {
+        let square = Square(2);
+        let shape: &dyn Shape = &square;
+        let mut scope = UnsizedScopeCell::new(shape);
+        scope.get_mut().scale(3);
+        assert_eq!(scope.get().area(), 36);
+        assert_eq!(scope.original().area(), 4);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{Participant, ScopeCellMut, ScopeError};
+
+type Validator<'a, T, E> = Box<dyn Fn(&T) -> Result<(), E> + Send + 'a>;
+
+/// A ValidatedScopeCell is a `ScopeCellMut` with an invariant attached at construction.
+/// `commit()` and `into_inner()` check the edited value first; if it violates the
+/// invariant, the changes are reverted and the validator's error is returned.
+pub struct ValidatedScopeCell<'a, T: Clone, E> {
+    cell: ScopeCellMut<'a, T>,
+    validator: Validator<'a, T, E>,
+}
+
+impl<'a, T: Clone, E> ValidatedScopeCell<'a, T, E> {
+    // Create a new ValidatedScopeCell from a mutable reference and an invariant check
+    pub fn new<F>(data: &'a mut T, validator: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), E> + Send + 'a,
+    {
+        ValidatedScopeCell {
+            cell: ScopeCellMut::new(data),
+            validator: Box::new(validator),
+        }
+    }
+
+    // Borrow the data, showing either the original or the modified version
+    pub fn get(&self) -> &T {
+        self.cell.get()
+    }
+
+    // Mutably borrow the data, creating a temporary mutable copy if necessary.
+    // The invariant is only checked on commit.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.cell.get_mut()
+    }
+
+    // Mutably borrow the data like `get_mut()`, or fail if the cell is frozen
+    pub fn try_get_mut(&mut self) -> Result<&mut T, ScopeError> {
+        self.cell.try_get_mut()
+    }
+
+    // Make the cell read-only, see `ScopeCellMut::freeze`
+    pub fn freeze(&mut self) {
+        self.cell.freeze();
+    }
+
+    // Allow edits again
+    pub fn unfreeze(&mut self) {
+        self.cell.unfreeze();
+    }
+
+    // Check the effective value against the invariant without committing
+    pub fn validate(&self) -> Result<(), E> {
+        (self.validator)(self.cell.get())
+    }
+
+    // Whether the data has been mutably accessed since creation or the last revert
+    pub fn is_modified(&self) -> bool {
+        self.cell.is_modified()
+    }
+
+    // Revert the changes made to the data
+    pub fn revert(&mut self) {
+        self.cell.revert();
+    }
+
+    // Write the modified data back if it satisfies the invariant; otherwise revert and
+    // return the error
+    pub fn commit(self) -> Result<(), E> {
+        self.validate()?;
+        self.cell.commit();
+        Ok(())
+    }
+
+    // Write the modified data back in place if it satisfies the invariant; otherwise revert
+    // and return the error
+    pub(crate) fn write_back(&mut self) -> Result<(), E> {
+        if let Err(err) = self.validate() {
+            self.cell.revert();
+            return Err(err);
+        }
+        self.cell.write_back();
+        Ok(())
+    }
+
+    // Return the effective value if it satisfies the invariant; otherwise revert and
+    // return the error
+    pub fn into_inner(self) -> Result<T, E> {
+        self.validate()?;
+        Ok(self.cell.into_inner())
+    }
+
+    // Like `commit()`, but reports a rejected value as `ScopeError::ValidationFailed`
+    pub fn try_commit(self) -> Result<(), ScopeError> {
+        self.commit().map_err(|_| ScopeError::ValidationFailed)
+    }
+
+    // Like `into_inner()`, but reports a rejected value as `ScopeError::ValidationFailed`
+    pub fn try_into_inner(self) -> Result<T, ScopeError> {
+        self.into_inner().map_err(|_| ScopeError::ValidationFailed)
+    }
+}
+
+impl<'a, T: Clone, E> Participant<E> for ValidatedScopeCell<'a, T, E> {
+    fn prepare(&mut self) -> Result<(), E> {
+        self.validate()
+    }
+
+    fn commit(&mut self) {
+        self.cell.write_back();
+    }
+
+    fn abort(&mut self) {
+        self.cell.revert();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Coordinator, ScopeVec};
+
+    fn port_in_range(port: &u32) -> Result<(), String> {
+        if (1..=65535).contains(port) {
+            Ok(())
+        } else {
+            Err(format!("port {} out of range", port))
+        }
+    }
+
+    #[test]
+    fn test_commit_valid_value() {
+        let mut port = 80;
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        *cell.get_mut() = 8080;
+        assert_eq!(cell.commit(), Ok(()));
+        assert_eq!(port, 8080);
+    }
+
+    #[test]
+    fn test_commit_invalid_value_reverts() {
+        let mut port = 80;
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        *cell.get_mut() = 70000;
+        assert!(cell.validate().is_err());
+        assert_eq!(cell.commit(), Err(String::from("port 70000 out of range")));
+        assert_eq!(port, 80);
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let mut port = 80;
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        *cell.get_mut() = 0;
+        assert!(cell.into_inner().is_err());
+
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        *cell.get_mut() = 443;
+        assert_eq!(cell.into_inner(), Ok(443));
+        assert_eq!(port, 80); // into_inner does not write back
+    }
+
+    #[test]
+    fn test_try_commit_and_try_get_mut() {
+        let mut port = 80;
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        *cell.get_mut() = 0;
+        assert_eq!(cell.try_commit(), Err(ScopeError::ValidationFailed));
+
+        let mut cell = ValidatedScopeCell::new(&mut port, port_in_range);
+        cell.freeze();
+        assert_eq!(cell.try_get_mut().err(), Some(ScopeError::Frozen));
+        cell.unfreeze();
+        *cell.try_get_mut().unwrap() = 22;
+        assert_eq!(cell.try_into_inner(), Ok(22));
+    }
+
+    #[test]
+    fn test_vetoes_in_coordinator() {
+        let mut port = 80;
+        let mut hosts = vec!["a"];
+        {
+            let mut port_cell = ValidatedScopeCell::new(&mut port, port_in_range);
+            *port_cell.get_mut() = 0;
+            let mut hosts_cell = ScopeVec::new(&mut hosts);
+            hosts_cell.push("b");
+
+            let mut coordinator = Coordinator::new();
+            coordinator.add(&mut hosts_cell).add(&mut port_cell);
+            assert!(coordinator.run().is_err());
+        }
+        assert_eq!(port, 80);
+        assert_eq!(hosts, vec!["a"]);
+    }
+}
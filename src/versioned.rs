@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, PoisonError};
+
+use crate::primitives::{Mutex, MutexGuard, RwLock, RwLockReadGuard};
+
+/// A VersionedCell keeps the last few committed versions of a value, numbered from 0.
+/// Readers pin a version by holding its `Arc`, so a long read stays stable while a writer
+/// prepares the next version in a scope; committing never disturbs a pinned version.
+pub struct VersionedCell<T: Clone> {
+    versions: RwLock<VecDeque<(u64, Arc<T>)>>, // Oldest first, never empty
+    capacity: usize,                           // Number of versions kept for `read_at`
+    writer: Mutex<()>,                         // Serializes scoped writers
+}
+
+impl<T: Clone> VersionedCell<T> {
+    // Create a new VersionedCell with `value` as version 0, keeping at most `capacity`
+    // versions.  Panics if `capacity` is zero.
+    pub fn new(value: T, capacity: usize) -> Self {
+        assert!(capacity > 0, "VersionedCell must keep at least one version");
+        VersionedCell {
+            versions: RwLock::new(VecDeque::from([(0, Arc::new(value))])),
+            capacity,
+            writer: Mutex::new(()),
+        }
+    }
+
+    // Number of the latest committed version
+    pub fn version(&self) -> u64 {
+        self.lock_versions().back().unwrap().0
+    }
+
+    // Pin the latest committed version
+    pub fn read(&self) -> Arc<T> {
+        Arc::clone(&self.lock_versions().back().unwrap().1)
+    }
+
+    // Pin a specific version, if it is still kept
+    pub fn read_at(&self, version: u64) -> Option<Arc<T>> {
+        self.lock_versions()
+            .iter()
+            .find(|(v, _)| *v == version)
+            .map(|(_, value)| Arc::clone(value))
+    }
+
+    // Numbers of the versions currently kept, oldest first
+    pub fn versions(&self) -> Vec<u64> {
+        self.lock_versions().iter().map(|(v, _)| *v).collect()
+    }
+
+    // Start preparing the next version from a copy of the latest one.  Only one writer
+    // runs at a time; readers are never blocked by it.
+    pub fn write(&self) -> VersionedWrite<'_, T> {
+        let writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        VersionedWrite {
+            cell: self,
+            modified_data: T::clone(&self.read()),
+            _writer: writer,
+        }
+    }
+
+    // Run a closure against the next version, committing it if the closure returns `Ok`
+    pub fn scope<R, E, F>(&self, f: F) -> Result<(u64, R), E>
+    where
+        F: FnOnce(&mut T) -> Result<R, E>,
+    {
+        let mut next = self.write();
+        let result = f(&mut next)?;
+        Ok((next.commit(), result))
+    }
+
+    fn lock_versions(&self) -> RwLockReadGuard<'_, VecDeque<(u64, Arc<T>)>> {
+        self.versions.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Append a committed version, dropping the oldest beyond capacity
+    fn publish(&self, value: T) -> u64 {
+        let mut versions = self
+            .versions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let version = versions.back().unwrap().0 + 1;
+        versions.push_back((version, Arc::new(value)));
+        while versions.len() > self.capacity {
+            versions.pop_front();
+        }
+        version
+    }
+}
+
+/// The next version of a VersionedCell, being prepared.  Dropping it without `commit()`
+/// discards it.
+pub struct VersionedWrite<'c, T: Clone> {
+    cell: &'c VersionedCell<T>,
+    modified_data: T, // Private copy of the latest version
+    _writer: MutexGuard<'c, ()>,
+}
+
+impl<'c, T: Clone> VersionedWrite<'c, T> {
+    // Publish the prepared value as a new version and return its number
+    pub fn commit(self) -> u64 {
+        self.cell.publish(self.modified_data)
+    }
+}
+
+impl<'c, T: Clone> Deref for VersionedWrite<'c, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.modified_data
+    }
+}
+
+impl<'c, T: Clone> DerefMut for VersionedWrite<'c, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.modified_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_pinned_read_survives_commits() {
+        let world = VersionedCell::new(vec![0u32; 3], 2);
+        let frame = world.read(); // Pinned for a long render pass
+        for step in 1..=3 {
+            let mut next = world.write();
+            next[0] = step;
+            assert_eq!(next.commit(), u64::from(step));
+        }
+        assert_eq!(*frame, vec![0, 0, 0]);
+        assert_eq!(world.versions(), vec![2, 3]);
+        assert!(world.read_at(0).is_none()); // Evicted, though `frame` still holds it
+        assert_eq!(world.read_at(2).unwrap()[0], 2);
+        assert_eq!(world.read()[0], 3);
+    }
+
+    #[test]
+    fn test_discarded_write_and_scope() {
+        let cell = VersionedCell::new(String::from("v0"), 4);
+        cell.write().push_str(" (discarded)");
+        assert_eq!(cell.version(), 0);
+        let (version, len) = cell
+            .scope(|s| {
+                s.push('!');
+                Ok::<_, ()>(s.len())
+            })
+            .unwrap();
+        assert_eq!((version, len), (1, 3));
+        assert!(cell.scope(|_| Err::<(), _>("rejected")).is_err());
+        assert_eq!(cell.version(), 1);
+    }
+
+    #[test]
+    fn test_readers_alongside_writer() {
+        let cell = VersionedCell::new(0u64, 8);
+        thread::scope(|s| {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    cell.scope(|n| {
+                        *n += 1;
+                        Ok::<_, ()>(())
+                    })
+                    .unwrap();
+                }
+            });
+            s.spawn(|| {
+                for _ in 0..100 {
+                    let version = cell.version();
+                    if let Some(value) = cell.read_at(version) {
+                        assert_eq!(*value, version); // Each commit added exactly one
+                    }
+                }
+            });
+        });
+        assert_eq!(cell.version(), 100);
+    }
+}
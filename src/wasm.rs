@@ -0,0 +1,127 @@
+use js_sys::{Object, Reflect};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// A JsScopeCell brings scoped edits to JavaScript, e.g. for form state: edits made with
+/// `set()` or `setField()` go to a pending value, `revert()` discards it and `commit()`
+/// adopts it as the new baseline.  JavaScript objects are shared by reference, so the first
+/// `setField()` shallow-copies the baseline object rather than writing into it; an object
+/// returned by `get()` should likewise be edited through the cell, not in place.
+#[wasm_bindgen]
+pub struct JsScopeCell {
+    original: JsValue,
+    pending: Option<JsValue>, // Holds the edited value
+}
+
+#[wasm_bindgen]
+impl JsScopeCell {
+    // Create a new JsScopeCell with `value` as its baseline
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: JsValue) -> JsScopeCell {
+        JsScopeCell {
+            original: value,
+            pending: None,
+        }
+    }
+
+    // The effective value: the pending edit if there is one, otherwise the baseline
+    pub fn get(&self) -> JsValue {
+        self.pending.as_ref().unwrap_or(&self.original).clone()
+    }
+
+    // The baseline, ignoring any pending edit
+    pub fn original(&self) -> JsValue {
+        self.original.clone()
+    }
+
+    // Replace the effective value
+    pub fn set(&mut self, value: JsValue) {
+        self.pending = Some(value);
+    }
+
+    // Set one property of the effective value, copying the baseline object on first use.
+    // Fails if the effective value is not an object.
+    #[wasm_bindgen(js_name = setField)]
+    pub fn set_field(&mut self, key: &str, value: JsValue) -> Result<(), JsValue> {
+        if self.pending.is_none() {
+            if !self.original.is_object() {
+                return Err(JsValue::from_str("JsScopeCell value is not an object"));
+            }
+            let copy = Object::assign(&Object::new(), self.original.unchecked_ref());
+            self.pending = Some(copy.into());
+        }
+        let target = self.pending.as_ref().unwrap();
+        Reflect::set(target, &JsValue::from_str(key), &value)?;
+        Ok(())
+    }
+
+    // Whether there is a pending edit
+    #[wasm_bindgen(js_name = isModified)]
+    pub fn is_modified(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // Discard the pending edit
+    pub fn revert(&mut self) {
+        self.pending = None;
+    }
+
+    // Adopt the pending edit, if any, as the new baseline
+    pub fn commit(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            self.original = pending;
+        }
+    }
+}
+
+impl JsScopeCell {
+    // Create a new JsScopeCell from a Rust value, converted with `serde-wasm-bindgen`
+    pub fn from_serde<T: Serialize>(value: &T) -> Result<JsScopeCell, serde_wasm_bindgen::Error> {
+        Ok(JsScopeCell::new(serde_wasm_bindgen::to_value(value)?))
+    }
+
+    // Convert the effective value back into a Rust value
+    pub fn get_as<T: DeserializeOwned>(&self) -> Result<T, serde_wasm_bindgen::Error> {
+        serde_wasm_bindgen::from_value(self.get())
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Form {
+        name: String,
+        age: u32,
+    }
+
+    #[wasm_bindgen_test]
+    fn test_set_field_copies_the_baseline() {
+        let form = Form {
+            name: String::from("Ada"),
+            age: 36,
+        };
+        let mut cell = JsScopeCell::from_serde(&form).unwrap();
+        cell.set_field("age", JsValue::from(37)).unwrap();
+        assert!(cell.is_modified());
+        assert_eq!(cell.get_as::<Form>().unwrap().age, 37);
+        let original: Form = serde_wasm_bindgen::from_value(cell.original()).unwrap();
+        assert_eq!(original, form); // The baseline object was not written to
+        cell.revert();
+        assert_eq!(cell.get_as::<Form>().unwrap(), form);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_commit_and_non_objects() {
+        let mut cell = JsScopeCell::new(JsValue::from(1));
+        assert!(cell.set_field("x", JsValue::NULL).is_err());
+        cell.set(JsValue::from(2));
+        cell.commit();
+        assert!(!cell.is_modified());
+        assert_eq!(cell.original().as_f64(), Some(2.0));
+    }
+}
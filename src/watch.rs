@@ -0,0 +1,139 @@
+use std::ops::Deref;
+use tokio::sync::watch::{self, Receiver, Sender};
+
+use crate::Participant;
+
+/// A WatchScopeCell owns a `tokio::sync::watch::Sender<T>` whose current value is the
+/// baseline.  Edits go to a private copy; `commit()` publishes the copy so receivers wake up
+/// from `.changed().await`, while reverted or dropped edits are never sent.
+pub struct WatchScopeCell<T: Clone> {
+    sender: Sender<T>,
+    modified_data: Option<T>, // Holds temporary modified data
+}
+
+impl<T: Clone> WatchScopeCell<T> {
+    // Create a new channel with `value` as its baseline, returning the cell and a receiver
+    pub fn new(value: T) -> (Self, Receiver<T>) {
+        let (sender, receiver) = watch::channel(value);
+        (WatchScopeCell::from_sender(sender), receiver)
+    }
+
+    // Wrap an existing sender; its current value becomes the baseline
+    pub fn from_sender(sender: Sender<T>) -> Self {
+        WatchScopeCell {
+            sender,
+            modified_data: None,
+        }
+    }
+
+    // Open another receiver on the channel
+    pub fn subscribe(&self) -> Receiver<T> {
+        self.sender.subscribe()
+    }
+
+    // Borrow the data, showing either the published value or the modified version
+    pub fn get(&self) -> WatchRef<'_, T> {
+        match self.modified_data {
+            Some(ref modified) => WatchRef::Modified(modified),
+            None => WatchRef::Published(self.sender.borrow()),
+        }
+    }
+
+    // Mutably borrow the data, cloning the published value if necessary
+    pub fn get_mut(&mut self) -> &mut T {
+        let sender = &self.sender;
+        self.modified_data
+            .get_or_insert_with(|| sender.borrow().clone())
+    }
+
+    // Whether an unpublished modified copy exists
+    pub fn is_modified(&self) -> bool {
+        self.modified_data.is_some()
+    }
+
+    // Discard the modified copy without publishing it
+    pub fn revert(&mut self) {
+        self.modified_data = None;
+    }
+
+    // Publish the modified copy, if any, to every receiver.  Returns whether a value was sent.
+    pub fn commit(&mut self) -> bool {
+        match self.modified_data.take() {
+            Some(modified) => {
+                self.sender.send_replace(modified);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Consume the cell, discarding unpublished edits, and return the sender
+    pub fn into_sender(self) -> Sender<T> {
+        self.sender
+    }
+}
+
+/// A view of a WatchScopeCell's effective value.
+pub enum WatchRef<'b, T> {
+    Published(watch::Ref<'b, T>),
+    Modified(&'b T),
+}
+
+impl<'b, T> Deref for WatchRef<'b, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            WatchRef::Published(published) => published,
+            WatchRef::Modified(modified) => modified,
+        }
+    }
+}
+
+impl<T: Clone, E> Participant<E> for WatchScopeCell<T> {
+    fn commit(&mut self) {
+        WatchScopeCell::commit(self);
+    }
+
+    fn abort(&mut self) {
+        self.revert();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_commit_wakes_receivers() {
+        let (mut cell, mut rx) = WatchScopeCell::new(1);
+        let waiter = tokio::spawn(async move {
+            rx.changed().await.unwrap();
+            *rx.borrow_and_update()
+        });
+        *cell.get_mut() = 2;
+        assert_eq!(*cell.get(), 2);
+        assert!(cell.commit());
+        assert_eq!(waiter.await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_reverted_edits_are_not_published() {
+        let (mut cell, mut rx) = WatchScopeCell::new(String::from("v1"));
+        rx.mark_unchanged();
+        cell.get_mut().push_str("-draft");
+        cell.revert();
+        assert!(!cell.commit()); // Nothing left to publish
+        assert!(!rx.has_changed().unwrap());
+        assert_eq!(*cell.get(), "v1");
+    }
+
+    #[test]
+    fn test_dropped_cell_publishes_nothing() {
+        let (mut cell, rx) = WatchScopeCell::new(vec![1]);
+        cell.get_mut().push(2);
+        let sender = cell.into_sender();
+        assert_eq!(*rx.borrow(), vec![1]);
+        assert_eq!(*sender.borrow(), vec![1]);
+    }
+}
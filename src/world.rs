@@ -0,0 +1,143 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A WorldOverride installs replacement resources into an ECS-style resource map, keyed by
+/// type, and puts the originals back when dropped, so a system can be tested against a
+/// fake clock, input source or RNG without disturbing the world it runs in.  `insert()`
+/// swaps a replacement in and `remove()` hides a resource for the scope; either way the
+/// original is saved the first time its type is touched, and `commit()` keeps the
+/// replacements instead.
+pub struct WorldOverride<'w> {
+    resources: &'w mut HashMap<TypeId, Box<dyn Any>>,
+    saved: Vec<(TypeId, Option<Box<dyn Any>>)>, // Originals, in the order they were replaced
+}
+
+impl<'w> WorldOverride<'w> {
+    // Start overriding resources in `resources`
+    pub fn new(resources: &'w mut HashMap<TypeId, Box<dyn Any>>) -> Self {
+        WorldOverride {
+            resources,
+            saved: Vec::new(),
+        }
+    }
+
+    // Take the resource of type `id` out, saving it if it is the original
+    fn save(&mut self, id: TypeId) {
+        let current = self.resources.remove(&id);
+        if !self.saved.iter().any(|(saved, _)| *saved == id) {
+            self.saved.push((id, current));
+        }
+    }
+
+    // Replace the resource of type `R` for the scope
+    pub fn insert<R: Any>(&mut self, value: R) -> &mut Self {
+        let id = TypeId::of::<R>();
+        self.save(id);
+        self.resources.insert(id, Box::new(value));
+        self
+    }
+
+    // Builder form of `insert`
+    pub fn with<R: Any>(mut self, value: R) -> Self {
+        self.insert(value);
+        self
+    }
+
+    // Hide the resource of type `R` for the scope
+    pub fn remove<R: Any>(&mut self) -> &mut Self {
+        self.save(TypeId::of::<R>());
+        self
+    }
+
+    // The resource of type `R` as systems currently see it
+    pub fn get<R: Any>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref()
+    }
+
+    // Mutable access to the resource of type `R` as systems currently see it
+    pub fn get_mut<R: Any>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut()
+    }
+
+    // The resource of type `R` as it was before this override, ignoring the replacement
+    pub fn original<R: Any>(&self) -> Option<&R> {
+        let id = TypeId::of::<R>();
+        match self.saved.iter().find(|(saved, _)| *saved == id) {
+            Some((_, original)) => original.as_ref()?.downcast_ref(),
+            None => self.get(),
+        }
+    }
+
+    // Whether the resource of type `R` is overridden or hidden
+    pub fn is_overridden<R: Any>(&self) -> bool {
+        let id = TypeId::of::<R>();
+        self.saved.iter().any(|(saved, _)| *saved == id)
+    }
+
+    // Keep the replacements, dropping the originals
+    pub fn commit(mut self) {
+        self.saved.clear();
+    }
+}
+
+impl<'w> Drop for WorldOverride<'w> {
+    fn drop(&mut self) {
+        for (id, original) in self.saved.drain(..).rev() {
+            match original {
+                Some(original) => self.resources.insert(id, original),
+                None => self.resources.remove(&id),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Time(f32);
+    struct Gravity(f32);
+    struct Paused;
+
+    fn world() -> HashMap<TypeId, Box<dyn Any>> {
+        let mut resources: HashMap<TypeId, Box<dyn Any>> = HashMap::new();
+        resources.insert(TypeId::of::<Time>(), Box::new(Time(12.5)));
+        resources.insert(TypeId::of::<Gravity>(), Box::new(Gravity(9.8)));
+        resources
+    }
+
+    fn get<R: Any>(resources: &HashMap<TypeId, Box<dyn Any>>) -> Option<&R> {
+        resources.get(&TypeId::of::<R>())?.downcast_ref()
+    }
+
+    #[test]
+    fn test_override_restores_on_drop() {
+        let mut resources = world();
+        {
+            let mut scope = WorldOverride::new(&mut resources).with(Time(0.0));
+            scope.insert(Paused).remove::<Gravity>();
+            scope.insert(Time(1.0)); // A second replacement keeps the first original
+            scope.get_mut::<Time>().unwrap().0 += 0.5;
+            assert_eq!(scope.get::<Time>().unwrap().0, 1.5);
+            assert_eq!(scope.original::<Time>().unwrap().0, 12.5);
+            assert!(scope.get::<Gravity>().is_none());
+            assert!(scope.get::<Paused>().is_some() && scope.original::<Paused>().is_none());
+            assert!(scope.is_overridden::<Gravity>());
+        }
+        assert_eq!(get::<Time>(&resources).unwrap().0, 12.5);
+        assert_eq!(get::<Gravity>(&resources).unwrap().0, 9.8);
+        assert!(get::<Paused>(&resources).is_none());
+        assert_eq!(resources.len(), 2);
+    }
+
+    #[test]
+    fn test_commit_keeps_replacements() {
+        let mut resources = world();
+        let mut scope = WorldOverride::new(&mut resources);
+        scope.insert(Gravity(1.6)).remove::<Time>();
+        assert!(!scope.is_overridden::<Paused>());
+        scope.commit();
+        assert_eq!(get::<Gravity>(&resources).unwrap().0, 1.6);
+        assert!(get::<Time>(&resources).is_none());
+    }
+}
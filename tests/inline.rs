@@ -0,0 +1,59 @@
+// Runs in its own binary so the counting allocator does not replace the allocator of the
+// unit tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use scope_cell::{ScopeCell, ScopeCellMut};
+
+// Counts the allocations made by the current thread, so parallel tests do not interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_copy_types_edit_without_allocating() {
+    let pixel = [0u8; 4];
+    let before = ALLOCATIONS.with(Cell::get);
+    let mut cell = ScopeCell::new(&pixel);
+    assert!(!cell.is_inline());
+    for i in 0..1000 {
+        cell.get_mut()[i % 4] = i as u8;
+        cell.revert();
+    }
+    cell.get_mut()[0] = 255;
+    assert!(cell.is_inline());
+    let mut owned = pixel;
+    let mut cell_mut = ScopeCellMut::new(&mut owned);
+    cell_mut.get_mut()[1] = 1;
+    assert!(cell_mut.is_inline());
+    cell_mut.commit();
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(owned, [0, 1, 0, 0]);
+}
+
+#[test]
+fn test_heap_holding_copies_are_not_inline() {
+    let names = vec![String::from("a")];
+    let mut cell = ScopeCell::new(&names);
+    let before = ALLOCATIONS.with(Cell::get);
+    cell.get_mut().push(String::from("b"));
+    assert!(ALLOCATIONS.with(Cell::get) > before);
+    assert!(!cell.is_inline());
+}